@@ -1,7 +1,10 @@
 use crate::io::AxPollState;
-use axerrno::AxResult;
-use axnet::{UdpSocket, TcpSocket};
+use axerrno::{AxError, AxResult};
+use axnet::{CongestionControl, IcmpSocket, UdpSocket, TcpSocket};
 use core::net::{IpAddr, SocketAddr};
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+use core::time::Duration;
 
 /// A handle to a TCP socket.
 pub struct AxTcpSocketHandle(TcpSocket);
@@ -9,6 +12,12 @@ pub struct AxTcpSocketHandle(TcpSocket);
 /// A handle to a UDP socket.
 pub struct AxUdpSocketHandle(UdpSocket);
 
+/// A handle to an ICMP socket.
+pub struct AxIcmpSocketHandle(IcmpSocket);
+
+pub use axnet::InterfaceInfo as AxNetIfInfo;
+pub use axnet::TcpInfo as AxTcpInfo;
+
 ////////////////////////////////////////////////////////////////////////////////
 // TCP socket
 ////////////////////////////////////////////////////////////////////////////////
@@ -38,8 +47,8 @@ pub fn ax_tcp_bind(socket: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult {
     socket.0.bind(addr)
 }
 
-pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, _backlog: usize) -> AxResult {
-    socket.0.listen()
+pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, backlog: usize) -> AxResult {
+    socket.0.listen(backlog)
 }
 
 pub fn ax_tcp_accept(socket: &AxTcpSocketHandle) -> AxResult<(AxTcpSocketHandle, SocketAddr)> {
@@ -56,6 +65,20 @@ pub fn ax_tcp_recv(socket: &AxTcpSocketHandle, buf: &mut [u8]) -> AxResult<usize
     socket.0.recv(buf)
 }
 
+pub fn ax_tcp_send_zc(socket: &AxTcpSocketHandle, f: impl FnOnce(&mut [u8]) -> usize) -> AxResult<usize> {
+    socket.0.send_zc(|buf| {
+        let len = f(buf);
+        (len, len)
+    })
+}
+
+pub fn ax_tcp_recv_zc(socket: &AxTcpSocketHandle, f: impl FnOnce(&[u8]) -> usize) -> AxResult<usize> {
+    socket.0.recv_zc(|buf| {
+        let len = f(buf);
+        (len, len)
+    })
+}
+
 pub fn ax_tcp_poll(socket: &AxTcpSocketHandle) -> AxResult<AxPollState> {
     socket.0.poll()
 }
@@ -64,6 +87,76 @@ pub fn ax_tcp_shutdown(socket: &AxTcpSocketHandle) -> AxResult {
     socket.0.shutdown()
 }
 
+pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_recv_timeout(timeout)
+}
+
+pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.recv_timeout())
+}
+
+pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_send_timeout(timeout)
+}
+
+pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.send_timeout())
+}
+
+pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool) -> AxResult {
+    socket.0.set_nodelay(nodelay);
+    Ok(())
+}
+
+pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> AxResult<bool> {
+    Ok(socket.0.nodelay())
+}
+
+pub fn ax_tcp_set_linger(socket: &AxTcpSocketHandle, linger: Option<Duration>) -> AxResult {
+    socket.0.set_linger(linger);
+    Ok(())
+}
+
+pub fn ax_tcp_linger(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.linger())
+}
+
+pub fn ax_tcp_set_keepalive(socket: &AxTcpSocketHandle, keepalive: bool) -> AxResult {
+    socket.0.set_keep_alive(keepalive);
+    Ok(())
+}
+
+pub fn ax_tcp_keepalive(socket: &AxTcpSocketHandle) -> AxResult<bool> {
+    Ok(socket.0.keep_alive())
+}
+
+pub fn ax_tcp_set_congestion_control(socket: &AxTcpSocketHandle, name: &str) -> AxResult {
+    let cc = CongestionControl::from_name(name)
+        .ok_or_else(|| axerrno::ax_err_type!(NotFound, "unknown congestion control"))?;
+    socket.0.set_congestion_control(cc);
+    Ok(())
+}
+
+pub fn ax_tcp_congestion_control(socket: &AxTcpSocketHandle) -> AxResult<&'static str> {
+    Ok(socket.0.congestion_control().name())
+}
+
+pub fn ax_tcp_info(socket: &AxTcpSocketHandle) -> AxResult<AxTcpInfo> {
+    Ok(socket.0.tcp_info())
+}
+
+pub fn ax_tcp_recv_buffer_size(socket: &AxTcpSocketHandle) -> AxResult<usize> {
+    Ok(socket.0.recv_buffer_size())
+}
+
+pub fn ax_tcp_send_buffer_size(socket: &AxTcpSocketHandle) -> AxResult<usize> {
+    Ok(socket.0.send_buffer_size())
+}
+
+pub fn ax_tcp_take_error(socket: &AxTcpSocketHandle) -> AxResult<Option<AxError>> {
+    Ok(socket.0.take_error())
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // UDP socket
 ////////////////////////////////////////////////////////////////////////////////
@@ -101,6 +194,14 @@ pub fn ax_udp_send_to(socket: &AxUdpSocketHandle, buf: &[u8], addr: SocketAddr)
     socket.0.send_to(buf, addr)
 }
 
+pub fn ax_udp_recv_zc(socket: &AxUdpSocketHandle, f: impl FnOnce(&[u8], SocketAddr)) -> AxResult {
+    socket.0.recv_zc(f)
+}
+
+pub fn ax_udp_send_zc_to(socket: &AxUdpSocketHandle, len: usize, addr: SocketAddr, f: impl FnOnce(&mut [u8])) -> AxResult {
+    socket.0.send_zc_to(len, addr, f)
+}
+
 pub fn ax_udp_connect(socket: &AxUdpSocketHandle, addr: SocketAddr) -> AxResult {
     socket.0.connect(addr)
 }
@@ -117,6 +218,47 @@ pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState> {
     socket.0.poll()
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// ICMP socket
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_icmp_socket() -> AxIcmpSocketHandle {
+    AxIcmpSocketHandle(IcmpSocket::new())
+}
+
+pub fn ax_icmp_ident(socket: &AxIcmpSocketHandle) -> AxResult<u16> {
+    socket.0.ident()
+}
+
+pub fn ax_icmp_set_nonblocking(socket: &AxIcmpSocketHandle, nonblocking: bool) -> AxResult {
+    socket.0.set_nonblocking(nonblocking);
+    Ok(())
+}
+
+pub fn ax_icmp_set_hop_limit(socket: &AxIcmpSocketHandle, hop_limit: Option<u8>) -> AxResult {
+    socket.0.set_hop_limit(hop_limit)
+}
+
+pub fn ax_icmp_set_read_timeout(socket: &AxIcmpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_recv_timeout(timeout)
+}
+
+pub fn ax_icmp_bind(socket: &AxIcmpSocketHandle, ident: u16) -> AxResult {
+    socket.0.bind(ident)
+}
+
+pub fn ax_icmp_recv_from(socket: &AxIcmpSocketHandle, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+    socket.0.recv_from(buf)
+}
+
+pub fn ax_icmp_send_to(socket: &AxIcmpSocketHandle, buf: &[u8], addr: IpAddr) -> AxResult<usize> {
+    socket.0.send_to(buf, addr)
+}
+
+pub fn ax_icmp_poll(socket: &AxIcmpSocketHandle) -> AxResult<AxPollState> {
+    socket.0.poll()
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Miscellaneous
 ////////////////////////////////////////////////////////////////////////////////
@@ -129,3 +271,119 @@ pub fn ax_poll_interfaces() -> AxResult {
     axnet::poll_interfaces();
     Ok(())
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// Interface configuration
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_net_interfaces() -> alloc::vec::Vec<AxNetIfInfo> {
+    axnet::interfaces()
+}
+
+pub fn ax_net_add_addr(ifname: &str, addr: IpAddr, prefix_len: u8) -> AxResult {
+    axnet::add_ip_addr(ifname, addr, prefix_len)
+}
+
+pub fn ax_net_remove_addr(ifname: &str, addr: IpAddr) -> AxResult {
+    axnet::remove_ip_addr(ifname, addr)
+}
+
+pub fn ax_net_add_route(dest: IpAddr, prefix_len: u8, gateway: IpAddr) -> AxResult {
+    axnet::add_route(dest, prefix_len, gateway)
+}
+
+pub fn ax_net_remove_route(dest: IpAddr, prefix_len: u8) -> AxResult {
+    axnet::remove_route(dest, prefix_len)
+}
+
+pub fn ax_net_set_gateway(gateway: IpAddr) -> AxResult {
+    axnet::set_default_gateway(gateway)
+}
+
+pub fn ax_net_set_mtu(ifname: &str, mtu: usize) -> AxResult {
+    axnet::set_mtu(ifname, mtu)
+}
+
+pub fn ax_net_dns_servers() -> alloc::vec::Vec<IpAddr> {
+    axnet::dns_servers()
+}
+
+pub fn ax_net_set_dns_servers(servers: &[IpAddr]) -> AxResult {
+    axnet::set_dns_servers(servers)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Asynchronous operations
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "async")]
+pub fn ax_tcp_poll_connected(socket: &AxTcpSocketHandle, cx: &mut Context<'_>) -> Poll<AxResult> {
+    socket.0.poll_connected(cx)
+}
+
+#[cfg(feature = "async")]
+pub fn ax_tcp_poll_accept(
+    socket: &AxTcpSocketHandle,
+    cx: &mut Context<'_>,
+) -> Poll<AxResult<(AxTcpSocketHandle, SocketAddr)>> {
+    socket.0.poll_accept(cx).map(|res| {
+        let new_sock = res?;
+        let addr = new_sock.peer_addr()?;
+        Ok((AxTcpSocketHandle(new_sock), addr))
+    })
+}
+
+#[cfg(feature = "async")]
+pub fn ax_tcp_poll_send(
+    socket: &AxTcpSocketHandle,
+    cx: &mut Context<'_>,
+    buf: &[u8],
+) -> Poll<AxResult<usize>> {
+    socket.0.poll_send(cx, buf)
+}
+
+#[cfg(feature = "async")]
+pub fn ax_tcp_poll_recv(
+    socket: &AxTcpSocketHandle,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<AxResult<usize>> {
+    socket.0.poll_recv(cx, buf)
+}
+
+#[cfg(feature = "async")]
+pub fn ax_udp_poll_send_to(
+    socket: &AxUdpSocketHandle,
+    cx: &mut Context<'_>,
+    buf: &[u8],
+    addr: SocketAddr,
+) -> Poll<AxResult<usize>> {
+    socket.0.poll_send_to(cx, buf, addr)
+}
+
+#[cfg(feature = "async")]
+pub fn ax_udp_poll_recv_from(
+    socket: &AxUdpSocketHandle,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<AxResult<(usize, SocketAddr)>> {
+    socket.0.poll_recv_from(cx, buf)
+}
+
+#[cfg(feature = "async")]
+pub fn ax_udp_poll_send(
+    socket: &AxUdpSocketHandle,
+    cx: &mut Context<'_>,
+    buf: &[u8],
+) -> Poll<AxResult<usize>> {
+    socket.0.poll_send(cx, buf)
+}
+
+#[cfg(feature = "async")]
+pub fn ax_udp_poll_recv(
+    socket: &AxUdpSocketHandle,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<AxResult<usize>> {
+    socket.0.poll_recv(cx, buf)
+}
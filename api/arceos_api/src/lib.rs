@@ -265,15 +265,20 @@ pub mod fs {
     }
 }
 
-/// Networking primitives for TCP/UDP communication.
+/// Networking primitives for TCP/UDP/ICMP communication.
 pub mod net {
-    use crate::{AxResult, io::AxPollState};
+    use crate::{AxError, AxResult, io::AxPollState};
     use core::net::{IpAddr, SocketAddr};
+    use core::task::{Context, Poll};
+    use core::time::Duration;
 
     define_api_type! {
         @cfg "net";
         pub type AxTcpSocketHandle;
         pub type AxUdpSocketHandle;
+        pub type AxIcmpSocketHandle;
+        pub type AxNetIfInfo;
+        pub type AxTcpInfo;
     }
 
     define_api! {
@@ -294,8 +299,9 @@ pub mod net {
         pub fn ax_tcp_connect(handle: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult;
         /// Binds the TCP socket to the given address and port.
         pub fn ax_tcp_bind(socket: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult;
-        /// Starts listening on the bound address and port.
-        pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, _backlog: usize) -> AxResult;
+        /// Starts listening on the bound address and port, with at most
+        /// `backlog` established connections waiting to be accepted.
+        pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, backlog: usize) -> AxResult;
         /// Accepts a new connection on the TCP socket.
         ///
         /// This function will block the calling thread until a new TCP connection
@@ -307,11 +313,53 @@ pub mod net {
         /// Receives data on the TCP socket, and stores it in the given buffer.
         /// On success, returns the number of bytes read.
         pub fn ax_tcp_recv(socket: &AxTcpSocketHandle, buf: &mut [u8]) -> AxResult<usize>;
+        /// Transmits data on the TCP socket by lending the free space of its
+        /// send buffer to `f`, which fills it and returns the length of the data.
+        pub fn ax_tcp_send_zc(socket: &AxTcpSocketHandle, f: impl FnOnce(&mut [u8]) -> usize) -> AxResult<usize>;
+        /// Receives data on the TCP socket by lending its receive buffer to
+        /// `f`, which returns the number of bytes consumed.
+        pub fn ax_tcp_recv_zc(socket: &AxTcpSocketHandle, f: impl FnOnce(&[u8]) -> usize) -> AxResult<usize>;
         /// Returns whether the TCP socket is readable or writable.
         pub fn ax_tcp_poll(socket: &AxTcpSocketHandle) -> AxResult<AxPollState>;
         /// Closes the connection on the TCP socket.
         pub fn ax_tcp_shutdown(socket: &AxTcpSocketHandle) -> AxResult;
 
+        /// Sets the timeout of blocking receive operations on the TCP socket,
+        /// or no timeout if `timeout` is `None`.
+        pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of blocking receive operations on the TCP socket.
+        pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the timeout of blocking send operations on the TCP socket,
+        /// or no timeout if `timeout` is `None`.
+        pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of blocking send operations on the TCP socket.
+        pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Disables or enables Nagle's algorithm on the TCP socket.
+        pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool) -> AxResult;
+        /// Returns whether Nagle's algorithm is disabled on the TCP socket.
+        pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> AxResult<bool>;
+        /// Sets the linger timeout of closing the TCP socket.
+        pub fn ax_tcp_set_linger(socket: &AxTcpSocketHandle, linger: Option<Duration>) -> AxResult;
+        /// Returns the linger timeout of closing the TCP socket.
+        pub fn ax_tcp_linger(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Enables or disables TCP keep-alive probes on the TCP socket.
+        pub fn ax_tcp_set_keepalive(socket: &AxTcpSocketHandle, keepalive: bool) -> AxResult;
+        /// Returns whether TCP keep-alive probes are sent on the TCP socket.
+        pub fn ax_tcp_keepalive(socket: &AxTcpSocketHandle) -> AxResult<bool>;
+        /// Sets the congestion control algorithm of the TCP socket by its name
+        /// (`"none"`, `"reno"` or `"cubic"`).
+        pub fn ax_tcp_set_congestion_control(socket: &AxTcpSocketHandle, name: &str) -> AxResult;
+        /// Returns the name of the congestion control algorithm of the TCP socket.
+        pub fn ax_tcp_congestion_control(socket: &AxTcpSocketHandle) -> AxResult<&'static str>;
+        /// Returns the statistics of the connection on the TCP socket.
+        pub fn ax_tcp_info(socket: &AxTcpSocketHandle) -> AxResult<AxTcpInfo>;
+        /// Returns the size of the receive buffer of the TCP socket.
+        pub fn ax_tcp_recv_buffer_size(socket: &AxTcpSocketHandle) -> AxResult<usize>;
+        /// Returns the size of the send buffer of the TCP socket.
+        pub fn ax_tcp_send_buffer_size(socket: &AxTcpSocketHandle) -> AxResult<usize>;
+        /// Returns the pending error of the TCP socket and clears it.
+        pub fn ax_tcp_take_error(socket: &AxTcpSocketHandle) -> AxResult<Option<AxError>>;
+
         // UDP socket
 
         /// Creates a new UDP socket.
@@ -333,6 +381,12 @@ pub mod net {
         /// Sends data on the UDP socket to the given address. On success,
         /// returns the number of bytes written.
         pub fn ax_udp_send_to(socket: &AxUdpSocketHandle, buf: &[u8], addr: SocketAddr) -> AxResult<usize>;
+        /// Receives a single datagram message on the UDP socket by lending it to
+        /// `f` with its origin.
+        pub fn ax_udp_recv_zc(socket: &AxUdpSocketHandle, f: impl FnOnce(&[u8], SocketAddr)) -> AxResult;
+        /// Sends a datagram of `len` bytes on the UDP socket to the given
+        /// address, by lending a slot of its send buffer to `f` to fill.
+        pub fn ax_udp_send_zc_to(socket: &AxUdpSocketHandle, len: usize, addr: SocketAddr, f: impl FnOnce(&mut [u8])) -> AxResult;
 
         /// Connects this UDP socket to a remote address, allowing the `send` and
         /// `recv` to be used to send data and also applies filters to only receive
@@ -348,6 +402,53 @@ pub mod net {
         /// Returns whether the UDP socket is readable or writable.
         pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState>;
 
+        // ICMP socket
+
+        /// Creates a new ICMP socket for echo (ping) messages.
+        pub fn ax_icmp_socket() -> AxIcmpSocketHandle;
+        /// Returns the ICMP identifier that the ICMP socket is bound to.
+        pub fn ax_icmp_ident(socket: &AxIcmpSocketHandle) -> AxResult<u16>;
+        /// Moves this ICMP socket into or out of nonblocking mode.
+        pub fn ax_icmp_set_nonblocking(socket: &AxIcmpSocketHandle, nonblocking: bool) -> AxResult;
+        /// Sets the hop limit (TTL) of the messages sent on the ICMP socket.
+        pub fn ax_icmp_set_hop_limit(socket: &AxIcmpSocketHandle, hop_limit: Option<u8>) -> AxResult;
+        /// Sets the timeout of blocking receive operations on the ICMP
+        /// socket, or no timeout if `timeout` is `None`.
+        pub fn ax_icmp_set_read_timeout(socket: &AxIcmpSocketHandle, timeout: Option<Duration>) -> AxResult;
+
+        /// Binds the ICMP socket to the given ICMP identifier, or an
+        /// automatically chosen one if it is 0.
+        pub fn ax_icmp_bind(socket: &AxIcmpSocketHandle, ident: u16) -> AxResult;
+        /// Receives a single ICMP message on the ICMP socket.
+        pub fn ax_icmp_recv_from(socket: &AxIcmpSocketHandle, buf: &mut [u8]) -> AxResult<(usize, IpAddr)>;
+        /// Sends an ICMP message on the ICMP socket to the given address. On
+        /// success, returns the number of bytes written.
+        pub fn ax_icmp_send_to(socket: &AxIcmpSocketHandle, buf: &[u8], addr: IpAddr) -> AxResult<usize>;
+        /// Returns whether the ICMP socket is readable or writable.
+        pub fn ax_icmp_poll(socket: &AxIcmpSocketHandle) -> AxResult<AxPollState>;
+
+        // Interface configuration
+
+        /// Returns the information of all network interfaces.
+        pub fn ax_net_interfaces() -> alloc::vec::Vec<AxNetIfInfo>;
+        /// Adds an IP address with the given prefix length to the interface.
+        pub fn ax_net_add_addr(ifname: &str, addr: IpAddr, prefix_len: u8) -> AxResult;
+        /// Removes an IP address from the interface.
+        pub fn ax_net_remove_addr(ifname: &str, addr: IpAddr) -> AxResult;
+        /// Adds a route to the network `dest/prefix_len` via `gateway`,
+        /// replacing the existing route to the same network.
+        pub fn ax_net_add_route(dest: IpAddr, prefix_len: u8, gateway: IpAddr) -> AxResult;
+        /// Removes the route to the network `dest/prefix_len`.
+        pub fn ax_net_remove_route(dest: IpAddr, prefix_len: u8) -> AxResult;
+        /// Sets the default gateway of the address family of `gateway`.
+        pub fn ax_net_set_gateway(gateway: IpAddr) -> AxResult;
+        /// Sets the maximum transmission unit of the interface.
+        pub fn ax_net_set_mtu(ifname: &str, mtu: usize) -> AxResult;
+        /// Returns the DNS servers used by [`ax_dns_query`].
+        pub fn ax_net_dns_servers() -> alloc::vec::Vec<IpAddr>;
+        /// Replaces the DNS servers used by [`ax_dns_query`].
+        pub fn ax_net_set_dns_servers(servers: &[IpAddr]) -> AxResult;
+
         // Miscellaneous
 
         /// Resolves the host name to a list of IP addresses.
@@ -358,6 +459,63 @@ pub mod net {
         /// packets to the NIC.
         pub fn ax_poll_interfaces() -> AxResult;
     }
+
+    define_api! {
+        @cfg "net";
+
+        // Asynchronous operations. If the operation cannot complete
+        // immediately, the waker of `cx` is registered in the socket and
+        // `Poll::Pending` is returned.
+
+        /// Polls whether the connection started by [`ax_tcp_connect`] in
+        /// nonblocking mode is established.
+        #[cfg(feature = "async")]
+        pub fn ax_tcp_poll_connected(socket: &AxTcpSocketHandle, cx: &mut Context<'_>) -> Poll<AxResult>;
+        /// Polls for a new connection on the TCP socket.
+        #[cfg(feature = "async")]
+        pub fn ax_tcp_poll_accept(
+            socket: &AxTcpSocketHandle,
+            cx: &mut Context<'_>,
+        ) -> Poll<AxResult<(AxTcpSocketHandle, SocketAddr)>>;
+        /// Polls to transmit data in the given buffer on the TCP socket.
+        #[cfg(feature = "async")]
+        pub fn ax_tcp_poll_send(socket: &AxTcpSocketHandle, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>>;
+        /// Polls to receive data on the TCP socket into the given buffer.
+        #[cfg(feature = "async")]
+        pub fn ax_tcp_poll_recv(
+            socket: &AxTcpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<AxResult<usize>>;
+
+        /// Polls to send data on the UDP socket to the given address.
+        #[cfg(feature = "async")]
+        pub fn ax_udp_poll_send_to(
+            socket: &AxUdpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+            addr: SocketAddr,
+        ) -> Poll<AxResult<usize>>;
+        /// Polls to receive a single datagram message on the UDP socket.
+        #[cfg(feature = "async")]
+        pub fn ax_udp_poll_recv_from(
+            socket: &AxUdpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<AxResult<(usize, SocketAddr)>>;
+        /// Polls to send data on the UDP socket to the remote address to which
+        /// it is connected.
+        #[cfg(feature = "async")]
+        pub fn ax_udp_poll_send(socket: &AxUdpSocketHandle, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>>;
+        /// Polls to receive a single datagram message on the UDP socket from
+        /// the remote address to which it is connected.
+        #[cfg(feature = "async")]
+        pub fn ax_udp_poll_recv(
+            socket: &AxUdpSocketHandle,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<AxResult<usize>>;
+    }
 }
 
 /// Graphics manipulation operations.
@@ -396,6 +554,8 @@ pub mod modules {
 
     #[cfg(feature = "alloc")]
     pub use axalloc;
+    #[cfg(feature = "async")]
+    pub use axasync;
     #[cfg(feature = "display")]
     pub use axdisplay;
     #[cfg(feature = "dma")]
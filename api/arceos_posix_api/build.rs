@@ -71,15 +71,34 @@ typedef struct {{
             "sock.*",
             "fd_set",
             "timeval",
+            "itimerspec",
+            "itimerval",
             "pthread_t",
             "pthread_attr_t",
             "pthread_mutex_t",
             "pthread_mutexattr_t",
+            "pthread_cond_t",
+            "pthread_condattr_t",
+            "pthread_key_t",
+            "pthread_once_t",
             "epoll_event",
+            "pollfd",
+            "nfds_t",
             "iovec",
             "clockid_t",
             "rlimit",
             "aibuf",
+            "ifreq",
+            "ifconf",
+            "rtentry",
+            "linger",
+            "tcp_info",
+            "msghdr",
+            "cmsghdr",
+            "sigset_t",
+            "sigaction",
+            "siginfo_t",
+            "signalfd_siginfo",
         ];
 
         let allow_vars = [
@@ -88,14 +107,34 @@ typedef struct {{
             "AF_.*",
             "SOCK_.*",
             "IPPROTO_.*",
+            "IPV6_V6ONLY",
+            "SOL_.*",
+            "SO_.*",
+            "TCP_.*",
+            "MSG_.*",
+            "SCM_.*",
+            "SHUT_.*",
             "FD_.*",
             "F_.*",
             "_SC_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
+            "EFD_.*",
+            "TFD_.*",
+            "SFD_.*",
+            "POLL.*",
             "RLIMIT_.*",
             "EAI_.*",
             "MAXADDRS",
+            "SIOC.*",
+            "RTF_.*",
+            "FIONBIO",
+            "IFNAMSIZ",
+            "SIG.*",
+            "SA_.*",
+            "SI_USER",
+            "ITIMER_.*",
+            "AT_.*",
         ];
 
         #[derive(Debug)]
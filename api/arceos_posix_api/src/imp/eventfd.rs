@@ -0,0 +1,133 @@
+use alloc::sync::Arc;
+use core::ffi::{c_int, c_uint};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+
+use super::fd_ops::{FileLike, add_file_like_cloexec};
+use super::poll_set::{PollSet, PollWaker};
+use crate::ctypes;
+
+/// The maximum value of the counter.
+const EVENTFD_MAX: u64 = u64::MAX - 1;
+
+/// An event counter (`eventfd`), read and written as 8-byte integers.
+pub struct EventFd {
+    count: Mutex<u64>,
+    semaphore: bool,
+    nonblock: AtomicBool,
+    poll_set: PollSet,
+}
+
+impl EventFd {
+    pub fn new(initval: u64, semaphore: bool, nonblock: bool) -> Self {
+        Self {
+            count: Mutex::new(initval),
+            semaphore,
+            nonblock: AtomicBool::new(nonblock),
+            poll_set: PollSet::new(),
+        }
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+}
+
+impl FileLike for EventFd {
+    /// Takes the counter, or decrements it by one in the semaphore mode.
+    /// Blocks while the counter is zero.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let value = self.poll_set.block_on(self.is_nonblocking(), || {
+            let mut count = self.count.lock();
+            if *count == 0 {
+                return Err(LinuxError::EAGAIN);
+            }
+            let value = if self.semaphore { 1 } else { *count };
+            *count -= value;
+            Ok(value)
+        })?;
+        self.poll_set.wake();
+        buf[..size_of::<u64>()].copy_from_slice(&value.to_ne_bytes());
+        Ok(size_of::<u64>())
+    }
+
+    /// Adds to the counter. Blocks while the counter would overflow.
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        let value = buf
+            .get(..size_of::<u64>())
+            .and_then(|b| b.try_into().ok())
+            .map(u64::from_ne_bytes)
+            .ok_or(LinuxError::EINVAL)?;
+        if value == u64::MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        self.poll_set.block_on(self.is_nonblocking(), || {
+            let mut count = self.count.lock();
+            if EVENTFD_MAX - *count < value {
+                return Err(LinuxError::EAGAIN);
+            }
+            *count += value;
+            Ok(())
+        })?;
+        self.poll_set.wake();
+        Ok(size_of::<u64>())
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let count = *self.count.lock();
+        Ok(PollState {
+            readable: count > 0,
+            writable: count < EVENTFD_MAX,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblock.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register_poller(&self, waker: &Arc<dyn PollWaker>) -> bool {
+        self.poll_set.register(waker);
+        true
+    }
+}
+
+/// Creates an event counter with the initial value `initval`, and returns a
+/// file descriptor referring to it.
+///
+/// `flags` may contain `EFD_SEMAPHORE`, `EFD_NONBLOCK` and `EFD_CLOEXEC`.
+pub fn sys_eventfd2(initval: c_uint, flags: c_int) -> c_int {
+    debug!("sys_eventfd2 <= initval: {}, flags: {:#x}", initval, flags);
+    syscall_body!(sys_eventfd2, {
+        let flags = flags as u32;
+        if flags & !(ctypes::EFD_SEMAPHORE | ctypes::EFD_NONBLOCK | ctypes::EFD_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let eventfd = EventFd::new(
+            initval as u64,
+            flags & ctypes::EFD_SEMAPHORE != 0,
+            flags & ctypes::EFD_NONBLOCK != 0,
+        );
+        add_file_like_cloexec(Arc::new(eventfd), flags & ctypes::EFD_CLOEXEC != 0)
+    })
+}
@@ -7,6 +7,7 @@ use axns::{ResArc, def_resource};
 use flatten_objects::FlattenObjects;
 use spin::RwLock;
 
+use super::poll_set::PollWaker;
 use crate::ctypes;
 use crate::imp::stdio::{stdin, stdout};
 
@@ -20,22 +21,57 @@ pub trait FileLike: Send + Sync {
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+
+    /// Registers `waker` to be woken when the readiness of the file may
+    /// change.
+    ///
+    /// Returns `false` if the file never wakes it, so it has to be polled
+    /// repeatedly instead.
+    fn register_poller(&self, _waker: &Arc<dyn PollWaker>) -> bool {
+        false
+    }
 }
 
+/// An entry of the file descriptor table.
+#[derive(Clone)]
+pub(crate) struct FileDesc {
+    file: Arc<dyn FileLike>,
+    /// Whether the descriptor is closed on `execve` (`FD_CLOEXEC`).
+    cloexec: bool,
+}
+
+impl FileDesc {
+    fn new(file: Arc<dyn FileLike>) -> Self {
+        Self {
+            file,
+            cloexec: false,
+        }
+    }
+}
+
+pub(crate) type FdTable = FlattenObjects<FileDesc, AX_FILE_LIMIT>;
+
 def_resource! {
-    pub(crate) static FD_TABLE: ResArc<RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>>> = ResArc::new();
+    pub(crate) static FD_TABLE: ResArc<RwLock<FdTable>> = ResArc::new();
 }
 
 pub fn get_file_like(fd: c_int) -> LinuxResult<Arc<dyn FileLike>> {
     FD_TABLE
         .read()
         .get(fd as usize)
-        .cloned()
+        .map(|desc| desc.file.clone())
         .ok_or(LinuxError::EBADF)
 }
 
 pub fn add_file_like(f: Arc<dyn FileLike>) -> LinuxResult<c_int> {
-    Ok(FD_TABLE.write().add(f).map_err(|_| LinuxError::EMFILE)? as c_int)
+    add_file_like_cloexec(f, false)
+}
+
+/// Like [`add_file_like`], and marks the new descriptor close-on-exec if
+/// `cloexec` is set.
+pub(crate) fn add_file_like_cloexec(f: Arc<dyn FileLike>, cloexec: bool) -> LinuxResult<c_int> {
+    let desc = FileDesc { file: f, cloexec };
+    Ok(FD_TABLE.write().add(desc).map_err(|_| LinuxError::EMFILE)? as c_int)
 }
 
 pub fn close_file_like(fd: c_int) -> LinuxResult {
@@ -47,6 +83,51 @@ pub fn close_file_like(fd: c_int) -> LinuxResult {
     Ok(())
 }
 
+/// Returns a copy of the file descriptor table, sharing the open files.
+///
+/// The descriptors marked close-on-exec are left out if `exec` is set, for a
+/// new program.
+#[cfg(feature = "uspace")]
+pub(crate) fn copy_fd_table(exec: bool) -> FdTable {
+    let table = FD_TABLE.read();
+    let mut new_table = FlattenObjects::new();
+    for fd in table.ids() {
+        let desc = table.get(fd).unwrap();
+        if exec && desc.cloexec {
+            continue;
+        }
+        new_table
+            .add_at(fd, desc.clone())
+            .unwrap_or_else(|_| panic!());
+    }
+    new_table
+}
+
+/// Closes the files whose descriptors are marked close-on-exec, as `execve`.
+#[cfg(feature = "uspace")]
+pub(crate) fn close_cloexec_files() {
+    let files: alloc::vec::Vec<_> = {
+        let mut table = FD_TABLE.write();
+        let fds: alloc::vec::Vec<_> = table
+            .ids()
+            .filter(|&fd| table.get(fd).is_some_and(|desc| desc.cloexec))
+            .collect();
+        fds.into_iter().filter_map(|fd| table.remove(fd)).collect()
+    };
+    drop(files);
+}
+
+/// Closes all files of the current file descriptor table.
+#[cfg(feature = "uspace")]
+pub(crate) fn close_all_files() {
+    let files: alloc::vec::Vec<_> = {
+        let mut table = FD_TABLE.write();
+        let fds: alloc::vec::Vec<_> = table.ids().collect();
+        fds.into_iter().filter_map(|fd| table.remove(fd)).collect()
+    };
+    drop(files);
+}
+
 /// Close a file by `fd`.
 pub fn sys_close(fd: c_int) -> c_int {
     debug!("sys_close <= {}", fd);
@@ -56,16 +137,35 @@ pub fn sys_close(fd: c_int) -> c_int {
     syscall_body!(sys_close, close_file_like(fd).map(|_| 0))
 }
 
-fn dup_fd(old_fd: c_int) -> LinuxResult<c_int> {
+fn dup_fd(old_fd: c_int, cloexec: bool) -> LinuxResult<c_int> {
     let f = get_file_like(old_fd)?;
-    let new_fd = add_file_like(f)?;
+    let new_fd = add_file_like_cloexec(f, cloexec)?;
     Ok(new_fd)
 }
 
+/// Returns the descriptor flags of `fd` (`F_GETFD`).
+fn fd_flags(fd: c_int) -> LinuxResult<c_int> {
+    let table = FD_TABLE.read();
+    let desc = table.get(fd as usize).ok_or(LinuxError::EBADF)?;
+    Ok(if desc.cloexec {
+        ctypes::FD_CLOEXEC as c_int
+    } else {
+        0
+    })
+}
+
+/// Sets the descriptor flags of `fd` (`F_SETFD`).
+fn set_fd_flags(fd: c_int, flags: usize) -> LinuxResult<c_int> {
+    let mut table = FD_TABLE.write();
+    let desc = table.get_mut(fd as usize).ok_or(LinuxError::EBADF)?;
+    desc.cloexec = flags & ctypes::FD_CLOEXEC as usize != 0;
+    Ok(0)
+}
+
 /// Duplicate a file descriptor.
 pub fn sys_dup(old_fd: c_int) -> c_int {
     debug!("sys_dup <= {}", old_fd);
-    syscall_body!(sys_dup, dup_fd(old_fd))
+    syscall_body!(sys_dup, dup_fd(old_fd, false))
 }
 
 /// Duplicate a file descriptor, but it uses the file descriptor number specified in `new_fd`.
@@ -89,7 +189,7 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
         let f = get_file_like(old_fd)?;
         FD_TABLE
             .write()
-            .add_at(new_fd as usize, f)
+            .add_at(new_fd as usize, FileDesc::new(f))
             .map_err(|_| LinuxError::EMFILE)?;
 
         Ok(new_fd)
@@ -103,11 +203,10 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     debug!("sys_fcntl <= fd: {} cmd: {} arg: {}", fd, cmd, arg);
     syscall_body!(sys_fcntl, {
         match cmd as u32 {
-            ctypes::F_DUPFD => dup_fd(fd),
-            ctypes::F_DUPFD_CLOEXEC => {
-                // TODO: Change fd flags
-                dup_fd(fd)
-            }
+            ctypes::F_DUPFD => dup_fd(fd, false),
+            ctypes::F_DUPFD_CLOEXEC => dup_fd(fd, true),
+            ctypes::F_GETFD => fd_flags(fd),
+            ctypes::F_SETFD => set_fd_flags(fd, arg),
             ctypes::F_SETFL => {
                 if fd == 0 || fd == 1 || fd == 2 {
                     return Ok(0);
@@ -115,6 +214,10 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
                 get_file_like(fd)?.set_nonblocking(arg & (ctypes::O_NONBLOCK as usize) > 0)?;
                 Ok(0)
             }
+            #[cfg(feature = "pipe")]
+            ctypes::F_GETPIPE_SZ => super::pipe::pipe_size(fd),
+            #[cfg(feature = "pipe")]
+            ctypes::F_SETPIPE_SZ => super::pipe::set_pipe_size(fd, arg),
             _ => {
                 warn!("unsupported fcntl parameters: cmd {}", cmd);
                 Ok(0)
@@ -123,17 +226,47 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     })
 }
 
+/// Manipulate the underlying device parameters of special files.
+///
+/// Only `FIONBIO` and the socket configuration controls (`SIOC*`), including
+/// the routing table ones, are supported.
+pub unsafe fn sys_ioctl(fd: c_int, request: c_int, arg: usize) -> c_int {
+    debug!(
+        "sys_ioctl <= fd: {} request: {:#x} arg: {:#x}",
+        fd, request, arg
+    );
+    syscall_body!(sys_ioctl, {
+        match request as u32 {
+            ctypes::FIONBIO => {
+                if arg == 0 {
+                    return Err(LinuxError::EFAULT);
+                }
+                let nonblocking = unsafe { *(arg as *const c_int) } != 0;
+                get_file_like(fd)?.set_nonblocking(nonblocking)?;
+                Ok(0)
+            }
+            // Socket configuration controls, see `linux/sockios.h`.
+            #[cfg(feature = "net")]
+            0x8900..=0x89ff => unsafe { super::net::socket_ioctl(fd, request as u32, arg) },
+            _ => {
+                warn!("unsupported ioctl request: {:#x}", request);
+                Err(LinuxError::ENOTTY)
+            }
+        }
+    })
+}
+
 #[ctor_bare::register_ctor]
 fn init_stdio() {
     let mut fd_table = flatten_objects::FlattenObjects::new();
     fd_table
-        .add_at(0, Arc::new(stdin()) as _)
+        .add_at(0, FileDesc::new(Arc::new(stdin())))
         .unwrap_or_else(|_| panic!()); // stdin
     fd_table
-        .add_at(1, Arc::new(stdout()) as _)
+        .add_at(1, FileDesc::new(Arc::new(stdout())))
         .unwrap_or_else(|_| panic!()); // stdout
     fd_table
-        .add_at(2, Arc::new(stdout()) as _)
+        .add_at(2, FileDesc::new(Arc::new(stdout())))
         .unwrap_or_else(|_| panic!()); // stderr
     FD_TABLE.init_new(spin::RwLock::new(fd_table));
 }
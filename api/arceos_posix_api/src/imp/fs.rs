@@ -1,8 +1,11 @@
+use alloc::format;
+use alloc::string::String;
 use alloc::sync::Arc;
 use core::ffi::{c_char, c_int};
+use core::sync::atomic::{AtomicU32, Ordering};
 
-use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axfs::fops::{DirEntry, FileAttr, FilePerm, OpenOptions};
 use axio::{PollState, SeekFrom};
 use axsync::Mutex;
 
@@ -20,8 +23,8 @@ impl File {
         }
     }
 
-    fn add_to_fd_table(self) -> LinuxResult<c_int> {
-        super::fd_ops::add_file_like(Arc::new(self))
+    fn add_to_fd_table(self, cloexec: bool) -> LinuxResult<c_int> {
+        super::fd_ops::add_file_like_cloexec(Arc::new(self), cloexec)
     }
 
     fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
@@ -42,21 +45,7 @@ impl FileLike for File {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        Ok(attr_to_stat(self.inner.lock().get_attr()?))
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -75,10 +64,110 @@ impl FileLike for File {
     }
 }
 
-/// Convert open flags to [`OpenOptions`].
-fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
+pub struct Directory {
+    inner: Mutex<axfs::fops::Directory>,
+    path: String,
+}
+
+impl Directory {
+    fn open(path: &str) -> LinuxResult<Self> {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        let inner = axfs::fops::Directory::open_dir(path, &options)?;
+        Ok(Self {
+            inner: Mutex::new(inner),
+            path: axfs::api::canonicalize(path)?,
+        })
+    }
+
+    fn add_to_fd_table(self, cloexec: bool) -> LinuxResult<c_int> {
+        super::fd_ops::add_file_like_cloexec(Arc::new(self), cloexec)
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        let f = super::fd_ops::get_file_like(fd)?;
+        f.into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::ENOTDIR)
+    }
+
+    /// Sets the position of the next entry to read, which is the index of
+    /// the entry.
+    fn seek(&self, pos: SeekFrom) -> LinuxResult<u64> {
+        let mut inner = self.inner.lock();
+        let idx = match pos {
+            SeekFrom::Start(idx) => Some(idx),
+            SeekFrom::Current(off) => (inner.entry_index() as u64).checked_add_signed(off),
+            SeekFrom::End(_) => None,
+        }
+        .ok_or(LinuxError::EINVAL)?;
+        inner.set_entry_index(idx as _);
+        Ok(idx)
+    }
+}
+
+impl FileLike for Directory {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EISDIR)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(attr_to_stat(self.inner.lock().get_attr()?))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+fn attr_to_stat(attr: FileAttr) -> ctypes::stat {
+    let ty = attr.file_type() as u8;
+    let perm = attr.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    ctypes::stat {
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode,
+        st_uid: 1000,
+        st_gid: 1000,
+        st_size: attr.size() as _,
+        st_blocks: attr.blocks() as _,
+        st_blksize: 512,
+        ..Default::default()
+    }
+}
+
+/// Convert the permission bits of `mode` to [`FilePerm`].
+fn mode_to_perm(mode: ctypes::mode_t) -> FilePerm {
+    FilePerm::from_bits_truncate((mode & 0o777) as u16)
+}
+
+/// Convert the `mode` of a created node to [`FilePerm`], masked by the umask.
+pub(crate) fn create_perm(mode: ctypes::mode_t) -> FilePerm {
+    mode_to_perm(mode & !UMASK.load(Ordering::Relaxed))
+}
+
+/// Convert open flags to [`OpenOptions`], with the `mode` of a created file
+/// masked by the umask.
+fn flags_to_options(flags: c_int, mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
     let mut options = OpenOptions::new();
+    options.mode(mode & 0o777 & !UMASK.load(Ordering::Relaxed));
     match flags & 0b11 {
         ctypes::O_RDONLY => options.read(true),
         ctypes::O_WRONLY => options.write(true),
@@ -102,6 +191,41 @@ fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     options
 }
 
+/// Resolves `path` of an `*at` call relative to the directory `dirfd`.
+fn resolve_at(dirfd: c_int, path: &str) -> LinuxResult<String> {
+    if path.starts_with('/') || dirfd == ctypes::AT_FDCWD {
+        return Ok(path.into());
+    }
+    let dir = Directory::from_fd(dirfd)?;
+    Ok(format!("{}/{}", dir.path.trim_end_matches('/'), path))
+}
+
+fn open_path(path: &str, flags: c_int, mode: ctypes::mode_t) -> LinuxResult<c_int> {
+    let cloexec = flags as u32 & ctypes::O_CLOEXEC != 0;
+    #[cfg(feature = "pipe")]
+    if let Some(fifo) = super::pipe::open_fifo(path, flags)? {
+        return super::fd_ops::add_file_like_cloexec(Arc::new(fifo), cloexec);
+    }
+    if flags as u32 & ctypes::O_DIRECTORY != 0 {
+        return Directory::open(path)?.add_to_fd_table(cloexec);
+    }
+    let options = flags_to_options(flags, mode);
+    let file = axfs::fops::File::open(path, &options)?;
+    // Directories can only be opened for reading, which gives a directory fd.
+    if file.get_attr()?.is_dir() {
+        drop(file);
+        return Directory::open(path)?.add_to_fd_table(cloexec);
+    }
+    File::new(file).add_to_fd_table(cloexec)
+}
+
+fn stat_path(path: &str) -> LinuxResult<ctypes::stat> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    let file = axfs::fops::File::open(path, &options)?;
+    File::new(file).stat()
+}
+
 /// Open a file by `filename` and insert it into the file descriptor table.
 ///
 /// Return its index in the file table (`fd`). Return `EMFILE` if it already
@@ -109,10 +233,24 @@ fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
 pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) -> c_int {
     let filename = char_ptr_to_str(filename);
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
-    syscall_body!(sys_open, {
-        let options = flags_to_options(flags, mode);
-        let file = axfs::fops::File::open(filename?, &options)?;
-        File::new(file).add_to_fd_table()
+    syscall_body!(sys_open, open_path(filename?, flags, mode))
+}
+
+/// Open a file by `filename` relative to the directory `dirfd`, like
+/// [`sys_open`].
+pub fn sys_openat(
+    dirfd: c_int,
+    filename: *const c_char,
+    flags: c_int,
+    mode: ctypes::mode_t,
+) -> c_int {
+    let filename = char_ptr_to_str(filename);
+    debug!(
+        "sys_openat <= {} {:?} {:#o} {:#o}",
+        dirfd, filename, flags, mode
+    );
+    syscall_body!(sys_openat, {
+        open_path(&resolve_at(dirfd, filename?)?, flags, mode)
     })
 }
 
@@ -128,7 +266,10 @@ pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off
             2 => SeekFrom::End(offset as _),
             _ => return Err(LinuxError::EINVAL),
         };
-        let off = File::from_fd(fd)?.inner.lock().seek(pos)?;
+        let off = match Directory::from_fd(fd) {
+            Ok(dir) => dir.seek(pos)?,
+            Err(_) => File::from_fd(fd)?.inner.lock().seek(pos)?,
+        };
         Ok(off)
     })
 }
@@ -143,11 +284,7 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let mut options = OpenOptions::new();
-        options.read(true);
-        let file = axfs::fops::File::open(path?, &options)?;
-        let st = File::new(file).stat()?;
-        unsafe { *buf = st };
+        unsafe { *buf = stat_path(path?)? };
         Ok(0)
     })
 }
@@ -167,6 +304,45 @@ pub unsafe fn sys_fstat(fd: c_int, buf: *mut ctypes::stat) -> c_int {
     })
 }
 
+/// Get the metadata of the file at `path` relative to the directory `dirfd`
+/// and write into `buf`.
+///
+/// With `AT_EMPTY_PATH` and an empty `path`, get the metadata of `dirfd`
+/// itself. Return 0 if success.
+pub unsafe fn sys_fstatat(
+    dirfd: c_int,
+    path: *const c_char,
+    buf: *mut ctypes::stat,
+    flags: c_int,
+) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!(
+        "sys_fstatat <= {} {:?} {:#x} {:#x}",
+        dirfd, path, buf as usize, flags
+    );
+    syscall_body!(sys_fstatat, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let path = path?;
+        let flags = flags as u32;
+        if flags & !(ctypes::AT_EMPTY_PATH | ctypes::AT_SYMLINK_NOFOLLOW) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let st = if path.is_empty() && flags & ctypes::AT_EMPTY_PATH != 0 {
+            if dirfd == ctypes::AT_FDCWD {
+                stat_path(".")?
+            } else {
+                get_file_like(dirfd)?.stat()?
+            }
+        } else {
+            stat_path(&resolve_at(dirfd, path)?)?
+        };
+        unsafe { *buf = st };
+        Ok(0)
+    })
+}
+
 /// Get the metadata of the symbolic link and write into `buf`.
 ///
 /// Return 0 if success.
@@ -216,3 +392,293 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
         Ok(0)
     })
 }
+
+/// Rename `old` relative to the directory `old_dirfd` to `new` relative to
+/// the directory `new_dirfd`, like [`sys_rename`].
+pub fn sys_renameat(
+    old_dirfd: c_int,
+    old: *const c_char,
+    new_dirfd: c_int,
+    new: *const c_char,
+) -> c_int {
+    syscall_body!(sys_renameat, {
+        let old_path = char_ptr_to_str(old)?;
+        let new_path = char_ptr_to_str(new)?;
+        debug!(
+            "sys_renameat <= old: {} {:?}, new: {} {:?}",
+            old_dirfd, old_path, new_dirfd, new_path
+        );
+        axfs::api::rename(
+            &resolve_at(old_dirfd, old_path)?,
+            &resolve_at(new_dirfd, new_path)?,
+        )?;
+        Ok(0)
+    })
+}
+
+/// Remove the file at `path`.
+///
+/// Return 0 if success.
+pub fn sys_unlink(path: *const c_char) -> c_int {
+    sys_unlinkat(ctypes::AT_FDCWD, path, 0)
+}
+
+/// Remove the file, or the empty directory with `AT_REMOVEDIR`, at `path`
+/// relative to the directory `dirfd`.
+///
+/// Return 0 if success.
+pub fn sys_unlinkat(dirfd: c_int, path: *const c_char, flags: c_int) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_unlinkat <= {} {:?} {:#x}", dirfd, path, flags);
+    syscall_body!(sys_unlinkat, {
+        let path = resolve_at(dirfd, path?)?;
+        match flags as u32 {
+            0 => axfs::api::remove_file(&path)?,
+            ctypes::AT_REMOVEDIR => axfs::api::remove_dir(&path)?,
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
+
+/// Remove the empty directory at `path`.
+///
+/// Return 0 if success.
+pub fn sys_rmdir(path: *const c_char) -> c_int {
+    sys_unlinkat(ctypes::AT_FDCWD, path, ctypes::AT_REMOVEDIR as _)
+}
+
+/// Create a directory at `path`.
+///
+/// Return 0 if success.
+pub fn sys_mkdir(path: *const c_char, mode: ctypes::mode_t) -> c_int {
+    sys_mkdirat(ctypes::AT_FDCWD, path, mode)
+}
+
+/// Create a directory at `path` relative to the directory `dirfd`.
+///
+/// Return 0 if success.
+pub fn sys_mkdirat(dirfd: c_int, path: *const c_char, mode: ctypes::mode_t) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_mkdirat <= {} {:?} {:#o}", dirfd, path, mode);
+    syscall_body!(sys_mkdirat, {
+        let path = resolve_at(dirfd, path?)?;
+        axfs::api::DirBuilder::new()
+            .mode(create_perm(mode).bits() as u32)
+            .create(&path)?;
+        Ok(0)
+    })
+}
+
+/// Check whether the file at `path` can be accessed with `mode`, a mask of
+/// `R_OK`, `W_OK` and `X_OK`, or exists if `mode` is `F_OK`.
+///
+/// Return 0 if success.
+pub fn sys_access(path: *const c_char, mode: c_int) -> c_int {
+    sys_faccessat(ctypes::AT_FDCWD, path, mode, 0)
+}
+
+/// Check whether the file at `path` relative to the directory `dirfd` can be
+/// accessed with `mode`, as [`sys_access`].
+///
+/// `flags` may contain `AT_EACCESS` and `AT_SYMLINK_NOFOLLOW`, which make no
+/// difference here.
+///
+/// Return 0 if success.
+pub fn sys_faccessat(dirfd: c_int, path: *const c_char, mode: c_int, flags: c_int) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!(
+        "sys_faccessat <= {} {:?} {:#o} {:#x}",
+        dirfd, path, mode, flags
+    );
+    syscall_body!(sys_faccessat, {
+        let flags = flags as u32;
+        if flags & !(ctypes::AT_EACCESS | ctypes::AT_SYMLINK_NOFOLLOW) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let st = stat_path(&resolve_at(dirfd, path?)?)?;
+        let mode = mode as u32;
+        if mode & !0o7 != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        // Everything runs as the owner of the files.
+        if (st.st_mode >> 6) & mode != mode {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(0)
+    })
+}
+
+/// Change the current directory to `path`.
+///
+/// Return 0 if success.
+pub fn sys_chdir(path: *const c_char) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_chdir <= {:?}", path);
+    syscall_body!(sys_chdir, {
+        axfs::api::set_current_dir(path?)?;
+        Ok(0)
+    })
+}
+
+/// Change the current directory to the directory indicated by `fd`.
+///
+/// Return 0 if success.
+pub fn sys_fchdir(fd: c_int) -> c_int {
+    debug!("sys_fchdir <= {}", fd);
+    syscall_body!(sys_fchdir, {
+        axfs::api::set_current_dir(&Directory::from_fd(fd)?.path)?;
+        Ok(0)
+    })
+}
+
+/// Truncate or extend the file at `path` to `length` bytes.
+///
+/// Return 0 if success.
+pub fn sys_truncate(path: *const c_char, length: ctypes::off_t) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_truncate <= {:?} {}", path, length);
+    syscall_body!(sys_truncate, {
+        if length < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut options = OpenOptions::new();
+        options.write(true);
+        axfs::fops::File::open(path?, &options)?.truncate(length as _)?;
+        Ok(0)
+    })
+}
+
+/// Truncate or extend the file indicated by `fd` to `length` bytes.
+///
+/// Return 0 if success.
+pub fn sys_ftruncate(fd: c_int, length: ctypes::off_t) -> c_int {
+    debug!("sys_ftruncate <= {} {}", fd, length);
+    syscall_body!(sys_ftruncate, {
+        if length < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        File::from_fd(fd)?.inner.lock().truncate(length as _)?;
+        Ok(0)
+    })
+}
+
+fn sync_fd(fd: c_int) -> LinuxResult<c_int> {
+    match File::from_fd(fd)?.inner.lock().flush() {
+        // Nothing can be dirty through a read-only descriptor.
+        Ok(()) | Err(AxError::PermissionDenied) => Ok(0),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write the data and metadata of the file indicated by `fd` to the
+/// underlying device.
+///
+/// Return 0 if success.
+pub fn sys_fsync(fd: c_int) -> c_int {
+    debug!("sys_fsync <= {}", fd);
+    syscall_body!(sys_fsync, sync_fd(fd))
+}
+
+/// Write the data of the file indicated by `fd` to the underlying device.
+///
+/// Return 0 if success.
+pub fn sys_fdatasync(fd: c_int) -> c_int {
+    debug!("sys_fdatasync <= {}", fd);
+    syscall_body!(sys_fdatasync, sync_fd(fd))
+}
+
+/// Change the permission bits of the file at `path`.
+///
+/// The set-user-ID, set-group-ID and sticky bits of `mode` are ignored. Return
+/// 0 if success.
+pub fn sys_chmod(path: *const c_char, mode: ctypes::mode_t) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_chmod <= {:?} {:#o}", path, mode);
+    syscall_body!(sys_chmod, {
+        axfs::api::set_permissions(path?, mode_to_perm(mode))?;
+        Ok(0)
+    })
+}
+
+/// Change the permission bits of the file or directory indicated by `fd`.
+///
+/// Like [`sys_chmod`]. Return `EINVAL` if `fd` is neither. Return 0 if success.
+pub fn sys_fchmod(fd: c_int, mode: ctypes::mode_t) -> c_int {
+    debug!("sys_fchmod <= {} {:#o}", fd, mode);
+    syscall_body!(sys_fchmod, {
+        let perm = mode_to_perm(mode);
+        let f = get_file_like(fd)?.into_any();
+        if let Some(file) = f.downcast_ref::<File>() {
+            file.inner.lock().set_perm(perm)?;
+        } else if let Some(dir) = f.downcast_ref::<Directory>() {
+            axfs::api::set_permissions(&dir.path, perm)?;
+        } else {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(0)
+    })
+}
+
+/// The file mode creation mask.
+static UMASK: AtomicU32 = AtomicU32::new(0o022);
+
+/// Set the file mode creation mask to `mask`.
+///
+/// Return the previous mask.
+pub fn sys_umask(mask: ctypes::mode_t) -> ctypes::mode_t {
+    debug!("sys_umask <= {:#o}", mask);
+    UMASK.swap(mask & 0o777, Ordering::Relaxed)
+}
+
+/// Read the entries of the directory indicated by `fd` into `dirp` as
+/// `struct linux_dirent64` records, starting from its current position.
+///
+/// Return the number of bytes written, or 0 at the end of the directory.
+pub unsafe fn sys_getdents64(fd: c_int, dirp: *mut u8, count: usize) -> ctypes::ssize_t {
+    debug!("sys_getdents64 <= {} {:#x} {}", fd, dirp as usize, count);
+    syscall_body!(sys_getdents64, {
+        if dirp.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        // d_ino, d_off, d_reclen and d_type, followed by the name.
+        const HEADER_LEN: usize = 8 + 8 + 2 + 1;
+
+        let dir = Directory::from_fd(fd)?;
+        let mut inner = dir.inner.lock();
+        let buf = unsafe { core::slice::from_raw_parts_mut(dirp, count) };
+        let mut entries = [const { DirEntry::default() }; 16];
+        let mut written = 0;
+        let mut full = false;
+        'outer: loop {
+            let start = inner.entry_index();
+            let n = inner.read_dir(&mut entries)?;
+            if n == 0 {
+                break;
+            }
+            for (i, entry) in entries[..n].iter().enumerate() {
+                let name = entry.name_as_bytes();
+                let reclen = (HEADER_LEN + name.len() + 1).next_multiple_of(8);
+                if written + reclen > count {
+                    // Leave the rest for the next call.
+                    inner.set_entry_index(start + i);
+                    full = true;
+                    break 'outer;
+                }
+                let next = (start + i + 1) as u64;
+                let rec = &mut buf[written..written + reclen];
+                rec[..8].copy_from_slice(&next.to_ne_bytes()); // d_ino, never 0
+                rec[8..16].copy_from_slice(&next.to_ne_bytes()); // d_off
+                rec[16..18].copy_from_slice(&(reclen as u16).to_ne_bytes());
+                rec[18] = entry.entry_type() as u8;
+                rec[HEADER_LEN..HEADER_LEN + name.len()].copy_from_slice(name);
+                rec[HEADER_LEN + name.len()..].fill(0);
+                written += reclen;
+            }
+        }
+        if full && written == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(written as ctypes::ssize_t)
+    })
+}
@@ -37,7 +37,12 @@ fn write_impl(fd: c_int, buf: *const c_void, count: usize) -> LinuxResult<ctypes
     let src = unsafe { core::slice::from_raw_parts(buf as *const u8, count) };
     #[cfg(feature = "fd")]
     {
-        Ok(get_file_like(fd)?.write(src)? as ctypes::ssize_t)
+        let res = get_file_like(fd)?.write(src);
+        #[cfg(feature = "signal")]
+        if let Err(e) = &res {
+            super::signal::sigpipe_on(e);
+        }
+        Ok(res? as ctypes::ssize_t)
     }
     #[cfg(not(feature = "fd"))]
     match fd {
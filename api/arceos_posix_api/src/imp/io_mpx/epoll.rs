@@ -1,21 +1,95 @@
 //! `epoll` implementation.
 //!
-//! TODO: do not support `EPOLLET` flag
+//! Files that wake pollers (see [`FileLike::register_poller`]) put their fds
+//! on the ready list of the instance when their readiness may change, and
+//! only the fds on the list are polled. Other files are polled on every
+//! `epoll_wait`, so `EPOLLET` falls back to level-triggered for them.
 
-use alloc::collections::BTreeMap;
 use alloc::collections::btree_map::Entry;
-use alloc::sync::Arc;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use core::{ffi::c_int, time::Duration};
 
 use axerrno::{LinuxError, LinuxResult};
 use axhal::time::wall_time;
 use axsync::Mutex;
 
+use super::wait_events;
 use crate::ctypes;
 use crate::imp::fd_ops::{FileLike, add_file_like, get_file_like};
+use crate::imp::poll_set::{PollWaker, Poller};
+
+/// The events always reported, even if not requested.
+const EPOLL_ALWAYS: u32 = ctypes::EPOLLERR | ctypes::EPOLLHUP;
+
+/// The fds that may be ready, and the poller of the waiting tasks.
+struct ReadyList {
+    fds: Mutex<BTreeSet<c_int>>,
+    poller: Poller,
+}
+
+impl PollWaker for ReadyList {
+    fn wake(&self) {
+        self.poller.wake();
+    }
+}
+
+/// Puts the fd of an interest on the ready list when its file wakes it.
+struct ReadyWaker {
+    fd: c_int,
+    ready: Arc<ReadyList>,
+}
+
+impl PollWaker for ReadyWaker {
+    fn wake(&self) {
+        self.ready.fds.lock().insert(self.fd);
+        self.ready.poller.wake();
+    }
+}
+
+/// A file registered in an epoll instance.
+struct Interest {
+    file: Weak<dyn FileLike>,
+    event: ctypes::epoll_event,
+    /// The registration in the file, `None` if the file is polled repeatedly.
+    waker: Option<Arc<dyn PollWaker>>,
+    /// Set after an event of an `EPOLLONESHOT` interest is reported, until
+    /// the interest is modified.
+    disabled: bool,
+}
+
+impl Interest {
+    fn is_polled(&self) -> bool {
+        self.waker.is_none()
+    }
+
+    /// Returns the events to report, or `None` if the file has been closed.
+    fn poll(&self) -> Option<u32> {
+        let file = self.file.upgrade()?;
+        if self.disabled {
+            return Some(0);
+        }
+        let events = match file.poll() {
+            Ok(state) => {
+                let mut events = 0;
+                if state.readable {
+                    events |= ctypes::EPOLLIN;
+                }
+                if state.writable {
+                    events |= ctypes::EPOLLOUT;
+                }
+                events
+            }
+            Err(_) => ctypes::EPOLLERR,
+        };
+        Some(events & (self.event.events | EPOLL_ALWAYS))
+    }
+}
 
 pub struct EpollInstance {
-    events: Mutex<BTreeMap<usize, ctypes::epoll_event>>,
+    interests: Mutex<BTreeMap<c_int, Interest>>,
+    ready: Arc<ReadyList>,
 }
 
 unsafe impl Send for ctypes::epoll_event {}
@@ -25,7 +99,11 @@ impl EpollInstance {
     // TODO: parse flags
     pub fn new(_flags: usize) -> Self {
         Self {
-            events: Mutex::new(BTreeMap::new()),
+            interests: Mutex::new(BTreeMap::new()),
+            ready: Arc::new(ReadyList {
+                fds: Mutex::new(BTreeSet::new()),
+                poller: Poller::new(),
+            }),
         }
     }
 
@@ -36,72 +114,111 @@ impl EpollInstance {
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    fn control(&self, op: usize, fd: usize, event: &ctypes::epoll_event) -> LinuxResult<usize> {
-        match get_file_like(fd as c_int) {
-            Ok(_) => {}
-            Err(e) => return Err(e),
+    /// Registers `file` with a new interest, and puts it on the ready list to
+    /// report its current readiness.
+    fn new_interest(
+        &self,
+        fd: c_int,
+        file: &Arc<dyn FileLike>,
+        event: ctypes::epoll_event,
+    ) -> Interest {
+        let waker: Arc<dyn PollWaker> = Arc::new(ReadyWaker {
+            fd,
+            ready: self.ready.clone(),
+        });
+        let registered = file.register_poller(&waker);
+        if registered {
+            waker.wake();
         }
+        Interest {
+            file: Arc::downgrade(file),
+            event,
+            waker: registered.then_some(waker),
+            disabled: false,
+        }
+    }
 
-        match op as u32 {
+    fn control(&self, op: u32, fd: c_int, event: Option<&ctypes::epoll_event>) -> LinuxResult {
+        let file = get_file_like(fd)?;
+        let mut interests = self.interests.lock();
+        match op {
             ctypes::EPOLL_CTL_ADD => {
-                if let Entry::Vacant(e) = self.events.lock().entry(fd) {
-                    e.insert(*event);
-                } else {
-                    return Err(LinuxError::EEXIST);
+                let event = event.ok_or(LinuxError::EFAULT)?;
+                match interests.entry(fd) {
+                    Entry::Vacant(e) => {
+                        e.insert(self.new_interest(fd, &file, *event));
+                    }
+                    Entry::Occupied(_) => return Err(LinuxError::EEXIST),
                 }
             }
             ctypes::EPOLL_CTL_MOD => {
-                let mut events = self.events.lock();
-                if let Entry::Occupied(mut ocp) = events.entry(fd) {
-                    ocp.insert(*event);
-                } else {
-                    return Err(LinuxError::ENOENT);
+                let event = event.ok_or(LinuxError::EFAULT)?;
+                match interests.entry(fd) {
+                    Entry::Occupied(mut e) => {
+                        e.insert(self.new_interest(fd, &file, *event));
+                    }
+                    Entry::Vacant(_) => return Err(LinuxError::ENOENT),
                 }
             }
             ctypes::EPOLL_CTL_DEL => {
-                let mut events = self.events.lock();
-                if let Entry::Occupied(ocp) = events.entry(fd) {
-                    ocp.remove_entry();
-                } else {
-                    return Err(LinuxError::ENOENT);
-                }
-            }
-            _ => {
-                return Err(LinuxError::EINVAL);
+                interests.remove(&fd).ok_or(LinuxError::ENOENT)?;
             }
+            _ => return Err(LinuxError::EINVAL),
         }
-        Ok(0)
+        Ok(())
     }
 
-    fn poll_all(&self, events: &mut [ctypes::epoll_event]) -> LinuxResult<usize> {
-        let ready_list = self.events.lock();
+    /// Polls the fds on the ready list and the files polled repeatedly, and
+    /// writes at most `events.len()` events.
+    ///
+    /// Returns the number of events, and whether any file has to be polled
+    /// repeatedly.
+    fn poll_all(&self, events: &mut [ctypes::epoll_event]) -> (usize, bool) {
+        let mut interests = self.interests.lock();
+        let mut ready = core::mem::take(&mut *self.ready.fds.lock());
+        let mut requeue = Vec::new();
+        let mut closed = Vec::new();
+        let mut polled = false;
         let mut events_num = 0;
 
-        for (infd, ev) in ready_list.iter() {
-            match get_file_like(*infd as c_int)?.poll() {
-                Err(_) => {
-                    if (ev.events & ctypes::EPOLLERR) != 0 {
-                        events[events_num].events = ctypes::EPOLLERR;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-                }
-                Ok(state) => {
-                    if state.readable && (ev.events & ctypes::EPOLLIN != 0) {
-                        events[events_num].events = ctypes::EPOLLIN;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
+        for (&fd, interest) in interests.iter_mut() {
+            if events_num == events.len() {
+                break;
+            }
+            if interest.is_polled() {
+                polled |= !interest.disabled;
+            } else if !ready.remove(&fd) {
+                continue;
+            }
+            let Some(revents) = interest.poll() else {
+                closed.push(fd);
+                continue;
+            };
+            if revents == 0 {
+                continue;
+            }
+            events[events_num].events = revents;
+            events[events_num].data = interest.event.data;
+            events_num += 1;
 
-                    if state.writable && (ev.events & ctypes::EPOLLOUT != 0) {
-                        events[events_num].events = ctypes::EPOLLOUT;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-                }
+            let flags = interest.event.events;
+            if flags & ctypes::EPOLLONESHOT != 0 {
+                interest.disabled = true;
+            } else if flags & ctypes::EPOLLET == 0 && !interest.is_polled() {
+                // Level-triggered, so poll it again next time.
+                requeue.push(fd);
             }
         }
-        Ok(events_num)
+        for fd in closed {
+            interests.remove(&fd);
+        }
+
+        // Keep the fds not polled because `events` is full.
+        ready.retain(|fd| interests.contains_key(fd));
+        let mut ready_fds = self.ready.fds.lock();
+        ready_fds.extend(ready);
+        ready_fds.extend(requeue);
+        (events_num, polled)
     }
 }
 
@@ -152,6 +269,9 @@ pub fn sys_epoll_create(size: c_int) -> c_int {
 }
 
 /// Control interface for an epoll file descriptor
+///
+/// `EPOLLET` and `EPOLLONESHOT` are supported in `event`, which may be null
+/// for `EPOLL_CTL_DEL`.
 pub unsafe fn sys_epoll_ctl(
     epfd: c_int,
     op: c_int,
@@ -160,10 +280,12 @@ pub unsafe fn sys_epoll_ctl(
 ) -> c_int {
     debug!("sys_epoll_ctl <= epfd: {} op: {} fd: {}", epfd, op, fd);
     syscall_body!(sys_epoll_ctl, {
-        let ret = unsafe {
-            EpollInstance::from_fd(epfd)?.control(op as usize, fd as usize, &(*event))? as c_int
-        };
-        Ok(ret)
+        if epfd == fd {
+            return Err(LinuxError::EINVAL);
+        }
+        let event = unsafe { event.as_ref() };
+        EpollInstance::from_fd(epfd)?.control(op as u32, fd, event)?;
+        Ok(0)
     })
 }
 
@@ -187,10 +309,15 @@ pub unsafe fn sys_epoll_wait(
         let deadline =
             (!timeout.is_negative()).then(|| wall_time() + Duration::from_millis(timeout as u64));
         let epoll_instance = EpollInstance::from_fd(epfd)?;
+        #[cfg(feature = "signal")]
+        let _guard = {
+            let waker: Arc<dyn PollWaker> = epoll_instance.ready.clone();
+            crate::imp::signal::interrupt_on(&waker)
+        };
         loop {
             #[cfg(feature = "net")]
             axnet::poll_interfaces();
-            let events_num = epoll_instance.poll_all(events)?;
+            let (events_num, polled) = epoll_instance.poll_all(events);
             if events_num > 0 {
                 return Ok(events_num as c_int);
             }
@@ -199,7 +326,7 @@ pub unsafe fn sys_epoll_wait(
                 debug!("    timeout!");
                 return Ok(0);
             }
-            crate::sys_sched_yield();
+            wait_events(&epoll_instance.ready.poller, polled, deadline)?;
         }
     })
 }
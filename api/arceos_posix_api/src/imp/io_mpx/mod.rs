@@ -1,16 +1,46 @@
 //! I/O multiplexing:
 //!
 //! * [`select`](select::sys_select)
+//! * [`poll`](poll::sys_poll)
+//! * [`ppoll`](poll::sys_ppoll)
 //! * [`epoll_create`](epoll::sys_epoll_create)
 //! * [`epoll_ctl`](epoll::sys_epoll_ctl)
 //! * [`epoll_wait`](epoll::sys_epoll_wait)
 
 #[cfg(feature = "epoll")]
 mod epoll;
+#[cfg(feature = "poll")]
+mod poll;
 #[cfg(feature = "select")]
 mod select;
 
 #[cfg(feature = "epoll")]
 pub use self::epoll::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+#[cfg(feature = "poll")]
+pub use self::poll::{sys_poll, sys_ppoll};
 #[cfg(feature = "select")]
 pub use self::select::sys_select;
+
+use axerrno::LinuxResult;
+use axhal::time::{TimeValue, wall_time};
+
+use super::poll_set::Poller;
+
+/// Waits until `poller` is woken or the deadline is reached.
+///
+/// If some of the files have to be `polled` repeatedly, it only yields the
+/// CPU and the caller polls again.
+///
+/// With the `signal` feature, fails with `EINTR` if a signal handler is
+/// called, regardless of `SA_RESTART`. The caller registers `poller` with
+/// `signal::interrupt_on` to be woken by signals.
+fn wait_events(poller: &Poller, polled: bool, deadline: Option<TimeValue>) -> LinuxResult {
+    #[cfg(feature = "signal")]
+    super::signal::interrupted(false)?;
+    if polled {
+        crate::sys_sched_yield();
+    } else {
+        poller.wait(deadline.map(|ddl| ddl.saturating_sub(wall_time())));
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! `poll` implementation.
+
+use alloc::sync::Arc;
+use core::ffi::{c_int, c_short};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{TimeValue, wall_time};
+
+use super::wait_events;
+use crate::ctypes;
+use crate::imp::fd_ops::{AX_FILE_LIMIT, get_file_like};
+use crate::imp::poll_set::{PollWaker, Poller};
+
+/// The events always reported, even if not requested.
+const POLL_ALWAYS: u32 = ctypes::POLLERR | ctypes::POLLHUP | ctypes::POLLNVAL;
+
+/// Polls all the fds and sets their `revents`. Returns the number of fds with
+/// events.
+fn poll_all(fds: &mut [ctypes::pollfd]) -> usize {
+    let mut events_num = 0;
+    for pfd in fds.iter_mut() {
+        // Negative fds are ignored.
+        if pfd.fd < 0 {
+            pfd.revents = 0;
+            continue;
+        }
+        let revents = match get_file_like(pfd.fd).map(|f| f.poll()) {
+            Ok(Ok(state)) => {
+                let mut revents = 0;
+                if state.readable {
+                    revents |= ctypes::POLLIN;
+                }
+                if state.writable {
+                    revents |= ctypes::POLLOUT;
+                }
+                revents
+            }
+            Ok(Err(_)) => ctypes::POLLERR,
+            Err(_) => ctypes::POLLNVAL,
+        };
+        let revents = revents & (pfd.events as u16 as u32 | POLL_ALWAYS);
+        pfd.revents = revents as c_short;
+        if revents != 0 {
+            events_num += 1;
+        }
+    }
+    events_num
+}
+
+fn poll_until(fds: &mut [ctypes::pollfd], deadline: Option<TimeValue>) -> LinuxResult<c_int> {
+    let poller = Arc::new(Poller::new());
+    let waker: Arc<dyn PollWaker> = poller.clone();
+    let mut polled = false;
+    for pfd in fds.iter() {
+        if let Ok(f) = get_file_like(pfd.fd) {
+            polled |= !f.register_poller(&waker);
+        }
+    }
+    #[cfg(feature = "signal")]
+    let _guard = crate::imp::signal::interrupt_on(&waker);
+
+    loop {
+        #[cfg(feature = "net")]
+        axnet::poll_interfaces();
+        let events_num = poll_all(fds);
+        if events_num > 0 {
+            return Ok(events_num as c_int);
+        }
+
+        if deadline.is_some_and(|ddl| wall_time() >= ddl) {
+            debug!("    timeout!");
+            return Ok(0);
+        }
+        wait_events(&poller, polled, deadline)?;
+    }
+}
+
+unsafe fn pollfds<'a>(
+    fds: *mut ctypes::pollfd,
+    nfds: ctypes::nfds_t,
+) -> LinuxResult<&'a mut [ctypes::pollfd]> {
+    if nfds as usize > AX_FILE_LIMIT {
+        return Err(LinuxError::EINVAL);
+    }
+    if nfds == 0 {
+        return Ok(&mut []);
+    }
+    if fds.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(unsafe { core::slice::from_raw_parts_mut(fds, nfds as usize) })
+}
+
+/// Waits for one of a set of file descriptors to become ready to perform I/O.
+///
+/// A negative `timeout` means an infinite timeout.
+pub unsafe fn sys_poll(fds: *mut ctypes::pollfd, nfds: ctypes::nfds_t, timeout: c_int) -> c_int {
+    debug!("sys_poll <= nfds: {}, timeout: {}", nfds, timeout);
+    syscall_body!(sys_poll, {
+        let fds = unsafe { pollfds(fds, nfds)? };
+        let deadline =
+            (!timeout.is_negative()).then(|| wall_time() + Duration::from_millis(timeout as u64));
+        poll_until(fds, deadline)
+    })
+}
+
+/// Like [`sys_poll`], but the timeout is given as a `timespec`, and a null
+/// `timeout` means an infinite timeout.
+///
+/// If `sigmask` is not null, the signal mask of the thread is replaced with
+/// it while waiting. Without the `signal` feature, it is ignored.
+pub unsafe fn sys_ppoll(
+    fds: *mut ctypes::pollfd,
+    nfds: ctypes::nfds_t,
+    timeout: *const ctypes::timespec,
+    sigmask: *const ctypes::sigset_t,
+) -> c_int {
+    debug!(
+        "sys_ppoll <= nfds: {}, timeout: {:#x}, sigmask: {:#x}",
+        nfds, timeout as usize, sigmask as usize
+    );
+    syscall_body!(sys_ppoll, {
+        let fds = unsafe { pollfds(fds, nfds)? };
+        let deadline = unsafe { timeout.as_ref().map(|ts| wall_time() + (*ts).into()) };
+        #[cfg(feature = "signal")]
+        let _mask = unsafe { sigmask.as_ref() }.map(crate::imp::signal::replace_mask);
+        #[cfg(not(feature = "signal"))]
+        let _ = sigmask;
+        poll_until(fds, deadline)
+    })
+}
@@ -1,8 +1,11 @@
+use alloc::sync::Arc;
 use core::ffi::c_int;
 
 use axerrno::{LinuxError, LinuxResult};
 use axhal::time::wall_time;
 
+use super::wait_events;
+use crate::imp::poll_set::{PollWaker, Poller};
 use crate::{ctypes, imp::fd_ops::get_file_like};
 
 const FD_SETSIZE: usize = 1024;
@@ -46,6 +49,25 @@ impl FdSets {
         Self { nfds, bits }
     }
 
+    /// Registers `waker` to all the fds in the sets, and returns whether any
+    /// of them has to be polled repeatedly.
+    fn register_poller(&self, waker: &Arc<dyn PollWaker>) -> bool {
+        let mut polled = false;
+        for fd in 0..self.nfds {
+            let (i, bit) = (fd / BITS_PER_USIZE, 1 << (fd % BITS_PER_USIZE));
+            let all_bits = self.bits[i]
+                | self.bits[i + FD_SETSIZE_USIZES]
+                | self.bits[i + FD_SETSIZE_USIZES * 2];
+            if all_bits & bit == 0 {
+                continue;
+            }
+            if let Ok(f) = get_file_like(fd as _) {
+                polled |= !f.register_poller(waker);
+            }
+        }
+        polled
+    }
+
     fn poll_all(
         &self,
         res_read_fds: *mut ctypes::fd_set,
@@ -133,6 +155,12 @@ pub unsafe fn sys_select(
             zero_fd_set(exceptfds, nfds);
         }
 
+        let poller = Arc::new(Poller::new());
+        let waker: Arc<dyn PollWaker> = poller.clone();
+        let polled = fd_sets.register_poller(&waker);
+        #[cfg(feature = "signal")]
+        let _guard = crate::imp::signal::interrupt_on(&waker);
+
         loop {
             #[cfg(feature = "net")]
             axnet::poll_interfaces();
@@ -145,7 +173,7 @@ pub unsafe fn sys_select(
                 debug!("    timeout!");
                 return Ok(0);
             }
-            crate::sys_sched_yield();
+            wait_events(&poller, polled, deadline)?;
         }
     })
 }
@@ -6,15 +6,29 @@ pub mod sys;
 pub mod task;
 pub mod time;
 
+#[cfg(feature = "eventfd")]
+pub mod eventfd;
 #[cfg(feature = "fd")]
 pub mod fd_ops;
 #[cfg(feature = "fs")]
 pub mod fs;
-#[cfg(any(feature = "select", feature = "epoll"))]
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 pub mod io_mpx;
 #[cfg(feature = "net")]
 pub mod net;
 #[cfg(feature = "pipe")]
 pub mod pipe;
+#[cfg(any(feature = "fd", feature = "signal"))]
+pub mod poll_set;
+#[cfg(feature = "uspace")]
+pub mod process;
 #[cfg(feature = "multitask")]
 pub mod pthread;
+#[cfg(feature = "signal")]
+pub mod signal;
+#[cfg(feature = "signalfd")]
+pub mod signalfd;
+#[cfg(feature = "timerfd")]
+pub mod timerfd;
+#[cfg(feature = "net")]
+pub mod unix;
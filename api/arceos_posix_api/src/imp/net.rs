@@ -1,25 +1,83 @@
 use alloc::{sync::Arc, vec, vec::Vec};
-use core::ffi::{c_char, c_int, c_void};
+use core::ffi::{CStr, c_char, c_int, c_void};
 use core::mem::size_of;
-use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+#[cfg(feature = "multitask")]
+use core::task::Waker;
+use core::time::Duration;
 
-use axerrno::{LinuxError, LinuxResult};
+use axerrno::{AxError, LinuxError, LinuxResult};
 use axio::PollState;
-use axnet::{TcpSocket, UdpSocket};
+use axnet::{CongestionControl, IcmpSocket, RawSocket, TcpSocket, TcpState, UdpSocket};
 use axsync::Mutex;
 
-use super::fd_ops::FileLike;
+use super::fd_ops::{FileLike, add_file_like, close_file_like, get_file_like};
+#[cfg(feature = "multitask")]
+use super::poll_set::PollSet;
+use super::poll_set::PollWaker;
+use super::unix::{SCM_MAX_FD, UnixAddr, UnixSocket};
 use crate::ctypes;
 use crate::utils::char_ptr_to_str;
 
-pub enum Socket {
+pub struct Socket {
+    /// The address family, `AF_INET`, `AF_INET6` or `AF_UNIX`.
+    domain: u32,
+    inner: SocketInner,
+    /// The pollers of a TCP or UDP socket, woken by the events of its smoltcp
+    /// sockets through `waker`.
+    #[cfg(feature = "multitask")]
+    poll_set: Arc<PollSet>,
+    #[cfg(feature = "multitask")]
+    waker: Waker,
+}
+
+enum SocketInner {
     Udp(Mutex<UdpSocket>),
     Tcp(Mutex<TcpSocket>),
+    /// An ICMP echo (ping) socket, with the ICMP identifier as the port.
+    Icmp(Mutex<IcmpSocket>),
+    Raw(Mutex<RawSocket>),
+    Unix(UnixSocket),
+}
+
+/// A socket address of the `AF_INET`, `AF_INET6` or `AF_UNIX` family.
+#[derive(Debug, Clone)]
+enum SockAddr {
+    Inet(SocketAddr),
+    Unix(UnixAddr),
+}
+
+impl From<SocketAddr> for SockAddr {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Inet(addr)
+    }
+}
+
+impl SockAddr {
+    fn into_unix(self) -> LinuxResult<UnixAddr> {
+        match self {
+            Self::Unix(addr) => Ok(addr),
+            Self::Inet(_) => Err(LinuxError::EINVAL),
+        }
+    }
 }
 
 impl Socket {
-    fn add_to_fd_table(self) -> LinuxResult<c_int> {
-        super::fd_ops::add_file_like(Arc::new(self))
+    fn new(domain: u32, inner: SocketInner) -> Self {
+        #[cfg(feature = "multitask")]
+        let poll_set = Arc::new(PollSet::new());
+        Self {
+            domain,
+            inner,
+            #[cfg(feature = "multitask")]
+            waker: Waker::from(poll_set.clone()),
+            #[cfg(feature = "multitask")]
+            poll_set,
+        }
+    }
+
+    fn add_to_fd_table(self, cloexec: bool) -> LinuxResult<c_int> {
+        super::fd_ops::add_file_like_cloexec(Arc::new(self), cloexec)
     }
 
     fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
@@ -30,102 +88,480 @@ impl Socket {
     }
 
     fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
+            SocketInner::Icmp(_) | SocketInner::Raw(_) => Err(LinuxError::EDESTADDRREQ),
+            SocketInner::Unix(unixsocket) => unixsocket.send_msg(buf, Vec::new(), None),
         }
     }
 
     fn recv(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
+            SocketInner::Icmp(icmpsocket) => Ok(icmpsocket.lock().recv_from(buf).map(|e| e.0)?),
+            SocketInner::Raw(rawsocket) => Ok(rawsocket.lock().recv_from(buf).map(|e| e.0)?),
+            SocketInner::Unix(unixsocket) => Ok(unixsocket.recv_msg(buf)?.len),
         }
     }
 
     pub fn poll(&self) -> LinuxResult<PollState> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().poll()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().poll()?),
+        // Registered before polling, so that a later event is not missed.
+        #[cfg(feature = "multitask")]
+        self.register_poll_waker();
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().poll()?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().poll()?),
+            SocketInner::Icmp(icmpsocket) => Ok(icmpsocket.lock().poll()?),
+            SocketInner::Raw(rawsocket) => Ok(rawsocket.lock().poll()?),
+            SocketInner::Unix(unixsocket) => Ok(unixsocket.poll()),
         }
     }
 
-    fn local_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().local_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?),
+    /// Registers the waker of the pollers for the next event of a TCP or UDP
+    /// socket. Returns `false` if the socket has no events to wait for yet,
+    /// or is of another type.
+    #[cfg(feature = "multitask")]
+    fn register_poll_waker(&self) -> bool {
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => udpsocket.lock().register_poll_waker(&self.waker),
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().register_poll_waker(&self.waker),
+            _ => false,
         }
     }
 
-    fn peer_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().peer_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?),
+    fn local_addr(&self) -> LinuxResult<SockAddr> {
+        let addr = match &self.inner {
+            SocketInner::Udp(udpsocket) => udpsocket.lock().local_addr()?,
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().local_addr()?,
+            SocketInner::Icmp(icmpsocket) => {
+                let ident = icmpsocket.lock().ident().unwrap_or(0);
+                SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), ident)
+            }
+            SocketInner::Raw(_) => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+            SocketInner::Unix(unixsocket) => return Ok(SockAddr::Unix(unixsocket.local_addr())),
+        };
+        Ok(self.addr_to_user(addr).into())
+    }
+
+    fn peer_addr(&self) -> LinuxResult<SockAddr> {
+        let addr = match &self.inner {
+            SocketInner::Udp(udpsocket) => udpsocket.lock().peer_addr()?,
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().peer_addr()?,
+            SocketInner::Icmp(_) | SocketInner::Raw(_) => return Err(LinuxError::ENOTCONN),
+            SocketInner::Unix(unixsocket) => return Ok(SockAddr::Unix(unixsocket.peer_addr()?)),
+        };
+        Ok(self.addr_to_user(addr).into())
+    }
+
+    /// Converts an address from the user to the one used by axnet.
+    ///
+    /// An `AF_INET6` socket accepts IPv4-mapped IPv6 addresses, which are
+    /// converted to IPv4 addresses.
+    fn addr_from_user(&self, addr: SockAddr) -> LinuxResult<SocketAddr> {
+        match (self.domain, addr) {
+            (ctypes::AF_INET, SockAddr::Inet(addr @ SocketAddr::V4(_))) => Ok(addr),
+            (ctypes::AF_INET6, SockAddr::Inet(addr @ SocketAddr::V6(v6))) => {
+                match v6.ip().to_ipv4_mapped() {
+                    Some(ip) => Ok(SocketAddr::new(ip.into(), v6.port())),
+                    None => Ok(addr),
+                }
+            }
+            _ => Err(LinuxError::EAFNOSUPPORT),
         }
     }
 
-    fn bind(&self, addr: SocketAddr) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
+    /// Converts an address from axnet to the address family of the socket.
+    ///
+    /// IPv4 addresses are returned as IPv4-mapped IPv6 addresses to an
+    /// `AF_INET6` socket, as it also handles IPv4 traffic.
+    fn addr_to_user(&self, addr: SocketAddr) -> SocketAddr {
+        match (self.domain, addr) {
+            (ctypes::AF_INET6, SocketAddr::V4(v4)) => {
+                let ip = if v4.ip().is_unspecified() {
+                    Ipv6Addr::UNSPECIFIED
+                } else {
+                    v4.ip().to_ipv6_mapped()
+                };
+                SocketAddr::new(ip.into(), v4.port())
+            }
+            _ => addr,
         }
     }
 
-    fn connect(&self, addr: SocketAddr) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr)?),
+    fn bind(&self, addr: SockAddr) -> LinuxResult {
+        if let SocketInner::Unix(unixsocket) = &self.inner {
+            return unixsocket.bind(addr.into_unix()?);
+        }
+        let addr = self.addr_from_user(addr)?;
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
+            SocketInner::Icmp(icmpsocket) => Ok(icmpsocket.lock().bind(addr.port())?),
+            // binding a raw socket to a local address is not supported
+            SocketInner::Raw(_) => Ok(()),
+            SocketInner::Unix(_) => unreachable!(),
         }
     }
 
-    fn sendto(&self, buf: &[u8], addr: SocketAddr) -> LinuxResult<usize> {
-        match self {
+    fn connect(&self, addr: SockAddr) -> LinuxResult {
+        if let SocketInner::Unix(unixsocket) = &self.inner {
+            return unixsocket.connect(addr.into_unix()?);
+        }
+        let addr = self.addr_from_user(addr)?;
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr)?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr)?),
+            SocketInner::Icmp(_) | SocketInner::Raw(_) => Err(LinuxError::EOPNOTSUPP),
+            SocketInner::Unix(_) => unreachable!(),
+        }
+    }
+
+    fn sendto(&self, buf: &[u8], addr: SockAddr) -> LinuxResult<usize> {
+        if let SocketInner::Unix(unixsocket) = &self.inner {
+            return unixsocket.send_msg(buf, Vec::new(), Some(addr.into_unix()?));
+        }
+        let addr = self.addr_from_user(addr)?;
+        match &self.inner {
             // diff: must bind before sendto
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
-            Socket::Tcp(_) => Err(LinuxError::EISCONN),
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
+            SocketInner::Tcp(_) => Err(LinuxError::EISCONN),
+            SocketInner::Icmp(icmpsocket) => Ok(icmpsocket.lock().send_to(buf, addr.ip())?),
+            SocketInner::Raw(rawsocket) => Ok(rawsocket.lock().send_to(buf, addr.ip())?),
+            SocketInner::Unix(_) => unreachable!(),
         }
     }
 
-    fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SocketAddr>)> {
-        match self {
+    fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SockAddr>)> {
+        match &self.inner {
             // diff: must bind before recvfrom
-            Socket::Udp(udpsocket) => Ok(udpsocket
+            SocketInner::Udp(udpsocket) => Ok(udpsocket
                 .lock()
                 .recv_from(buf)
-                .map(|res| (res.0, Some(res.1)))?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
+                .map(|res| (res.0, Some(self.addr_to_user(res.1).into())))?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
+            SocketInner::Icmp(icmpsocket) => {
+                let (len, ip) = icmpsocket.lock().recv_from(buf)?;
+                Ok((len, Some(self.addr_to_user(SocketAddr::new(ip, 0)).into())))
+            }
+            SocketInner::Raw(rawsocket) => {
+                let (len, ip) = rawsocket.lock().recv_from(buf)?;
+                Ok((len, Some(self.addr_to_user(SocketAddr::new(ip, 0)).into())))
+            }
+            SocketInner::Unix(unixsocket) => {
+                let msg = unixsocket.recv_msg(buf)?;
+                Ok((msg.len, msg.from.map(SockAddr::Unix)))
+            }
         }
     }
 
-    fn listen(&self) -> LinuxResult {
-        match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
+    fn listen(&self, backlog: usize) -> LinuxResult {
+        match &self.inner {
+            SocketInner::Udp(_) | SocketInner::Icmp(_) | SocketInner::Raw(_) => {
+                Err(LinuxError::EOPNOTSUPP)
+            }
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen(backlog)?),
+            SocketInner::Unix(unixsocket) => unixsocket.listen(backlog),
         }
     }
 
-    fn accept(&self) -> LinuxResult<TcpSocket> {
-        match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().accept()?),
+    fn accept(&self) -> LinuxResult<Socket> {
+        match &self.inner {
+            SocketInner::Udp(_) | SocketInner::Icmp(_) | SocketInner::Raw(_) => {
+                Err(LinuxError::EOPNOTSUPP)
+            }
+            SocketInner::Tcp(tcpsocket) => {
+                let new_socket = tcpsocket.lock().accept()?;
+                Ok(Socket::new(
+                    self.domain,
+                    SocketInner::Tcp(Mutex::new(new_socket)),
+                ))
+            }
+            SocketInner::Unix(unixsocket) => {
+                let new_socket = unixsocket.accept()?;
+                Ok(Socket::new(self.domain, SocketInner::Unix(new_socket)))
+            }
         }
     }
 
-    fn shutdown(&self) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => {
+    fn shutdown(&self, how: u32) -> LinuxResult {
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => {
                 let udpsocket = udpsocket.lock();
                 udpsocket.peer_addr()?;
                 udpsocket.shutdown()?;
                 Ok(())
             }
 
-            Socket::Tcp(tcpsocket) => {
+            SocketInner::Tcp(tcpsocket) => {
                 let tcpsocket = tcpsocket.lock();
                 tcpsocket.peer_addr()?;
                 tcpsocket.shutdown()?;
                 Ok(())
             }
+
+            SocketInner::Icmp(_) | SocketInner::Raw(_) => Err(LinuxError::ENOTCONN),
+            SocketInner::Unix(unixsocket) => unixsocket.shutdown(how),
+        }
+    }
+
+    fn socktype(&self) -> u32 {
+        match &self.inner {
+            SocketInner::Udp(_) | SocketInner::Icmp(_) => ctypes::SOCK_DGRAM,
+            SocketInner::Tcp(_) => ctypes::SOCK_STREAM,
+            SocketInner::Raw(_) => ctypes::SOCK_RAW,
+            SocketInner::Unix(unixsocket) => unixsocket.socktype(),
+        }
+    }
+
+    fn set_option(&self, level: u32, name: u32, optval: &[u8]) -> LinuxResult {
+        match (level, name) {
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => {
+                let reuse = read_optval::<c_int>(optval)? != 0;
+                match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().set_reuse_address(reuse),
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_reuse_address(reuse),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                let size = read_optval::<c_int>(optval)?.max(0) as usize;
+                match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().set_recv_buffer_size(size)?,
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_recv_buffer_size(size)?,
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                let size = read_optval::<c_int>(optval)?.max(0) as usize;
+                match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().set_send_buffer_size(size)?,
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_send_buffer_size(size)?,
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                let timeout = timeval_to_timeout(read_optval(optval)?)?;
+                match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().set_recv_timeout(timeout)?,
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_recv_timeout(timeout)?,
+                    SocketInner::Icmp(icmpsocket) => icmpsocket.lock().set_recv_timeout(timeout)?,
+                    SocketInner::Raw(rawsocket) => rawsocket.lock().set_recv_timeout(timeout)?,
+                    SocketInner::Unix(_) => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                let timeout = timeval_to_timeout(read_optval(optval)?)?;
+                match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().set_send_timeout(timeout)?,
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_send_timeout(timeout)?,
+                    SocketInner::Icmp(icmpsocket) => icmpsocket.lock().set_send_timeout(timeout)?,
+                    SocketInner::Raw(rawsocket) => rawsocket.lock().set_send_timeout(timeout)?,
+                    SocketInner::Unix(_) => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE) => {
+                let keep_alive = read_optval::<c_int>(optval)? != 0;
+                match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_keep_alive(keep_alive),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_LINGER) => {
+                let linger = read_optval::<ctypes::linger>(optval)?;
+                let linger = (linger.l_onoff != 0)
+                    .then(|| Duration::from_secs(linger.l_linger.max(0) as u64));
+                match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_linger(linger),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_BINDTODEVICE) => {
+                // The name is not necessarily terminated by a NUL.
+                let len = optval.iter().position(|&b| b == 0).unwrap_or(optval.len());
+                let ifname =
+                    core::str::from_utf8(&optval[..len]).map_err(|_| LinuxError::EINVAL)?;
+                let res = match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().bind_to_device(ifname),
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().bind_to_device(ifname),
+                    SocketInner::Icmp(icmpsocket) => icmpsocket.lock().bind_to_device(ifname),
+                    SocketInner::Raw(rawsocket) => rawsocket.lock().bind_to_device(ifname),
+                    SocketInner::Unix(_) => return Err(LinuxError::ENOPROTOOPT),
+                };
+                res.map_err(|e| match e {
+                    AxError::NotFound => LinuxError::ENODEV,
+                    e => e.into(),
+                })?;
+            }
+            (ctypes::IPPROTO_IPV6, ctypes::IPV6_V6ONLY) => {
+                let only_v6 = read_optval::<c_int>(optval)? != 0;
+                match &self.inner {
+                    SocketInner::Udp(udpsocket) if self.domain == ctypes::AF_INET6 => {
+                        udpsocket.lock().set_only_v6(only_v6)?
+                    }
+                    SocketInner::Tcp(tcpsocket) if self.domain == ctypes::AF_INET6 => {
+                        tcpsocket.lock().set_only_v6(only_v6)?
+                    }
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY) => {
+                let nodelay = read_optval::<c_int>(optval)? != 0;
+                match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_nodelay(nodelay),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_CONGESTION) => {
+                let len = optval.iter().position(|&b| b == 0).unwrap_or(optval.len());
+                let name = core::str::from_utf8(&optval[..len]).map_err(|_| LinuxError::EINVAL)?;
+                let cc = CongestionControl::from_name(name).ok_or(LinuxError::ENOENT)?;
+                match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_congestion_control(cc),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                }
+            }
+            _ => {
+                warn!("unsupported socket option: level {}, name {}", level, name);
+                return Err(LinuxError::ENOPROTOOPT);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the value of a socket option, and writes it to `optval`. Returns
+    /// the length of the value.
+    fn get_option(&self, level: u32, name: u32, optval: &mut [u8]) -> LinuxResult<usize> {
+        match (level, name) {
+            (ctypes::SOL_SOCKET, ctypes::SO_TYPE) => write_optval(optval, self.socktype() as c_int),
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => {
+                let reuse = match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().reuse_address(),
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().reuse_address(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                write_optval(optval, reuse as c_int)
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                let size = match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().recv_buffer_size(),
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().recv_buffer_size(),
+                    SocketInner::Unix(unixsocket) => unixsocket.buffer_size(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                write_optval(optval, size.min(c_int::MAX as usize) as c_int)
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                let size = match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().send_buffer_size(),
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().send_buffer_size(),
+                    SocketInner::Unix(unixsocket) => unixsocket.buffer_size(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                write_optval(optval, size.min(c_int::MAX as usize) as c_int)
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                let timeout = match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().recv_timeout(),
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().recv_timeout(),
+                    SocketInner::Icmp(icmpsocket) => icmpsocket.lock().recv_timeout(),
+                    SocketInner::Raw(rawsocket) => rawsocket.lock().recv_timeout(),
+                    SocketInner::Unix(_) => None,
+                };
+                write_optval(optval, timeout_to_timeval(timeout))
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                let timeout = match &self.inner {
+                    SocketInner::Udp(udpsocket) => udpsocket.lock().send_timeout(),
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().send_timeout(),
+                    SocketInner::Icmp(icmpsocket) => icmpsocket.lock().send_timeout(),
+                    SocketInner::Raw(rawsocket) => rawsocket.lock().send_timeout(),
+                    SocketInner::Unix(_) => None,
+                };
+                write_optval(optval, timeout_to_timeval(timeout))
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE) => {
+                let keep_alive = match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().keep_alive(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                write_optval(optval, keep_alive as c_int)
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_LINGER) => {
+                let linger = match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().linger(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                let linger = ctypes::linger {
+                    l_onoff: linger.is_some() as c_int,
+                    l_linger: linger.map_or(0, |t| t.as_secs().min(c_int::MAX as u64) as c_int),
+                };
+                write_optval(optval, linger)
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_ERROR) => {
+                let error = match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().take_error(),
+                    _ => None,
+                };
+                write_optval(optval, error.map_or(0, |e| LinuxError::from(e).code()))
+            }
+            (ctypes::IPPROTO_IPV6, ctypes::IPV6_V6ONLY) => {
+                let only_v6 = match &self.inner {
+                    SocketInner::Udp(udpsocket) if self.domain == ctypes::AF_INET6 => {
+                        udpsocket.lock().only_v6()
+                    }
+                    SocketInner::Tcp(tcpsocket) if self.domain == ctypes::AF_INET6 => {
+                        tcpsocket.lock().only_v6()
+                    }
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                write_optval(optval, only_v6 as c_int)
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY) => {
+                let nodelay = match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().nodelay(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                write_optval(optval, nodelay as c_int)
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_CONGESTION) => {
+                let name = match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().congestion_control().name(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                // Like Linux, the name is padded with NULs to `TCP_CA_NAME_MAX`.
+                let mut buf = [0u8; TCP_CA_NAME_MAX];
+                buf[..name.len()].copy_from_slice(name.as_bytes());
+                write_optval(optval, buf)
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_INFO) => {
+                let info = match &self.inner {
+                    SocketInner::Tcp(tcpsocket) => tcpsocket.lock().tcp_info(),
+                    _ => return Err(LinuxError::ENOPROTOOPT),
+                };
+                // The fields not tracked (congestion window, MSS, ...) are
+                // left zero.
+                let micros = |dur: Duration| dur.as_micros().min(u32::MAX as u128) as u32;
+                let info = ctypes::tcp_info {
+                    tcpi_state: tcp_state_to_linux(info.state),
+                    tcpi_retransmits: info.retransmits.min(u8::MAX as u32) as u8,
+                    tcpi_rtt: info.rtt.map_or(0, micros),
+                    tcpi_rttvar: micros(info.rtt_var),
+                    tcpi_min_rtt: info.min_rtt.map_or(0, micros),
+                    tcpi_total_retrans: info.total_retrans,
+                    tcpi_segs_out: info.segs_out,
+                    tcpi_segs_in: info.segs_in,
+                    tcpi_bytes_acked: info.bytes_acked,
+                    tcpi_bytes_received: info.bytes_received,
+                    ..Default::default()
+                };
+                write_optval(optval, info)
+            }
+            _ => {
+                warn!("unsupported socket option: level {}, name {}", level, name);
+                Err(LinuxError::ENOPROTOOPT)
+            }
         }
     }
 }
@@ -162,12 +598,30 @@ impl FileLike for Socket {
     }
 
     fn set_nonblocking(&self, nonblock: bool) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => udpsocket.lock().set_nonblocking(nonblock),
-            Socket::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(nonblock),
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => udpsocket.lock().set_nonblocking(nonblock),
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(nonblock),
+            SocketInner::Icmp(icmpsocket) => icmpsocket.lock().set_nonblocking(nonblock),
+            SocketInner::Raw(rawsocket) => rawsocket.lock().set_nonblocking(nonblock),
+            SocketInner::Unix(unixsocket) => unixsocket.set_nonblocking(nonblock),
         }
         Ok(())
     }
+
+    fn register_poller(&self, waker: &Arc<dyn PollWaker>) -> bool {
+        // TCP and UDP sockets wake the pollers on the events of their smoltcp
+        // sockets, which are registered again on each poll. The ICMP and raw
+        // sockets, and unconnected TCP ones, are polled repeatedly.
+        match &self.inner {
+            SocketInner::Unix(unixsocket) => unixsocket.register_poller(waker),
+            #[cfg(feature = "multitask")]
+            SocketInner::Udp(_) | SocketInner::Tcp(_) => {
+                self.poll_set.register(waker);
+                self.register_poll_waker()
+            }
+            _ => false,
+        }
+    }
 }
 
 impl From<SocketAddrV4> for ctypes::sockaddr_in {
@@ -194,34 +648,112 @@ impl From<ctypes::sockaddr_in> for SocketAddrV4 {
     }
 }
 
-fn into_sockaddr(addr: SocketAddr) -> (ctypes::sockaddr, ctypes::socklen_t) {
-    debug!("    Sockaddr: {}", addr);
-    match addr {
-        SocketAddr::V4(addr) => (
-            unsafe { *(&ctypes::sockaddr_in::from(addr) as *const _ as *const ctypes::sockaddr) },
-            size_of::<ctypes::sockaddr>() as _,
-        ),
-        SocketAddr::V6(_) => panic!("IPv6 is not supported"),
+impl From<SocketAddrV6> for ctypes::sockaddr_in6 {
+    fn from(addr: SocketAddrV6) -> ctypes::sockaddr_in6 {
+        ctypes::sockaddr_in6 {
+            sin6_family: ctypes::AF_INET6 as u16,
+            sin6_port: addr.port().to_be(),
+            sin6_flowinfo: addr.flowinfo().to_be(),
+            sin6_addr: ctypes::in6_addr {
+                __in6_union: ctypes::in6_addr__bindgen_ty_1 {
+                    __s6_addr: addr.ip().octets(),
+                },
+            },
+            sin6_scope_id: addr.scope_id(),
+        }
+    }
+}
+
+impl From<ctypes::sockaddr_in6> for SocketAddrV6 {
+    fn from(addr: ctypes::sockaddr_in6) -> SocketAddrV6 {
+        SocketAddrV6::new(
+            Ipv6Addr::from(unsafe { addr.sin6_addr.__in6_union.__s6_addr }),
+            u16::from_be(addr.sin6_port),
+            u32::from_be(addr.sin6_flowinfo),
+            addr.sin6_scope_id,
+        )
     }
 }
 
+/// Writes `addr` to the buffer `dst` of `*addrlen` bytes, and sets `*addrlen`
+/// to the size of the address.
+///
+/// The address is truncated if the buffer is too small, like Linux does.
+unsafe fn write_sockaddr(
+    addr: SockAddr,
+    dst: *mut ctypes::sockaddr,
+    addrlen: *mut ctypes::socklen_t,
+) {
+    debug!("    Sockaddr: {:?}", addr);
+    let (sin, sin6, mut sun);
+    let src = match addr {
+        SockAddr::Inet(SocketAddr::V4(addr)) => {
+            sin = ctypes::sockaddr_in::from(addr);
+            unsafe { as_bytes(&sin) }
+        }
+        SockAddr::Inet(SocketAddr::V6(addr)) => {
+            sin6 = ctypes::sockaddr_in6::from(addr);
+            unsafe { as_bytes(&sin6) }
+        }
+        SockAddr::Unix(addr) => {
+            sun = ctypes::sockaddr_un {
+                sun_family: ctypes::AF_UNIX as u16,
+                ..Default::default()
+            };
+            let sun_path = unsafe {
+                core::slice::from_raw_parts_mut(
+                    sun.sun_path.as_mut_ptr() as *mut u8,
+                    sun.sun_path.len(),
+                )
+            };
+            let len = addr.to_sun_path(sun_path);
+            unsafe { &as_bytes(&sun)[..len] }
+        }
+    };
+    unsafe {
+        let len = src.len().min(*addrlen as usize);
+        core::ptr::copy_nonoverlapping(src.as_ptr(), dst as *mut u8, len);
+        *addrlen = src.len() as _;
+    }
+}
+
+unsafe fn as_bytes<T>(val: &T) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(val as *const T as *const u8, size_of::<T>()) }
+}
+
 fn from_sockaddr(
     addr: *const ctypes::sockaddr,
     addrlen: ctypes::socklen_t,
-) -> LinuxResult<SocketAddr> {
+) -> LinuxResult<SockAddr> {
     if addr.is_null() {
         return Err(LinuxError::EFAULT);
     }
-    if addrlen != size_of::<ctypes::sockaddr>() as _ {
-        return Err(LinuxError::EINVAL);
-    }
-
-    let mid = unsafe { *(addr as *const ctypes::sockaddr_in) };
-    if mid.sin_family != ctypes::AF_INET as u16 {
+    if (addrlen as usize) < size_of::<ctypes::sa_family_t>() {
         return Err(LinuxError::EINVAL);
     }
 
-    let res = SocketAddr::V4(mid.into());
+    let res = match unsafe { (*addr).sa_family } as u32 {
+        ctypes::AF_INET => {
+            if (addrlen as usize) < size_of::<ctypes::sockaddr_in>() {
+                return Err(LinuxError::EINVAL);
+            }
+            SocketAddr::V4(unsafe { *(addr as *const ctypes::sockaddr_in) }.into()).into()
+        }
+        ctypes::AF_INET6 => {
+            if (addrlen as usize) < size_of::<ctypes::sockaddr_in6>() {
+                return Err(LinuxError::EINVAL);
+            }
+            SocketAddr::V6(unsafe { *(addr as *const ctypes::sockaddr_in6) }.into()).into()
+        }
+        ctypes::AF_UNIX => {
+            let offset = size_of::<ctypes::sa_family_t>();
+            let len = (addrlen as usize).min(size_of::<ctypes::sockaddr_un>()) - offset;
+            let sun_path =
+                unsafe { core::slice::from_raw_parts((addr as *const u8).add(offset), len) };
+            SockAddr::Unix(UnixAddr::from_sun_path(sun_path)?)
+        }
+        _ => return Err(LinuxError::EAFNOSUPPORT),
+    };
     debug!("    load sockaddr:{:#x} => {:?}", addr as usize, res);
     Ok(res)
 }
@@ -233,17 +765,89 @@ pub fn sys_socket(domain: c_int, socktype: c_int, protocol: c_int) -> c_int {
     debug!("sys_socket <= {} {} {}", domain, socktype, protocol);
     let (domain, socktype, protocol) = (domain as u32, socktype as u32, protocol as u32);
     syscall_body!(sys_socket, {
-        match (domain, socktype, protocol) {
-            (ctypes::AF_INET, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
-            | (ctypes::AF_INET, ctypes::SOCK_STREAM, 0) => {
-                Socket::Tcp(Mutex::new(TcpSocket::new())).add_to_fd_table()
+        let nonblock = socktype & ctypes::SOCK_NONBLOCK != 0;
+        let cloexec = socktype & ctypes::SOCK_CLOEXEC != 0;
+        let socktype = socktype & !(ctypes::SOCK_NONBLOCK | ctypes::SOCK_CLOEXEC);
+        if domain == ctypes::AF_UNIX {
+            check_unix_socktype(socktype, protocol)?;
+            let socket = Socket::new(domain, SocketInner::Unix(UnixSocket::new(socktype)));
+            socket.set_nonblocking(nonblock)?;
+            return socket.add_to_fd_table(cloexec);
+        }
+        if domain != ctypes::AF_INET && domain != ctypes::AF_INET6 {
+            return Err(LinuxError::EAFNOSUPPORT);
+        }
+        let inner = match (socktype, protocol) {
+            (ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP) | (ctypes::SOCK_STREAM, 0) => {
+                SocketInner::Tcp(Mutex::new(TcpSocket::new()))
+            }
+            (ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP) | (ctypes::SOCK_DGRAM, 0) => {
+                SocketInner::Udp(Mutex::new(UdpSocket::new()))
+            }
+            (ctypes::SOCK_DGRAM, ctypes::IPPROTO_ICMP) if domain == ctypes::AF_INET => {
+                SocketInner::Icmp(Mutex::new(IcmpSocket::new()))
+            }
+            (ctypes::SOCK_DGRAM, ctypes::IPPROTO_ICMPV6) if domain == ctypes::AF_INET6 => {
+                SocketInner::Icmp(Mutex::new(IcmpSocket::new()))
             }
-            (ctypes::AF_INET, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
-            | (ctypes::AF_INET, ctypes::SOCK_DGRAM, 0) => {
-                Socket::Udp(Mutex::new(UdpSocket::new())).add_to_fd_table()
+            // sending packets with the IP header (`IPPROTO_RAW`) is not supported
+            (ctypes::SOCK_RAW, 0) | (ctypes::SOCK_RAW, ctypes::IPPROTO_RAW) => {
+                return Err(LinuxError::EPROTONOSUPPORT);
             }
-            _ => Err(LinuxError::EINVAL),
+            (ctypes::SOCK_RAW, _) if protocol <= u8::MAX as u32 => SocketInner::Raw(Mutex::new(
+                RawSocket::new(domain == ctypes::AF_INET6, protocol as u8),
+            )),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let socket = Socket::new(domain, inner);
+        socket.set_nonblocking(nonblock)?;
+        socket.add_to_fd_table(cloexec)
+    })
+}
+
+/// Checks the type of a Unix domain socket, only `SOCK_STREAM` and
+/// `SOCK_DGRAM` are supported.
+fn check_unix_socktype(socktype: u32, protocol: u32) -> LinuxResult {
+    match (socktype, protocol) {
+        (ctypes::SOCK_STREAM | ctypes::SOCK_DGRAM, 0) => Ok(()),
+        (ctypes::SOCK_STREAM | ctypes::SOCK_DGRAM, _) => Err(LinuxError::EPROTONOSUPPORT),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Create a pair of connected sockets.
+///
+/// Only `AF_UNIX` is supported.
+///
+/// Return 0 if success.
+pub fn sys_socketpair(domain: c_int, socktype: c_int, protocol: c_int, fds: &mut [c_int]) -> c_int {
+    debug!("sys_socketpair <= {} {} {}", domain, socktype, protocol);
+    let (domain, socktype, protocol) = (domain as u32, socktype as u32, protocol as u32);
+    syscall_body!(sys_socketpair, {
+        if fds.len() != 2 {
+            return Err(LinuxError::EFAULT);
+        }
+        if domain != ctypes::AF_UNIX {
+            return Err(LinuxError::EOPNOTSUPP);
         }
+        let nonblock = socktype & ctypes::SOCK_NONBLOCK != 0;
+        let cloexec = socktype & ctypes::SOCK_CLOEXEC != 0;
+        let socktype = socktype & !(ctypes::SOCK_NONBLOCK | ctypes::SOCK_CLOEXEC);
+        check_unix_socktype(socktype, protocol)?;
+
+        let (a, b) = UnixSocket::pair(socktype);
+        a.set_nonblocking(nonblock);
+        b.set_nonblocking(nonblock);
+        let fd_a = Socket::new(domain, SocketInner::Unix(a)).add_to_fd_table(cloexec)?;
+        let fd_b = Socket::new(domain, SocketInner::Unix(b))
+            .add_to_fd_table(cloexec)
+            .inspect_err(|_| {
+                close_file_like(fd_a).ok();
+            })?;
+
+        fds[0] = fd_a;
+        fds[1] = fd_b;
+        Ok(0)
     })
 }
 
@@ -292,7 +896,7 @@ pub fn sys_sendto(
     socket_fd: c_int,
     buf_ptr: *const c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int, // only `MSG_NOSIGNAL` is supported
     socket_addr: *const ctypes::sockaddr,
     addrlen: ctypes::socklen_t,
 ) -> ctypes::ssize_t {
@@ -306,7 +910,7 @@ pub fn sys_sendto(
         }
         let addr = from_sockaddr(socket_addr, addrlen)?;
         let buf = unsafe { core::slice::from_raw_parts(buf_ptr as *const u8, len) };
-        Socket::from_fd(socket_fd)?.sendto(buf, addr)
+        check_sigpipe(Socket::from_fd(socket_fd)?.sendto(buf, addr), flag)
     })
 }
 
@@ -317,7 +921,7 @@ pub fn sys_send(
     socket_fd: c_int,
     buf_ptr: *const c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int, // only `MSG_NOSIGNAL` is supported
 ) -> ctypes::ssize_t {
     debug!(
         "sys_sendto <= {} {:#x} {} {}",
@@ -328,7 +932,7 @@ pub fn sys_send(
             return Err(LinuxError::EFAULT);
         }
         let buf = unsafe { core::slice::from_raw_parts(buf_ptr as *const u8, len) };
-        Socket::from_fd(socket_fd)?.send(buf)
+        check_sigpipe(Socket::from_fd(socket_fd)?.send(buf), flag)
     })
 }
 
@@ -356,9 +960,7 @@ pub unsafe fn sys_recvfrom(
 
         let res = socket.recvfrom(buf)?;
         if let Some(addr) = res.1 {
-            unsafe {
-                (*socket_addr, *addrlen) = into_sockaddr(addr);
-            }
+            unsafe { write_sockaddr(addr, socket_addr, addrlen) };
         }
         Ok(res.0)
     })
@@ -386,16 +988,143 @@ pub fn sys_recv(
     })
 }
 
+/// Send a message on a socket, gathered from `msg_iov`.
+///
+/// Only the `SCM_RIGHTS` control message of Unix domain sockets is supported.
+///
+/// Return the number of bytes sent if success.
+pub unsafe fn sys_sendmsg(
+    socket_fd: c_int,
+    msg: *const ctypes::msghdr,
+    flag: c_int, // only `MSG_NOSIGNAL` is supported
+) -> ctypes::ssize_t {
+    debug!("sys_sendmsg <= {} {:#x} {}", socket_fd, msg as usize, flag);
+    syscall_body!(sys_sendmsg, {
+        if msg.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let msg = unsafe { &*msg };
+        let iovs = unsafe { iovecs(msg.msg_iov, msg.msg_iovlen)? };
+        let mut buf = Vec::with_capacity(iovs.iter().map(|iov| iov.iov_len).sum());
+        for iov in iovs {
+            buf.extend_from_slice(unsafe {
+                core::slice::from_raw_parts(iov.iov_base as *const u8, iov.iov_len)
+            });
+        }
+        let addr = if msg.msg_name.is_null() || msg.msg_namelen == 0 {
+            None
+        } else {
+            Some(from_sockaddr(msg.msg_name as _, msg.msg_namelen)?)
+        };
+        let fds = if msg.msg_control.is_null() {
+            Vec::new()
+        } else {
+            let control = unsafe {
+                core::slice::from_raw_parts(
+                    msg.msg_control as *const u8,
+                    msg.msg_controllen as usize,
+                )
+            };
+            read_scm_rights(control)?
+        };
+
+        let socket = Socket::from_fd(socket_fd)?;
+        let res = match (&socket.inner, addr) {
+            (SocketInner::Unix(unixsocket), addr) => {
+                let addr = addr.map(SockAddr::into_unix).transpose()?;
+                unixsocket.send_msg(&buf, fds, addr)
+            }
+            _ if !fds.is_empty() => Err(LinuxError::EINVAL),
+            (_, Some(addr)) => socket.sendto(&buf, addr),
+            (_, None) => socket.send(&buf),
+        };
+        check_sigpipe(res, flag)
+    })
+}
+
+/// Receive a message on a socket, scattered to `msg_iov`.
+///
+/// The files passed by `SCM_RIGHTS` are installed as new file descriptors,
+/// and `MSG_CTRUNC` is set if the control buffer cannot hold all of them.
+///
+/// Return the number of bytes received if success.
+pub unsafe fn sys_recvmsg(
+    socket_fd: c_int,
+    msg: *mut ctypes::msghdr,
+    flag: c_int, // currently not used
+) -> ctypes::ssize_t {
+    debug!("sys_recvmsg <= {} {:#x} {}", socket_fd, msg as usize, flag);
+    syscall_body!(sys_recvmsg, {
+        if msg.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let msg = unsafe { &mut *msg };
+        let iovs = unsafe { iovecs(msg.msg_iov, msg.msg_iovlen)? };
+        let mut buf = vec![0; iovs.iter().map(|iov| iov.iov_len).sum()];
+
+        let socket = Socket::from_fd(socket_fd)?;
+        let (len, msg_len, addr, fds) = match &socket.inner {
+            SocketInner::Unix(unixsocket) => {
+                let res = unixsocket.recv_msg(&mut buf)?;
+                (res.len, res.msg_len, res.from.map(SockAddr::Unix), res.fds)
+            }
+            _ => {
+                let (len, addr) = socket.recvfrom(&mut buf)?;
+                (len, len, addr, Vec::new())
+            }
+        };
+
+        let mut copied = 0;
+        for iov in iovs {
+            let n = iov.iov_len.min(len - copied);
+            unsafe {
+                core::ptr::copy_nonoverlapping(buf[copied..].as_ptr(), iov.iov_base as *mut u8, n)
+            };
+            copied += n;
+        }
+        if !msg.msg_name.is_null() {
+            match addr {
+                Some(addr) => unsafe {
+                    write_sockaddr(addr, msg.msg_name as _, &mut msg.msg_namelen)
+                },
+                None => msg.msg_namelen = 0,
+            }
+        }
+        msg.msg_flags = 0;
+        if msg_len > len {
+            msg.msg_flags |= ctypes::MSG_TRUNC as c_int;
+        }
+        if msg.msg_control.is_null() {
+            msg.msg_controllen = 0;
+            if !fds.is_empty() {
+                msg.msg_flags |= ctypes::MSG_CTRUNC as c_int;
+            }
+        } else {
+            let control = unsafe {
+                core::slice::from_raw_parts_mut(
+                    msg.msg_control as *mut u8,
+                    msg.msg_controllen as usize,
+                )
+            };
+            let (control_len, truncated) = write_scm_rights(control, fds)?;
+            msg.msg_controllen = control_len as _;
+            if truncated {
+                msg.msg_flags |= ctypes::MSG_CTRUNC as c_int;
+            }
+        }
+        Ok(len)
+    })
+}
+
 /// Listen for connections on a socket
 ///
+/// A negative `backlog` is treated as 0, and it is clamped to a supported range.
+///
 /// Return 0 if success.
-pub fn sys_listen(
-    socket_fd: c_int,
-    backlog: c_int, // currently not used
-) -> c_int {
+pub fn sys_listen(socket_fd: c_int, backlog: c_int) -> c_int {
     debug!("sys_listen <= {} {}", socket_fd, backlog);
     syscall_body!(sys_listen, {
-        Socket::from_fd(socket_fd)?.listen()?;
+        Socket::from_fd(socket_fd)?.listen(backlog.max(0) as usize)?;
         Ok(0)
     })
 }
@@ -419,38 +1148,37 @@ pub unsafe fn sys_accept(
         let socket = Socket::from_fd(socket_fd)?;
         let new_socket = socket.accept()?;
         let addr = new_socket.peer_addr()?;
-        let new_fd = Socket::add_to_fd_table(Socket::Tcp(Mutex::new(new_socket)))?;
-        unsafe {
-            (*socket_addr, *socket_len) = into_sockaddr(addr);
-        }
+        let new_fd = new_socket.add_to_fd_table(false)?;
+        unsafe { write_sockaddr(addr, socket_addr, socket_len) };
         Ok(new_fd)
     })
 }
 
 /// Shut down a full-duplex connection.
 ///
+/// `flag` (`SHUT_RD`, `SHUT_WR` or `SHUT_RDWR`) is only supported by Unix
+/// domain sockets, others are always shut down in both directions.
+///
 /// Return 0 if success.
-pub fn sys_shutdown(
-    socket_fd: c_int,
-    flag: c_int, // currently not used
-) -> c_int {
+pub fn sys_shutdown(socket_fd: c_int, flag: c_int) -> c_int {
     debug!("sys_shutdown <= {} {}", socket_fd, flag);
     syscall_body!(sys_shutdown, {
-        Socket::from_fd(socket_fd)?.shutdown()?;
+        Socket::from_fd(socket_fd)?.shutdown(flag as u32)?;
         Ok(0)
     })
 }
 
 /// Query addresses for a domain name.
 ///
-/// Only IPv4. Ports are always 0. Ignore servname and hint.
+/// Both IPv4 and IPv6 addresses are returned, unless `ai_family` of the hint
+/// is `AF_INET` or `AF_INET6`. Other fields of the hint are ignored.
 /// Results' ai_flags and ai_canonname are 0 or NULL.
 ///
 /// Return address number if success.
 pub unsafe fn sys_getaddrinfo(
     nodename: *const c_char,
     servname: *const c_char,
-    _hints: *const ctypes::addrinfo,
+    hints: *const ctypes::addrinfo,
     res: *mut *mut ctypes::addrinfo,
 ) -> c_int {
     let name = char_ptr_to_str(nodename);
@@ -463,6 +1191,11 @@ pub unsafe fn sys_getaddrinfo(
         if res.is_null() {
             return Err(LinuxError::EFAULT);
         }
+        let family = if hints.is_null() {
+            ctypes::AF_UNSPEC
+        } else {
+            unsafe { (*hints).ai_family as u32 }
+        };
 
         let port = port.map_or(0, |p| p.parse::<u16>().unwrap_or(0));
         let ip_addrs = if let Ok(domain) = name {
@@ -471,9 +1204,19 @@ pub unsafe fn sys_getaddrinfo(
             } else {
                 axnet::dns_query(domain)?
             }
+        } else if family == ctypes::AF_INET6 {
+            vec![Ipv6Addr::LOCALHOST.into()]
         } else {
             vec![Ipv4Addr::LOCALHOST.into()]
         };
+        let ip_addrs: Vec<_> = ip_addrs
+            .into_iter()
+            .filter(|ip| match family {
+                ctypes::AF_INET => ip.is_ipv4(),
+                ctypes::AF_INET6 => ip.is_ipv6(),
+                _ => true,
+            })
+            .collect();
 
         let len = ip_addrs.len().min(ctypes::MAXADDRS as usize);
         if len == 0 {
@@ -482,31 +1225,41 @@ pub unsafe fn sys_getaddrinfo(
 
         let mut out: Vec<ctypes::aibuf> = Vec::with_capacity(len);
         for (i, &ip) in ip_addrs.iter().enumerate().take(len) {
-            let buf = match ip {
-                IpAddr::V4(ip) => ctypes::aibuf {
-                    ai: ctypes::addrinfo {
-                        ai_family: ctypes::AF_INET as _,
-                        // TODO: This is a hard-code part, only return TCP parameters
-                        ai_socktype: ctypes::SOCK_STREAM as _,
-                        ai_protocol: ctypes::IPPROTO_TCP as _,
-                        ai_addrlen: size_of::<ctypes::sockaddr_in>() as _,
-                        ai_addr: core::ptr::null_mut(),
-                        ai_canonname: core::ptr::null_mut(),
-                        ai_next: core::ptr::null_mut(),
-                        ai_flags: 0,
-                    },
-                    sa: ctypes::aibuf_sa {
+            let (ai_family, ai_addrlen, sa) = match ip {
+                IpAddr::V4(ip) => (
+                    ctypes::AF_INET,
+                    size_of::<ctypes::sockaddr_in>(),
+                    ctypes::aibuf_sa {
                         sin: SocketAddrV4::new(ip, port).into(),
                     },
-                    slot: i as i16,
-                    lock: [0],
-                    ref_: 0,
+                ),
+                IpAddr::V6(ip) => (
+                    ctypes::AF_INET6,
+                    size_of::<ctypes::sockaddr_in6>(),
+                    ctypes::aibuf_sa {
+                        sin6: SocketAddrV6::new(ip, port, 0, 0).into(),
+                    },
+                ),
+            };
+            let buf = ctypes::aibuf {
+                ai: ctypes::addrinfo {
+                    ai_family: ai_family as _,
+                    // TODO: This is a hard-code part, only return TCP parameters
+                    ai_socktype: ctypes::SOCK_STREAM as _,
+                    ai_protocol: ctypes::IPPROTO_TCP as _,
+                    ai_addrlen: ai_addrlen as _,
+                    ai_addr: core::ptr::null_mut(),
+                    ai_canonname: core::ptr::null_mut(),
+                    ai_next: core::ptr::null_mut(),
+                    ai_flags: 0,
                 },
-                _ => panic!("IPv6 is not supported"),
+                sa,
+                slot: i as i16,
+                lock: [0],
+                ref_: 0,
             };
             out.push(buf);
-            out[i].ai.ai_addr =
-                unsafe { core::ptr::addr_of_mut!(out[i].sa.sin) as *mut ctypes::sockaddr };
+            out[i].ai.ai_addr = core::ptr::addr_of_mut!(out[i].sa) as *mut ctypes::sockaddr;
             if i > 0 {
                 out[i - 1].ai.ai_next = core::ptr::addr_of_mut!(out[i].ai);
             }
@@ -546,12 +1299,8 @@ pub unsafe fn sys_getsockname(
         if addr.is_null() || addrlen.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
-            return Err(LinuxError::EINVAL);
-        }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.local_addr()?);
-        }
+        let local_addr = Socket::from_fd(sock_fd)?.local_addr()?;
+        unsafe { write_sockaddr(local_addr, addr, addrlen) };
         Ok(0)
     })
 }
@@ -570,12 +1319,400 @@ pub unsafe fn sys_getpeername(
         if addr.is_null() || addrlen.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
-            return Err(LinuxError::EINVAL);
+        let peer_addr = Socket::from_fd(sock_fd)?.peer_addr()?;
+        unsafe { write_sockaddr(peer_addr, addr, addrlen) };
+        Ok(0)
+    })
+}
+
+/// Set options on a socket.
+///
+/// Return 0 if success.
+pub unsafe fn sys_setsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_setsockopt <= {} {} {} {:#x} {}",
+        socket_fd, level, optname, optval as usize, optlen
+    );
+    syscall_body!(sys_setsockopt, {
+        if optval.is_null() {
+            return Err(LinuxError::EFAULT);
         }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.peer_addr()?);
+        let optval = unsafe { core::slice::from_raw_parts(optval as *const u8, optlen as usize) };
+        Socket::from_fd(socket_fd)?.set_option(level as u32, optname as u32, optval)?;
+        Ok(0)
+    })
+}
+
+/// Get options on a socket.
+///
+/// The value is truncated if the buffer is too small, and `optlen` is set
+/// to the length written.
+///
+/// Return 0 if success.
+pub unsafe fn sys_getsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_getsockopt <= {} {} {} {:#x} {:#x}",
+        socket_fd, level, optname, optval as usize, optlen as usize
+    );
+    syscall_body!(sys_getsockopt, {
+        if optval.is_null() || optlen.is_null() {
+            return Err(LinuxError::EFAULT);
         }
+        let optval =
+            unsafe { core::slice::from_raw_parts_mut(optval as *mut u8, *optlen as usize) };
+        let len = Socket::from_fd(socket_fd)?.get_option(level as u32, optname as u32, optval)?;
+        unsafe { *optlen = len as ctypes::socklen_t };
         Ok(0)
     })
 }
+
+/// Reads an option value of type `T` from the buffer of `setsockopt`.
+fn read_optval<T: Copy>(optval: &[u8]) -> LinuxResult<T> {
+    if optval.len() < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(unsafe { (optval.as_ptr() as *const T).read_unaligned() })
+}
+
+/// Writes an option value to the buffer of `getsockopt`, truncated to the
+/// buffer length. Returns the length written.
+fn write_optval<T: Copy>(optval: &mut [u8], val: T) -> LinuxResult<usize> {
+    let len = optval.len().min(size_of::<T>());
+    let bytes = unsafe { core::slice::from_raw_parts(&val as *const T as *const u8, len) };
+    optval[..len].copy_from_slice(bytes);
+    Ok(len)
+}
+
+/// The maximum length of the name of a congestion control algorithm,
+/// including the NUL terminator.
+const TCP_CA_NAME_MAX: usize = 16;
+
+/// Converts the state of a TCP socket to the `TCP_*` value of `tcpi_state`.
+fn tcp_state_to_linux(state: TcpState) -> u8 {
+    (match state {
+        TcpState::Closed => ctypes::TCP_CLOSE,
+        TcpState::Listen => ctypes::TCP_LISTEN,
+        TcpState::SynSent => ctypes::TCP_SYN_SENT,
+        TcpState::SynReceived => ctypes::TCP_SYN_RECV,
+        TcpState::Established => ctypes::TCP_ESTABLISHED,
+        TcpState::FinWait1 => ctypes::TCP_FIN_WAIT1,
+        TcpState::FinWait2 => ctypes::TCP_FIN_WAIT2,
+        TcpState::CloseWait => ctypes::TCP_CLOSE_WAIT,
+        TcpState::Closing => ctypes::TCP_CLOSING,
+        TcpState::LastAck => ctypes::TCP_LAST_ACK,
+        TcpState::TimeWait => ctypes::TCP_TIME_WAIT,
+    }) as u8
+}
+
+/// Sends `SIGPIPE` if sending failed with `EPIPE`, unless `MSG_NOSIGNAL` is
+/// set in `flags`.
+fn check_sigpipe<T>(res: LinuxResult<T>, flags: c_int) -> LinuxResult<T> {
+    #[cfg(feature = "signal")]
+    if let Some(e) = res
+        .as_ref()
+        .err()
+        .filter(|_| flags as u32 & ctypes::MSG_NOSIGNAL == 0)
+    {
+        super::signal::sigpipe_on(e);
+    }
+    #[cfg(not(feature = "signal"))]
+    let _ = flags;
+    res
+}
+
+/// Returns the I/O vectors of a `msghdr`.
+unsafe fn iovecs<'a>(iov: *const ctypes::iovec, iovlen: c_int) -> LinuxResult<&'a [ctypes::iovec]> {
+    if !(0..=1024).contains(&iovlen) {
+        return Err(LinuxError::EMSGSIZE);
+    }
+    if iovlen == 0 {
+        return Ok(&[]);
+    }
+    if iov.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(unsafe { core::slice::from_raw_parts(iov, iovlen as usize) })
+}
+
+/// Aligns the length of a control message, like `CMSG_ALIGN`.
+const fn cmsg_align(len: usize) -> usize {
+    (len + size_of::<usize>() - 1) & !(size_of::<usize>() - 1)
+}
+
+/// The offset of the data in a control message.
+const CMSG_DATA_OFFSET: usize = cmsg_align(size_of::<ctypes::cmsghdr>());
+
+/// Collects the files passed in the `SCM_RIGHTS` control messages of
+/// `sendmsg`.
+fn read_scm_rights(control: &[u8]) -> LinuxResult<Vec<Arc<dyn FileLike>>> {
+    let mut fds = Vec::new();
+    let mut rest = control;
+    while rest.len() >= size_of::<ctypes::cmsghdr>() {
+        let cmsg = unsafe { (rest.as_ptr() as *const ctypes::cmsghdr).read_unaligned() };
+        let len = cmsg.cmsg_len as usize;
+        if len < CMSG_DATA_OFFSET || len > rest.len() {
+            return Err(LinuxError::EINVAL);
+        }
+        if cmsg.cmsg_level as u32 != ctypes::SOL_SOCKET
+            || cmsg.cmsg_type as u32 != ctypes::SCM_RIGHTS
+        {
+            warn!(
+                "unsupported control message: level {}, type {}",
+                cmsg.cmsg_level, cmsg.cmsg_type
+            );
+            return Err(LinuxError::EINVAL);
+        }
+        for fd in rest[CMSG_DATA_OFFSET..len].chunks_exact(size_of::<c_int>()) {
+            fds.push(get_file_like(c_int::from_ne_bytes(fd.try_into().unwrap()))?);
+        }
+        if fds.len() > SCM_MAX_FD {
+            return Err(LinuxError::EINVAL);
+        }
+        rest = &rest[cmsg_align(len).min(rest.len())..];
+    }
+    Ok(fds)
+}
+
+/// Installs the files received by `recvmsg` as new file descriptors, and
+/// writes them to the control buffer as an `SCM_RIGHTS` message.
+///
+/// Returns the length of the control message, and whether some files are
+/// discarded because the buffer is too small.
+fn write_scm_rights(control: &mut [u8], fds: Vec<Arc<dyn FileLike>>) -> LinuxResult<(usize, bool)> {
+    let count = fds
+        .len()
+        .min(control.len().saturating_sub(CMSG_DATA_OFFSET) / size_of::<c_int>());
+    let truncated = count < fds.len();
+    if count == 0 {
+        return Ok((0, truncated));
+    }
+    let len = CMSG_DATA_OFFSET + count * size_of::<c_int>();
+    // `cmsghdr` has padding on 64-bit targets only.
+    #[allow(clippy::needless_update)]
+    let cmsg = ctypes::cmsghdr {
+        cmsg_len: len as _,
+        cmsg_level: ctypes::SOL_SOCKET as _,
+        cmsg_type: ctypes::SCM_RIGHTS as _,
+        ..Default::default()
+    };
+    control[..size_of::<ctypes::cmsghdr>()].copy_from_slice(unsafe { as_bytes(&cmsg) });
+    let data = control[CMSG_DATA_OFFSET..len].chunks_exact_mut(size_of::<c_int>());
+    for (dst, f) in data.zip(fds) {
+        dst.copy_from_slice(&add_file_like(f)?.to_ne_bytes());
+    }
+    Ok((cmsg_align(len).min(control.len()), truncated))
+}
+
+/// Converts the timeout of `SO_RCVTIMEO` and `SO_SNDTIMEO`, where zero means
+/// no timeout.
+fn timeval_to_timeout(tv: ctypes::timeval) -> LinuxResult<Option<Duration>> {
+    if tv.tv_sec < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if !(0..1_000_000).contains(&tv.tv_usec) {
+        return Err(LinuxError::EDOM);
+    }
+    let timeout = Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000);
+    Ok((!timeout.is_zero()).then_some(timeout))
+}
+
+fn timeout_to_timeval(timeout: Option<Duration>) -> ctypes::timeval {
+    let timeout = timeout.unwrap_or(Duration::ZERO);
+    ctypes::timeval {
+        tv_sec: timeout.as_secs() as _,
+        tv_usec: timeout.subsec_micros() as _,
+    }
+}
+
+fn ifreq_name(ifr: &ctypes::ifreq) -> LinuxResult<&str> {
+    let name = unsafe { &ifr.ifr_ifrn.ifrn_name };
+    let bytes = unsafe { core::slice::from_raw_parts(name.as_ptr() as *const u8, name.len()) };
+    CStr::from_bytes_until_nul(bytes)
+        .ok()
+        .and_then(|s| s.to_str().ok())
+        .ok_or(LinuxError::EINVAL)
+}
+
+fn ipv4_to_sockaddr(addr: Ipv4Addr) -> ctypes::sockaddr {
+    let sin = ctypes::sockaddr_in::from(SocketAddrV4::new(addr, 0));
+    unsafe { *(&sin as *const _ as *const ctypes::sockaddr) }
+}
+
+fn sockaddr_to_ipv4(addr: &ctypes::sockaddr) -> LinuxResult<Ipv4Addr> {
+    match from_sockaddr(addr, size_of::<ctypes::sockaddr>() as _)?.ip() {
+        IpAddr::V4(addr) => Ok(addr),
+        IpAddr::V6(_) => Err(LinuxError::EINVAL),
+    }
+}
+
+fn netmask_to_prefix_len(mask: Ipv4Addr) -> LinuxResult<u8> {
+    let bits = mask.to_bits();
+    if bits.leading_ones() != bits.count_ones() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(bits.leading_ones() as u8)
+}
+
+fn prefix_len_to_netmask(prefix_len: u8) -> Ipv4Addr {
+    Ipv4Addr::from_bits(u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0))
+}
+
+/// Adds (`SIOCADDRT`) or removes (`SIOCDELRT`) an IPv4 route, like `route`
+/// does.
+///
+/// Only routes through a gateway can be added, as the on-link routes follow
+/// the addresses of the interfaces.
+fn route_ioctl(request: u32, rt: &ctypes::rtentry) -> LinuxResult {
+    let flags = rt.rt_flags as u32;
+    let dest = sockaddr_to_ipv4(&rt.rt_dst)?;
+    let prefix_len = if flags & ctypes::RTF_HOST != 0 {
+        32
+    } else {
+        netmask_to_prefix_len(sockaddr_to_ipv4(&rt.rt_genmask)?)?
+    };
+    if request == ctypes::SIOCDELRT {
+        return axnet::remove_route(IpAddr::V4(dest), prefix_len).map_err(|e| match e {
+            AxError::NotFound => LinuxError::ESRCH,
+            e => e.into(),
+        });
+    }
+    if flags & ctypes::RTF_GATEWAY == 0 {
+        warn!(
+            "unsupported route without a gateway to {}/{}",
+            dest, prefix_len
+        );
+        return Err(LinuxError::EINVAL);
+    }
+    let gateway = sockaddr_to_ipv4(&rt.rt_gateway)?;
+    axnet::add_route(IpAddr::V4(dest), prefix_len, IpAddr::V4(gateway))?;
+    Ok(())
+}
+
+/// Handles the socket configuration controls (`SIOC*`) of
+/// [`sys_ioctl`](super::fd_ops::sys_ioctl).
+///
+/// Only IPv4 addresses can be configured in this way, like `ifconfig` does.
+pub(crate) unsafe fn socket_ioctl(fd: c_int, request: u32, arg: usize) -> LinuxResult<c_int> {
+    Socket::from_fd(fd)?;
+    if arg == 0 {
+        return Err(LinuxError::EFAULT);
+    }
+
+    if request == ctypes::SIOCGIFCONF {
+        let ifc = unsafe { &mut *(arg as *mut ctypes::ifconf) };
+        let mut reqs = Vec::new();
+        for info in axnet::interfaces() {
+            for &(addr, _) in &info.addrs {
+                if let IpAddr::V4(addr) = addr {
+                    let mut ifr = ctypes::ifreq::default();
+                    for (dst, &src) in unsafe { ifr.ifr_ifrn.ifrn_name.iter_mut() }
+                        .zip(info.name.as_bytes())
+                        .take(ctypes::IFNAMSIZ as usize - 1)
+                    {
+                        *dst = src as c_char;
+                    }
+                    ifr.ifr_ifru.ifru_addr = ipv4_to_sockaddr(addr);
+                    reqs.push(ifr);
+                }
+            }
+        }
+        let buf = unsafe { ifc.ifc_ifcu.ifcu_req };
+        if buf.is_null() {
+            // Only query the required buffer size.
+            ifc.ifc_len = (reqs.len() * size_of::<ctypes::ifreq>()) as c_int;
+            return Ok(0);
+        }
+        let count = (ifc.ifc_len.max(0) as usize / size_of::<ctypes::ifreq>()).min(reqs.len());
+        unsafe { core::ptr::copy_nonoverlapping(reqs.as_ptr(), buf, count) };
+        ifc.ifc_len = (count * size_of::<ctypes::ifreq>()) as c_int;
+        return Ok(0);
+    }
+
+    if matches!(request, ctypes::SIOCADDRT | ctypes::SIOCDELRT) {
+        let rt = unsafe { &*(arg as *const ctypes::rtentry) };
+        return route_ioctl(request, rt).map(|_| 0);
+    }
+
+    let ifr = unsafe { &mut *(arg as *mut ctypes::ifreq) };
+    let name = ifreq_name(ifr)?;
+    let info = axnet::interfaces()
+        .into_iter()
+        .find(|info| info.name == name)
+        .ok_or(LinuxError::ENODEV)?;
+    let ipv4 = info
+        .addrs
+        .iter()
+        .find_map(|&(addr, prefix_len)| match addr {
+            IpAddr::V4(addr) => Some((addr, prefix_len)),
+            IpAddr::V6(_) => None,
+        });
+
+    match request {
+        ctypes::SIOCGIFADDR => {
+            let (addr, _) = ipv4.ok_or(LinuxError::EADDRNOTAVAIL)?;
+            ifr.ifr_ifru.ifru_addr = ipv4_to_sockaddr(addr);
+        }
+        ctypes::SIOCSIFADDR => {
+            let addr = sockaddr_to_ipv4(unsafe { &ifr.ifr_ifru.ifru_addr })?;
+            // Replace the current IPv4 address and keep its netmask.
+            let prefix_len = match ipv4 {
+                Some((old, prefix_len)) => {
+                    axnet::remove_ip_addr(name, IpAddr::V4(old))?;
+                    prefix_len
+                }
+                None => 24,
+            };
+            axnet::add_ip_addr(name, IpAddr::V4(addr), prefix_len)?;
+        }
+        ctypes::SIOCDIFADDR => {
+            let addr = sockaddr_to_ipv4(unsafe { &ifr.ifr_ifru.ifru_addr })?;
+            axnet::remove_ip_addr(name, IpAddr::V4(addr))?;
+        }
+        ctypes::SIOCGIFNETMASK => {
+            let (_, prefix_len) = ipv4.ok_or(LinuxError::EADDRNOTAVAIL)?;
+            ifr.ifr_ifru.ifru_netmask = ipv4_to_sockaddr(prefix_len_to_netmask(prefix_len));
+        }
+        ctypes::SIOCSIFNETMASK => {
+            let mask = sockaddr_to_ipv4(unsafe { &ifr.ifr_ifru.ifru_netmask })?;
+            let prefix_len = netmask_to_prefix_len(mask)?;
+            let (addr, _) = ipv4.ok_or(LinuxError::EADDRNOTAVAIL)?;
+            axnet::remove_ip_addr(name, IpAddr::V4(addr))?;
+            axnet::add_ip_addr(name, IpAddr::V4(addr), prefix_len)?;
+        }
+        ctypes::SIOCGIFMTU => ifr.ifr_ifru.ifru_mtu = info.mtu as c_int,
+        ctypes::SIOCSIFMTU => {
+            let mtu = unsafe { ifr.ifr_ifru.ifru_mtu };
+            if mtu < 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            axnet::set_mtu(name, mtu as usize)?;
+        }
+        ctypes::SIOCGIFHWADDR => {
+            let mut hwaddr = ctypes::sockaddr {
+                sa_family: 1, // ARPHRD_ETHER
+                ..Default::default()
+            };
+            for (dst, &src) in hwaddr.sa_data.iter_mut().zip(&info.mac_addr) {
+                *dst = src as c_char;
+            }
+            ifr.ifr_ifru.ifru_hwaddr = hwaddr;
+        }
+        _ => {
+            warn!("unsupported socket ioctl request: {:#x}", request);
+            return Err(LinuxError::EINVAL);
+        }
+    }
+    Ok(0)
+}
@@ -1,162 +1,212 @@
+//! Pipes and named pipes (FIFOs).
+//!
+//! The two ends of a pipe share a [`PipeInner`], which counts the open ends
+//! of each kind. A named pipe is a node in axfs, registered in [`FIFOS`] by
+//! its number, whose ends share a [`PipeInner`] while any of them is open.
+
+use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
 use axsync::Mutex;
 
-use super::fd_ops::{FileLike, add_file_like, close_file_like};
+use super::fd_ops::{FileLike, add_file_like, close_file_like, get_file_like};
+use super::poll_set::{PollSet, PollWaker};
 use crate::ctypes;
 
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    Full,
-    Empty,
-    Normal,
-}
-
-const RING_BUFFER_SIZE: usize = 256;
+/// Writes of at most this many bytes are atomic.
+pub const PIPE_BUF: usize = 4096;
+/// The capacity of a new pipe.
+const PIPE_DEFAULT_SIZE: usize = 64 * 1024;
+/// The maximum capacity that can be set with `F_SETPIPE_SZ`.
+const PIPE_MAX_SIZE: usize = 1024 * 1024;
 
-pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
-    head: usize,
-    tail: usize,
-    status: RingBufferStatus,
+struct PipeBuffer {
+    data: VecDeque<u8>,
+    capacity: usize,
+    /// The number of open read ends.
+    readers: usize,
+    /// The number of open write ends.
+    writers: usize,
 }
 
-impl PipeRingBuffer {
-    pub const fn new() -> Self {
-        Self {
-            arr: [0; RING_BUFFER_SIZE],
-            head: 0,
-            tail: 0,
-            status: RingBufferStatus::Empty,
-        }
-    }
-
-    pub fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::Normal;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::Full;
-        }
-    }
-
-    pub fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::Normal;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::Empty;
-        }
-        c
+impl PipeBuffer {
+    fn available_write(&self) -> usize {
+        self.capacity.saturating_sub(self.data.len())
     }
+}
 
-    /// Get the length of remaining data in the buffer
-    pub const fn available_read(&self) -> usize {
-        if matches!(self.status, RingBufferStatus::Empty) {
-            0
-        } else if self.tail > self.head {
-            self.tail - self.head
-        } else {
-            self.tail + RING_BUFFER_SIZE - self.head
-        }
-    }
+struct PipeInner {
+    buffer: Mutex<PipeBuffer>,
+    poll_set: PollSet,
+}
 
-    /// Get the length of remaining space in the buffer
-    pub const fn available_write(&self) -> usize {
-        if matches!(self.status, RingBufferStatus::Full) {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
-        }
+impl PipeInner {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            buffer: Mutex::new(PipeBuffer {
+                data: VecDeque::new(),
+                capacity: PIPE_DEFAULT_SIZE,
+                readers: 0,
+                writers: 0,
+            }),
+            poll_set: PollSet::new(),
+        })
     }
 }
 
 pub struct Pipe {
     readable: bool,
-    buffer: Arc<Mutex<PipeRingBuffer>>,
+    writable: bool,
+    nonblock: AtomicBool,
+    inner: Arc<PipeInner>,
 }
 
 impl Pipe {
     pub fn new() -> (Pipe, Pipe) {
-        let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
-        let read_end = Pipe {
-            readable: true,
-            buffer: buffer.clone(),
-        };
-        let write_end = Pipe {
-            readable: false,
-            buffer,
-        };
+        let inner = PipeInner::new();
+        let read_end = Self::open(&inner, true, false, false);
+        let write_end = Self::open(&inner, false, true, false);
         (read_end, write_end)
     }
 
+    /// Opens an end of `inner`, and wakes the tasks waiting for it.
+    fn open(inner: &Arc<PipeInner>, readable: bool, writable: bool, nonblock: bool) -> Self {
+        let mut buf = inner.buffer.lock();
+        buf.readers += readable as usize;
+        buf.writers += writable as usize;
+        drop(buf);
+        inner.poll_set.wake();
+        Self {
+            readable,
+            writable,
+            nonblock: AtomicBool::new(nonblock),
+            inner: inner.clone(),
+        }
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EBADF)
+    }
+
     pub const fn readable(&self) -> bool {
         self.readable
     }
 
     pub const fn writable(&self) -> bool {
-        !self.readable
+        self.writable
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
     }
 
-    pub fn write_end_close(&self) -> bool {
-        Arc::strong_count(&self.buffer) == 1
+    /// Returns the capacity of the pipe in bytes.
+    pub fn capacity(&self) -> usize {
+        self.inner.buffer.lock().capacity
+    }
+
+    /// Sets the capacity of the pipe to at least `size` bytes, and returns
+    /// the new capacity.
+    ///
+    /// Like Linux, the capacity is rounded up to a power of two, and at least
+    /// one page. It cannot be less than the length of the data in the pipe.
+    pub fn set_capacity(&self, size: usize) -> LinuxResult<usize> {
+        if size > PIPE_MAX_SIZE {
+            return Err(LinuxError::EPERM);
+        }
+        let capacity = size.max(PIPE_BUF).next_power_of_two();
+        let mut buf = self.inner.buffer.lock();
+        if capacity < buf.data.len() {
+            return Err(LinuxError::EBUSY);
+        }
+        buf.capacity = capacity;
+        buf.data.shrink_to(capacity);
+        drop(buf);
+        // The writers may have more space now.
+        self.inner.poll_set.wake();
+        Ok(capacity)
     }
 }
 
 impl FileLike for Pipe {
+    /// Reads the available data, blocking while the pipe is empty and there
+    /// is any write end open.
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
         if !self.readable() {
-            return Err(LinuxError::EPERM);
+            return Err(LinuxError::EBADF);
         }
-        let mut read_size = 0usize;
-        let max_len = buf.len();
-        loop {
-            let mut ring_buffer = self.buffer.lock();
-            let loop_read = ring_buffer.available_read();
-            if loop_read == 0 {
-                if self.write_end_close() {
-                    return Ok(read_size);
-                }
-                drop(ring_buffer);
-                // Data not ready, wait for write end
-                crate::sys_sched_yield(); // TODO: use synconize primitive
-                continue;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let read_size = self.inner.poll_set.block_on(self.is_nonblocking(), || {
+            let mut pipe = self.inner.buffer.lock();
+            if pipe.data.is_empty() {
+                return if pipe.writers == 0 {
+                    Ok(0)
+                } else {
+                    Err(LinuxError::EAGAIN)
+                };
             }
-            for _ in 0..loop_read {
-                if read_size == max_len {
-                    return Ok(read_size);
-                }
-                buf[read_size] = ring_buffer.read_byte();
-                read_size += 1;
+            let n = buf.len().min(pipe.data.len());
+            for (dst, src) in buf.iter_mut().zip(pipe.data.drain(..n)) {
+                *dst = src;
             }
+            Ok(n)
+        })?;
+        if read_size > 0 {
+            self.inner.poll_set.wake();
         }
+        Ok(read_size)
     }
 
+    /// Writes all of `buf`, blocking while the pipe is full. Writes of at
+    /// most [`PIPE_BUF`] bytes are not interleaved with other writes.
+    ///
+    /// Returns `EPIPE` if there is no read end open. In the non-blocking
+    /// mode, writes as much as possible, or fails with `EAGAIN` if nothing
+    /// can be written.
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
         if !self.writable() {
-            return Err(LinuxError::EPERM);
+            return Err(LinuxError::EBADF);
         }
-        let mut write_size = 0usize;
-        let max_len = buf.len();
-        loop {
-            let mut ring_buffer = self.buffer.lock();
-            let loop_write = ring_buffer.available_write();
-            if loop_write == 0 {
-                drop(ring_buffer);
-                // Buffer is full, wait for read end to consume
-                crate::sys_sched_yield(); // TODO: use synconize primitive
-                continue;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let atomic = buf.len() <= PIPE_BUF;
+        let mut write_size = 0;
+        let res = self.inner.poll_set.block_on(self.is_nonblocking(), || {
+            let mut pipe = self.inner.buffer.lock();
+            if pipe.readers == 0 {
+                return Err(LinuxError::EPIPE);
             }
-            for _ in 0..loop_write {
-                if write_size == max_len {
-                    return Ok(write_size);
-                }
-                ring_buffer.write_byte(buf[write_size]);
-                write_size += 1;
+            let remaining = &buf[write_size..];
+            let space = pipe.available_write();
+            if space == 0 || (atomic && space < remaining.len()) {
+                return Err(LinuxError::EAGAIN);
             }
+            let n = space.min(remaining.len());
+            pipe.data.extend(&remaining[..n]);
+            write_size += n;
+            drop(pipe);
+            self.inner.poll_set.wake();
+            if write_size < buf.len() {
+                Err(LinuxError::EAGAIN)
+            } else {
+                Ok(())
+            }
+        });
+        match res {
+            Ok(()) => Ok(write_size),
+            // A partial write succeeds.
+            Err(LinuxError::EAGAIN | LinuxError::EPIPE) if write_size > 0 => Ok(write_size),
+            Err(e) => Err(e),
         }
     }
 
@@ -178,16 +228,45 @@ impl FileLike for Pipe {
     }
 
     fn poll(&self) -> LinuxResult<PollState> {
-        let buf = self.buffer.lock();
+        let pipe = self.inner.buffer.lock();
         Ok(PollState {
-            readable: self.readable() && buf.available_read() > 0,
-            writable: self.writable() && buf.available_write() > 0,
+            // The end of file is readable.
+            readable: self.readable() && (!pipe.data.is_empty() || pipe.writers == 0),
+            // So is a pipe without readers, where writing fails with `EPIPE`.
+            writable: self.writable() && (pipe.available_write() >= PIPE_BUF || pipe.readers == 0),
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblock.store(nonblocking, Ordering::Release);
         Ok(())
     }
+
+    fn register_poller(&self, waker: &Arc<dyn PollWaker>) -> bool {
+        self.inner.poll_set.register(waker);
+        true
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let mut pipe = self.inner.buffer.lock();
+        pipe.readers -= self.readable as usize;
+        pipe.writers -= self.writable as usize;
+        drop(pipe);
+        self.inner.poll_set.wake();
+    }
+}
+
+/// Returns the capacity of the pipe referred to by `fd` (`F_GETPIPE_SZ`).
+pub fn pipe_size(fd: c_int) -> LinuxResult<c_int> {
+    Ok(Pipe::from_fd(fd)?.capacity() as c_int)
+}
+
+/// Sets the capacity of the pipe referred to by `fd` (`F_SETPIPE_SZ`), and
+/// returns the new capacity.
+pub fn set_pipe_size(fd: c_int, size: usize) -> LinuxResult<c_int> {
+    Ok(Pipe::from_fd(fd)?.set_capacity(size)? as c_int)
 }
 
 /// Create a pipe
@@ -212,3 +291,92 @@ pub fn sys_pipe(fds: &mut [c_int]) -> c_int {
         Ok(0)
     })
 }
+
+#[cfg(feature = "fs")]
+pub use self::fifo::{open_fifo, sys_mkfifo};
+
+#[cfg(feature = "fs")]
+mod fifo {
+    use alloc::collections::BTreeMap;
+    use alloc::sync::{Arc, Weak};
+    use core::ffi::{c_char, c_int};
+
+    use axerrno::{LinuxError, LinuxResult};
+    use axsync::Mutex;
+
+    use super::{Pipe, PipeInner};
+    use crate::{ctypes, utils::char_ptr_to_str};
+
+    /// The pipes shared by the open ends of the named pipes, by the numbers
+    /// identifying the named pipes in axfs.
+    static FIFOS: Mutex<BTreeMap<u64, Weak<PipeInner>>> = Mutex::new(BTreeMap::new());
+
+    /// Opens the named pipe at `path` with the open `flags`, or returns
+    /// `None` if `path` is not a named pipe.
+    ///
+    /// Opening an end blocks until the other end is opened, unless
+    /// `O_NONBLOCK` is set. Then opening the write end fails with `ENXIO` if
+    /// the read end is not open.
+    pub fn open_fifo(path: &str, flags: c_int) -> LinuxResult<Option<Pipe>> {
+        let Some(id) = axfs::api::fifo_id(path) else {
+            return Ok(None);
+        };
+        let flags = flags as u32;
+        let (readable, writable) = match flags & 0b11 {
+            ctypes::O_RDONLY => (true, false),
+            ctypes::O_WRONLY => (false, true),
+            _ => (true, true),
+        };
+        let nonblock = flags & ctypes::O_NONBLOCK != 0;
+
+        let inner = {
+            let mut fifos = FIFOS.lock();
+            // Those of the named pipes that are not open any more.
+            fifos.retain(|_, inner| inner.strong_count() > 0);
+            let entry = fifos.entry(id).or_default();
+            match entry.upgrade() {
+                Some(inner) => inner,
+                None => {
+                    let inner = PipeInner::new();
+                    *entry = Arc::downgrade(&inner);
+                    inner
+                }
+            }
+        };
+        if writable && !readable && nonblock && inner.buffer.lock().readers == 0 {
+            return Err(LinuxError::ENXIO);
+        }
+        let pipe = Pipe::open(&inner, readable, writable, nonblock);
+        if !nonblock {
+            inner.poll_set.block_on(false, || {
+                let buf = inner.buffer.lock();
+                let ready = if !readable {
+                    buf.readers > 0
+                } else if !writable {
+                    buf.writers > 0
+                } else {
+                    true
+                };
+                if ready {
+                    Ok(())
+                } else {
+                    Err(LinuxError::EAGAIN)
+                }
+            })?;
+        }
+        Ok(Some(pipe))
+    }
+
+    /// Creates a named pipe at `pathname`, with the permissions of `mode`
+    /// masked by the umask.
+    ///
+    /// Only the RAM filesystems, such as `/tmp`, can store named pipes.
+    pub fn sys_mkfifo(pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
+        let pathname = char_ptr_to_str(pathname);
+        debug!("sys_mkfifo <= {:?} {:#o}", pathname, mode);
+        syscall_body!(sys_mkfifo, {
+            axfs::api::create_fifo(pathname?, crate::imp::fs::create_perm(mode))?;
+            Ok(0)
+        })
+    }
+}
@@ -0,0 +1,159 @@
+//! Readiness notification of files.
+//!
+//! A file that can tell when its readiness may change owns a [`PollSet`], and
+//! wakes the [`PollWaker`]s registered in it. `poll`, `select` and
+//! `epoll_wait` register wakers through [`FileLike::register_poller`], and
+//! sleep until one of them is woken.
+//!
+//! [`FileLike::register_poller`]: super::fd_ops::FileLike::register_poller
+
+// Only the pollers are used by signals without the `fd` feature.
+#![cfg_attr(not(feature = "fd"), allow(dead_code))]
+
+use alloc::sync::{Arc, Weak};
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+
+/// Something to be woken when the readiness of a file may change.
+pub trait PollWaker: Send + Sync {
+    fn wake(&self);
+}
+
+/// The wakers registered in a file.
+///
+/// The wakers are held weakly, and stay registered until they are dropped.
+#[derive(Default)]
+pub struct PollSet {
+    wakers: Mutex<Vec<Weak<dyn PollWaker>>>,
+}
+
+impl PollSet {
+    pub const fn new() -> Self {
+        Self {
+            wakers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers `waker`, to be woken until it is dropped.
+    pub fn register(&self, waker: &Arc<dyn PollWaker>) {
+        let mut wakers = self.wakers.lock();
+        wakers.retain(|w| w.strong_count() > 0);
+        wakers.push(Arc::downgrade(waker));
+    }
+
+    /// Calls `f` until it does not fail with `EAGAIN`, and blocks until the
+    /// set is woken between the calls. If `nonblocking` is set, `f` is called
+    /// only once.
+    ///
+    /// With the `signal` feature, fails with `EINTR` if a signal handler is
+    /// called while blocking, unless it was installed with `SA_RESTART`.
+    pub fn block_on<T>(
+        &self,
+        nonblocking: bool,
+        mut f: impl FnMut() -> LinuxResult<T>,
+    ) -> LinuxResult<T> {
+        match f() {
+            Err(LinuxError::EAGAIN) if !nonblocking => {}
+            res => return res,
+        }
+        // Registered before calling `f` again, so no wakeup is missed.
+        let poller = Arc::new(Poller::new());
+        let waker: Arc<dyn PollWaker> = poller.clone();
+        self.register(&waker);
+        #[cfg(feature = "signal")]
+        let _guard = super::signal::interrupt_on(&waker);
+        loop {
+            match f() {
+                Err(LinuxError::EAGAIN) => {
+                    #[cfg(feature = "signal")]
+                    super::signal::interrupted(true)?;
+                    poller.wait(None);
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Wakes all the registered wakers.
+    pub fn wake(&self) {
+        let wakers: Vec<_> = {
+            let mut wakers = self.wakers.lock();
+            if wakers.is_empty() {
+                return;
+            }
+            wakers.retain(|w| w.strong_count() > 0);
+            wakers.iter().filter_map(Weak::upgrade).collect()
+        };
+        // Wake without the lock held, as wakers may take their own locks.
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Wakes the set from a [`Waker`](core::task::Waker), for the files whose
+/// events are reported by wakers, like inet sockets.
+impl Wake for PollSet {
+    fn wake(self: Arc<Self>) {
+        PollSet::wake(&self);
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        PollSet::wake(self);
+    }
+}
+
+/// A waker that blocks the task waiting for it.
+pub struct Poller {
+    woken: AtomicBool,
+    #[cfg(feature = "multitask")]
+    wq: axtask::WaitQueue,
+}
+
+impl Poller {
+    pub const fn new() -> Self {
+        Self {
+            woken: AtomicBool::new(false),
+            #[cfg(feature = "multitask")]
+            wq: axtask::WaitQueue::new(),
+        }
+    }
+
+    /// Blocks the current task until the poller is woken, or `timeout` has
+    /// elapsed.
+    ///
+    /// Without the `multitask` feature, or without the `irq` feature if a
+    /// timeout is given, it just yields the CPU and the caller polls again.
+    pub fn wait(&self, timeout: Option<Duration>) {
+        #[cfg(feature = "multitask")]
+        match timeout {
+            None => self.wq.wait_until(|| self.woken.load(Ordering::Acquire)),
+            #[cfg(feature = "irq")]
+            Some(dur) => {
+                self.wq
+                    .wait_timeout_until(dur, || self.woken.load(Ordering::Acquire));
+            }
+            #[cfg(not(feature = "irq"))]
+            Some(_) => crate::sys_sched_yield(),
+        }
+        #[cfg(not(feature = "multitask"))]
+        {
+            let _ = timeout;
+            crate::sys_sched_yield();
+        }
+        self.woken.store(false, Ordering::Release);
+    }
+}
+
+impl PollWaker for Poller {
+    fn wake(&self) {
+        self.woken.store(true, Ordering::Release);
+        #[cfg(feature = "multitask")]
+        self.wq.notify_all(false);
+    }
+}
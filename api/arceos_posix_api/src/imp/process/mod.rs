@@ -0,0 +1,474 @@
+//! User processes, for running Linux programs in user space.
+//!
+//! Each process runs in a single task, whose ID is the process ID, and has its
+//! own address space and namespace, hence its own file descriptor table. The
+//! first process is started by [`run_user_app`]; the others are forked from
+//! it, and may replace their image with `execve`.
+
+mod syscall;
+
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::ffi::{c_char, c_int};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::context::{TrapFrame, UspaceContext};
+use axhal::mem::VirtAddr;
+use axhal::trap::{PAGE_FAULT, PageFaultFlags, register_trap_handler};
+use axloader::ElfFile;
+use axmm::AddrSpace;
+use axns::{AxNamespace, AxNamespaceIf};
+use axsync::Mutex;
+use axtask::{AxTaskRef, TaskExtRef, TaskInner, WaitQueue};
+use memory_addr::VirtAddrRange;
+
+use super::fd_ops::{FD_TABLE, close_all_files, close_cloexec_files, copy_fd_table};
+use crate::utils::char_ptr_to_str;
+
+/// The lowest address of user space.
+const USER_SPACE_BASE: usize = 0x1000;
+/// The size of user space.
+const USER_SPACE_SIZE: usize = 0x3f_ffff_f000;
+/// The top of the user stack, at the end of user space.
+const USER_STACK_TOP: usize = USER_SPACE_BASE + USER_SPACE_SIZE;
+/// The size of the user stack.
+const USER_STACK_SIZE: usize = 0x4_0000;
+/// Where `mmap` starts to look for free space.
+const USER_MMAP_BASE: usize = 0x10_0000_0000;
+
+/// Do not block in `wait4` if no child has exited.
+const WNOHANG: u32 = 1;
+/// The signal that kills a process on an invalid memory access.
+const SIGSEGV: i32 = 11;
+/// The number of signals.
+const NSIG: usize = 64;
+/// The handler of ignored signals.
+const SIG_IGN: usize = 1;
+/// The maximum total size of the arguments and the environment of a program,
+/// counting the pointers to the strings and their terminators.
+const ARG_MAX: usize = 0x2_0000;
+
+/// `struct sigaction` as the system call takes it, in words: the handler, the
+/// flags, the restorer on the architectures that have one, and the mask.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+type SigAction = [usize; 4];
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+type SigAction = [usize; 3];
+
+/// A user process.
+pub struct Process {
+    pid: u64,
+    parent: spin::Mutex<Weak<Process>>,
+    children: spin::Mutex<Vec<Arc<Process>>>,
+    aspace: Mutex<AddrSpace>,
+    ns: AxNamespace,
+    /// The start and the current end of the heap.
+    heap: Mutex<(VirtAddr, VirtAddr)>,
+    /// The wait status, valid once the process is a zombie.
+    exit_status: AtomicI32,
+    zombie: AtomicBool,
+    /// Woken when a child exits.
+    child_exit: WaitQueue,
+    /// The actions of the signals, set by `rt_sigaction`. Signals are not
+    /// delivered to user space, so they are only recorded.
+    sigactions: spin::Mutex<[SigAction; NSIG]>,
+}
+
+/// The task extended data of user tasks.
+struct TaskExt {
+    process: Arc<Process>,
+    /// The address to clear and wake as a futex when the thread exits, set by
+    /// `set_tid_address`.
+    clear_child_tid: AtomicUsize,
+    /// The signal mask of the thread, set by `rt_sigprocmask`.
+    sigmask: AtomicU64,
+}
+
+axtask::def_task_ext!(TaskExt);
+
+struct AxNamespaceImpl;
+
+#[crate_interface::impl_interface]
+impl AxNamespaceIf for AxNamespaceImpl {
+    fn current_namespace_base() -> *mut u8 {
+        match axtask::current_may_uninit() {
+            Some(curr) if !unsafe { curr.task_ext_ptr() }.is_null() => {
+                curr.task_ext().process.ns.base()
+            }
+            _ => AxNamespace::global().base(),
+        }
+    }
+}
+
+/// Returns the process of the current task, or `None` for a kernel task.
+fn current_process() -> Option<Arc<Process>> {
+    let curr = axtask::current_may_uninit()?;
+    if unsafe { curr.task_ext_ptr() }.is_null() {
+        return None;
+    }
+    Some(curr.task_ext().process.clone())
+}
+
+/// Returns the range of the user stack.
+fn user_stack() -> VirtAddrRange {
+    VirtAddrRange::from_start_size(
+        VirtAddr::from(USER_STACK_TOP - USER_STACK_SIZE),
+        USER_STACK_SIZE,
+    )
+}
+
+impl Process {
+    /// Returns the wait status of a process that exits with `code`.
+    const fn exit_status(code: i32) -> i32 {
+        (code & 0xff) << 8
+    }
+
+    /// Returns the wait status of a process killed by `sig`.
+    const fn killed_status(sig: i32) -> i32 {
+        sig & 0x7f
+    }
+
+    fn is_zombie(&self) -> bool {
+        self.zombie.load(Ordering::Acquire)
+    }
+}
+
+/// Creates a namespace whose file descriptor table holds `fd_table`.
+fn new_namespace(fd_table: super::fd_ops::FdTable) -> AxNamespace {
+    let mut ns = AxNamespace::new_thread_local();
+    FD_TABLE
+        .deref_from_mut(&mut ns)
+        .reinit_shared(Arc::new(spin::RwLock::new(fd_table)));
+    ns
+}
+
+/// Starts a process running `uctx` in `aspace` with the user thread pointer
+/// `tls`, as a child of `parent`.
+///
+/// A child inherits the signal actions of `parent`, and the signal mask of
+/// the current thread, which forks it.
+fn spawn_process(
+    name: String,
+    uctx: UspaceContext,
+    tls: usize,
+    aspace: AddrSpace,
+    ns: AxNamespace,
+    heap: (VirtAddr, VirtAddr),
+    parent: Option<&Arc<Process>>,
+) -> (Arc<Process>, AxTaskRef) {
+    let mut task = TaskInner::new(
+        move || {
+            let kstack_top = axtask::current().kernel_stack_top().unwrap();
+            unsafe { uctx.enter_uspace(kstack_top) }
+        },
+        name,
+        axconfig::TASK_STACK_SIZE,
+    );
+    task.ctx_mut().set_page_table_root(aspace.page_table_root());
+    // The user thread pointer is in the task context on these architectures,
+    // and in the trap frame on the others.
+    #[cfg(target_arch = "x86_64")]
+    {
+        task.ctx_mut().fs_base = tls;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        task.ctx_mut().tpidr_el0 = tls as u64;
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = tls;
+    let process = Arc::new(Process {
+        pid: task.id().as_u64(),
+        parent: spin::Mutex::new(parent.map_or(Weak::new(), Arc::downgrade)),
+        children: spin::Mutex::new(Vec::new()),
+        aspace: Mutex::new(aspace),
+        ns,
+        heap: Mutex::new(heap),
+        exit_status: AtomicI32::new(0),
+        zombie: AtomicBool::new(false),
+        child_exit: WaitQueue::new(),
+        sigactions: spin::Mutex::new(parent.map_or([SigAction::default(); NSIG], |parent| {
+            *parent.sigactions.lock()
+        })),
+    });
+    let sigmask = parent.map_or(0, |_| {
+        axtask::current().task_ext().sigmask.load(Ordering::Acquire)
+    });
+    if let Some(parent) = parent {
+        parent.children.lock().push(process.clone());
+    }
+    task.init_task_ext(TaskExt {
+        process: process.clone(),
+        clear_child_tid: AtomicUsize::new(0),
+        sigmask: AtomicU64::new(sigmask),
+    });
+    (process, axtask::spawn_task(task))
+}
+
+/// Forks the current process, which trapped into the kernel with `tf`.
+///
+/// The child shares the memory of the parent copy-on-write, and gets a copy
+/// of its file descriptor table.
+fn fork(tf: &TrapFrame, stack: usize) -> LinuxResult<u64> {
+    let parent = current_process().ok_or(LinuxError::EPERM)?;
+    let aspace = parent.aspace.lock().clone_cow()?;
+    let ns = new_namespace(copy_fd_table(false));
+    let mut uctx = UspaceContext::from(tf);
+    if stack != 0 {
+        uctx.set_sp(stack);
+    }
+    uctx.set_retval(0);
+    let heap = *parent.heap.lock();
+    let (child, _) = spawn_process(
+        axtask::current().name(),
+        uctx,
+        axhal::asm::read_thread_pointer(),
+        aspace,
+        ns,
+        heap,
+        Some(&parent),
+    );
+    Ok(child.pid)
+}
+
+/// Replaces the image of the current process with the program at `path`,
+/// closes its files marked close-on-exec and resets its caught signals to
+/// their default actions.
+///
+/// Returns the context to enter the new image. Errors after the old image is
+/// gone kill the process instead.
+fn exec(path: &str, args: &[String], envs: &[String]) -> LinuxResult<UspaceContext> {
+    let process = current_process().ok_or(LinuxError::EPERM)?;
+    let data = axfs::api::read(path)?;
+    let elf = ElfFile::parse(&data).map_err(|_| LinuxError::ENOEXEC)?;
+
+    let mut aspace = process.aspace.lock();
+    aspace.clear();
+    let res = elf.load(&mut aspace, args, envs, user_stack());
+    drop(aspace);
+    drop(elf);
+    drop(data);
+    let image = match res {
+        Ok(image) => image,
+        Err(e) => {
+            warn!("failed to load {:?}: {:?}", path, e);
+            drop(process);
+            exit_current(Process::killed_status(SIGSEGV));
+        }
+    };
+    *process.heap.lock() = (image.brk, image.brk);
+    close_cloexec_files();
+    for action in process.sigactions.lock().iter_mut() {
+        if action[0] != SIG_IGN {
+            *action = SigAction::default();
+        }
+    }
+    Ok(image.uspace_context())
+}
+
+/// Exits the current process as killed by the signal `sig`, or returns if the
+/// current task is a kernel task.
+pub(crate) fn exit_on_signal(sig: i32) {
+    if current_process().is_some() {
+        exit_current(Process::killed_status(sig));
+    }
+}
+
+/// Exits the current process with the wait status `status`.
+///
+/// Its files and memory are released at once, and the rest when the parent
+/// waits for it. Its children are orphaned, and freed when they exit.
+fn exit_current(status: i32) -> ! {
+    let process = current_process().expect("not in a user process");
+    debug!("process {} exits with status {:#x}", process.pid, status);
+    let tidptr = axtask::current()
+        .task_ext()
+        .clear_child_tid
+        .load(Ordering::Acquire);
+    if tidptr != 0 && syscall::check_user_write(tidptr as *mut u32, 1).is_ok() {
+        let futex = unsafe { &*(tidptr as *const AtomicU32) };
+        futex.store(0, Ordering::Release);
+        axtask::futex_wake(futex, 1);
+    }
+    close_all_files();
+    process.aspace.lock().clear();
+    for child in process.children.lock().drain(..) {
+        *child.parent.lock() = Weak::new();
+    }
+    process.exit_status.store(status, Ordering::Release);
+    process.zombie.store(true, Ordering::Release);
+    if let Some(parent) = process.parent.lock().upgrade() {
+        parent.child_exit.notify_all(false);
+    }
+    drop(process);
+    axtask::exit(status);
+}
+
+/// Waits for a child process selected by `pid` to exit, as `wait4`.
+///
+/// Returns its process ID and wait status, or `None` if `WNOHANG` is set and
+/// none has exited.
+fn wait4(pid: i32, options: u32) -> LinuxResult<Option<(u64, i32)>> {
+    let process = current_process().ok_or(LinuxError::ECHILD)?;
+    // Process groups are not supported, so `pid <= 0` means any child.
+    let selected = |child: &Arc<Process>| pid <= 0 || child.pid == pid as u64;
+    loop {
+        {
+            let mut children = process.children.lock();
+            if !children.iter().any(selected) {
+                return Err(LinuxError::ECHILD);
+            }
+            if let Some(idx) = children.iter().position(|c| selected(c) && c.is_zombie()) {
+                let child = children.remove(idx);
+                let status = child.exit_status.load(Ordering::Acquire);
+                return Ok(Some((child.pid, status)));
+            }
+        }
+        if options & WNOHANG != 0 {
+            return Ok(None);
+        }
+        process.child_exit.wait_until(|| {
+            let children = process.children.lock();
+            children.iter().any(|c| selected(c) && c.is_zombie())
+        });
+    }
+}
+
+#[register_trap_handler(PAGE_FAULT)]
+fn handle_page_fault(vaddr: VirtAddr, access_flags: PageFaultFlags, is_user: bool) -> bool {
+    let Some(process) = current_process() else {
+        return false;
+    };
+    if process.aspace.lock().handle_page_fault(vaddr, access_flags) {
+        return true;
+    }
+    if !is_user {
+        return false;
+    }
+    warn!(
+        "process {} killed by a page fault at {:#x} ({:?})",
+        process.pid, vaddr, access_flags
+    );
+    drop(process);
+    exit_current(Process::killed_status(SIGSEGV));
+}
+
+/// Runs the program `args[0]` in a new process with the environment `envs`,
+/// and waits for it to exit.
+///
+/// The process starts with the file descriptors of the kernel, and its system
+/// calls are handled by the POSIX API.
+///
+/// Returns its exit code, or the negated signal number that killed it.
+pub fn run_user_app(args: &[String], envs: &[String]) -> LinuxResult<i32> {
+    let path = args.first().ok_or(LinuxError::EINVAL)?;
+    run_program(path, args, envs)
+}
+
+/// Like [`run_user_app`], with the program at `path`.
+fn run_program(path: &str, args: &[String], envs: &[String]) -> LinuxResult<i32> {
+    let mut aspace = axmm::new_user_aspace(VirtAddr::from(USER_SPACE_BASE), USER_SPACE_SIZE)?;
+    let image = axloader::load_user_app(&mut aspace, path, args, envs, user_stack())?;
+
+    let uctx = image.uspace_context();
+    let ns = new_namespace(copy_fd_table(true));
+    let heap = (image.brk, image.brk);
+    let (process, task) = spawn_process(path.into(), uctx, 0, aspace, ns, heap, None);
+    task.join();
+    let status = process.exit_status.load(Ordering::Acquire);
+    Ok(match status & 0x7f {
+        0 => (status >> 8) & 0xff,
+        sig => -sig,
+    })
+}
+
+/// Takes the space of the argument or environment string `s` from `budget`,
+/// what is left of [`ARG_MAX`], or fails with `E2BIG` if it does not fit.
+fn take_arg_space(budget: &mut usize, s: &str) -> LinuxResult {
+    let size = size_of::<usize>() + s.len() + 1;
+    *budget = budget.checked_sub(size).ok_or(LinuxError::E2BIG)?;
+    Ok(())
+}
+
+/// Reads a NULL-terminated array of strings, taking their space from
+/// `budget`.
+unsafe fn read_str_array(
+    array: *const *const c_char,
+    budget: &mut usize,
+) -> LinuxResult<Vec<String>> {
+    let mut strs = Vec::new();
+    if array.is_null() {
+        return Ok(strs);
+    }
+    for i in 0.. {
+        let ptr = unsafe { array.add(i).read() };
+        if ptr.is_null() {
+            break;
+        }
+        let s = char_ptr_to_str(ptr)?;
+        take_arg_space(budget, s)?;
+        strs.push(s.into());
+    }
+    Ok(strs)
+}
+
+/// Creates a copy of the calling process.
+///
+/// Only user processes can fork, through the system call, so this fails with
+/// `ENOSYS` in kernel tasks such as C applications.
+pub fn sys_fork() -> c_int {
+    debug!("sys_fork");
+    syscall_body!(sys_fork, Err::<c_int, _>(LinuxError::ENOSYS))
+}
+
+/// Runs the program at `path` with the arguments `argv` and the environment
+/// `envp`, in place of the calling kernel task.
+///
+/// The program runs in a new user process, as [`run_user_app`], without the
+/// files marked close-on-exec, and the task exits with its exit code once it
+/// exits, or with 128 plus the number of the signal that killed it. Returns
+/// only if the program cannot be started, with `E2BIG` if the arguments and
+/// the environment take more than 128 KiB.
+pub unsafe fn sys_execve(
+    path: *const c_char,
+    argv: *const *const c_char,
+    envp: *const *const c_char,
+) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_execve <= {:?}", path);
+    let ret = syscall_body!(sys_execve, {
+        let path = path?;
+        let mut budget = ARG_MAX;
+        let args = unsafe { read_str_array(argv, &mut budget) }?;
+        let envs = unsafe { read_str_array(envp, &mut budget) }?;
+        let code = run_program(path, &args, &envs)?;
+        Ok(if code < 0 { 128 - code } else { code })
+    });
+    // An error, or the exit code of the program.
+    if ret < 0 {
+        return ret;
+    }
+    crate::sys_exit(ret)
+}
+
+/// Waits for a child process selected by `pid` to exit, and stores its wait
+/// status in `wstatus` if it is not null.
+///
+/// Returns the process ID of the child, or 0 if `WNOHANG` is set in `options`
+/// and none has exited. Kernel tasks have no children, so they get `ECHILD`.
+pub unsafe fn sys_waitpid(pid: c_int, wstatus: *mut c_int, options: c_int) -> c_int {
+    debug!("sys_waitpid <= {} {:#x}", pid, options);
+    syscall_body!(sys_waitpid, {
+        let Some((pid, status)) = wait4(pid, options as u32)? else {
+            return Ok(0);
+        };
+        if !wstatus.is_null() {
+            unsafe { wstatus.write(status) };
+        }
+        Ok(pid as c_int)
+    })
+}
@@ -0,0 +1,652 @@
+//! The system call dispatcher of user processes.
+
+use alloc::{string::String, vec::Vec};
+use core::ffi::{c_char, c_int};
+use core::sync::atomic::Ordering;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::context::TrapFrame;
+use axhal::mem::{PAGE_SIZE_4K, VirtAddr, VirtAddrRange};
+use axhal::paging::MappingFlags;
+use axhal::trap::{SYSCALL, register_trap_handler};
+use axtask::TaskExtRef;
+use memory_addr::{MemoryAddr, align_up_4k};
+
+use super::{
+    ARG_MAX, NSIG, SigAction, USER_MMAP_BASE, USER_SPACE_BASE, USER_SPACE_SIZE, current_process,
+    take_arg_space,
+};
+use crate::{ctypes, utils::char_ptr_to_str};
+
+/// System call numbers of x86_64.
+#[cfg(target_arch = "x86_64")]
+mod sysno {
+    pub const READ: usize = 0;
+    pub const WRITE: usize = 1;
+    pub const OPEN: usize = 2;
+    pub const CLOSE: usize = 3;
+    pub const STAT: usize = 4;
+    pub const FSTAT: usize = 5;
+    pub const LSTAT: usize = 6;
+    pub const LSEEK: usize = 8;
+    pub const MMAP: usize = 9;
+    pub const MUNMAP: usize = 11;
+    pub const BRK: usize = 12;
+    pub const RT_SIGACTION: usize = 13;
+    pub const RT_SIGPROCMASK: usize = 14;
+    pub const IOCTL: usize = 16;
+    pub const WRITEV: usize = 20;
+    pub const ACCESS: usize = 21;
+    pub const PIPE: usize = 22;
+    pub const SCHED_YIELD: usize = 24;
+    pub const DUP: usize = 32;
+    pub const DUP2: usize = 33;
+    pub const NANOSLEEP: usize = 35;
+    pub const GETPID: usize = 39;
+    pub const CLONE: usize = 56;
+    pub const FORK: usize = 57;
+    pub const VFORK: usize = 58;
+    pub const EXECVE: usize = 59;
+    pub const EXIT: usize = 60;
+    pub const WAIT4: usize = 61;
+    pub const UNAME: usize = 63;
+    pub const FCNTL: usize = 72;
+    pub const FSYNC: usize = 74;
+    pub const FTRUNCATE: usize = 77;
+    pub const GETCWD: usize = 79;
+    pub const CHDIR: usize = 80;
+    pub const FCHDIR: usize = 81;
+    pub const RENAME: usize = 82;
+    pub const MKDIR: usize = 83;
+    pub const RMDIR: usize = 84;
+    pub const UNLINK: usize = 87;
+    pub const UMASK: usize = 95;
+    pub const GETPPID: usize = 110;
+    pub const ARCH_PRCTL: usize = 158;
+    pub const GETTID: usize = 186;
+    pub const GETDENTS64: usize = 217;
+    pub const SET_TID_ADDRESS: usize = 218;
+    pub const CLOCK_GETTIME: usize = 228;
+    pub const EXIT_GROUP: usize = 231;
+    pub const OPENAT: usize = 257;
+    pub const MKDIRAT: usize = 258;
+    pub const NEWFSTATAT: usize = 262;
+    pub const UNLINKAT: usize = 263;
+    pub const RENAMEAT: usize = 264;
+    pub const FACCESSAT: usize = 269;
+    pub const DUP3: usize = 292;
+    pub const PIPE2: usize = 293;
+}
+
+/// System call numbers of the generic table, used by RISC-V, AArch64 and
+/// LoongArch.
+#[cfg(not(target_arch = "x86_64"))]
+mod sysno {
+    pub const GETCWD: usize = 17;
+    pub const DUP: usize = 23;
+    pub const DUP3: usize = 24;
+    pub const FCNTL: usize = 25;
+    pub const IOCTL: usize = 29;
+    pub const MKDIRAT: usize = 34;
+    pub const UNLINKAT: usize = 35;
+    pub const RENAMEAT: usize = 38;
+    pub const FTRUNCATE: usize = 46;
+    pub const FACCESSAT: usize = 48;
+    pub const CHDIR: usize = 49;
+    pub const FCHDIR: usize = 50;
+    pub const OPENAT: usize = 56;
+    pub const CLOSE: usize = 57;
+    pub const PIPE2: usize = 59;
+    pub const GETDENTS64: usize = 61;
+    pub const LSEEK: usize = 62;
+    pub const READ: usize = 63;
+    pub const WRITE: usize = 64;
+    pub const WRITEV: usize = 66;
+    pub const NEWFSTATAT: usize = 79;
+    pub const FSTAT: usize = 80;
+    pub const FSYNC: usize = 82;
+    pub const EXIT: usize = 93;
+    pub const EXIT_GROUP: usize = 94;
+    pub const SET_TID_ADDRESS: usize = 96;
+    pub const NANOSLEEP: usize = 101;
+    pub const CLOCK_GETTIME: usize = 113;
+    pub const SCHED_YIELD: usize = 124;
+    pub const RT_SIGACTION: usize = 134;
+    pub const RT_SIGPROCMASK: usize = 135;
+    pub const UNAME: usize = 160;
+    pub const UMASK: usize = 166;
+    pub const GETPID: usize = 172;
+    pub const GETPPID: usize = 173;
+    pub const GETTID: usize = 178;
+    pub const BRK: usize = 214;
+    pub const MUNMAP: usize = 215;
+    pub const CLONE: usize = 220;
+    pub const EXECVE: usize = 221;
+    pub const MMAP: usize = 222;
+    pub const WAIT4: usize = 260;
+}
+
+const CLONE_VM: usize = 0x100;
+
+const PROT_READ: usize = 1;
+const PROT_WRITE: usize = 2;
+const PROT_EXEC: usize = 4;
+const MAP_FIXED: usize = 0x10;
+const MAP_ANONYMOUS: usize = 0x20;
+
+#[cfg(target_arch = "x86_64")]
+const ARCH_SET_FS: usize = 0x1002;
+#[cfg(target_arch = "x86_64")]
+const ARCH_GET_FS: usize = 0x1003;
+
+/// The length of each field of `struct utsname`.
+const UTSNAME_LEN: usize = 65;
+
+#[register_trap_handler(SYSCALL)]
+fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
+    let args = [
+        tf.arg0(),
+        tf.arg1(),
+        tf.arg2(),
+        tf.arg3(),
+        tf.arg4(),
+        tf.arg5(),
+    ];
+    trace!("syscall {} <= {:#x?}", syscall_num, args);
+    let ret = dispatch(tf, syscall_num, args);
+    trace!("syscall {} => {}", syscall_num, ret);
+    ret
+}
+
+/// Converts the result of a system call implemented here.
+fn ret(res: LinuxResult<usize>) -> isize {
+    match res {
+        Ok(v) => v as isize,
+        Err(e) => -(e.code() as isize),
+    }
+}
+
+/// Checks that the `len` bytes at `addr` are in user space, and mapped in the
+/// current process with `flags`.
+fn check_user(addr: usize, len: usize, flags: MappingFlags) -> LinuxResult {
+    if len == 0 {
+        return Ok(());
+    }
+    let end = addr.checked_add(len).ok_or(LinuxError::EFAULT)?;
+    if addr < USER_SPACE_BASE || end > USER_SPACE_BASE + USER_SPACE_SIZE {
+        return Err(LinuxError::EFAULT);
+    }
+    let process = current_process().ok_or(LinuxError::EFAULT)?;
+    if process
+        .aspace
+        .lock()
+        .can_access_range(VirtAddr::from(addr), len, flags)
+    {
+        Ok(())
+    } else {
+        Err(LinuxError::EFAULT)
+    }
+}
+
+/// Checks that `count` values of `T` can be read at `ptr`.
+fn check_user_read<T>(ptr: *const T, count: usize) -> LinuxResult {
+    let len = count
+        .checked_mul(size_of::<T>())
+        .ok_or(LinuxError::EFAULT)?;
+    check_user(ptr as usize, len, MappingFlags::READ)
+}
+
+/// Checks that `count` values of `T` can be written at `ptr`.
+pub(super) fn check_user_write<T>(ptr: *mut T, count: usize) -> LinuxResult {
+    let len = count
+        .checked_mul(size_of::<T>())
+        .ok_or(LinuxError::EFAULT)?;
+    check_user(ptr as usize, len, MappingFlags::READ | MappingFlags::WRITE)
+}
+
+/// Like [`check_user_read`] for a single value, but `ptr` may be null.
+fn check_user_read_opt<T>(ptr: *const T) -> LinuxResult {
+    if ptr.is_null() {
+        Ok(())
+    } else {
+        check_user_read(ptr, 1)
+    }
+}
+
+/// Like [`check_user_write`] for a single value, but `ptr` may be null.
+fn check_user_write_opt<T>(ptr: *mut T) -> LinuxResult {
+    if ptr.is_null() {
+        Ok(())
+    } else {
+        check_user_write(ptr, 1)
+    }
+}
+
+/// Checks that a NUL-terminated string can be read at `ptr`.
+fn check_user_str(ptr: *const c_char) -> LinuxResult {
+    let mut addr = ptr as usize;
+    loop {
+        // Up to the end of the page, which is mapped or not as a whole.
+        let len = PAGE_SIZE_4K - addr % PAGE_SIZE_4K;
+        check_user(addr, len, MappingFlags::READ)?;
+        let bytes = unsafe { core::slice::from_raw_parts(addr as *const u8, len) };
+        if bytes.contains(&0) {
+            return Ok(());
+        }
+        addr += len;
+    }
+}
+
+/// Checks the buffers described by the `iovcnt` entries of `iov`.
+fn check_user_iovecs(iov: *const ctypes::iovec, iovcnt: usize) -> LinuxResult {
+    check_user_read(iov, iovcnt)?;
+    for i in 0..iovcnt {
+        let iov = unsafe { iov.add(i).read() };
+        check_user_read(iov.iov_base as *const u8, iov.iov_len)?;
+    }
+    Ok(())
+}
+
+/// Checks the pointers among the arguments of the system call `sysno`, so
+/// that its implementation can access the memory they point to.
+fn check_args(sysno: usize, args: [usize; 6]) -> LinuxResult {
+    use self::sysno::*;
+
+    let [a0, a1, a2, a3, ..] = args;
+    let stat = |ptr: usize| check_user_write(ptr as *mut ctypes::stat, 1);
+    match sysno {
+        READ => check_user_write(a1 as *mut u8, a2),
+        WRITE => check_user_read(a1 as *const u8, a2),
+        // The count is checked by `sys_writev` first.
+        WRITEV if a2 <= 1024 => check_user_iovecs(a1 as _, a2),
+        OPENAT | MKDIRAT | UNLINKAT | FACCESSAT => check_user_str(a1 as _),
+        RENAMEAT => check_user_str(a1 as _).and(check_user_str(a3 as _)),
+        RT_SIGACTION => check_user_read_opt(a1 as *const SigAction)
+            .and(check_user_write_opt(a2 as *mut SigAction)),
+        RT_SIGPROCMASK => {
+            check_user_read_opt(a1 as *const u64).and(check_user_write_opt(a2 as *mut u64))
+        }
+        FSTAT => stat(a1),
+        NEWFSTATAT => check_user_str(a1 as _).and(stat(a2)),
+        GETDENTS64 => check_user_write(a1 as *mut u8, a2),
+        PIPE2 => check_user_write(a0 as *mut c_int, 2),
+        GETCWD => check_user_write(a0 as *mut u8, a1),
+        CHDIR => check_user_str(a0 as _),
+        CLOCK_GETTIME => check_user_write(a1 as *mut ctypes::timespec, 1),
+        NANOSLEEP => check_user_read(a0 as *const ctypes::timespec, 1)
+            .and(check_user_write_opt(a1 as *mut ctypes::timespec)),
+        UNAME => check_user_write(a0 as *mut [[u8; UTSNAME_LEN]; 6], 1),
+        WAIT4 => check_user_write_opt(a1 as *mut i32),
+        EXECVE => check_user_str(a0 as _),
+        #[cfg(target_arch = "x86_64")]
+        OPEN | ACCESS | MKDIR | RMDIR | UNLINK => check_user_str(a0 as _),
+        #[cfg(target_arch = "x86_64")]
+        STAT | LSTAT => check_user_str(a0 as _).and(stat(a1)),
+        #[cfg(target_arch = "x86_64")]
+        RENAME => check_user_str(a0 as _).and(check_user_str(a1 as _)),
+        #[cfg(target_arch = "x86_64")]
+        PIPE => check_user_write(a0 as *mut c_int, 2),
+        #[cfg(target_arch = "x86_64")]
+        ARCH_PRCTL if a0 == ARCH_GET_FS => check_user_write(a1 as *mut usize, 1),
+        _ => Ok(()),
+    }
+}
+
+fn dispatch(tf: &TrapFrame, sysno: usize, args: [usize; 6]) -> isize {
+    use self::sysno::*;
+
+    if let Err(e) = check_args(sysno, args) {
+        return -(e.code() as isize);
+    }
+    let [a0, a1, a2, a3, a4, a5] = args;
+    unsafe {
+        match sysno {
+            READ => crate::sys_read(a0 as _, a1 as _, a2) as _,
+            WRITE => crate::sys_write(a0 as _, a1 as _, a2) as _,
+            WRITEV => crate::sys_writev(a0 as _, a1 as _, a2 as _) as _,
+            OPENAT => crate::sys_openat(a0 as _, a1 as _, a2 as _, a3 as _) as _,
+            CLOSE => crate::sys_close(a0 as _) as _,
+            LSEEK => crate::sys_lseek(a0 as _, a1 as _, a2 as _) as _,
+            FSTAT => crate::sys_fstat(a0 as _, a1 as _) as _,
+            NEWFSTATAT => crate::sys_fstatat(a0 as _, a1 as _, a2 as _, a3 as _) as _,
+            GETDENTS64 => crate::sys_getdents64(a0 as _, a1 as _, a2) as _,
+            DUP => crate::sys_dup(a0 as _) as _,
+            DUP3 if a0 == a1 => -(LinuxError::EINVAL.code() as isize),
+            DUP3 => crate::sys_dup2(a0 as _, a1 as _) as _,
+            FCNTL => crate::sys_fcntl(a0 as _, a1 as _, a2) as _,
+            IOCTL => crate::sys_ioctl(a0 as _, a1 as _, a2) as _,
+            PIPE2 => crate::sys_pipe(core::slice::from_raw_parts_mut(a0 as *mut c_int, 2)) as _,
+            MKDIRAT => crate::sys_mkdirat(a0 as _, a1 as _, a2 as _) as _,
+            UNLINKAT => crate::sys_unlinkat(a0 as _, a1 as _, a2 as _) as _,
+            RENAMEAT => crate::sys_renameat(a0 as _, a1 as _, a2 as _, a3 as _) as _,
+            FACCESSAT => crate::sys_faccessat(a0 as _, a1 as _, a2 as _, 0) as _,
+            FTRUNCATE => crate::sys_ftruncate(a0 as _, a1 as _) as _,
+            FSYNC => crate::sys_fsync(a0 as _) as _,
+            GETCWD => sys_getcwd(a0 as _, a1),
+            CHDIR => crate::sys_chdir(a0 as _) as _,
+            FCHDIR => crate::sys_fchdir(a0 as _) as _,
+            UMASK => crate::sys_umask(a0 as _) as _,
+            CLOCK_GETTIME => crate::sys_clock_gettime(a0 as _, a1 as _) as _,
+            NANOSLEEP => crate::sys_nanosleep(a0 as _, a1 as _) as _,
+            SCHED_YIELD => crate::sys_sched_yield() as _,
+            RT_SIGACTION => ret(sys_rt_sigaction(a0, a1 as _, a2 as _, a3)),
+            RT_SIGPROCMASK => ret(sys_rt_sigprocmask(a0 as _, a1 as _, a2 as _, a3)),
+            GETPID => current_process().map_or(0, |process| process.pid as _),
+            GETTID => axtask::current().id().as_u64() as _,
+            SET_TID_ADDRESS => sys_set_tid_address(a0),
+            GETPPID => sys_getppid(),
+            UNAME => ret(sys_uname(a0 as _)),
+            BRK => sys_brk(a0) as _,
+            MMAP => ret(sys_mmap(a0, a1, a2, a3, a4 as _, a5)),
+            MUNMAP => ret(sys_munmap(a0, a1)),
+            CLONE => ret(sys_clone(tf, a0, a1)),
+            EXECVE => ret(sys_execve(a0 as _, a1 as _, a2 as _)),
+            WAIT4 => ret(sys_wait4(a0 as _, a1 as _, a2 as _)),
+            EXIT | EXIT_GROUP => super::exit_current(super::Process::exit_status(a0 as _)),
+            #[cfg(target_arch = "x86_64")]
+            OPEN => crate::sys_open(a0 as _, a1 as _, a2 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            STAT => crate::sys_stat(a0 as _, a1 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            LSTAT => crate::sys_lstat(a0 as _, a1 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            ACCESS => crate::sys_access(a0 as _, a1 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            PIPE => crate::sys_pipe(core::slice::from_raw_parts_mut(a0 as *mut c_int, 2)) as _,
+            #[cfg(target_arch = "x86_64")]
+            DUP2 => crate::sys_dup2(a0 as _, a1 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            MKDIR => crate::sys_mkdir(a0 as _, a1 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            RMDIR => crate::sys_rmdir(a0 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            UNLINK => crate::sys_unlink(a0 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            RENAME => crate::sys_rename(a0 as _, a1 as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            FORK | VFORK => ret(super::fork(tf, 0).map(|pid| pid as _)),
+            #[cfg(target_arch = "x86_64")]
+            ARCH_PRCTL => ret(sys_arch_prctl(a0, a1)),
+            _ => {
+                warn!("unsupported syscall: {}", sysno);
+                -(LinuxError::ENOSYS.code() as isize)
+            }
+        }
+    }
+}
+
+/// Returns the length of the current directory, with the terminating NUL.
+fn sys_getcwd(buf: *mut c_char, size: usize) -> isize {
+    let ret = crate::sys_getcwd(buf, size) as isize;
+    if ret < 0 {
+        return ret;
+    }
+    unsafe { core::ffi::CStr::from_ptr(buf) }.count_bytes() as isize + 1
+}
+
+fn sys_wait4(pid: i32, wstatus: *mut i32, options: u32) -> LinuxResult<usize> {
+    let Some((pid, status)) = super::wait4(pid, options)? else {
+        return Ok(0);
+    };
+    if !wstatus.is_null() {
+        unsafe { wstatus.write(status) };
+    }
+    Ok(pid as usize)
+}
+
+/// Sets the address to clear and wake as a futex when the current thread
+/// exits, and returns its thread ID.
+fn sys_set_tid_address(tidptr: usize) -> isize {
+    let curr = axtask::current();
+    curr.task_ext()
+        .clear_child_tid
+        .store(tidptr, Ordering::Release);
+    curr.id().as_u64() as _
+}
+
+/// Returns the bit of the signal `sig` in a signal set.
+const fn sigbit(sig: u32) -> u64 {
+    1 << (sig - 1)
+}
+
+/// The signals that cannot be caught or blocked.
+const UNCATCHABLE: u64 = sigbit(ctypes::SIGKILL) | sigbit(ctypes::SIGSTOP);
+
+/// Examines and changes the action of the signal `sig`.
+///
+/// The action is only recorded, as signals are not delivered to user space.
+fn sys_rt_sigaction(
+    sig: usize,
+    act: *const SigAction,
+    oldact: *mut SigAction,
+    sigsetsize: usize,
+) -> LinuxResult<usize> {
+    if sigsetsize != size_of::<u64>() || !(1..=NSIG).contains(&sig) {
+        return Err(LinuxError::EINVAL);
+    }
+    let process = current_process().ok_or(LinuxError::EPERM)?;
+    let old = {
+        let mut actions = process.sigactions.lock();
+        let old = actions[sig - 1];
+        if let Some(act) = unsafe { act.as_ref() } {
+            if UNCATCHABLE & sigbit(sig as u32) != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            actions[sig - 1] = *act;
+        }
+        old
+    };
+    if let Some(oldact) = unsafe { oldact.as_mut() } {
+        *oldact = old;
+    }
+    Ok(0)
+}
+
+/// Examines and changes the signal mask of the current thread.
+///
+/// `SIGKILL` and `SIGSTOP` cannot be blocked, and are silently left out.
+fn sys_rt_sigprocmask(
+    how: u32,
+    set: *const u64,
+    oldset: *mut u64,
+    sigsetsize: usize,
+) -> LinuxResult<usize> {
+    if sigsetsize != size_of::<u64>() {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = axtask::current();
+    let mask = &curr.task_ext().sigmask;
+    let old = mask.load(Ordering::Acquire);
+    if let Some(&set) = unsafe { set.as_ref() } {
+        let new = match how {
+            ctypes::SIG_BLOCK => old | set,
+            ctypes::SIG_UNBLOCK => old & !set,
+            ctypes::SIG_SETMASK => set,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        mask.store(new & !UNCATCHABLE, Ordering::Release);
+    }
+    if let Some(oldset) = unsafe { oldset.as_mut() } {
+        *oldset = old;
+    }
+    Ok(0)
+}
+
+/// Returns the process ID of the parent, or 0 if it has exited.
+fn sys_getppid() -> isize {
+    current_process()
+        .and_then(|process| process.parent.lock().upgrade())
+        .map_or(0, |parent| parent.pid as isize)
+}
+
+fn sys_uname(buf: *mut [[u8; UTSNAME_LEN]; 6]) -> LinuxResult<usize> {
+    if buf.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let fields = [
+        "Linux",
+        "arceos",
+        "6.0.0",
+        env!("CARGO_PKG_VERSION"),
+        if cfg!(target_arch = "x86_64") {
+            "x86_64"
+        } else if cfg!(target_arch = "aarch64") {
+            "aarch64"
+        } else if cfg!(target_arch = "riscv64") {
+            "riscv64"
+        } else {
+            "loongarch64"
+        },
+        "",
+    ];
+    let mut uts = [[0; UTSNAME_LEN]; 6];
+    for (dst, src) in uts.iter_mut().zip(fields) {
+        dst[..src.len()].copy_from_slice(src.as_bytes());
+    }
+    unsafe { buf.write(uts) };
+    Ok(0)
+}
+
+/// Sets the end of the heap to `addr`, and returns the new end, or the
+/// current one if it cannot be changed.
+fn sys_brk(addr: usize) -> usize {
+    let process = current_process().unwrap();
+    let mut heap = process.heap.lock();
+    let (bottom, top) = *heap;
+    let new_top = VirtAddr::from(addr);
+    if new_top < bottom {
+        return top.as_usize();
+    }
+    let mut aspace = process.aspace.lock();
+    let (old_end, new_end) = (top.align_up_4k(), new_top.align_up_4k());
+    let res = if new_end > old_end {
+        let flags = MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER;
+        aspace.map_alloc(old_end, new_end - old_end, flags, false)
+    } else if new_end < old_end {
+        aspace.unmap(new_end, old_end - new_end)
+    } else {
+        Ok(())
+    };
+    if res.is_ok() {
+        heap.1 = new_top;
+    }
+    heap.1.as_usize()
+}
+
+/// Maps anonymous memory. Files cannot be mapped.
+fn sys_mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: c_int,
+    _offset: usize,
+) -> LinuxResult<usize> {
+    if len == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if flags & MAP_ANONYMOUS == 0 {
+        warn!("sys_mmap: file mappings are not supported (fd {})", fd);
+        return Err(LinuxError::ENODEV);
+    }
+    let size = align_up_4k(len);
+    let mut map_flags = MappingFlags::USER;
+    if prot & PROT_READ != 0 {
+        map_flags |= MappingFlags::READ;
+    }
+    if prot & PROT_WRITE != 0 {
+        map_flags |= MappingFlags::WRITE;
+    }
+    if prot & PROT_EXEC != 0 {
+        map_flags |= MappingFlags::EXECUTE;
+    }
+
+    let process = current_process().unwrap();
+    let mut aspace = process.aspace.lock();
+    let start = if flags & MAP_FIXED != 0 {
+        let start = VirtAddr::from(addr);
+        if !start.is_aligned_4k() {
+            return Err(LinuxError::EINVAL);
+        }
+        aspace.unmap(start, size)?;
+        start
+    } else {
+        let hint = VirtAddr::from(if addr == 0 { USER_MMAP_BASE } else { addr });
+        let limit =
+            VirtAddrRange::from_start_size(VirtAddr::from(USER_SPACE_BASE), USER_SPACE_SIZE);
+        aspace
+            .find_free_area(hint.align_down_4k(), size, limit)
+            .ok_or(LinuxError::ENOMEM)?
+    };
+    aspace.map_alloc(start, size, map_flags, false)?;
+    Ok(start.as_usize())
+}
+
+fn sys_munmap(addr: usize, len: usize) -> LinuxResult<usize> {
+    let start = VirtAddr::from(addr);
+    if !start.is_aligned_4k() || len == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let process = current_process().unwrap();
+    process.aspace.lock().unmap(start, align_up_4k(len))?;
+    Ok(0)
+}
+
+/// Creates a child process, as `fork` with an optional new stack. Threads
+/// (`CLONE_VM`) are not supported.
+fn sys_clone(tf: &TrapFrame, flags: usize, stack: usize) -> LinuxResult<usize> {
+    if flags & CLONE_VM != 0 {
+        warn!("sys_clone: unsupported flags {:#x}", flags);
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(super::fork(tf, stack)? as usize)
+}
+
+/// Reads a NULL-terminated array of strings from user space, taking their
+/// space from `budget`.
+fn read_str_array(array: *const *const c_char, budget: &mut usize) -> LinuxResult<Vec<String>> {
+    let mut strs = Vec::new();
+    if array.is_null() {
+        return Ok(strs);
+    }
+    for i in 0.. {
+        let entry = array.wrapping_add(i);
+        check_user_read(entry, 1)?;
+        let ptr = unsafe { entry.read() };
+        if ptr.is_null() {
+            break;
+        }
+        check_user_str(ptr)?;
+        let s = char_ptr_to_str(ptr)?;
+        take_arg_space(budget, s)?;
+        strs.push(s.into());
+    }
+    Ok(strs)
+}
+
+/// Replaces the current image, and enters it at once on success.
+fn sys_execve(
+    path: *const c_char,
+    argv: *const *const c_char,
+    envp: *const *const c_char,
+) -> LinuxResult<usize> {
+    let uctx = {
+        let path = String::from(char_ptr_to_str(path)?);
+        let mut budget = ARG_MAX;
+        let args = read_str_array(argv, &mut budget)?;
+        let envs = read_str_array(envp, &mut budget)?;
+        super::exec(&path, &args, &envs)?
+    };
+    let kstack_top = axtask::current().kernel_stack_top().unwrap();
+    unsafe { uctx.enter_uspace(kstack_top) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn sys_arch_prctl(code: usize, addr: usize) -> LinuxResult<usize> {
+    match code {
+        ARCH_SET_FS => unsafe { axhal::asm::write_thread_pointer(addr) },
+        ARCH_GET_FS => unsafe { (addr as *mut usize).write(axhal::asm::read_thread_pointer()) },
+        _ => return Err(LinuxError::EINVAL),
+    }
+    Ok(0)
+}
@@ -0,0 +1,71 @@
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+use core::ffi::c_int;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use super::mutex::PthreadMutex;
+
+/// Index of the `int` in `pthread_cond_t` used as the futex word.
+///
+/// `pthread_cond_init` only sets `_c_shared` (`__p[0]`) and `_c_clock`
+/// (`__i[4]`), so `__i[3]` is free to hold the wake-up sequence number.
+const COND_SEQ_INDEX: usize = 3;
+
+static_assertions::const_assert!(
+    size_of::<ctypes::pthread_cond_t>() >= (COND_SEQ_INDEX + 1) * size_of::<u32>()
+);
+
+fn cond_seq<'a>(cond: *mut ctypes::pthread_cond_t) -> &'a AtomicU32 {
+    unsafe { AtomicU32::from_ptr(cond.cast::<u32>().add(COND_SEQ_INDEX)) }
+}
+
+/// Wait on a condition variable, the mutex is released while waiting.
+pub fn sys_pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    debug!(
+        "sys_pthread_cond_wait <= {:#x} {:#x}",
+        cond as usize, mutex as usize
+    );
+    syscall_body!(sys_pthread_cond_wait, {
+        check_null_mut_ptr(cond)?;
+        check_null_mut_ptr(mutex)?;
+        let seq = cond_seq(cond);
+        let mutex = unsafe { &*mutex.cast::<PthreadMutex>() };
+
+        let val = seq.load(Ordering::Acquire);
+        mutex.unlock()?;
+        // `WouldBlock` means the condition has been signaled after we
+        // released the mutex, just return as if we were woken up.
+        axtask::futex_wait(seq, val, None).ok();
+        mutex.lock()?;
+        Ok(0)
+    })
+}
+
+/// Unblock at least one of the threads that are blocked on the condition
+/// variable.
+pub fn sys_pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_signal <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_signal, {
+        check_null_mut_ptr(cond)?;
+        let seq = cond_seq(cond);
+        seq.fetch_add(1, Ordering::Release);
+        axtask::futex_wake(seq, 1);
+        Ok(0)
+    })
+}
+
+/// Unblock all threads that are blocked on the condition variable.
+pub fn sys_pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_broadcast <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_broadcast, {
+        check_null_mut_ptr(cond)?;
+        let seq = cond_seq(cond);
+        seq.fetch_add(1, Ordering::Release);
+        axtask::futex_wake(seq, usize::MAX);
+        Ok(0)
+    })
+}
@@ -0,0 +1,170 @@
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::ffi::{c_int, c_void};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axerrno::LinuxError;
+
+use super::Pthread;
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+/// The maximum number of keys, `PTHREAD_KEYS_MAX`.
+const KEYS_MAX: usize = 128;
+/// The maximum number of rounds of destructor calls at thread exit,
+/// `PTHREAD_DESTRUCTOR_ITERATIONS`.
+const DESTRUCTOR_ITERATIONS: usize = 4;
+
+type Destructor = unsafe extern "C" fn(*mut c_void);
+
+struct Key {
+    /// Odd while the key is allocated. It changes each time the key is
+    /// created or deleted, which makes the values set before stale.
+    seq: AtomicUsize,
+    /// The destructor, or 0 if none.
+    destructor: AtomicUsize,
+}
+
+static KEYS: [Key; KEYS_MAX] = [const {
+    Key {
+        seq: AtomicUsize::new(0),
+        destructor: AtomicUsize::new(0),
+    }
+}; KEYS_MAX];
+
+impl Key {
+    fn get(key: ctypes::pthread_key_t) -> Option<&'static Key> {
+        KEYS.get(key as usize)
+    }
+
+    /// Returns the sequence number of the key if it is allocated.
+    fn allocated_seq(&self) -> Option<usize> {
+        let seq = self.seq.load(Ordering::Acquire);
+        (seq % 2 == 1).then_some(seq)
+    }
+}
+
+/// The values of the keys of a thread, indexed by key, each with the
+/// sequence number of the key it was set for.
+///
+/// Only the thread itself accesses them.
+#[derive(Default)]
+pub(super) struct Values(UnsafeCell<Vec<(usize, usize)>>);
+
+impl Values {
+    /// Runs `f` on the values of the current thread, or returns `None` if it
+    /// is not a pthread.
+    fn with_current<T>(f: impl FnOnce(&mut Vec<(usize, usize)>) -> T) -> Option<T> {
+        let thread = Pthread::current()?;
+        Some(f(unsafe { &mut *thread.values.0.get() }))
+    }
+}
+
+/// Calls the destructors of the non-null values of the current thread, and
+/// removes all its values.
+pub(super) fn run_destructors() {
+    for _ in 0..DESTRUCTOR_ITERATIONS {
+        // Values are reset to null before their destructors are called.
+        let pending: Vec<_> = Values::with_current(|values| {
+            values
+                .iter_mut()
+                .zip(&KEYS)
+                .filter_map(|((seq, value), key)| {
+                    if *value == 0 || key.allocated_seq() != Some(*seq) {
+                        return None;
+                    }
+                    let destructor = key.destructor.load(Ordering::Acquire);
+                    if destructor == 0 {
+                        return None;
+                    }
+                    let destructor: Destructor = unsafe { core::mem::transmute(destructor) };
+                    Some((destructor, core::mem::take(value)))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+        if pending.is_empty() {
+            break;
+        }
+        for (destructor, value) in pending {
+            unsafe { destructor(value as *mut c_void) };
+        }
+    }
+    Values::with_current(|values| *values = Vec::new());
+}
+
+/// Create a thread-specific data key, with an optional destructor that is
+/// called with the non-null value of the key when a thread exits.
+pub unsafe fn sys_pthread_key_create(
+    key: *mut ctypes::pthread_key_t,
+    destructor: Option<Destructor>,
+) -> c_int {
+    debug!("sys_pthread_key_create <= {:#x}", key as usize);
+    syscall_body!(sys_pthread_key_create, {
+        check_null_mut_ptr(key)?;
+        let idx = KEYS
+            .iter()
+            .position(|slot| {
+                let seq = slot.seq.load(Ordering::Acquire);
+                seq % 2 == 0
+                    && slot
+                        .seq
+                        .compare_exchange(seq, seq + 1, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+            })
+            .ok_or(LinuxError::EAGAIN)?;
+        // No thread can set a value for the key before it is returned.
+        KEYS[idx]
+            .destructor
+            .store(destructor.map_or(0, |f| f as usize), Ordering::Release);
+        unsafe { key.write(idx as _) };
+        Ok(0)
+    })
+}
+
+/// Delete a thread-specific data key, without calling any destructors.
+///
+/// The values of the key become stale, and are never returned again.
+pub fn sys_pthread_key_delete(key: ctypes::pthread_key_t) -> c_int {
+    debug!("sys_pthread_key_delete <= {}", key);
+    syscall_body!(sys_pthread_key_delete, {
+        let slot = Key::get(key).ok_or(LinuxError::EINVAL)?;
+        let seq = slot.allocated_seq().ok_or(LinuxError::EINVAL)?;
+        slot.seq
+            .compare_exchange(seq, seq + 1, Ordering::AcqRel, Ordering::Acquire)
+            .map_err(|_| LinuxError::EINVAL)?;
+        Ok(0)
+    })
+}
+
+/// Get the value of `key` for the current thread, or null if it is not set.
+pub fn sys_pthread_getspecific(key: ctypes::pthread_key_t) -> *mut c_void {
+    let Some(seq) = Key::get(key).and_then(Key::allocated_seq) else {
+        return core::ptr::null_mut();
+    };
+    Values::with_current(|values| match values.get(key as usize) {
+        Some(&(value_seq, value)) if value_seq == seq => value as *mut c_void,
+        _ => core::ptr::null_mut(),
+    })
+    .unwrap_or(core::ptr::null_mut())
+}
+
+/// Set the value of `key` for the current thread.
+///
+/// Returns `ENOMEM` if the current thread was not created as a pthread.
+pub fn sys_pthread_setspecific(key: ctypes::pthread_key_t, value: *const c_void) -> c_int {
+    debug!("sys_pthread_setspecific <= {} {:#x}", key, value as usize);
+    syscall_body!(sys_pthread_setspecific, {
+        let seq = Key::get(key)
+            .and_then(Key::allocated_seq)
+            .ok_or(LinuxError::EINVAL)?;
+        Values::with_current(|values| {
+            let idx = key as usize;
+            if values.len() <= idx {
+                values.resize(idx + 1, (0, 0));
+            }
+            values[idx] = (seq, value as usize);
+        })
+        .ok_or(LinuxError::ENOMEM)?;
+        Ok(0)
+    })
+}
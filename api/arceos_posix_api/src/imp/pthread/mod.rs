@@ -1,14 +1,41 @@
-use alloc::{boxed::Box, collections::BTreeMap, sync::Arc};
+use alloc::{boxed::Box, collections::BTreeMap, string::String, sync::Arc};
 use core::cell::UnsafeCell;
-use core::ffi::{c_int, c_void};
+use core::ffi::{c_char, c_int, c_ulong, c_void};
+use core::sync::atomic::{AtomicU8, Ordering};
 
 use axerrno::{LinuxError, LinuxResult};
 use axtask::AxTaskRef;
 use spin::RwLock;
 
 use crate::ctypes;
+use crate::utils::char_ptr_to_str;
 
+pub mod condvar;
+pub mod key;
 pub mod mutex;
+pub mod once;
+
+/// The maximum length of a thread name, including the terminating NUL.
+const THREAD_NAME_MAX: usize = 16;
+
+/// The thread may still be joined.
+const STATE_JOINABLE: u8 = 0;
+/// The thread has been detached and frees itself when it exits.
+const STATE_DETACHED: u8 = 1;
+/// The thread has exited and waits to be joined.
+const STATE_EXITED: u8 = 2;
+/// The thread is being joined, and can be neither detached nor joined again.
+const STATE_JOINING: u8 = 3;
+
+/// Moves the thread `state` to `new` if it may still be joined or detached,
+/// and returns the previous state.
+fn claim(state: &AtomicU8, new: u8) -> LinuxResult<u8> {
+    state
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+            matches!(state, STATE_JOINABLE | STATE_EXITED).then_some(new)
+        })
+        .map_err(|_| LinuxError::EINVAL)
+}
 
 lazy_static::lazy_static! {
     static ref TID_TO_PTHREAD: RwLock<BTreeMap<u64, ForceSendSync<ctypes::pthread_t>>> = {
@@ -20,6 +47,8 @@ lazy_static::lazy_static! {
             retval: Arc::new(Packet {
                 result: UnsafeCell::new(core::ptr::null_mut()),
             }),
+            state: Arc::new(AtomicU8::new(STATE_JOINABLE)),
+            values: key::Values::default(),
         };
         let ptr = Box::into_raw(Box::new(main_thread)) as *mut c_void;
         map.insert(main_tid, ForceSendSync(ptr));
@@ -37,39 +66,111 @@ unsafe impl<T> Sync for Packet<T> {}
 pub struct Pthread {
     inner: AxTaskRef,
     retval: Arc<Packet<*mut c_void>>,
+    state: Arc<AtomicU8>,
+    /// The values of the thread-specific data keys.
+    values: key::Values,
+}
+
+/// The attributes of a new thread that are honoured: the stack size and
+/// whether it starts detached.
+fn parse_attr(attr: *const ctypes::pthread_attr_t) -> (usize, bool) {
+    if attr.is_null() {
+        return (axconfig::TASK_STACK_SIZE, false);
+    }
+    // The layout is defined by the `_a_*` macros in `pthread.h`.
+    let attr = unsafe { &(*attr).__u };
+    let stack_size = unsafe { attr.__s[0] } as usize;
+    let detached = unsafe { attr.__i[3 * size_of::<c_ulong>() / size_of::<c_int>()] } != 0;
+    let stack_size = if stack_size == 0 {
+        axconfig::TASK_STACK_SIZE
+    } else {
+        stack_size
+    };
+    (stack_size, detached)
 }
 
 impl Pthread {
     fn create(
-        _attr: *const ctypes::pthread_attr_t,
+        attr: *const ctypes::pthread_attr_t,
         start_routine: extern "C" fn(arg: *mut c_void) -> *mut c_void,
         arg: *mut c_void,
     ) -> LinuxResult<ctypes::pthread_t> {
+        let (stack_size, detached) = parse_attr(attr);
         let arg_wrapper = ForceSendSync(arg);
 
         let my_packet: Arc<Packet<*mut c_void>> = Arc::new(Packet {
             result: UnsafeCell::new(core::ptr::null_mut()),
         });
         let their_packet = my_packet.clone();
+        let state = Arc::new(AtomicU8::new(STATE_JOINABLE));
+        let their_state = state.clone();
+        #[cfg(feature = "signal")]
+        let sigmask = super::signal::thread_mask();
 
         let main = move || {
+            #[cfg(feature = "signal")]
+            super::signal::init_thread(sigmask);
             let arg = arg_wrapper;
             let ret = start_routine(arg.0);
             unsafe { *their_packet.result.get() = ret };
             drop(their_packet);
+            Self::on_exit(&their_state);
         };
 
-        let task_inner = axtask::spawn(main);
+        let task_inner = axtask::spawn_raw(main, String::new(), stack_size);
         let tid = task_inner.id().as_u64();
         let thread = Pthread {
             inner: task_inner,
             retval: my_packet,
+            state,
+            values: key::Values::default(),
         };
         let ptr = Box::into_raw(Box::new(thread)) as *mut c_void;
         TID_TO_PTHREAD.write().insert(tid, ForceSendSync(ptr));
+        // Only detach once the thread is in the table, where it can find
+        // itself to be freed.
+        if detached {
+            Self::detach(ptr)?;
+        }
         Ok(ptr)
     }
 
+    /// Runs the key destructors of the current thread, and frees it if it
+    /// has been detached.
+    fn on_exit(state: &AtomicU8) {
+        key::run_destructors();
+        let tid = axtask::current().id().as_u64();
+        // A thread being joined is freed by the joiner.
+        if let Err(STATE_DETACHED) = state.compare_exchange(
+            STATE_JOINABLE,
+            STATE_EXITED,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            let ptr = TID_TO_PTHREAD.read().get(&tid).map(|ptr| ptr.0);
+            if let Some(ptr) = ptr {
+                Self::reap(ptr);
+            }
+        }
+    }
+
+    /// Frees the thread `ptr` that will never be joined.
+    fn reap(ptr: ctypes::pthread_t) {
+        let thread = unsafe { Box::from_raw(ptr as *mut Pthread) };
+        let tid = thread.inner.id().as_u64();
+        TID_TO_PTHREAD.write().remove(&tid);
+        #[cfg(feature = "signal")]
+        super::signal::remove_thread(tid);
+    }
+
+    fn detach(ptr: ctypes::pthread_t) -> LinuxResult {
+        let thread = unsafe { &*(ptr as *const Pthread) };
+        if claim(&thread.state, STATE_DETACHED)? == STATE_EXITED {
+            Self::reap(ptr);
+        }
+        Ok(())
+    }
+
     fn current_ptr() -> *mut Pthread {
         let tid = axtask::current().id().as_u64();
         match TID_TO_PTHREAD.read().get(&tid) {
@@ -85,6 +186,9 @@ impl Pthread {
     fn exit_current(retval: *mut c_void) -> ! {
         let thread = Self::current().expect("fail to get current thread");
         unsafe { *thread.retval.result.get() = retval };
+        // Clone the state, as a detached thread is freed on exit.
+        let state = thread.state.clone();
+        Self::on_exit(&state);
         axtask::exit(0);
     }
 
@@ -92,17 +196,42 @@ impl Pthread {
         if core::ptr::eq(ptr, Self::current_ptr() as _) {
             return Err(LinuxError::EDEADLK);
         }
+        // Claimed at once, so that it is not detached or joined meanwhile.
+        claim(&unsafe { &*(ptr as *const Pthread) }.state, STATE_JOINING)?;
 
         let thread = unsafe { Box::from_raw(ptr as *mut Pthread) };
         thread.inner.join();
         let tid = thread.inner.id().as_u64();
         let retval = unsafe { *thread.retval.result.get() };
         TID_TO_PTHREAD.write().remove(&tid);
+        #[cfg(feature = "signal")]
+        super::signal::remove_thread(tid);
         drop(thread);
         Ok(retval)
     }
 }
 
+/// Returns the task ID of the thread `ptr`, or `None` if there is no such
+/// thread.
+pub fn pthread_tid(ptr: ctypes::pthread_t) -> Option<u64> {
+    TID_TO_PTHREAD
+        .read()
+        .iter()
+        .find(|(_, thread)| core::ptr::eq(thread.0, ptr))
+        .map(|(&tid, _)| tid)
+}
+
+/// Runs `f` on the thread `ptr`, which cannot be freed meanwhile, or returns
+/// `ESRCH` if there is no such thread.
+fn with_thread<T>(ptr: ctypes::pthread_t, f: impl FnOnce(&Pthread) -> T) -> LinuxResult<T> {
+    // Threads are removed from the table before they are freed.
+    let threads = TID_TO_PTHREAD.read();
+    if !threads.values().any(|thread| core::ptr::eq(thread.0, ptr)) {
+        return Err(LinuxError::ESRCH);
+    }
+    Ok(f(unsafe { &*(ptr as *const Pthread) }))
+}
+
 /// Returns the `pthread` struct of current thread.
 pub fn sys_pthread_self() -> ctypes::pthread_t {
     Pthread::current().expect("fail to get current thread") as *const Pthread as _
@@ -147,6 +276,57 @@ pub unsafe fn sys_pthread_join(thread: ctypes::pthread_t, retval: *mut *mut c_vo
     })
 }
 
+/// Detaches the given thread, so that it is freed as soon as it exits.
+pub fn sys_pthread_detach(thread: ctypes::pthread_t) -> c_int {
+    debug!("sys_pthread_detach <= {:#x}", thread as usize);
+    syscall_body!(sys_pthread_detach, {
+        if pthread_tid(thread).is_none() {
+            return Err(LinuxError::ESRCH);
+        }
+        Pthread::detach(thread)?;
+        Ok(0)
+    })
+}
+
+/// Sets the name of the given thread, at most 15 bytes long.
+pub fn sys_pthread_setname_np(thread: ctypes::pthread_t, name: *const c_char) -> c_int {
+    let name = char_ptr_to_str(name);
+    debug!(
+        "sys_pthread_setname_np <= {:#x} {:?}",
+        thread as usize, name
+    );
+    syscall_body!(sys_pthread_setname_np, {
+        let name = name?;
+        if name.len() >= THREAD_NAME_MAX {
+            return Err(LinuxError::ERANGE);
+        }
+        with_thread(thread, |thread| thread.inner.set_name(name))?;
+        Ok(0)
+    })
+}
+
+/// Copies the name of the given thread into `buf` of `len` bytes.
+pub unsafe fn sys_pthread_getname_np(
+    thread: ctypes::pthread_t,
+    buf: *mut c_char,
+    len: usize,
+) -> c_int {
+    debug!("sys_pthread_getname_np <= {:#x}", thread as usize);
+    syscall_body!(sys_pthread_getname_np, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let name = with_thread(thread, |thread| thread.inner.name())?;
+        if name.len() >= len {
+            return Err(LinuxError::ERANGE);
+        }
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst[..name.len()].copy_from_slice(name.as_bytes());
+        dst[name.len()] = 0;
+        Ok(0)
+    })
+}
+
 #[derive(Clone, Copy)]
 struct ForceSendSync<T>(T);
 
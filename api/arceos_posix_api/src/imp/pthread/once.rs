@@ -0,0 +1,56 @@
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+const ONCE_INIT: u32 = 0;
+const ONCE_RUNNING: u32 = 1;
+const ONCE_DONE: u32 = 2;
+/// Running, with callers waiting on the futex.
+const ONCE_WAITING: u32 = 3;
+
+/// Call `init_routine` exactly once for `once_control`, which must have been
+/// initialized with `PTHREAD_ONCE_INIT`.
+///
+/// Other callers wait on `once_control` as a futex until the first call
+/// returns.
+pub unsafe fn sys_pthread_once(
+    once_control: *mut ctypes::pthread_once_t,
+    init_routine: extern "C" fn(),
+) -> c_int {
+    debug!("sys_pthread_once <= {:#x}", once_control as usize);
+    syscall_body!(sys_pthread_once, {
+        check_null_mut_ptr(once_control)?;
+        let state = unsafe { AtomicU32::from_ptr(once_control.cast()) };
+        loop {
+            match state.compare_exchange(
+                ONCE_INIT,
+                ONCE_RUNNING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    init_routine();
+                    if state.swap(ONCE_DONE, Ordering::Release) == ONCE_WAITING {
+                        axtask::futex_wake(state, usize::MAX);
+                    }
+                    break;
+                }
+                Err(ONCE_DONE) => break,
+                Err(ONCE_RUNNING) => {
+                    // Ask the caller running `init_routine` to wake us up.
+                    let _ = state.compare_exchange(
+                        ONCE_RUNNING,
+                        ONCE_WAITING,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    );
+                }
+                Err(_) => {
+                    axtask::futex_wait(state, ONCE_WAITING, None).ok();
+                }
+            }
+        }
+        Ok(0)
+    })
+}
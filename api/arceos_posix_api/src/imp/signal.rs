@@ -0,0 +1,796 @@
+//! Signals.
+//!
+//! A signal is sent to the process (`kill`) or to a thread (`pthread_kill`),
+//! and stays pending until a thread that does not block it reaches a safe
+//! point: the return of a syscall, or a wakeup in a blocking call. The
+//! handler is then called on that thread, and the interrupted blocking call
+//! fails with `EINTR`, or goes on if the handler was installed with
+//! `SA_RESTART`.
+//!
+//! A signal whose default action is to terminate exits the thread that takes
+//! it with the code `128 + sig`, or the user process it runs in. The app ends
+//! when that thread is its main thread. Stopping is not supported, and the
+//! stop signals are ignored.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ffi::{c_int, c_uint, c_ulong, c_void};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{TimeValue, monotonic_time};
+use axsync::Mutex;
+
+#[cfg(feature = "signalfd")]
+use super::poll_set::PollSet;
+use super::poll_set::{PollWaker, Poller};
+use crate::ctypes;
+
+/// The number of signals, numbered from 1.
+const NSIG: usize = 64;
+
+const SIG_DFL: usize = 0;
+const SIG_IGN: usize = 1;
+
+const fn sigbit(sig: usize) -> u64 {
+    1 << (sig - 1)
+}
+
+/// The signals that cannot be caught, blocked or ignored.
+pub const UNCATCHABLE: u64 = sigbit(ctypes::SIGKILL as _) | sigbit(ctypes::SIGSTOP as _);
+
+/// The signals ignored by default.
+const DEFAULT_IGNORED: u64 = sigbit(ctypes::SIGCHLD as _)
+    | sigbit(ctypes::SIGCONT as _)
+    | sigbit(ctypes::SIGURG as _)
+    | sigbit(ctypes::SIGWINCH as _);
+
+/// The signals that stop the process by default.
+const DEFAULT_STOP: u64 = sigbit(ctypes::SIGSTOP as _)
+    | sigbit(ctypes::SIGTSTP as _)
+    | sigbit(ctypes::SIGTTIN as _)
+    | sigbit(ctypes::SIGTTOU as _);
+
+#[derive(Clone, Copy)]
+struct SigAction {
+    /// The handler, or `SIG_DFL` or `SIG_IGN`.
+    handler: usize,
+    flags: u32,
+    /// The signals blocked while the handler runs.
+    mask: u64,
+}
+
+impl SigAction {
+    const DEFAULT: Self = Self {
+        handler: SIG_DFL,
+        flags: 0,
+        mask: 0,
+    };
+
+    /// Whether a signal with this action is discarded.
+    fn ignores(&self, sig: usize) -> bool {
+        match self.handler {
+            SIG_IGN => true,
+            SIG_DFL => DEFAULT_IGNORED & sigbit(sig) != 0,
+            _ => false,
+        }
+    }
+}
+
+static ACTIONS: Mutex<[SigAction; NSIG]> = Mutex::new([SigAction::DEFAULT; NSIG]);
+
+/// The signals sent to the process and not delivered yet.
+static PROCESS_PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// The number of signals pending on threads, so that a syscall can return
+/// without looking up the current thread if there is none.
+static THREAD_PENDING_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The signal state of a thread.
+#[derive(Default)]
+struct ThreadSignals {
+    blocked: AtomicU64,
+    /// The signals sent to the thread and not delivered yet.
+    pending: AtomicU64,
+    /// Woken when a signal is sent while the thread is in a blocking call.
+    interrupt: Mutex<Option<Weak<dyn PollWaker>>>,
+}
+
+impl ThreadSignals {
+    fn set_pending(&self, sig: usize) {
+        if self.pending.fetch_or(sigbit(sig), Ordering::AcqRel) & sigbit(sig) == 0 {
+            THREAD_PENDING_COUNT.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    fn interrupt(&self) {
+        let waker = self.interrupt.lock().as_ref().and_then(Weak::upgrade);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Takes a pending signal that the thread does not block, preferring the
+    /// ones sent to the thread.
+    fn take_pending(&self) -> Option<usize> {
+        self.take_pending_in(!self.blocked.load(Ordering::Acquire))
+    }
+
+    /// Takes a pending signal in `mask`, preferring the ones sent to the
+    /// thread.
+    fn take_pending_in(&self, mask: u64) -> Option<usize> {
+        if let Some(sig) = take_lowest(&self.pending, mask) {
+            THREAD_PENDING_COUNT.fetch_sub(1, Ordering::AcqRel);
+            return Some(sig);
+        }
+        take_lowest(&PROCESS_PENDING, mask)
+    }
+
+    fn blocks(&self, sig: usize) -> bool {
+        self.blocked.load(Ordering::Acquire) & sigbit(sig) != 0
+    }
+}
+
+/// Clears the lowest set bit of `pending` in `mask`, and returns its signal.
+fn take_lowest(pending: &AtomicU64, mask: u64) -> Option<usize> {
+    let mut cur = pending.load(Ordering::Acquire);
+    loop {
+        let sigs = cur & mask;
+        if sigs == 0 {
+            return None;
+        }
+        let bit = sigs & sigs.wrapping_neg();
+        match pending.compare_exchange_weak(cur, cur & !bit, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => return Some(bit.trailing_zeros() as usize + 1),
+            Err(actual) => cur = actual,
+        }
+    }
+}
+
+/// The signal states of the threads that have used signals, by task IDs.
+static THREADS: Mutex<BTreeMap<u64, Arc<ThreadSignals>>> = Mutex::new(BTreeMap::new());
+
+/// Woken when a signal is sent, for the tasks waiting on signalfds.
+#[cfg(feature = "signalfd")]
+static SIGNALFD_POLL_SET: PollSet = PollSet::new();
+
+/// Returns the ID of the current thread, as `getpid` does.
+fn current_tid() -> u64 {
+    #[cfg(feature = "multitask")]
+    {
+        axtask::current().id().as_u64()
+    }
+    #[cfg(not(feature = "multitask"))]
+    {
+        2 // `main` task ID
+    }
+}
+
+fn thread(tid: u64) -> Arc<ThreadSignals> {
+    THREADS.lock().entry(tid).or_default().clone()
+}
+
+fn current() -> Arc<ThreadSignals> {
+    thread(current_tid())
+}
+
+/// Returns the signal mask of the current thread, to be inherited by a new
+/// thread with [`init_thread`].
+pub fn thread_mask() -> u64 {
+    current().blocked.load(Ordering::Acquire)
+}
+
+/// Sets up the signal state of a new thread.
+pub fn init_thread(mask: u64) {
+    current().blocked.store(mask, Ordering::Release);
+}
+
+/// Removes the signal state of the exited thread `tid`. The signals pending
+/// on it are discarded.
+pub fn remove_thread(tid: u64) {
+    if let Some(thread) = THREADS.lock().remove(&tid) {
+        let pending = thread.pending.swap(0, Ordering::AcqRel);
+        THREAD_PENDING_COUNT.fetch_sub(pending.count_ones() as usize, Ordering::AcqRel);
+    }
+}
+
+/// A registration of a waker to interrupt the blocking call of the current
+/// thread. See [`interrupt_on`].
+pub struct InterruptGuard {
+    thread: Arc<ThreadSignals>,
+    prev: Option<Weak<dyn PollWaker>>,
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        *self.thread.interrupt.lock() = self.prev.take();
+    }
+}
+
+/// Makes a signal sent to the current thread wake `waker`, until the
+/// returned guard is dropped. Blocking calls use it to wait for their events
+/// and signals at the same time, then check [`interrupted`] on each wakeup.
+pub fn interrupt_on(waker: &Arc<dyn PollWaker>) -> InterruptGuard {
+    let thread = current();
+    let prev = thread.interrupt.lock().replace(Arc::downgrade(waker));
+    InterruptGuard { thread, prev }
+}
+
+/// A temporary signal mask of the current thread. See [`replace_mask`].
+pub struct MaskGuard {
+    thread: Arc<ThreadSignals>,
+    old: u64,
+}
+
+impl Drop for MaskGuard {
+    fn drop(&mut self) {
+        self.thread.blocked.store(self.old, Ordering::Release);
+    }
+}
+
+/// Replaces the signal mask of the current thread with `set`, until the
+/// returned guard is dropped, as `ppoll` does while it waits.
+pub fn replace_mask(set: &ctypes::sigset_t) -> MaskGuard {
+    let thread = current();
+    let mask = sigset_to_mask(set) & !UNCATCHABLE;
+    let old = thread.blocked.swap(mask, Ordering::AcqRel);
+    MaskGuard { thread, old }
+}
+
+/// Sends `sig` to the thread `tid`, or to the process if `tid` is `None`.
+/// A signal that would be ignored is discarded, unless it is blocked: its
+/// action may change before it is unblocked, or it may be read from a
+/// signalfd.
+fn send(tid: Option<u64>, sig: usize) {
+    if ACTIONS.lock()[sig - 1].ignores(sig) {
+        let blocked = match tid {
+            Some(tid) => thread(tid).blocks(sig),
+            None => THREADS.lock().values().all(|thread| thread.blocks(sig)),
+        };
+        if !blocked {
+            return;
+        }
+    }
+    match tid {
+        Some(tid) => {
+            let thread = thread(tid);
+            thread.set_pending(sig);
+            thread.interrupt();
+        }
+        None => {
+            PROCESS_PENDING.fetch_or(sigbit(sig), Ordering::AcqRel);
+            let threads: Vec<_> = THREADS.lock().values().cloned().collect();
+            for thread in threads {
+                if !thread.blocks(sig) {
+                    thread.interrupt();
+                }
+            }
+        }
+    }
+    #[cfg(feature = "signalfd")]
+    SIGNALFD_POLL_SET.wake();
+}
+
+/// Returns the signals in `mask` pending on the current thread or the
+/// process.
+#[cfg(feature = "signalfd")]
+pub fn pending_in(mask: u64) -> u64 {
+    let pending =
+        current().pending.load(Ordering::Acquire) | PROCESS_PENDING.load(Ordering::Acquire);
+    pending & mask
+}
+
+/// Takes a signal in `mask` pending on the current thread or the process,
+/// without delivering it, as reading a signalfd does.
+#[cfg(feature = "signalfd")]
+pub fn take_pending_in(mask: u64) -> Option<usize> {
+    current().take_pending_in(mask)
+}
+
+/// Returns the poll set woken when a signal is sent.
+#[cfg(feature = "signalfd")]
+pub fn signalfd_poll_set() -> &'static PollSet {
+    &SIGNALFD_POLL_SET
+}
+
+/// Discards the pending `sig`, after it is set to be ignored.
+fn discard(sig: usize) {
+    PROCESS_PENDING.fetch_and(!sigbit(sig), Ordering::AcqRel);
+    for thread in THREADS.lock().values() {
+        if thread.pending.fetch_and(!sigbit(sig), Ordering::AcqRel) & sigbit(sig) != 0 {
+            THREAD_PENDING_COUNT.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// Sends `SIGPIPE` to the current thread if `err` is `EPIPE`, as writing to
+/// a pipe or socket whose reading side is closed does.
+pub fn sigpipe_on(err: &LinuxError) {
+    if *err == LinuxError::EPIPE {
+        send(Some(current_tid()), ctypes::SIGPIPE as _);
+    }
+}
+
+fn default_action(sig: usize) {
+    if DEFAULT_IGNORED & sigbit(sig) != 0 {
+        return;
+    }
+    if DEFAULT_STOP & sigbit(sig) != 0 {
+        warn!("stopping on signal {} is not supported", sig);
+        return;
+    }
+    error!("terminated by signal {}", sig);
+    #[cfg(feature = "uspace")]
+    super::process::exit_on_signal(sig as c_int);
+    super::task::sys_exit(128 + sig as c_int);
+}
+
+fn call_handler(action: &SigAction, sig: usize) {
+    if action.flags & ctypes::SA_SIGINFO != 0 {
+        let handler: extern "C" fn(c_int, *mut ctypes::siginfo_t, *mut c_void) =
+            unsafe { core::mem::transmute(action.handler) };
+        let mut info = ctypes::siginfo_t {
+            si_signo: sig as c_int,
+            si_code: ctypes::SI_USER as c_int,
+            ..Default::default()
+        };
+        handler(sig as c_int, &mut info, core::ptr::null_mut());
+    } else {
+        let handler: extern "C" fn(c_int) = unsafe { core::mem::transmute(action.handler) };
+        handler(sig as c_int);
+    }
+}
+
+/// Delivers the pending signals of the current thread.
+///
+/// Returns `None` if no handler was called, otherwise whether all the
+/// called handlers were installed with `SA_RESTART`.
+fn deliver() -> Option<bool> {
+    check_itimer();
+    if PROCESS_PENDING.load(Ordering::Acquire) == 0
+        && THREAD_PENDING_COUNT.load(Ordering::Acquire) == 0
+    {
+        return None;
+    }
+    let thread = current();
+    let mut restart = None;
+    while let Some(sig) = thread.take_pending() {
+        let action = {
+            let mut actions = ACTIONS.lock();
+            let action = actions[sig - 1];
+            if action.flags & ctypes::SA_RESETHAND != 0 {
+                actions[sig - 1] = SigAction::DEFAULT;
+            }
+            action
+        };
+        match action.handler {
+            SIG_IGN => {}
+            SIG_DFL => default_action(sig),
+            _ => {
+                let mut mask = action.mask;
+                if action.flags & ctypes::SA_NODEFER == 0 {
+                    mask |= sigbit(sig);
+                }
+                let old_mask = thread.blocked.fetch_or(mask, Ordering::AcqRel);
+                call_handler(&action, sig);
+                thread.blocked.store(old_mask, Ordering::Release);
+                *restart.get_or_insert(true) &= action.flags & ctypes::SA_RESTART != 0;
+            }
+        }
+    }
+    restart
+}
+
+/// Delivers the pending signals of the current thread, at the return of a
+/// syscall.
+pub fn deliver_pending() {
+    deliver();
+}
+
+/// Delivers the pending signals of the current thread in a blocking call.
+///
+/// Returns `EINTR` if a handler was called, unless `restart` is set and the
+/// handlers were installed with `SA_RESTART`.
+pub fn interrupted(restart: bool) -> LinuxResult {
+    match deliver() {
+        Some(true) if restart => Ok(()),
+        Some(_) => Err(LinuxError::EINTR),
+        None => Ok(()),
+    }
+}
+
+/// Sleeps for `dur`, or until a handler is called on the current thread.
+pub fn sleep(dur: Duration) -> LinuxResult {
+    let deadline = monotonic_time() + dur;
+    let poller = Arc::new(Poller::new());
+    let waker: Arc<dyn PollWaker> = poller.clone();
+    let _guard = interrupt_on(&waker);
+    loop {
+        interrupted(false)?;
+        let now = monotonic_time();
+        if now >= deadline {
+            return Ok(());
+        }
+        poller.wait(Some(deadline - now));
+    }
+}
+
+fn check_signum(signum: c_int) -> LinuxResult<usize> {
+    if (1..=NSIG as c_int).contains(&signum) {
+        Ok(signum as usize)
+    } else {
+        Err(LinuxError::EINVAL)
+    }
+}
+
+/// Converts `set` to a mask of signals, with the bit of signal `n` at
+/// `n - 1`.
+pub fn sigset_to_mask(set: &ctypes::sigset_t) -> u64 {
+    let bits = c_ulong::BITS;
+    set.__bits
+        .iter()
+        .take((u64::BITS / bits) as usize)
+        .enumerate()
+        .fold(0, |mask, (i, &word)| {
+            mask | (u64::from(word) << (i as u32 * bits))
+        })
+}
+
+fn mask_to_sigset(mask: u64) -> ctypes::sigset_t {
+    let bits = c_ulong::BITS;
+    let mut set = ctypes::sigset_t::default();
+    for (i, word) in set
+        .__bits
+        .iter_mut()
+        .take((u64::BITS / bits) as usize)
+        .enumerate()
+    {
+        *word = (mask >> (i as u32 * bits)) as c_ulong;
+    }
+    set
+}
+
+/// Examines and changes the action taken on the signal `signum`.
+///
+/// `SA_SIGINFO`, `SA_RESTART`, `SA_NODEFER` and `SA_RESETHAND` are supported
+/// in `sa_flags`. A handler with `SA_SIGINFO` gets the signal number and
+/// `SI_USER` in the `siginfo_t`, and a null context.
+pub unsafe fn sys_sigaction(
+    signum: c_int,
+    act: *const ctypes::sigaction,
+    oldact: *mut ctypes::sigaction,
+) -> c_int {
+    debug!(
+        "sys_sigaction <= signum: {}, act: {:#x}, oldact: {:#x}",
+        signum, act as usize, oldact as usize
+    );
+    syscall_body!(sys_sigaction, {
+        let sig = check_signum(signum)?;
+        let act = unsafe { act.as_ref() };
+        if act.is_some() && UNCATCHABLE & sigbit(sig) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut actions = ACTIONS.lock();
+        if let Some(oldact) = unsafe { oldact.as_mut() } {
+            let old = &actions[sig - 1];
+            *oldact = ctypes::sigaction {
+                __sa_handler: unsafe { core::mem::transmute::<usize, _>(old.handler) },
+                sa_mask: mask_to_sigset(old.mask),
+                sa_flags: old.flags as c_int,
+                sa_restorer: None,
+            };
+        }
+        if let Some(act) = act {
+            let action = SigAction {
+                handler: unsafe { core::mem::transmute_copy(&act.__sa_handler) },
+                flags: act.sa_flags as u32,
+                mask: sigset_to_mask(&act.sa_mask) & !UNCATCHABLE,
+            };
+            actions[sig - 1] = action;
+            drop(actions);
+            if action.ignores(sig) {
+                discard(sig);
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// Examines and changes the signal mask of the current thread.
+///
+/// `SIGKILL` and `SIGSTOP` cannot be blocked, and are silently left out.
+pub unsafe fn sys_sigprocmask(
+    how: c_int,
+    set: *const ctypes::sigset_t,
+    oldset: *mut ctypes::sigset_t,
+) -> c_int {
+    debug!(
+        "sys_sigprocmask <= how: {}, set: {:#x}, oldset: {:#x}",
+        how, set as usize, oldset as usize
+    );
+    syscall_body!(sys_sigprocmask, {
+        let thread = current();
+        let old = thread.blocked.load(Ordering::Acquire);
+        if let Some(set) = unsafe { set.as_ref() } {
+            let set = sigset_to_mask(set);
+            let new = match how as u32 {
+                ctypes::SIG_BLOCK => old | set,
+                ctypes::SIG_UNBLOCK => old & !set,
+                ctypes::SIG_SETMASK => set,
+                _ => return Err(LinuxError::EINVAL),
+            };
+            thread.blocked.store(new & !UNCATCHABLE, Ordering::Release);
+        }
+        if let Some(oldset) = unsafe { oldset.as_mut() } {
+            *oldset = mask_to_sigset(old);
+        }
+        Ok(0)
+    })
+}
+
+/// Returns the signals pending on the current thread or the process.
+pub unsafe fn sys_sigpending(set: *mut ctypes::sigset_t) -> c_int {
+    debug!("sys_sigpending <= {:#x}", set as usize);
+    syscall_body!(sys_sigpending, {
+        let set = unsafe { set.as_mut() }.ok_or(LinuxError::EFAULT)?;
+        let pending =
+            current().pending.load(Ordering::Acquire) | PROCESS_PENDING.load(Ordering::Acquire);
+        *set = mask_to_sigset(pending);
+        Ok(0)
+    })
+}
+
+/// Replaces the signal mask of the current thread with `mask`, and waits
+/// until a handler is called. Always fails with `EINTR`.
+pub unsafe fn sys_sigsuspend(mask: *const ctypes::sigset_t) -> c_int {
+    debug!("sys_sigsuspend <= {:#x}", mask as usize);
+    syscall_body!(sys_sigsuspend, {
+        let mask = sigset_to_mask(unsafe { mask.as_ref() }.ok_or(LinuxError::EFAULT)?);
+        let thread = current();
+        let old = thread.blocked.swap(mask & !UNCATCHABLE, Ordering::AcqRel);
+        let poller = Arc::new(Poller::new());
+        let waker: Arc<dyn PollWaker> = poller.clone();
+        let guard = interrupt_on(&waker);
+        while deliver().is_none() {
+            poller.wait(None);
+        }
+        drop(guard);
+        thread.blocked.store(old, Ordering::Release);
+        Err::<c_int, _>(LinuxError::EINTR)
+    })
+}
+
+/// Sends the signal `sig` to the process `pid`.
+///
+/// There is only one process, which `pid` refers to if it is 0, -1 or the
+/// ID of any of its threads. If `sig` is 0, only `pid` is checked.
+pub fn sys_kill(pid: c_int, sig: c_int) -> c_int {
+    debug!("sys_kill <= pid: {}, sig: {}", pid, sig);
+    syscall_body!(sys_kill, {
+        let valid_pid = matches!(pid, 0 | -1)
+            || pid as u64 == current_tid()
+            || THREADS.lock().contains_key(&(pid as u64));
+        if !valid_pid {
+            return Err(LinuxError::ESRCH);
+        }
+        if sig != 0 {
+            send(None, check_signum(sig)?);
+        }
+        Ok(0)
+    })
+}
+
+/// Sends the signal `sig` to the thread `thread`. If `sig` is 0, only
+/// `thread` is checked.
+#[cfg(feature = "multitask")]
+pub fn sys_pthread_kill(thread: ctypes::pthread_t, sig: c_int) -> c_int {
+    debug!("sys_pthread_kill <= {:#x} {}", thread as usize, sig);
+    syscall_body!(sys_pthread_kill, {
+        let tid = super::pthread::pthread_tid(thread).ok_or(LinuxError::ESRCH)?;
+        if sig != 0 {
+            send(Some(tid), check_signum(sig)?);
+        }
+        Ok(0)
+    })
+}
+
+/// The real-time interval timer (`ITIMER_REAL`), which sends `SIGALRM` to
+/// the process at each expiration.
+struct ITimer {
+    /// The next expiration in monotonic time, `None` if disarmed.
+    deadline: Option<TimeValue>,
+    interval: Duration,
+}
+
+impl ITimer {
+    /// Returns the time until the next expiration and the interval.
+    fn get(&self, now: TimeValue) -> (Duration, Duration) {
+        let remaining = self
+            .deadline
+            .map_or(Duration::ZERO, |ddl| ddl.saturating_sub(now));
+        (remaining, self.interval)
+    }
+}
+
+static ITIMER: Mutex<ITimer> = Mutex::new(ITimer {
+    deadline: None,
+    interval: Duration::ZERO,
+});
+
+/// The deadline of [`ITIMER`] in nanoseconds, or 0 if disarmed, to check it
+/// without locking.
+static ITIMER_DEADLINE: AtomicU64 = AtomicU64::new(0);
+
+/// Bumped each time [`ITIMER`] is set, to stop the notifier of the previous
+/// setting.
+#[cfg(feature = "multitask")]
+static ITIMER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Wakes the notifier when [`ITIMER`] is set again.
+#[cfg(feature = "multitask")]
+static ITIMER_WQ: axtask::WaitQueue = axtask::WaitQueue::new();
+
+/// Sends `SIGALRM` if [`ITIMER`] has expired, and rearms it if periodic.
+fn check_itimer() {
+    let deadline = ITIMER_DEADLINE.load(Ordering::Acquire);
+    if deadline == 0 || (monotonic_time().as_nanos() as u64) < deadline {
+        return;
+    }
+    let now = monotonic_time();
+    let expired = {
+        let mut timer = ITIMER.lock();
+        match timer.deadline {
+            Some(deadline) if now >= deadline => {
+                timer.deadline = (!timer.interval.is_zero()).then(|| {
+                    let interval = timer.interval.as_nanos();
+                    let n = (now - deadline).as_nanos() / interval + 1;
+                    deadline + Duration::from_nanos((n * interval) as u64)
+                });
+                ITIMER_DEADLINE.store(
+                    timer.deadline.map_or(0, |ddl| ddl.as_nanos() as u64),
+                    Ordering::Release,
+                );
+                true
+            }
+            _ => false,
+        }
+    };
+    if expired {
+        send(None, ctypes::SIGALRM as _);
+    }
+}
+
+/// Arms [`ITIMER`] to expire after `value` and then every `interval`, or
+/// disarms it if `value` is zero.
+///
+/// Returns the time until the previous expiration and the previous interval.
+fn set_itimer(value: Duration, interval: Duration) -> (Duration, Duration) {
+    let now = monotonic_time();
+    let deadline = (!value.is_zero()).then(|| now + value);
+    let old = {
+        let mut timer = ITIMER.lock();
+        let old = timer.get(now);
+        timer.deadline = deadline;
+        timer.interval = interval;
+        ITIMER_DEADLINE.store(
+            deadline.map_or(0, |ddl| ddl.as_nanos() as u64),
+            Ordering::Release,
+        );
+        old
+    };
+    #[cfg(feature = "multitask")]
+    {
+        let generation = ITIMER_GENERATION.fetch_add(1, Ordering::AcqRel) + 1;
+        ITIMER_WQ.notify_all(false);
+        if deadline.is_some() {
+            spawn_itimer_notifier(generation);
+        }
+    }
+    old
+}
+
+fn get_itimer() -> (Duration, Duration) {
+    check_itimer();
+    ITIMER.lock().get(monotonic_time())
+}
+
+/// Spawns a task to send `SIGALRM` at each expiration of [`ITIMER`], until
+/// it is set again or disarmed. Without it, the expirations are only noticed
+/// at the safe points.
+///
+/// It only makes the signal pending and wakes a blocked thread: the handler
+/// still runs at the next safe point of the thread that takes it.
+#[cfg(feature = "multitask")]
+fn spawn_itimer_notifier(generation: u64) {
+    let current = move || ITIMER_GENERATION.load(Ordering::Acquire) == generation;
+    let notifier = move || {
+        while current() {
+            let Some(deadline) = ITIMER.lock().deadline else {
+                break;
+            };
+            let dur = deadline.saturating_sub(monotonic_time());
+            #[cfg(feature = "irq")]
+            ITIMER_WQ.wait_timeout_until(dur, || !current());
+            #[cfg(not(feature = "irq"))]
+            axtask::sleep(dur);
+            if current() {
+                check_itimer();
+            }
+        }
+    };
+    axtask::spawn_raw(notifier, "itimer".into(), axconfig::TASK_STACK_SIZE);
+}
+
+/// Arranges for `SIGALRM` to be sent to the process in `seconds`, or cancels
+/// the alarm if `seconds` is 0.
+///
+/// Returns the seconds remaining until the previous alarm, or 0 if none.
+///
+/// The `SIGALRM` is only taken at a safe point: a thread that runs without
+/// making syscalls or blocking calls does not call its handler until it does.
+pub fn sys_alarm(seconds: c_uint) -> c_uint {
+    debug!("sys_alarm <= {}", seconds);
+    syscall_body!(sys_alarm, {
+        let (old, _) = set_itimer(Duration::from_secs(seconds as u64), Duration::ZERO);
+        // Rounded up, so that a pending alarm is never reported as none.
+        Ok(old.as_secs() + (old.subsec_nanos() > 0) as u64)
+    })
+}
+
+fn itimerval_to_durations(value: &ctypes::itimerval) -> LinuxResult<(Duration, Duration)> {
+    let valid = |tv: &ctypes::timeval| tv.tv_sec >= 0 && (0..1_000_000).contains(&tv.tv_usec);
+    if !valid(&value.it_value) || !valid(&value.it_interval) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok((value.it_value.into(), value.it_interval.into()))
+}
+
+fn durations_to_itimerval((value, interval): (Duration, Duration)) -> ctypes::itimerval {
+    ctypes::itimerval {
+        it_interval: interval.into(),
+        it_value: value.into(),
+    }
+}
+
+/// Sets the interval timer `which`, and returns its previous setting in
+/// `old_value` if it is not null.
+///
+/// Only `ITIMER_REAL` is supported, as CPU time is not accounted. Like that
+/// of [`sys_alarm`], the `SIGALRM` is only taken at a safe point.
+pub unsafe fn sys_setitimer(
+    which: c_int,
+    new_value: *const ctypes::itimerval,
+    old_value: *mut ctypes::itimerval,
+) -> c_int {
+    debug!("sys_setitimer <= which: {}", which);
+    syscall_body!(sys_setitimer, {
+        if which as u32 != ctypes::ITIMER_REAL {
+            return Err(LinuxError::EINVAL);
+        }
+        let new_value = unsafe { new_value.as_ref() }.ok_or(LinuxError::EFAULT)?;
+        let (value, interval) = itimerval_to_durations(new_value)?;
+        let old = set_itimer(value, interval);
+        if let Some(old_value) = unsafe { old_value.as_mut() } {
+            *old_value = durations_to_itimerval(old);
+        }
+        Ok(0)
+    })
+}
+
+/// Returns the time until the next expiration and the interval of the
+/// interval timer `which`.
+///
+/// Only `ITIMER_REAL` is supported, as CPU time is not accounted.
+pub unsafe fn sys_getitimer(which: c_int, curr_value: *mut ctypes::itimerval) -> c_int {
+    debug!("sys_getitimer <= which: {}", which);
+    syscall_body!(sys_getitimer, {
+        if which as u32 != ctypes::ITIMER_REAL {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr_value = unsafe { curr_value.as_mut() }.ok_or(LinuxError::EFAULT)?;
+        *curr_value = durations_to_itimerval(get_itimer());
+        Ok(0)
+    })
+}
@@ -0,0 +1,142 @@
+use alloc::sync::Arc;
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+
+use super::fd_ops::{FileLike, add_file_like_cloexec, get_file_like};
+use super::poll_set::PollWaker;
+use super::signal;
+use crate::ctypes;
+
+const SIGINFO_SIZE: usize = size_of::<ctypes::signalfd_siginfo>();
+
+/// A file to read the pending signals in a mask from (`signalfd`), instead of
+/// delivering them to handlers. Reading it takes the signals pending on the
+/// reading thread or the process, each as a `signalfd_siginfo`.
+///
+/// The signals should be blocked, otherwise they may be delivered before
+/// they are read.
+pub struct SignalFd {
+    mask: AtomicU64,
+    nonblock: AtomicBool,
+}
+
+impl SignalFd {
+    pub fn new(mask: u64, nonblock: bool) -> Self {
+        Self {
+            mask: AtomicU64::new(mask),
+            nonblock: AtomicBool::new(nonblock),
+        }
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<SignalFd>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+}
+
+fn write_siginfo(buf: &mut [u8], sig: usize) {
+    let info = ctypes::signalfd_siginfo {
+        ssi_signo: sig as u32,
+        ssi_code: ctypes::SI_USER as i32,
+        ..Default::default()
+    };
+    let bytes =
+        unsafe { core::slice::from_raw_parts(&info as *const _ as *const u8, SIGINFO_SIZE) };
+    buf.copy_from_slice(bytes);
+}
+
+impl FileLike for SignalFd {
+    /// Takes as many pending signals as fit in `buf`, blocking until there is
+    /// any.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < SIGINFO_SIZE {
+            return Err(LinuxError::EINVAL);
+        }
+        let mask = self.mask.load(Ordering::Acquire);
+        let first = signal::signalfd_poll_set().block_on(self.is_nonblocking(), || {
+            signal::take_pending_in(mask).ok_or(LinuxError::EAGAIN)
+        })?;
+        let mut next = Some(first);
+        let mut len = 0;
+        for chunk in buf.chunks_exact_mut(SIGINFO_SIZE) {
+            // Taken only if there is room for it.
+            let Some(sig) = next.take().or_else(|| signal::take_pending_in(mask)) else {
+                break;
+            };
+            write_siginfo(chunk, sig);
+            len += SIGINFO_SIZE;
+        }
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: signal::pending_in(self.mask.load(Ordering::Acquire)) != 0,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblock.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register_poller(&self, waker: &Arc<dyn PollWaker>) -> bool {
+        signal::signalfd_poll_set().register(waker);
+        true
+    }
+}
+
+/// Creates a file descriptor to read the signals in `mask` from, or replaces
+/// the mask of the signalfd `fd` if it is not -1.
+///
+/// `SIGKILL` and `SIGSTOP` are silently left out of `mask`. `flags` may
+/// contain `SFD_NONBLOCK` and `SFD_CLOEXEC`.
+pub unsafe fn sys_signalfd(fd: c_int, mask: *const ctypes::sigset_t, flags: c_int) -> c_int {
+    debug!(
+        "sys_signalfd <= fd: {}, mask: {:#x}, flags: {:#x}",
+        fd, mask as usize, flags
+    );
+    syscall_body!(sys_signalfd, {
+        let mask = unsafe { mask.as_ref() }.ok_or(LinuxError::EFAULT)?;
+        let mask = signal::sigset_to_mask(mask) & !signal::UNCATCHABLE;
+        let flags = flags as u32;
+        if flags & !(ctypes::SFD_NONBLOCK | ctypes::SFD_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if fd == -1 {
+            let signalfd = SignalFd::new(mask, flags & ctypes::SFD_NONBLOCK != 0);
+            add_file_like_cloexec(Arc::new(signalfd), flags & ctypes::SFD_CLOEXEC != 0)
+        } else {
+            SignalFd::from_fd(fd)?.mask.store(mask, Ordering::Release);
+            Ok(fd)
+        }
+    })
+}
@@ -73,9 +73,12 @@ pub unsafe fn sys_nanosleep(req: *const ctypes::timespec, rem: *mut ctypes::time
 
         let now = axhal::time::monotonic_time();
 
-        #[cfg(feature = "multitask")]
+        // Interrupted by a signal handler, it returns early with `EINTR`.
+        #[cfg(feature = "signal")]
+        super::signal::sleep(dur).ok();
+        #[cfg(all(feature = "multitask", not(feature = "signal")))]
         axtask::sleep(dur);
-        #[cfg(not(feature = "multitask"))]
+        #[cfg(not(any(feature = "multitask", feature = "signal")))]
         axhal::time::busy_wait(dur);
 
         let after = axhal::time::monotonic_time();
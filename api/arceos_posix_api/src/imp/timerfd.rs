@@ -0,0 +1,335 @@
+use alloc::sync::Arc;
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{TimeValue, monotonic_time, wall_time};
+use axio::PollState;
+use axsync::Mutex;
+
+use super::fd_ops::{FileLike, add_file_like_cloexec, get_file_like};
+use super::poll_set::{PollSet, PollWaker};
+use crate::ctypes;
+
+struct TimerState {
+    /// The next expiration in monotonic time, `None` if disarmed.
+    deadline: Option<TimeValue>,
+    interval: Duration,
+    /// The number of expirations not read yet.
+    expirations: u64,
+}
+
+impl TimerState {
+    /// Counts the expirations until `now`.
+    fn update(&mut self, now: TimeValue) {
+        let Some(deadline) = self.deadline else {
+            return;
+        };
+        if now < deadline {
+            return;
+        }
+        if self.interval.is_zero() {
+            self.expirations += 1;
+            self.deadline = None;
+        } else {
+            let interval = self.interval.as_nanos();
+            let n = (now - deadline).as_nanos() / interval + 1;
+            self.expirations = self.expirations.saturating_add(n as u64);
+            self.deadline = Some(deadline + Duration::from_nanos((n * interval) as u64));
+        }
+    }
+}
+
+/// The part of a timer shared with its notifier task.
+struct Timer {
+    state: Mutex<TimerState>,
+    poll_set: PollSet,
+    /// Bumped each time the timer is set or closed, to make the notifier wait
+    /// for the new setting, or exit.
+    generation: AtomicU64,
+    /// Wakes the notifier when the timer is set again or closed.
+    #[cfg(feature = "multitask")]
+    wq: axtask::WaitQueue,
+    /// Whether the notifier has been spawned, on the first arming.
+    #[cfg(feature = "multitask")]
+    has_notifier: AtomicBool,
+    /// Set when the timer is closed, to stop the notifier.
+    #[cfg(feature = "multitask")]
+    closed: AtomicBool,
+}
+
+/// A timer that expires at a given time and optionally periodically after
+/// that (`timerfd`). Reading it returns the number of expirations as an
+/// 8-byte integer.
+///
+/// With the `multitask` feature, a notifier task sleeps until each
+/// expiration to wake the waiting tasks, and is woken to wait for the new
+/// expiration when the timer is set again. Otherwise, the expirations are
+/// only counted when the timer is read or polled.
+pub struct TimerFd {
+    clockid: u32,
+    nonblock: AtomicBool,
+    timer: Arc<Timer>,
+}
+
+impl TimerFd {
+    pub fn new(clockid: u32, nonblock: bool) -> Self {
+        Self {
+            clockid,
+            nonblock: AtomicBool::new(nonblock),
+            timer: Arc::new(Timer {
+                state: Mutex::new(TimerState {
+                    deadline: None,
+                    interval: Duration::ZERO,
+                    expirations: 0,
+                }),
+                poll_set: PollSet::new(),
+                generation: AtomicU64::new(0),
+                #[cfg(feature = "multitask")]
+                wq: axtask::WaitQueue::new(),
+                #[cfg(feature = "multitask")]
+                has_notifier: AtomicBool::new(false),
+                #[cfg(feature = "multitask")]
+                closed: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<TimerFd>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Returns the time until the next expiration and the interval.
+    pub fn get_time(&self) -> (Duration, Duration) {
+        let now = monotonic_time();
+        let mut state = self.timer.state.lock();
+        state.update(now);
+        let remaining = state
+            .deadline
+            .map_or(Duration::ZERO, |ddl| ddl.saturating_sub(now));
+        (remaining, state.interval)
+    }
+
+    /// Arms the timer to expire at `value` and then every `interval`, or
+    /// disarms it if `value` is zero. `value` is an absolute time of the
+    /// clock of the timer if `abstime` is set, otherwise relative to now.
+    ///
+    /// Returns the previous setting like [`TimerFd::get_time`].
+    pub fn set_time(
+        &self,
+        value: Duration,
+        interval: Duration,
+        abstime: bool,
+    ) -> (Duration, Duration) {
+        let old = self.get_time();
+        let now = monotonic_time();
+        let deadline = if value.is_zero() {
+            None
+        } else if !abstime {
+            Some(now + value)
+        } else if self.clockid == ctypes::CLOCK_REALTIME {
+            // Converted to monotonic time, a later change of the wall clock
+            // does not affect the timer.
+            Some((value + now).saturating_sub(wall_time()))
+        } else {
+            Some(value)
+        };
+
+        {
+            let mut state = self.timer.state.lock();
+            state.deadline = deadline;
+            state.interval = interval;
+            state.expirations = 0;
+            self.timer.generation.fetch_add(1, Ordering::AcqRel);
+        }
+        #[cfg(feature = "multitask")]
+        {
+            if deadline.is_some() && !self.timer.has_notifier.swap(true, Ordering::AcqRel) {
+                spawn_notifier(&self.timer);
+            }
+            self.timer.wq.notify_all(false);
+        }
+        self.timer.poll_set.wake();
+        old
+    }
+}
+
+/// Spawns the task to wake the waiters of `timer` at each expiration, which
+/// waits for the next setting while the timer is disarmed, and exits when it
+/// is closed.
+#[cfg(feature = "multitask")]
+fn spawn_notifier(timer: &Arc<Timer>) {
+    let timer = Arc::downgrade(timer);
+    let notifier = move || {
+        while let Some(t) = timer.upgrade() {
+            // Read before the deadline, so that a new setting is not missed.
+            let generation = t.generation.load(Ordering::Acquire);
+            if t.closed.load(Ordering::Acquire) {
+                break;
+            }
+            let changed = || t.generation.load(Ordering::Acquire) != generation;
+            let Some(deadline) = t.state.lock().deadline else {
+                t.wq.wait_until(changed);
+                continue;
+            };
+            let dur = deadline.saturating_sub(monotonic_time());
+            #[cfg(feature = "irq")]
+            t.wq.wait_timeout_until(dur, changed);
+            #[cfg(not(feature = "irq"))]
+            axtask::sleep(dur);
+            if !changed() {
+                t.state.lock().update(monotonic_time());
+                t.poll_set.wake();
+            }
+        }
+    };
+    axtask::spawn_raw(notifier, "timerfd".into(), axconfig::TASK_STACK_SIZE);
+}
+
+impl Drop for TimerFd {
+    fn drop(&mut self) {
+        // Stop the notifier.
+        #[cfg(feature = "multitask")]
+        self.timer.closed.store(true, Ordering::Release);
+        self.timer.generation.fetch_add(1, Ordering::AcqRel);
+        #[cfg(feature = "multitask")]
+        self.timer.wq.notify_all(false);
+    }
+}
+
+impl FileLike for TimerFd {
+    /// Returns the number of expirations since the last read, blocking until
+    /// there is any.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let expirations = self.timer.poll_set.block_on(self.is_nonblocking(), || {
+            let mut state = self.timer.state.lock();
+            state.update(monotonic_time());
+            match core::mem::take(&mut state.expirations) {
+                0 => Err(LinuxError::EAGAIN),
+                n => Ok(n),
+            }
+        })?;
+        buf[..size_of::<u64>()].copy_from_slice(&expirations.to_ne_bytes());
+        Ok(size_of::<u64>())
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let mut state = self.timer.state.lock();
+        state.update(monotonic_time());
+        Ok(PollState {
+            readable: state.expirations > 0,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblock.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register_poller(&self, waker: &Arc<dyn PollWaker>) -> bool {
+        self.timer.poll_set.register(waker);
+        // Without a notifier, the expirations are noticed only by polling.
+        cfg!(feature = "multitask")
+    }
+}
+
+fn itimerspec_to_durations(value: &ctypes::itimerspec) -> LinuxResult<(Duration, Duration)> {
+    let valid = |ts: &ctypes::timespec| ts.tv_sec >= 0 && (0..1_000_000_000).contains(&ts.tv_nsec);
+    if !valid(&value.it_value) || !valid(&value.it_interval) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok((value.it_value.into(), value.it_interval.into()))
+}
+
+fn durations_to_itimerspec((value, interval): (Duration, Duration)) -> ctypes::itimerspec {
+    ctypes::itimerspec {
+        it_interval: interval.into(),
+        it_value: value.into(),
+    }
+}
+
+/// Creates a timer on the clock `clockid`, and returns a file descriptor
+/// referring to it.
+///
+/// `flags` may contain `TFD_NONBLOCK` and `TFD_CLOEXEC`.
+pub fn sys_timerfd_create(clockid: c_int, flags: c_int) -> c_int {
+    debug!(
+        "sys_timerfd_create <= clockid: {}, flags: {:#x}",
+        clockid, flags
+    );
+    syscall_body!(sys_timerfd_create, {
+        let (clockid, flags) = (clockid as u32, flags as u32);
+        if !matches!(clockid, ctypes::CLOCK_REALTIME | ctypes::CLOCK_MONOTONIC) {
+            return Err(LinuxError::EINVAL);
+        }
+        if flags & !(ctypes::TFD_NONBLOCK | ctypes::TFD_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let timerfd = TimerFd::new(clockid, flags & ctypes::TFD_NONBLOCK != 0);
+        add_file_like_cloexec(Arc::new(timerfd), flags & ctypes::TFD_CLOEXEC != 0)
+    })
+}
+
+/// Arms or disarms the timer referred to by `fd`, and returns the previous
+/// setting in `old_value` if it is not null.
+///
+/// `TFD_TIMER_ABSTIME` is supported in `flags`.
+pub unsafe fn sys_timerfd_settime(
+    fd: c_int,
+    flags: c_int,
+    new_value: *const ctypes::itimerspec,
+    old_value: *mut ctypes::itimerspec,
+) -> c_int {
+    debug!("sys_timerfd_settime <= fd: {}, flags: {:#x}", fd, flags);
+    syscall_body!(sys_timerfd_settime, {
+        let new_value = unsafe { new_value.as_ref() }.ok_or(LinuxError::EFAULT)?;
+        let (value, interval) = itimerspec_to_durations(new_value)?;
+        let abstime = flags as u32 & ctypes::TFD_TIMER_ABSTIME != 0;
+        let old = TimerFd::from_fd(fd)?.set_time(value, interval, abstime);
+        if let Some(old_value) = unsafe { old_value.as_mut() } {
+            *old_value = durations_to_itimerspec(old);
+        }
+        Ok(0)
+    })
+}
+
+/// Returns the time until the next expiration and the interval of the timer
+/// referred to by `fd`.
+pub unsafe fn sys_timerfd_gettime(fd: c_int, curr_value: *mut ctypes::itimerspec) -> c_int {
+    debug!("sys_timerfd_gettime <= fd: {}", fd);
+    syscall_body!(sys_timerfd_gettime, {
+        let curr_value = unsafe { curr_value.as_mut() }.ok_or(LinuxError::EFAULT)?;
+        *curr_value = durations_to_itimerspec(TimerFd::from_fd(fd)?.get_time());
+        Ok(0)
+    })
+}
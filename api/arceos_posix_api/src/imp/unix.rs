@@ -0,0 +1,749 @@
+//! Unix domain sockets (`AF_UNIX`).
+//!
+//! Stream sockets are pairs of receive queues, one for each direction.
+//! Datagram sockets own a receive queue, which other sockets send to. Bound
+//! sockets are registered in [`BINDINGS`] by their addresses, which are either
+//! pathnames (files in axfs if the `fs` feature is enabled) or names in the
+//! abstract namespace.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+
+use super::fd_ops::FileLike;
+use super::poll_set::{PollSet, PollWaker};
+use crate::ctypes;
+
+/// The capacity of the receive queue of a socket.
+const UNIX_BUF_SIZE: usize = 64 * 1024;
+
+/// The maximum backlog of a listening socket, like `SOMAXCONN` of Linux.
+const MAX_BACKLOG: usize = 4096;
+
+/// The maximum number of file descriptors passed in one message, like
+/// `SCM_MAX_FD` of Linux.
+pub const SCM_MAX_FD: usize = 253;
+
+/// The size of the `sun_path` field of `sockaddr_un`.
+const SUN_PATH_LEN: usize = 108;
+
+/// The address of a Unix domain socket.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixAddr {
+    /// The address of an unbound socket.
+    Unnamed,
+    /// A pathname.
+    Path(String),
+    /// A name in the abstract namespace, without the leading NUL byte.
+    Abstract(Vec<u8>),
+}
+
+impl UnixAddr {
+    /// Parses the `sun_path` field of `sockaddr_un`, whose length is given by
+    /// the address length.
+    pub fn from_sun_path(path: &[u8]) -> LinuxResult<Self> {
+        if path.len() > SUN_PATH_LEN {
+            return Err(LinuxError::EINVAL);
+        }
+        match path {
+            [] => Ok(Self::Unnamed),
+            [0, name @ ..] => Ok(Self::Abstract(name.into())),
+            _ => {
+                // The pathname is not necessarily terminated by a NUL.
+                let len = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+                let path = core::str::from_utf8(&path[..len]).map_err(|_| LinuxError::EINVAL)?;
+                Ok(Self::Path(path.into()))
+            }
+        }
+    }
+
+    /// Writes the address to `sun_path`, and returns the length of the
+    /// address, including `sun_family`.
+    ///
+    /// The address is truncated if it does not fit in `sun_path`.
+    pub fn to_sun_path(&self, sun_path: &mut [u8]) -> usize {
+        let len = match self {
+            Self::Unnamed => 0,
+            Self::Path(path) => {
+                let len = path.len().min(sun_path.len());
+                sun_path[..len].copy_from_slice(&path.as_bytes()[..len]);
+                // A pathname filling `sun_path` has no terminating NUL.
+                if let Some(nul) = sun_path.get_mut(len) {
+                    *nul = 0;
+                }
+                (len + 1).min(sun_path.len())
+            }
+            Self::Abstract(name) => {
+                let len = name.len().min(sun_path.len().saturating_sub(1));
+                sun_path[0] = 0;
+                sun_path[1..=len].copy_from_slice(&name[..len]);
+                len + 1
+            }
+        };
+        size_of::<ctypes::sa_family_t>() + len
+    }
+}
+
+/// A message received by [`UnixSocket::recv_msg`].
+pub struct UnixMsg {
+    /// The number of bytes copied to the buffer.
+    pub len: usize,
+    /// The length of the message, larger than `len` if a datagram is
+    /// truncated.
+    pub msg_len: usize,
+    /// The address of the sender, only for datagram sockets.
+    pub from: Option<UnixAddr>,
+    /// The files passed with the message (`SCM_RIGHTS`).
+    pub fds: Vec<Arc<dyn FileLike>>,
+}
+
+/// A message in a receive queue.
+struct Message {
+    data: Vec<u8>,
+    /// The files passed with the message, delivered with its first byte.
+    fds: Vec<Arc<dyn FileLike>>,
+    from: UnixAddr,
+}
+
+/// The receive queue of a socket, shared with the sockets sending to it.
+#[derive(Default)]
+struct RecvQueue {
+    msgs: VecDeque<Message>,
+    /// The number of bytes in the queue.
+    len: usize,
+    /// Whether the peer of a stream socket has closed or shut down writing.
+    eof: bool,
+    /// Whether the socket owning the queue has been closed.
+    closed: bool,
+    /// Woken when the queue changes, for the owner and its peers.
+    poll_set: Arc<PollSet>,
+}
+
+/// The connections waiting to be accepted by a listening stream socket.
+#[derive(Default)]
+struct Backlog {
+    listening: bool,
+    max: usize,
+    pending: VecDeque<UnixSocket>,
+    /// Woken when a connection is queued or accepted, or the listening socket
+    /// is closed.
+    poll_set: Arc<PollSet>,
+}
+
+/// A bound socket that others can connect or send to.
+#[derive(Clone)]
+enum Binding {
+    Stream(Weak<Mutex<Backlog>>),
+    Dgram(Weak<Mutex<RecvQueue>>),
+}
+
+/// The bound sockets with the addresses they were bound to, by the resolved
+/// addresses.
+static BINDINGS: Mutex<BTreeMap<UnixAddr, (UnixAddr, Binding)>> = Mutex::new(BTreeMap::new());
+
+/// The number of sockets bound to an autogenerated abstract name.
+static AUTOBIND_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Resolves a pathname to its key in [`BINDINGS`].
+///
+/// If `create` is set, the socket file is created and the address is in use
+/// if it already exists. Otherwise, the file must exist.
+fn resolve_path(path: &str, create: bool) -> LinuxResult<String> {
+    if path.is_empty() {
+        return Err(LinuxError::ENOENT);
+    }
+    #[cfg(feature = "fs")]
+    {
+        let path = axfs::api::canonicalize(path)?;
+        if create {
+            let mut opts = axfs::fops::OpenOptions::new();
+            opts.write(true);
+            opts.create_new(true);
+            axfs::fops::File::open(&path, &opts).map_err(|e| match e {
+                axerrno::AxError::AlreadyExists => LinuxError::EADDRINUSE,
+                e => e.into(),
+            })?;
+        } else {
+            axfs::api::metadata(&path)?;
+        }
+        Ok(path)
+    }
+    #[cfg(not(feature = "fs"))]
+    {
+        let _ = create;
+        Ok(path.into())
+    }
+}
+
+/// Looks up the socket bound to `addr`, and returns the address it was bound
+/// to.
+fn lookup(addr: &UnixAddr) -> LinuxResult<(UnixAddr, Binding)> {
+    let key = match addr {
+        UnixAddr::Unnamed => return Err(LinuxError::EINVAL),
+        UnixAddr::Path(path) => UnixAddr::Path(resolve_path(path, false)?),
+        UnixAddr::Abstract(_) => addr.clone(),
+    };
+    match BINDINGS.lock().get(&key) {
+        Some(bound) => Ok(bound.clone()),
+        // Without a filesystem, the pathname exists only if it is bound.
+        None if matches!(key, UnixAddr::Path(_)) && !cfg!(feature = "fs") => {
+            Err(LinuxError::ENOENT)
+        }
+        None => Err(LinuxError::ECONNREFUSED),
+    }
+}
+
+/// The peer of a connected socket.
+struct Peer {
+    addr: UnixAddr,
+    rx: Weak<Mutex<RecvQueue>>,
+}
+
+struct State {
+    /// The address as given to `bind`, which is reported to the peers.
+    local: UnixAddr,
+    /// The key of the socket in [`BINDINGS`] if it is bound.
+    key: Option<UnixAddr>,
+    /// The backlog of a bound stream socket.
+    backlog: Option<Arc<Mutex<Backlog>>>,
+    peer: Option<Peer>,
+    shut_rd: bool,
+    shut_wr: bool,
+}
+
+/// A Unix domain socket of type `SOCK_STREAM` or `SOCK_DGRAM`.
+pub struct UnixSocket {
+    socktype: u32,
+    nonblock: AtomicBool,
+    rx: Arc<Mutex<RecvQueue>>,
+    state: Mutex<State>,
+}
+
+impl UnixSocket {
+    /// Creates a new unbound socket of the given type.
+    pub fn new(socktype: u32) -> Self {
+        Self {
+            socktype,
+            nonblock: AtomicBool::new(false),
+            rx: Arc::new(Mutex::new(RecvQueue::default())),
+            state: Mutex::new(State {
+                local: UnixAddr::Unnamed,
+                key: None,
+                backlog: None,
+                peer: None,
+                shut_rd: false,
+                shut_wr: false,
+            }),
+        }
+    }
+
+    /// Creates a pair of sockets connected to each other (`socketpair`).
+    pub fn pair(socktype: u32) -> (Self, Self) {
+        let (a, b) = (Self::new(socktype), Self::new(socktype));
+        a.state.lock().peer = Some(b.peer());
+        b.state.lock().peer = Some(a.peer());
+        (a, b)
+    }
+
+    fn is_stream(&self) -> bool {
+        self.socktype == ctypes::SOCK_STREAM
+    }
+
+    /// Returns the socket type, `SOCK_STREAM` or `SOCK_DGRAM`.
+    pub fn socktype(&self) -> u32 {
+        self.socktype
+    }
+
+    /// Returns this socket as the peer of another socket.
+    fn peer(&self) -> Peer {
+        Peer {
+            addr: self.state.lock().local.clone(),
+            rx: Arc::downgrade(&self.rx),
+        }
+    }
+
+    /// Moves this socket into non-blocking mode or out of it.
+    pub fn set_nonblocking(&self, nonblock: bool) {
+        self.nonblock.store(nonblock, Ordering::Release);
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Returns the address this socket is bound to.
+    pub fn local_addr(&self) -> UnixAddr {
+        self.state.lock().local.clone()
+    }
+
+    /// Returns the address of the peer this socket is connected to.
+    pub fn peer_addr(&self) -> LinuxResult<UnixAddr> {
+        let state = self.state.lock();
+        let peer = state.peer.as_ref().ok_or(LinuxError::ENOTCONN)?;
+        Ok(peer.addr.clone())
+    }
+
+    /// Binds this socket to `addr`, or to an autogenerated abstract name if
+    /// `addr` is unnamed.
+    pub fn bind(&self, addr: UnixAddr) -> LinuxResult {
+        let mut state = self.state.lock();
+        if state.local != UnixAddr::Unnamed {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut bindings = BINDINGS.lock();
+        let (addr, key) = match addr {
+            UnixAddr::Unnamed => loop {
+                let id = AUTOBIND_COUNT.fetch_add(1, Ordering::Relaxed) % 0x100000;
+                let key = UnixAddr::Abstract(alloc::format!("{id:05x}").into_bytes());
+                if !bindings.contains_key(&key) {
+                    break (key.clone(), key);
+                }
+            },
+            UnixAddr::Path(ref path) => {
+                let key = UnixAddr::Path(resolve_path(path, true)?);
+                // Without a filesystem, the pathname exists while it is bound.
+                if !cfg!(feature = "fs") && bindings.contains_key(&key) {
+                    return Err(LinuxError::EADDRINUSE);
+                }
+                (addr, key)
+            }
+            UnixAddr::Abstract(_) => {
+                if bindings.contains_key(&addr) {
+                    return Err(LinuxError::EADDRINUSE);
+                }
+                (addr.clone(), addr)
+            }
+        };
+        let binding = if self.is_stream() {
+            let backlog = Arc::new(Mutex::new(Backlog::default()));
+            let binding = Binding::Stream(Arc::downgrade(&backlog));
+            state.backlog = Some(backlog);
+            binding
+        } else {
+            Binding::Dgram(Arc::downgrade(&self.rx))
+        };
+        // The socket file may be unlinked and created again, replacing the
+        // binding of the old socket.
+        bindings.insert(key.clone(), (addr.clone(), binding));
+        state.local = addr;
+        state.key = Some(key);
+        Ok(())
+    }
+
+    /// Starts listening for connections, with at most `backlog` connections
+    /// waiting to be accepted.
+    pub fn listen(&self, backlog: usize) -> LinuxResult {
+        if !self.is_stream() {
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+        let state = self.state.lock();
+        if state.peer.is_some() {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut queue = state.backlog.as_ref().ok_or(LinuxError::EINVAL)?.lock();
+        queue.listening = true;
+        queue.max = backlog.clamp(1, MAX_BACKLOG);
+        Ok(())
+    }
+
+    fn listening_backlog(&self) -> Option<Arc<Mutex<Backlog>>> {
+        let state = self.state.lock();
+        let backlog = state.backlog.as_ref()?;
+        backlog.lock().listening.then(|| backlog.clone())
+    }
+
+    /// Accepts a new connection.
+    pub fn accept(&self) -> LinuxResult<UnixSocket> {
+        let backlog = self.listening_backlog().ok_or(LinuxError::EINVAL)?;
+        let poll_set = backlog.lock().poll_set.clone();
+        poll_set.block_on(self.is_nonblocking(), || {
+            let mut queue = backlog.lock();
+            let socket = queue.pending.pop_front().ok_or(LinuxError::EAGAIN)?;
+            // The connecting sockets may be able to queue now.
+            queue.poll_set.wake();
+            Ok(socket)
+        })
+    }
+
+    /// Connects this socket to `addr`.
+    ///
+    /// A stream socket waits in the backlog of the listening socket until it
+    /// is accepted, a datagram socket just sets the default destination.
+    pub fn connect(&self, addr: UnixAddr) -> LinuxResult {
+        let (bound_addr, binding) = lookup(&addr)?;
+        match binding {
+            Binding::Stream(backlog) if self.is_stream() => {
+                let backlog = backlog.upgrade().ok_or(LinuxError::ECONNREFUSED)?;
+                let mut state = self.state.lock();
+                if state.peer.is_some() {
+                    return Err(LinuxError::EISCONN);
+                }
+                if state.backlog.is_some() {
+                    return Err(LinuxError::EINVAL);
+                }
+                let poll_set = backlog.lock().poll_set.clone();
+                poll_set.block_on(self.is_nonblocking(), || {
+                    let mut queue = backlog.lock();
+                    if !queue.listening {
+                        return Err(LinuxError::ECONNREFUSED);
+                    }
+                    if queue.pending.len() >= queue.max {
+                        return Err(LinuxError::EAGAIN);
+                    }
+                    let server = UnixSocket::new(ctypes::SOCK_STREAM);
+                    server.state.lock().local = bound_addr.clone();
+                    server.state.lock().peer = Some(Peer {
+                        addr: state.local.clone(),
+                        rx: Arc::downgrade(&self.rx),
+                    });
+                    state.peer = Some(server.peer());
+                    queue.pending.push_back(server);
+                    queue.poll_set.wake();
+                    Ok(())
+                })
+            }
+            Binding::Dgram(rx) if !self.is_stream() => {
+                rx.upgrade().ok_or(LinuxError::ECONNREFUSED)?;
+                self.state.lock().peer = Some(Peer {
+                    addr: bound_addr,
+                    rx,
+                });
+                Ok(())
+            }
+            _ => Err(LinuxError::EPROTOTYPE),
+        }
+    }
+
+    /// Sends `buf` with the files `fds` to the peer, or to `to` for a datagram
+    /// socket. Returns the number of bytes sent.
+    pub fn send_msg(
+        &self,
+        buf: &[u8],
+        fds: Vec<Arc<dyn FileLike>>,
+        to: Option<UnixAddr>,
+    ) -> LinuxResult<usize> {
+        let (from, dest) = {
+            let state = self.state.lock();
+            if state.shut_wr {
+                return Err(LinuxError::EPIPE);
+            }
+            let dest = match (&state.peer, to) {
+                (Some(_), Some(_)) if self.is_stream() => return Err(LinuxError::EISCONN),
+                (None, Some(_)) if self.is_stream() => return Err(LinuxError::EOPNOTSUPP),
+                (_, Some(to)) => match lookup(&to)?.1 {
+                    Binding::Dgram(rx) => rx,
+                    Binding::Stream(_) => return Err(LinuxError::EPROTOTYPE),
+                },
+                (Some(peer), None) => peer.rx.clone(),
+                (None, None) => return Err(LinuxError::ENOTCONN),
+            };
+            (state.local.clone(), dest)
+        };
+        if self.is_stream() {
+            self.send_stream(buf, fds, from, dest)
+        } else {
+            self.send_dgram(buf, fds, from, dest)
+        }
+    }
+
+    fn send_stream(
+        &self,
+        buf: &[u8],
+        fds: Vec<Arc<dyn FileLike>>,
+        from: UnixAddr,
+        dest: Weak<Mutex<RecvQueue>>,
+    ) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let rx = dest.upgrade().ok_or(LinuxError::EPIPE)?;
+        let poll_set = rx.lock().poll_set.clone();
+        let mut fds = Some(fds);
+        let mut sent = 0;
+        while sent < buf.len() {
+            let res = poll_set.block_on(self.is_nonblocking(), || {
+                let mut queue = rx.lock();
+                if queue.closed {
+                    return Err(LinuxError::EPIPE);
+                }
+                let len = UNIX_BUF_SIZE
+                    .saturating_sub(queue.len)
+                    .min(buf.len() - sent);
+                if len == 0 {
+                    return Err(LinuxError::EAGAIN);
+                }
+                let data = &buf[sent..sent + len];
+                let fds = fds.take().unwrap_or_default();
+                match queue.msgs.back_mut() {
+                    // Merge small writes, the files are still delivered with
+                    // the first byte of their message.
+                    Some(last) if fds.is_empty() => last.data.extend_from_slice(data),
+                    _ => queue.msgs.push_back(Message {
+                        data: data.into(),
+                        fds,
+                        from: from.clone(),
+                    }),
+                }
+                queue.len += len;
+                queue.poll_set.wake();
+                Ok(len)
+            });
+            match res {
+                Ok(len) => sent += len,
+                // The bytes sent before an error are reported instead.
+                Err(e) if sent == 0 => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(sent)
+    }
+
+    fn send_dgram(
+        &self,
+        buf: &[u8],
+        fds: Vec<Arc<dyn FileLike>>,
+        from: UnixAddr,
+        dest: Weak<Mutex<RecvQueue>>,
+    ) -> LinuxResult<usize> {
+        if buf.len() > UNIX_BUF_SIZE {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        let mut msg = Some(Message {
+            data: buf.into(),
+            fds,
+            from,
+        });
+        let rx = dest.upgrade().ok_or(LinuxError::ECONNREFUSED)?;
+        let poll_set = rx.lock().poll_set.clone();
+        poll_set.block_on(self.is_nonblocking(), || {
+            let mut queue = rx.lock();
+            if queue.closed {
+                return Err(LinuxError::ECONNREFUSED);
+            }
+            if queue.len + buf.len() > UNIX_BUF_SIZE {
+                return Err(LinuxError::EAGAIN);
+            }
+            queue.msgs.extend(msg.take());
+            queue.len += buf.len();
+            queue.poll_set.wake();
+            Ok(buf.len())
+        })
+    }
+
+    /// Receives a message into `buf`.
+    ///
+    /// A stream socket returns the data of multiple messages, but not beyond
+    /// one with files attached, so that the files are returned with their
+    /// data. A datagram socket returns one message, and the excess bytes are
+    /// discarded.
+    pub fn recv_msg(&self, buf: &mut [u8]) -> LinuxResult<UnixMsg> {
+        {
+            let state = self.state.lock();
+            if self.is_stream() && state.peer.is_none() && !state.shut_rd {
+                return Err(if state.backlog.is_some() {
+                    LinuxError::EINVAL
+                } else {
+                    LinuxError::ENOTCONN
+                });
+            }
+        }
+        let poll_set = self.rx.lock().poll_set.clone();
+        poll_set.block_on(self.is_nonblocking(), || {
+            // Shutting down reading wakes the blocked receivers.
+            if self.state.lock().shut_rd {
+                return Ok(UnixMsg {
+                    len: 0,
+                    msg_len: 0,
+                    from: None,
+                    fds: Vec::new(),
+                });
+            }
+            let mut queue = self.rx.lock();
+            if queue.msgs.is_empty() && !queue.eof {
+                return Err(LinuxError::EAGAIN);
+            }
+            let msg = if self.is_stream() {
+                Self::recv_stream(&mut queue, buf)
+            } else {
+                Self::recv_dgram(&mut queue, buf)
+            };
+            // The senders may be able to send more.
+            queue.poll_set.wake();
+            Ok(msg)
+        })
+    }
+
+    fn recv_stream(queue: &mut RecvQueue, buf: &mut [u8]) -> UnixMsg {
+        let mut len = 0;
+        let mut fds = Vec::new();
+        while len < buf.len() {
+            let Some(msg) = queue.msgs.front_mut() else {
+                break;
+            };
+            if !msg.fds.is_empty() && len > 0 {
+                break;
+            }
+            let n = msg.data.len().min(buf.len() - len);
+            buf[len..len + n].copy_from_slice(&msg.data[..n]);
+            msg.data.drain(..n);
+            fds.append(&mut msg.fds);
+            if msg.data.is_empty() {
+                queue.msgs.pop_front();
+            }
+            queue.len -= n;
+            len += n;
+            if !fds.is_empty() {
+                break;
+            }
+        }
+        UnixMsg {
+            len,
+            msg_len: len,
+            from: None,
+            fds,
+        }
+    }
+
+    fn recv_dgram(queue: &mut RecvQueue, buf: &mut [u8]) -> UnixMsg {
+        let msg = queue.msgs.pop_front().unwrap();
+        queue.len -= msg.data.len();
+        let len = msg.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&msg.data[..len]);
+        UnixMsg {
+            len,
+            msg_len: msg.data.len(),
+            from: Some(msg.from),
+            fds: msg.fds,
+        }
+    }
+
+    /// Shuts down reading (`SHUT_RD`), writing (`SHUT_WR`) or both
+    /// (`SHUT_RDWR`).
+    pub fn shutdown(&self, how: u32) -> LinuxResult {
+        let mut state = self.state.lock();
+        let Some(peer) = &state.peer else {
+            return Err(LinuxError::ENOTCONN);
+        };
+        let (rd, wr) = match how {
+            ctypes::SHUT_RD => (true, false),
+            ctypes::SHUT_WR => (false, true),
+            ctypes::SHUT_RDWR => (true, true),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if wr && self.is_stream() {
+            let rx = peer.rx.upgrade();
+            if let Some(rx) = rx {
+                let mut queue = rx.lock();
+                queue.eof = true;
+                queue.poll_set.wake();
+            }
+        }
+        state.shut_rd |= rd;
+        state.shut_wr |= wr;
+        if rd {
+            self.rx.lock().poll_set.wake();
+        }
+        Ok(())
+    }
+
+    /// Returns whether the socket is readable or writable.
+    pub fn poll(&self) -> PollState {
+        if let Some(backlog) = self.listening_backlog() {
+            return PollState {
+                readable: !backlog.lock().pending.is_empty(),
+                writable: false,
+            };
+        }
+        let state = self.state.lock();
+        let readable = {
+            let queue = self.rx.lock();
+            state.shut_rd || !queue.msgs.is_empty() || queue.eof
+        };
+        // Writing to a closed peer fails immediately, so it is writable.
+        let writable = match &state.peer {
+            Some(peer) => {
+                state.shut_wr
+                    || peer.rx.upgrade().is_none_or(|rx| {
+                        let queue = rx.lock();
+                        queue.closed || queue.len < UNIX_BUF_SIZE
+                    })
+            }
+            None => !self.is_stream(),
+        };
+        PollState { readable, writable }
+    }
+
+    /// Registers `waker` to the queues and the backlog that the readiness of
+    /// the socket depends on. Returns `false` if an unconnected stream socket
+    /// may be connected later.
+    pub fn register_poller(&self, waker: &Arc<dyn PollWaker>) -> bool {
+        let state = self.state.lock();
+        if let Some(backlog) = &state.backlog {
+            backlog.lock().poll_set.register(waker);
+        } else if self.is_stream() && state.peer.is_none() {
+            return false;
+        }
+        self.rx.lock().poll_set.register(waker);
+        let peer_rx = state.peer.as_ref().and_then(|peer| peer.rx.upgrade());
+        if let Some(rx) = peer_rx {
+            rx.lock().poll_set.register(waker);
+        }
+        true
+    }
+
+    /// Returns the capacity of the receive queue.
+    pub fn buffer_size(&self) -> usize {
+        UNIX_BUF_SIZE
+    }
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        let state = self.state.lock();
+        if self.is_stream() {
+            let rx = state.peer.as_ref().and_then(|peer| peer.rx.upgrade());
+            if let Some(rx) = rx {
+                let mut queue = rx.lock();
+                queue.eof = true;
+                queue.poll_set.wake();
+            }
+        }
+        {
+            let mut queue = self.rx.lock();
+            queue.closed = true;
+            queue.poll_set.wake();
+        }
+        if let Some(backlog) = &state.backlog {
+            // Refuse the blocked connecting sockets, and close the pending
+            // connections without the backlog locked.
+            let pending = {
+                let mut queue = backlog.lock();
+                queue.listening = false;
+                queue.poll_set.wake();
+                core::mem::take(&mut queue.pending)
+            };
+            drop(pending);
+        }
+        if let Some(key) = &state.key {
+            // Unregister the address if it has not been taken by others.
+            let mut bindings = BINDINGS.lock();
+            let owned = match bindings.get(key) {
+                Some((_, Binding::Stream(backlog))) => state
+                    .backlog
+                    .as_ref()
+                    .is_some_and(|b| Weak::as_ptr(backlog) == Arc::as_ptr(b)),
+                Some((_, Binding::Dgram(rx))) => Weak::as_ptr(rx) == Arc::as_ptr(&self.rx),
+                None => false,
+            };
+            if owned {
+                bindings.remove(key);
+            }
+        }
+    }
+}
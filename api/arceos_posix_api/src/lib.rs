@@ -35,25 +35,63 @@ pub use imp::sys::sys_sysconf;
 pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
 pub use imp::time::{sys_clock_gettime, sys_nanosleep};
 
+#[cfg(feature = "eventfd")]
+pub use imp::eventfd::sys_eventfd2;
 #[cfg(feature = "fd")]
-pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
+pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl, sys_ioctl};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::fs::{
+    sys_access, sys_chdir, sys_chmod, sys_faccessat, sys_fchdir, sys_fchmod, sys_fdatasync,
+    sys_fstat, sys_fstatat, sys_fsync, sys_ftruncate, sys_getcwd, sys_getdents64, sys_lseek,
+    sys_lstat, sys_mkdir, sys_mkdirat, sys_open, sys_openat, sys_rename, sys_renameat, sys_rmdir,
+    sys_stat, sys_truncate, sys_umask, sys_unlink, sys_unlinkat,
+};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
 #[cfg(feature = "epoll")]
 pub use imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+#[cfg(feature = "poll")]
+pub use imp::io_mpx::{sys_poll, sys_ppoll};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
-    sys_getsockname, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto, sys_shutdown,
-    sys_socket,
+    sys_getsockname, sys_getsockopt, sys_listen, sys_recv, sys_recvfrom, sys_recvmsg, sys_send,
+    sys_sendmsg, sys_sendto, sys_setsockopt, sys_shutdown, sys_socket, sys_socketpair,
 };
+#[cfg(all(feature = "fs", feature = "pipe"))]
+pub use imp::pipe::sys_mkfifo;
 #[cfg(feature = "pipe")]
 pub use imp::pipe::sys_pipe;
+#[cfg(feature = "uspace")]
+pub use imp::process::{run_user_app, sys_execve, sys_fork, sys_waitpid};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::condvar::{
+    sys_pthread_cond_broadcast, sys_pthread_cond_signal, sys_pthread_cond_wait,
+};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::key::{
+    sys_pthread_getspecific, sys_pthread_key_create, sys_pthread_key_delete,
+    sys_pthread_setspecific,
+};
 #[cfg(feature = "multitask")]
 pub use imp::pthread::mutex::{
     sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_unlock,
 };
 #[cfg(feature = "multitask")]
-pub use imp::pthread::{sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_self};
+pub use imp::pthread::once::sys_pthread_once;
+#[cfg(feature = "multitask")]
+pub use imp::pthread::{
+    sys_pthread_create, sys_pthread_detach, sys_pthread_exit, sys_pthread_getname_np,
+    sys_pthread_join, sys_pthread_self, sys_pthread_setname_np,
+};
+#[cfg(all(feature = "signal", feature = "multitask"))]
+pub use imp::signal::sys_pthread_kill;
+#[cfg(feature = "signal")]
+pub use imp::signal::{
+    sys_alarm, sys_getitimer, sys_kill, sys_setitimer, sys_sigaction, sys_sigpending,
+    sys_sigprocmask, sys_sigsuspend,
+};
+#[cfg(feature = "signalfd")]
+pub use imp::signalfd::sys_signalfd;
+#[cfg(feature = "timerfd")]
+pub use imp::timerfd::{sys_timerfd_create, sys_timerfd_gettime, sys_timerfd_settime};
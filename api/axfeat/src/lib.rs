@@ -14,6 +14,7 @@
 //!     - `alloc-slab`: Use the slab allocator.
 //!     - `alloc-buddy`: Use the buddy system allocator.
 //!     - `paging`: Enable page table manipulation.
+//!     - `uspace`: Enable user space support.
 //!     - `tls`: Enable thread-local storage.
 //! - Task management
 //!     - `multitask`: Enable multi-threading support.
@@ -24,6 +25,9 @@
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
 //!     - `net`: Enable networking support.
+//!     - `dhcp`: Configure the network interface by DHCP.
+//!     - `tcp-cubic`: Use the CUBIC TCP congestion control by default (needs
+//!       `fp-simd` on architectures with hardware floating-point ABI).
 //!     - `display`: Enable graphics support.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
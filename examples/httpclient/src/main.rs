@@ -1,3 +1,24 @@
+//! Simple HTTP client.
+//!
+//! With the `https` feature, it connects to a local `openssl s_server` through
+//! TLS instead, verifying the server with the CA certificate `/ca.pem` on the
+//! disk image:
+//!
+//! ```bash
+//! # create a CA and a certificate for "localhost" signed by it
+//! openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:prime256v1 -nodes \
+//!     -subj /CN=ArceOS-CA -keyout ca.key -out ca.pem
+//! openssl req -newkey ec -pkeyopt ec_paramgen_curve:prime256v1 -nodes \
+//!     -subj /CN=localhost -keyout key.pem -out server.csr
+//! openssl x509 -req -in server.csr -CA ca.pem -CAkey ca.key -CAcreateserial \
+//!     -days 30 -extfile <(echo subjectAltName=DNS:localhost) -out cert.pem
+//! openssl s_server -accept 4433 -cert cert.pem -key key.pem -www
+//!
+//! # in another terminal
+//! make disk_img && mcopy -i disk.img ca.pem ::/ca.pem
+//! make A=examples/httpclient APP_FEATURES=https BLK=y NET=y run
+//! ```
+
 #![cfg_attr(feature = "axstd", no_std)]
 #![cfg_attr(feature = "axstd", no_main)]
 
@@ -8,23 +29,47 @@ extern crate axstd as std;
 use std::io::{self, prelude::*};
 use std::net::{TcpStream, ToSocketAddrs};
 
-#[cfg(feature = "dns")]
+#[cfg(feature = "https")]
+use std::net::tls::{self, RootCertStore, TlsConnector, TlsStream};
+
+#[cfg(all(feature = "dns", not(feature = "https")))]
 const DEST: &str = "ident.me:80";
-#[cfg(not(feature = "dns"))]
+#[cfg(not(any(feature = "dns", feature = "https")))]
 const DEST: &str = "65.108.151.63:80";
 
+/// The `openssl s_server` on the host, seen from the QEMU user-mode network.
+#[cfg(feature = "https")]
+const DEST: &str = "10.0.2.2:4433";
+#[cfg(feature = "https")]
+const SERVER_NAME: &str = "localhost";
+#[cfg(feature = "https")]
+const CA_CERT: &str = "/ca.pem";
+
 const REQUEST: &str = "\
 GET / HTTP/1.1\r\n\
 Host: ident.me\r\n\
 Accept: */*\r\n\
 \r\n";
 
+#[cfg(feature = "https")]
+fn tls_connect(stream: TcpStream) -> io::Result<TlsStream> {
+    // A fixed seed makes the handshakes reproducible, never do this in
+    // production.
+    tls::seed_rng(*b"arceos httpclient example seed!!");
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(tls::load_certs(CA_CERT)?);
+    TlsConnector::new(roots)?.connect(SERVER_NAME, stream)
+}
+
 fn client() -> io::Result<()> {
     for addr in DEST.to_socket_addrs()? {
         println!("dest: {} ({})", DEST, addr);
     }
 
+    #[cfg(not(feature = "https"))]
     let mut stream = TcpStream::connect(DEST)?;
+    #[cfg(feature = "https")]
+    let mut stream = tls_connect(TcpStream::connect(DEST)?)?;
     stream.write_all(REQUEST.as_bytes())?;
     let mut buf = [0; 2048];
     let n = stream.read(&mut buf)?;
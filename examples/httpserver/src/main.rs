@@ -5,6 +5,21 @@
 //! ```
 //! ab -n 5000 -c 20 http://X.X.X.X:5555/
 //! ```
+//!
+//! With the `https` feature, it serves HTTPS with the certificate `/cert.pem`
+//! and the private key `/key.pem` on the disk image (see the httpclient example
+//! to create them), which can be tested by `openssl s_client`. The random
+//! number generator of TLS is seeded from `/random-seed`, 32 bytes of entropy
+//! from the host, which is replaced by a new seed at each run:
+//!
+//! ```bash
+//! head -c 32 /dev/urandom > random-seed
+//! make disk_img && mcopy -i disk.img cert.pem key.pem random-seed ::/
+//! make A=examples/httpserver APP_FEATURES=https BLK=y NET=y run
+//!
+//! # in another terminal
+//! openssl s_client -connect localhost:5555 -CAfile ca.pem -servername localhost
+//! ```
 
 #![cfg_attr(feature = "axstd", no_std)]
 #![cfg_attr(feature = "axstd", no_main)]
@@ -17,9 +32,23 @@ use std::io::{self, prelude::*};
 use std::net::{TcpListener, TcpStream};
 use std::thread;
 
+#[cfg(feature = "https")]
+use std::net::tls::{self, TlsAcceptor};
+
 const LOCAL_IP: &str = "0.0.0.0";
 const LOCAL_PORT: u16 = 5555;
 
+#[cfg(not(feature = "https"))]
+const SCHEME: &str = "http";
+#[cfg(feature = "https")]
+const SCHEME: &str = "https";
+#[cfg(feature = "https")]
+const CERT: &str = "/cert.pem";
+#[cfg(feature = "https")]
+const KEY: &str = "/key.pem";
+#[cfg(feature = "https")]
+const RANDOM_SEED: &str = "/random-seed";
+
 macro_rules! header {
     () => {
         "\
@@ -59,7 +88,7 @@ macro_rules! info {
     };
 }
 
-fn http_server(mut stream: TcpStream) -> io::Result<()> {
+fn http_server(mut stream: impl Read + Write) -> io::Result<()> {
     let mut buf = [0u8; 4096];
     let _len = stream.read(&mut buf)?;
 
@@ -70,17 +99,35 @@ fn http_server(mut stream: TcpStream) -> io::Result<()> {
 }
 
 fn accept_loop() -> io::Result<()> {
+    #[cfg(feature = "https")]
+    tls::seed_rng_from_file(RANDOM_SEED)?;
+    #[cfg(feature = "https")]
+    let acceptor = TlsAcceptor::new(tls::load_certs(CERT)?, tls::load_private_key(KEY)?)?;
+
     let listener = TcpListener::bind((LOCAL_IP, LOCAL_PORT))?;
-    println!("listen on: http://{}/", listener.local_addr().unwrap());
+    println!(
+        "listen on: {}://{}/",
+        SCHEME,
+        listener.local_addr().unwrap()
+    );
 
     let mut i = 0;
     loop {
         match listener.accept() {
             Ok((stream, addr)) => {
                 info!("new client {}: {}", i, addr);
-                thread::spawn(move || match http_server(stream) {
-                    Err(e) => info!("client connection error: {:?}", e),
-                    Ok(()) => info!("client {} closed successfully", i),
+                #[cfg(feature = "https")]
+                let acceptor = acceptor.clone();
+                thread::spawn(move || {
+                    #[cfg(feature = "https")]
+                    let stream = match acceptor.accept(stream) {
+                        Ok(stream) => stream,
+                        Err(e) => return info!("client TLS handshake error: {:?}", e),
+                    };
+                    match http_server(stream) {
+                        Err(e) => info!("client connection error: {:?}", e),
+                        Ok(()) => info!("client {} closed successfully", i),
+                    }
                 });
             }
             Err(e) => return Err(e),
@@ -0,0 +1,134 @@
+#![cfg_attr(feature = "axstd", no_std)]
+#![cfg_attr(feature = "axstd", no_main)]
+
+#[macro_use]
+#[cfg(feature = "axstd")]
+extern crate axstd as std;
+
+#[cfg(feature = "axstd")]
+mod ping {
+    use std::io;
+    use std::net::{IcmpSocket, IpAddr, ToSocketAddrs};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// The gateway of the QEMU user-mode network.
+    const DEST: &str = "10.0.2.2";
+    const COUNT: u16 = 4;
+    const PAYLOAD_LEN: usize = 56;
+    const ICMP_HEADER_LEN: usize = 8;
+    const INTERVAL: Duration = Duration::from_secs(1);
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn echo_request(dest: IpAddr, seq: u16) -> [u8; ICMP_HEADER_LEN + PAYLOAD_LEN] {
+        let mut packet = [0; ICMP_HEADER_LEN + PAYLOAD_LEN];
+        // The identifier and the checksum are filled by the network stack.
+        packet[0] = if dest.is_ipv4() { 8 } else { 128 };
+        packet[6..8].copy_from_slice(&seq.to_be_bytes());
+        for (i, b) in packet[ICMP_HEADER_LEN..].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        packet
+    }
+
+    /// Returns the sequence number if `packet` is an echo reply to `ident`.
+    fn echo_reply_seq(packet: &[u8], dest: IpAddr, ident: u16) -> Option<u16> {
+        let reply_type = if dest.is_ipv4() { 0 } else { 129 };
+        if packet.len() < ICMP_HEADER_LEN || packet[0] != reply_type {
+            return None;
+        }
+        if u16::from_be_bytes([packet[4], packet[5]]) != ident {
+            return None;
+        }
+        Some(u16::from_be_bytes([packet[6], packet[7]]))
+    }
+
+    /// Waits for the echo reply of `seq` until the timeout, and returns the
+    /// round-trip time.
+    fn wait_reply(
+        socket: &IcmpSocket,
+        dest: IpAddr,
+        seq: u16,
+        sent_at: Instant,
+    ) -> io::Result<Option<(usize, Duration)>> {
+        let ident = socket.ident()?;
+        let mut buf = [0; 1024];
+        loop {
+            let remaining = TIMEOUT.saturating_sub(sent_at.elapsed());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            match socket.recv_from(&mut buf) {
+                Ok((n, from)) if from == dest => {
+                    if echo_reply_seq(&buf[..n], dest, ident) == Some(seq) {
+                        return Ok(Some((n, sent_at.elapsed())));
+                    }
+                }
+                Ok(_) => {}
+                Err(io::Error::WouldBlock) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn print_duration(name: &str, dur: Duration) {
+        let micros = dur.as_micros();
+        print!("{}={}.{:03} ms", name, micros / 1000, micros % 1000);
+    }
+
+    pub fn ping() -> io::Result<()> {
+        let dest = match (DEST, 0).to_socket_addrs()?.next() {
+            Some(addr) => addr.ip(),
+            None => return Err(io::Error::InvalidInput),
+        };
+        let socket = IcmpSocket::bind(0)?;
+        println!("PING {} ({}): {} data bytes", DEST, dest, PAYLOAD_LEN);
+
+        let mut received = 0;
+        let (mut min, mut max, mut total) = (Duration::MAX, Duration::ZERO, Duration::ZERO);
+        for seq in 0..COUNT {
+            let sent_at = Instant::now();
+            socket.send_to(&echo_request(dest, seq), dest)?;
+            match wait_reply(&socket, dest, seq, sent_at)? {
+                Some((n, rtt)) => {
+                    print!("{} bytes from {}: icmp_seq={} ", n, dest, seq);
+                    print_duration("time", rtt);
+                    println!();
+                    received += 1;
+                    min = min.min(rtt);
+                    max = max.max(rtt);
+                    total += rtt;
+                }
+                None => println!("Request timeout for icmp_seq {}", seq),
+            }
+            if seq + 1 < COUNT {
+                thread::sleep(INTERVAL.saturating_sub(sent_at.elapsed()));
+            }
+        }
+
+        println!("--- {} ping statistics ---", DEST);
+        println!(
+            "{} packets transmitted, {} packets received, {}% packet loss",
+            COUNT,
+            received,
+            (COUNT - received) as u32 * 100 / COUNT as u32
+        );
+        if received > 0 {
+            print_duration("round-trip min", min);
+            print_duration(" avg", total / received as u32);
+            print_duration(" max", max);
+            println!();
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "axstd", unsafe(no_mangle))]
+fn main() {
+    println!("Hello, ping!");
+    #[cfg(feature = "axstd")]
+    ping::ping().expect("test ping failed");
+    #[cfg(not(feature = "axstd"))]
+    println!("ICMP sockets are only available with the `axstd` feature");
+}
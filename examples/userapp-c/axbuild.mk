@@ -0,0 +1 @@
+app-objs := userapp.o
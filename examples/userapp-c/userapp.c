@@ -0,0 +1,16 @@
+#include <stdio.h>
+#include <unistd.h>
+
+// Runs a statically linked Linux program in a user process, in place of this
+// application. Copy it to `/bin/hello` of the disk image (`make disk_img`),
+// then run with `make A=examples/userapp-c BLK=y run`.
+int main()
+{
+    char *const argv[] = {"/bin/hello", NULL};
+    char *const envp[] = {"PATH=/bin", NULL};
+
+    puts("Hello, ArceOS user app loader!");
+    execve(argv[0], argv, envp);
+    perror("execve() error");
+    return -1;
+}
@@ -0,0 +1,203 @@
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::future::Future;
+use core::pin::{Pin, pin};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use axtask::WaitQueue;
+use kspin::SpinNoIrq;
+
+/// A waker that unblocks the executor task waiting in its wait queue.
+struct TaskWaker {
+    notified: AtomicBool,
+    wq: WaitQueue,
+}
+
+impl TaskWaker {
+    const fn new() -> Self {
+        Self {
+            notified: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    fn is_notified(&self) -> bool {
+        self.notified.load(Ordering::Acquire)
+    }
+
+    /// Blocks the current task until the waker is invoked, or the next timer
+    /// expires.
+    fn park(&self) {
+        match crate::timer::next_deadline() {
+            None => self.wq.wait_until(|| self.is_notified()),
+            #[cfg(feature = "irq")]
+            Some(deadline) => {
+                let now = axhal::time::wall_time();
+                if deadline > now {
+                    self.wq
+                        .wait_timeout_until(deadline - now, || self.is_notified());
+                }
+            }
+            #[cfg(not(feature = "irq"))]
+            Some(_) => {
+                // No timer interrupts to wake us up, poll the timers again
+                // after other tasks have run.
+                if !self.is_notified() {
+                    axtask::yield_now();
+                }
+            }
+        }
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.notified.store(true, Ordering::Release);
+        self.wq.notify_one(false);
+    }
+}
+
+/// Runs a future to completion on the current task.
+///
+/// When the future is pending, the current task is blocked until the future
+/// is woken up, or a timer created by [`sleep`](crate::sleep) expires.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let task_waker = Arc::new(TaskWaker::new());
+    let waker = Waker::from(task_waker.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        task_waker.notified.store(false, Ordering::Release);
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        crate::timer::expire_timers();
+        task_waker.park();
+    }
+}
+
+struct JoinInner<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// An owned permission to await the output of a future spawned by [`spawn`].
+pub struct JoinHandle<T> {
+    inner: Arc<SpinNoIrq<JoinInner<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Blocks the current task until the spawned future completes, returns its
+    /// output.
+    pub fn join(self) -> T {
+        block_on(self)
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut inner = self.inner.lock();
+        match inner.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                inner.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Spawns a new task that runs the given future to completion.
+///
+/// Returns a [`JoinHandle`] which can be awaited, or joined from a
+/// synchronous context, to get the output of the future.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let inner = Arc::new(SpinNoIrq::new(JoinInner {
+        output: None,
+        waker: None,
+    }));
+    let task_inner = inner.clone();
+    let task = axtask::spawn(move || {
+        let output = block_on(future);
+        let waker = {
+            let mut inner = task_inner.lock();
+            inner.output = Some(output);
+            inner.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    });
+    debug!("spawned async task {}", task.id_name());
+    JoinHandle { inner }
+}
+
+/// A future returned by [`yield_now`].
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Yields the current future once, allowing the executor to poll it again
+/// later.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn spawn_and_join() {
+        INIT.call_once(axtask::init_scheduler);
+
+        const NUM_TASKS: u32 = 10;
+        let handles: Vec<_> = (0..NUM_TASKS)
+            .map(|i| {
+                crate::spawn(async move {
+                    crate::yield_now().await;
+                    i * 2
+                })
+            })
+            .collect();
+        let sum = crate::block_on(async {
+            let mut sum = 0;
+            for handle in handles {
+                sum += handle.await;
+            }
+            sum
+        });
+        assert_eq!(sum, NUM_TASKS * (NUM_TASKS - 1));
+
+        let value = crate::spawn(async { 42 }).join();
+        assert_eq!(value, 42);
+        println!("spawn_and_join: OK");
+    }
+}
@@ -0,0 +1,33 @@
+//! [ArceOS](https://github.com/arceos-org/arceos) asynchronous runtime.
+//!
+//! It runs futures on top of the [axtask] scheduler. Each executor is an
+//! ordinary task: when none of its futures can make progress, the task is
+//! blocked in a [`WaitQueue`](axtask::WaitQueue) until one of the wakers is
+//! invoked, instead of spinning on the CPU.
+//!
+//! # Organization
+//!
+//! - [`block_on`]: Runs a future to completion on the current task.
+//! - [`spawn`]: Runs a future on a new task, returns a [`JoinHandle`] to
+//!   await its output.
+//! - [`sleep`], [`sleep_until`] and [`timeout`]: Timer futures, which are
+//!   kept in a global [`timer_list`] and expired by the executors.
+//! - [`yield_now`]: Yields the current future once.
+//!
+//! # Cargo Features
+//!
+//! - `irq`: Use the timer interrupt to wake up a blocked executor when the
+//!   next timer expires. If it is not enabled, an executor with pending timers
+//!   yields the CPU repeatedly instead of blocking.
+
+#![cfg_attr(not(test), no_std)]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+mod executor;
+mod timer;
+
+pub use self::executor::{JoinHandle, YieldNow, block_on, spawn, yield_now};
+pub use self::timer::{Sleep, TimedOut, Timeout, sleep, sleep_until, timeout};
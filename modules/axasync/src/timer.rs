@@ -0,0 +1,130 @@
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+
+use axhal::time::{TimeValue, wall_time};
+use kspin::SpinNoIrq;
+use timer_list::{TimerEvent, TimerList};
+
+static TIMER_LIST: SpinNoIrq<Option<TimerList<WakerEvent>>> = SpinNoIrq::new(None);
+
+struct WakerEvent(Waker);
+
+impl TimerEvent for WakerEvent {
+    fn callback(self, _now: TimeValue) {
+        self.0.wake();
+    }
+}
+
+fn set_timer(deadline: TimeValue, waker: Waker) {
+    TIMER_LIST
+        .lock()
+        .get_or_insert_with(TimerList::new)
+        .set(deadline, WakerEvent(waker));
+}
+
+/// Returns the deadline of the earliest pending timer.
+pub(crate) fn next_deadline() -> Option<TimeValue> {
+    TIMER_LIST.lock().as_ref()?.next_deadline()
+}
+
+/// Wakes up the futures whose timers have expired.
+pub(crate) fn expire_timers() {
+    let now = wall_time();
+    loop {
+        // Do not hold the lock while invoking the wakers.
+        let event = TIMER_LIST.lock().as_mut().and_then(|l| l.expire_one(now));
+        match event {
+            Some((_deadline, event)) => event.callback(now),
+            None => break,
+        }
+    }
+}
+
+/// A future returned by [`sleep`] and [`sleep_until`].
+pub struct Sleep {
+    deadline: TimeValue,
+    waker: Option<Waker>,
+}
+
+impl Sleep {
+    /// Returns the instant at which the future will complete.
+    pub fn deadline(&self) -> TimeValue {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if wall_time() >= self.deadline {
+            return Poll::Ready(());
+        }
+        // Only register a new timer if polled with a different waker, the
+        // timers of the stale wakers just cause spurious wakeups.
+        if !self.waker.as_ref().is_some_and(|w| w.will_wake(cx.waker())) {
+            set_timer(self.deadline, cx.waker().clone());
+            self.waker = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// Waits until `dur` has elapsed.
+pub fn sleep(dur: Duration) -> Sleep {
+    sleep_until(wall_time() + dur)
+}
+
+/// Waits until `deadline` is reached.
+///
+/// The deadline is measured by [`axhal::time::wall_time`].
+pub fn sleep_until(deadline: TimeValue) -> Sleep {
+    Sleep {
+        deadline,
+        waker: None,
+    }
+}
+
+/// The error returned by [`Timeout`] when the deadline is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+/// A future returned by [`timeout`].
+pub struct Timeout<F> {
+    future: F,
+    sleep: Sleep,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, TimedOut>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved out of `self`, and `sleep` is `Unpin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        if let Poll::Ready(output) = future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut this.sleep).poll(cx).map(|_| Err(TimedOut))
+    }
+}
+
+/// Requires the given future to complete before `dur` has elapsed.
+///
+/// If the future completes in time, its output is returned in `Ok`, otherwise
+/// [`Err(TimedOut)`](TimedOut) is returned and the future is dropped.
+pub fn timeout<F: Future>(dur: Duration, future: F) -> Timeout<F> {
+    Timeout {
+        future,
+        sleep: sleep(dur),
+    }
+}
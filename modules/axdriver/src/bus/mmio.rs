@@ -1,11 +1,29 @@
 #[allow(unused_imports)]
-use crate::{AllDevices, prelude::*};
+use crate::{AllDevices, DeviceIrq, prelude::*};
+
+/// Returns the IRQ number of the `i`-th VirtIO MMIO device.
+///
+/// Until the device tree is parsed, the interrupts are assumed to be assigned
+/// as on the QEMU `virt` machines, the only supported platforms with VirtIO
+/// MMIO devices.
+#[cfg(feature = "virtio")]
+fn virtio_mmio_irq(i: usize) -> Option<usize> {
+    if cfg!(target_arch = "riscv64") {
+        // PLIC sources 1 to 8.
+        Some(1 + i)
+    } else if cfg!(any(target_arch = "aarch64", target_arch = "arm")) {
+        // SPIs 16 to 47, which are GIC interrupts 48 to 79.
+        Some(48 + i)
+    } else {
+        None
+    }
+}
 
 impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
         // TODO: parse device tree
         #[cfg(feature = "virtio")]
-        for reg in axconfig::devices::VIRTIO_MMIO_RANGES {
+        for (i, reg) in axconfig::devices::VIRTIO_MMIO_RANGES.iter().enumerate() {
             for_each_drivers!(type Driver, {
                 if let Some(dev) = Driver::probe_mmio(reg.0, reg.1) {
                     info!(
@@ -14,7 +32,9 @@ impl AllDevices {
                         reg.0, reg.0 + reg.1,
                         dev.device_name(),
                     );
-                    self.add_device(dev);
+                    let irq = virtio_mmio_irq(i)
+                        .map(|irq| DeviceIrq::new(irq, Driver::setup_mmio_irq(reg.0)));
+                    self.add_device(dev, irq);
                     continue; // skip to the next device
                 }
             });
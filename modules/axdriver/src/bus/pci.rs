@@ -1,4 +1,4 @@
-use crate::{AllDevices, prelude::*};
+use crate::{AllDevices, DeviceIrq, prelude::*};
 use axdriver_pci::{
     BarInfo, Cam, Command, ConfigurationAccess, DeviceFunction, HeaderType, MemoryBarType, MmioCam,
     PciRangeAllocator, PciRoot,
@@ -7,6 +7,32 @@ use axhal::mem::phys_to_virt;
 
 const PCI_BAR_NUM: u8 = 6;
 
+/// Offset of the register holding the interrupt line and pin in the PCI
+/// configuration space header.
+const PCI_INTERRUPT_REG: u8 = 0x3c;
+
+/// Returns the IRQ number of the legacy interrupt (INTx) of a PCI device.
+///
+/// Only the interrupt line assigned by the firmware is used, which is mapped
+/// to the IRQ number expected by [`axhal::irq::register`] on x86_64. On
+/// other platforms the interrupt routing is described by the device tree and
+/// is not supported yet, so `None` is returned.
+fn pci_device_irq(cam: &impl ConfigurationAccess, bdf: DeviceFunction) -> Option<usize> {
+    let reg = cam.read_word(bdf, PCI_INTERRUPT_REG);
+    let line = (reg & 0xff) as usize;
+    let pin = (reg >> 8) & 0xff;
+    if pin == 0 || line == 0xff {
+        // no interrupt pin used, or the line is not assigned.
+        return None;
+    }
+    if cfg!(target_arch = "x86_64") {
+        // IOAPIC inputs are mapped to vectors starting from 0x20.
+        Some(0x20 + line)
+    } else {
+        None
+    }
+}
+
 fn config_pci_device(
     root: &mut PciRoot<impl ConfigurationAccess>,
     bdf: DeviceFunction,
@@ -88,7 +114,8 @@ impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
         let base_vaddr = phys_to_virt(axconfig::devices::PCI_ECAM_BASE.into());
         let cam = unsafe { MmioCam::new(base_vaddr.as_mut_ptr(), Cam::Ecam) };
-        let mut root = PciRoot::new(cam);
+        // `root` takes its own handle, as the one inside is not accessible.
+        let mut root = PciRoot::new(unsafe { cam.unsafe_clone() });
 
         // PCI 32-bit MMIO space
         let mut allocator = axconfig::devices::PCI_RANGES
@@ -110,7 +137,10 @@ impl AllDevices {
                                 bdf,
                                 dev.device_name(),
                             );
-                            self.add_device(dev);
+                            let irq = pci_device_irq(&cam, bdf).map(|irq| {
+                                DeviceIrq::new(irq, Driver::setup_pci_irq(&mut root, &cam, bdf))
+                            });
+                            self.add_device(dev, irq);
                             continue; // skip to the next device
                         }
                     }),
@@ -3,6 +3,7 @@
 #![allow(unused_imports, dead_code)]
 
 use crate::AxDeviceEnum;
+use crate::irq::IrqAck;
 use axdriver_base::DeviceType;
 
 #[cfg(feature = "virtio")]
@@ -31,6 +32,30 @@ pub trait DriverProbe {
     ) -> Option<AxDeviceEnum> {
         None
     }
+
+    /// Enables the interrupts of the device found by [`probe_mmio`], and
+    /// returns how to acknowledge them, if the device needs it.
+    ///
+    /// [`probe_mmio`]: DriverProbe::probe_mmio
+    #[cfg(bus = "mmio")]
+    fn setup_mmio_irq(_mmio_base: usize) -> Option<IrqAck> {
+        None
+    }
+
+    /// Enables the interrupts of the device found by [`probe_pci`], and
+    /// returns how to acknowledge them, if the device needs it.
+    ///
+    /// `cam` gives access to the same configuration space as `root`.
+    ///
+    /// [`probe_pci`]: DriverProbe::probe_pci
+    #[cfg(bus = "pci")]
+    fn setup_pci_irq<C: ConfigurationAccess>(
+        _root: &mut PciRoot<C>,
+        _cam: &C,
+        _bdf: DeviceFunction,
+    ) -> Option<IrqAck> {
+        None
+    }
 }
 
 #[cfg(net_dev = "virtio-net")]
@@ -129,6 +154,22 @@ cfg_if::cfg_if! {
                     }
                     None
             }
+
+            #[cfg(bus = "pci")]
+            fn setup_pci_irq<C: ConfigurationAccess>(
+                root: &mut axdriver_pci::PciRoot<C>,
+                _cam: &C,
+                bdf: axdriver_pci::DeviceFunction,
+            ) -> Option<IrqAck> {
+                match root.bar_info(bdf, 0).ok()? {
+                    Some(axdriver_pci::BarInfo::Memory { address, .. }) => {
+                        let regs = phys_to_virt((address as usize).into()).as_usize();
+                        crate::ixgbe::enable_rx_irq(regs);
+                        Some((crate::ixgbe::ack_irq, regs))
+                    }
+                    _ => None,
+                }
+            }
         }
     }
 }
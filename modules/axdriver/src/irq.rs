@@ -0,0 +1,31 @@
+//! Interrupts of the devices.
+
+/// A function to acknowledge the interrupt at the device, and its argument
+/// (usually the address of an interrupt status register).
+pub(crate) type IrqAck = (fn(usize), usize);
+
+/// The interrupt of a device.
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceIrq {
+    /// The IRQ number, as expected by `axhal::irq::register`.
+    pub irq: usize,
+    ack: Option<IrqAck>,
+}
+
+impl DeviceIrq {
+    pub(crate) const fn new(irq: usize, ack: Option<IrqAck>) -> Self {
+        Self { irq, ack }
+    }
+
+    /// Acknowledges the interrupt at the device, so that it stops asserting
+    /// the IRQ line.
+    ///
+    /// It must be called in the interrupt handler, otherwise a level-triggered
+    /// interrupt fires again as soon as it is unmasked. It does nothing if the
+    /// device does not need it.
+    pub fn ack(&self) {
+        if let Some((ack, arg)) = self.ack {
+            ack(arg);
+        }
+    }
+}
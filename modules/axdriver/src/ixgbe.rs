@@ -3,6 +3,33 @@ use axdriver_net::ixgbe::{IxgbeHal, PhysAddr as IxgbePhysAddr};
 use axhal::mem::{phys_to_virt, virt_to_phys};
 use core::{alloc::Layout, ptr::NonNull};
 
+/// Extended Interrupt Cause Register, cleared on read.
+const IXGBE_EICR: usize = 0x00800;
+/// Extended Interrupt Mask Set Register.
+const IXGBE_EIMS: usize = 0x00880;
+/// Interrupt Vector Allocation Register of RX/TX queues 0 and 1.
+const IXGBE_IVAR0: usize = 0x00900;
+/// Valid bit of an entry in `IVAR`.
+const IXGBE_IVAR_ALLOC_VAL: u32 = 0x80;
+
+/// Enables the interrupt of RX queue 0 of the device whose registers are at
+/// `regs`, the only queue in use.
+///
+/// The queue is mapped to bit 0 of `EICR`, which is the legacy interrupt
+/// (INTx) when MSI-X is not enabled.
+pub fn enable_rx_irq(regs: usize) {
+    unsafe {
+        let ivar = (regs + IXGBE_IVAR0) as *mut u32;
+        ivar.write_volatile((ivar.read_volatile() & !0xff) | IXGBE_IVAR_ALLOC_VAL);
+        ((regs + IXGBE_EIMS) as *mut u32).write_volatile(1);
+    }
+}
+
+/// Acknowledges the interrupts of the device whose registers are at `regs`.
+pub fn ack_irq(regs: usize) {
+    unsafe { ((regs + IXGBE_EICR) as *const u32).read_volatile() };
+}
+
 pub struct IxgbeHalImpl;
 
 unsafe impl IxgbeHal for IxgbeHalImpl {
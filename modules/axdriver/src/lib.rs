@@ -69,6 +69,7 @@ mod macros;
 mod bus;
 mod drivers;
 mod dummy;
+mod irq;
 mod structs;
 
 #[cfg(feature = "virtio")]
@@ -79,6 +80,7 @@ mod ixgbe;
 
 pub mod prelude;
 
+pub use self::irq::DeviceIrq;
 #[allow(unused_imports)]
 use self::prelude::*;
 pub use self::structs::{AxDeviceContainer, AxDeviceEnum};
@@ -96,6 +98,13 @@ pub struct AllDevices {
     /// All network device drivers.
     #[cfg(feature = "net")]
     pub net: AxDeviceContainer<AxNetDevice>,
+    /// Interrupts of the network devices, in the same order as [`net`].
+    ///
+    /// It is `None` if the IRQ number of the device is unknown.
+    ///
+    /// [`net`]: AllDevices::net
+    #[cfg(feature = "net")]
+    pub net_irqs: AxDeviceContainer<Option<DeviceIrq>>,
     /// All block device drivers.
     #[cfg(feature = "block")]
     pub block: AxDeviceContainer<AxBlockDevice>,
@@ -125,7 +134,7 @@ impl AllDevices {
                     dev.device_type(),
                     dev.device_name(),
                 );
-                self.add_device(dev);
+                self.add_device(dev, None);
             }
         });
 
@@ -133,11 +142,17 @@ impl AllDevices {
     }
 
     /// Adds one device into the corresponding container, according to its device category.
+    ///
+    /// `irq` is the interrupt of the device, if known.
     #[allow(dead_code)]
-    fn add_device(&mut self, dev: AxDeviceEnum) {
+    #[cfg_attr(not(feature = "net"), allow(unused_variables))]
+    fn add_device(&mut self, dev: AxDeviceEnum, irq: Option<DeviceIrq>) {
         match dev {
             #[cfg(feature = "net")]
-            AxDeviceEnum::Net(dev) => self.net.push(dev),
+            AxDeviceEnum::Net(dev) => {
+                self.net.push(dev);
+                self.net_irqs.push(irq);
+            }
             #[cfg(feature = "block")]
             AxDeviceEnum::Block(dev) => self.block.push(dev),
             #[cfg(feature = "display")]
@@ -157,9 +172,11 @@ pub fn init_drivers() -> AllDevices {
     #[cfg(feature = "net")]
     {
         debug!("number of NICs: {}", all_devs.net.len());
-        for (i, dev) in all_devs.net.iter().enumerate() {
+        let nics = all_devs.net.iter().zip(all_devs.net_irqs.iter());
+        for (i, (dev, irq)) in nics.enumerate() {
             assert_eq!(dev.device_type(), DeviceType::Net);
-            debug!("  NIC {}: {:?}", i, dev.device_name());
+            let irq = irq.map(|irq| irq.irq);
+            debug!("  NIC {}: {:?}, irq: {:?}", i, dev.device_name(), irq);
         }
     }
     #[cfg(feature = "block")]
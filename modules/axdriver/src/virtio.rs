@@ -7,11 +7,11 @@ use axdriver_virtio::{BufferDirection, PhysAddr, VirtIoHal};
 use axhal::mem::{phys_to_virt, virt_to_phys};
 use cfg_if::cfg_if;
 
-use crate::{AxDeviceEnum, drivers::DriverProbe};
+use crate::{AxDeviceEnum, drivers::DriverProbe, irq::IrqAck};
 
 cfg_if! {
     if #[cfg(bus = "pci")] {
-        use axdriver_pci::{ConfigurationAccess, DeviceFunction, DeviceFunctionInfo, PciRoot};
+        use axdriver_pci::{BarInfo, ConfigurationAccess, DeviceFunction, DeviceFunctionInfo, PciRoot};
         type VirtIoTransport = axdriver_virtio::PciTransport;
     } else if #[cfg(bus =  "mmio")] {
         type VirtIoTransport = axdriver_virtio::MmioTransport<'static>;
@@ -73,6 +73,64 @@ cfg_if! {
     }
 }
 
+/// Offset of the `InterruptStatus` register of a VirtIO MMIO device.
+#[cfg(bus = "mmio")]
+const VIRTIO_MMIO_INTERRUPT_STATUS: usize = 0x60;
+/// Offset of the `InterruptACK` register of a VirtIO MMIO device.
+#[cfg(bus = "mmio")]
+const VIRTIO_MMIO_INTERRUPT_ACK: usize = 0x64;
+
+/// Acknowledges the interrupts of the VirtIO MMIO device at `base`.
+#[cfg(bus = "mmio")]
+fn ack_mmio_irq(base: usize) {
+    unsafe {
+        let status = ((base + VIRTIO_MMIO_INTERRUPT_STATUS) as *const u32).read_volatile();
+        ((base + VIRTIO_MMIO_INTERRUPT_ACK) as *mut u32).write_volatile(status);
+    }
+}
+
+/// Acknowledges the interrupts of a VirtIO PCI device by reading its ISR
+/// status register at `isr`.
+#[cfg(bus = "pci")]
+fn ack_pci_irq(isr: usize) {
+    unsafe { (isr as *const u8).read_volatile() };
+}
+
+/// Returns the virtual address of the ISR status register of a VirtIO PCI
+/// device, described by its vendor-specific capability.
+#[cfg(bus = "pci")]
+fn pci_isr_vaddr<C: ConfigurationAccess>(
+    root: &mut PciRoot<C>,
+    cam: &C,
+    bdf: DeviceFunction,
+) -> Option<usize> {
+    const PCI_STATUS_CAP_LIST: u32 = 1 << 20;
+    const PCI_CAP_PTR: u8 = 0x34;
+    const PCI_CAP_ID_VNDR: u32 = 0x09;
+    const VIRTIO_PCI_CAP_ISR_CFG: u32 = 3;
+
+    if cam.read_word(bdf, 0x04) & PCI_STATUS_CAP_LIST == 0 {
+        return None;
+    }
+    let mut offset = (cam.read_word(bdf, PCI_CAP_PTR) & 0xfc) as u8;
+    while offset != 0 {
+        // capability ID, next pointer, length and `cfg_type`.
+        let header = cam.read_word(bdf, offset);
+        if header & 0xff == PCI_CAP_ID_VNDR && header >> 24 == VIRTIO_PCI_CAP_ISR_CFG {
+            let bar = cam.read_word(bdf, offset + 4) as u8;
+            let bar_offset = cam.read_word(bdf, offset + 8) as usize;
+            return match root.bar_info(bdf, bar).ok()? {
+                Some(BarInfo::Memory { address, .. }) => {
+                    Some(phys_to_virt((address as usize + bar_offset).into()).as_usize())
+                }
+                _ => None,
+            };
+        }
+        offset = ((header >> 8) & 0xfc) as u8;
+    }
+    None
+}
+
 /// A common driver for all VirtIO devices that implements [`DriverProbe`].
 pub struct VirtIoDriver<D: VirtIoDevMeta + ?Sized>(PhantomData<D>);
 
@@ -133,6 +191,25 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
         }
         None
     }
+
+    #[cfg(bus = "mmio")]
+    fn setup_mmio_irq(mmio_base: usize) -> Option<IrqAck> {
+        let base_vaddr = phys_to_virt(mmio_base.into());
+        Some((ack_mmio_irq, base_vaddr.as_usize()))
+    }
+
+    #[cfg(bus = "pci")]
+    fn setup_pci_irq<C: ConfigurationAccess>(
+        root: &mut PciRoot<C>,
+        cam: &C,
+        bdf: DeviceFunction,
+    ) -> Option<IrqAck> {
+        let isr = pci_isr_vaddr(root, cam, bdf);
+        if isr.is_none() {
+            warn!("VirtIO PCI device at {} has no ISR status register", bdf);
+        }
+        Some((ack_pci_irq, isr?))
+    }
 }
 
 pub struct VirtIoHalImpl;
@@ -2,7 +2,7 @@ use alloc::string::String;
 use axio::Result;
 use core::fmt;
 
-use super::FileType;
+use super::{FileType, Permissions};
 use crate::fops;
 
 /// Iterator over the entries in a directory.
@@ -23,9 +23,10 @@ pub struct DirEntry<'a> {
 }
 
 /// A builder used to create directories in various manners.
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct DirBuilder {
     recursive: bool,
+    perm: Permissions,
 }
 
 impl<'a> ReadDir<'a> {
@@ -120,7 +121,10 @@ impl DirBuilder {
     /// Creates a new set of options with default mode/security settings for all
     /// platforms and also non-recursive.
     pub fn new() -> Self {
-        Self { recursive: false }
+        Self {
+            recursive: false,
+            perm: Permissions::default_dir(),
+        }
     }
 
     /// Indicates that directories should be created recursively, creating all
@@ -131,13 +135,20 @@ impl DirBuilder {
         self
     }
 
+    /// Sets the permissions to create new directories with, where the
+    /// filesystem can store them.
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.perm = Permissions::from_bits_truncate(mode as u16);
+        self
+    }
+
     /// Creates the specified directory with the options configured in this
     /// builder.
     pub fn create(&self, path: &str) -> Result<()> {
         if self.recursive {
             self.create_dir_all(path)
         } else {
-            crate::root::create_dir(None, path)
+            crate::root::create_dir(None, path, self.perm)
         }
     }
 
@@ -148,3 +159,9 @@ impl DirBuilder {
         )
     }
 }
+
+impl Default for DirBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -70,6 +70,29 @@ pub fn create_dir_all(path: &str) -> io::Result<()> {
     DirBuilder::new().recursive(true).create(path)
 }
 
+/// Creates a named pipe (FIFO) with the permissions `perm` at the provided
+/// path.
+///
+/// Only the RAM filesystems can store named pipes: others return
+/// [`Unsupported`](io::Error::Unsupported).
+pub fn create_fifo(path: &str, perm: Permissions) -> io::Result<()> {
+    crate::root::create_fifo(None, path, perm)
+}
+
+/// Returns a number identifying the named pipe at `path`, which does not
+/// change when it is renamed, or `None` if it is not a named pipe.
+pub fn fifo_id(path: &str) -> Option<u64> {
+    crate::root::fifo_id(None, path)
+}
+
+/// Changes the permissions found on a file or a directory.
+///
+/// The filesystems that cannot store them, such as FAT, have them kept in
+/// memory until the file is removed.
+pub fn set_permissions(path: &str, perm: Permissions) -> io::Result<()> {
+    crate::root::set_perm(None, path, perm)
+}
+
 /// Removes an empty directory.
 pub fn remove_dir(path: &str) -> io::Result<()> {
     crate::root::remove_dir(None, path)
@@ -1,5 +1,6 @@
 //! Low-level filesystem operations.
 
+use alloc::string::String;
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
@@ -10,6 +11,7 @@ use core::fmt;
 pub use crate::dev::Disk;
 #[cfg(feature = "myfs")]
 pub use crate::fs::myfs::MyFileSystemIf;
+use crate::root::BaseDir;
 
 /// Alias of [`axfs_vfs::VfsNodeType`].
 pub type FileType = axfs_vfs::VfsNodeType;
@@ -23,6 +25,8 @@ pub type FilePerm = axfs_vfs::VfsNodePerm;
 /// An opened file object, with open permissions and a cursor.
 pub struct File {
     node: WithCap<VfsNodeRef>,
+    /// The absolute path it was opened at.
+    path: String,
     is_append: bool,
     offset: u64,
 }
@@ -31,6 +35,8 @@ pub struct File {
 /// [`read_dir`](Directory::read_dir).
 pub struct Directory {
     node: WithCap<VfsNodeRef>,
+    /// The absolute path it was opened at.
+    path: String,
     entry_idx: usize,
 }
 
@@ -46,7 +52,7 @@ pub struct OpenOptions {
     create_new: bool,
     // system-specific
     _custom_flags: i32,
-    _mode: u32,
+    mode: u32,
 }
 
 impl Default for OpenOptions {
@@ -68,7 +74,7 @@ impl OpenOptions {
             create_new: false,
             // system-specific
             _custom_flags: 0,
-            _mode: 0o666,
+            mode: 0o666,
         }
     }
     /// Sets the option for read access.
@@ -95,6 +101,10 @@ impl OpenOptions {
     pub fn create_new(&mut self, create_new: bool) {
         self.create_new = create_new;
     }
+    /// Sets the permission bits of a file created by the open.
+    pub fn mode(&mut self, mode: u32) {
+        self.mode = mode;
+    }
 
     const fn is_valid(&self) -> bool {
         if !self.read && !self.write && !self.append {
@@ -122,13 +132,14 @@ impl File {
         self.node.access_or_err(cap, AxError::PermissionDenied)
     }
 
-    fn _open_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn _open_at(dir: Option<BaseDir>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
         debug!("open file: {} {:?}", path, opts);
         if !opts.is_valid() {
             return ax_err!(InvalidInput);
         }
 
         let node_option = crate::root::lookup(dir, path);
+        let mut created = false;
         let node = if opts.create || opts.create_new {
             match node_option {
                 Ok(node) => {
@@ -139,7 +150,10 @@ impl File {
                     node
                 }
                 // not exists, create new
-                Err(VfsError::NotFound) => crate::root::create_file(dir, path)?,
+                Err(VfsError::NotFound) => {
+                    created = true;
+                    crate::root::create_file(dir, path)?
+                }
                 Err(e) => return Err(e),
             }
         } else {
@@ -147,14 +161,19 @@ impl File {
             node_option?
         };
 
-        let attr = node.get_attr()?;
+        let path = crate::root::node_key(dir, path)?;
+        let attr = crate::root::node_attr(&path, &node)?;
         if attr.is_dir()
             && (opts.create || opts.create_new || opts.write || opts.append || opts.truncate)
         {
             return ax_err!(IsADirectory);
         }
         let access_cap = opts.into();
-        if !perm_to_cap(attr.perm()).contains(access_cap) {
+        if created {
+            // The mode only applies to later opens, where the filesystem can
+            // store it.
+            crate::fs::store_perm(&node, FilePerm::from_bits_truncate(opts.mode as u16));
+        } else if !perm_to_cap(attr.perm()).contains(access_cap) {
             return ax_err!(PermissionDenied);
         }
 
@@ -164,6 +183,7 @@ impl File {
         }
         Ok(Self {
             node: WithCap::new(node, access_cap),
+            path,
             is_append: opts.append,
             offset: 0,
         })
@@ -250,7 +270,12 @@ impl File {
 
     /// Gets the file attributes.
     pub fn get_attr(&self) -> AxResult<FileAttr> {
-        self.access_node(Cap::empty())?.get_attr()
+        crate::root::node_attr(&self.path, self.access_node(Cap::empty())?)
+    }
+
+    /// Sets the permissions of the file.
+    pub fn set_perm(&self, perm: FilePerm) -> AxResult {
+        crate::root::set_node_perm(&self.path, self.access_node(Cap::empty())?, perm)
     }
 }
 
@@ -259,7 +284,7 @@ impl Directory {
         self.node.access_or_err(cap, AxError::PermissionDenied)
     }
 
-    fn _open_dir_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn _open_dir_at(dir: Option<BaseDir>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
         debug!("open dir: {}", path);
         if !opts.read {
             return ax_err!(InvalidInput);
@@ -269,7 +294,8 @@ impl Directory {
         }
 
         let node = crate::root::lookup(dir, path)?;
-        let attr = node.get_attr()?;
+        let path = crate::root::node_key(dir, path)?;
+        let attr = crate::root::node_attr(&path, &node)?;
         if !attr.is_dir() {
             return ax_err!(NotADirectory);
         }
@@ -281,15 +307,19 @@ impl Directory {
         node.open()?;
         Ok(Self {
             node: WithCap::new(node, access_cap),
+            path,
             entry_idx: 0,
         })
     }
 
-    fn access_at(&self, path: &str) -> AxResult<Option<&VfsNodeRef>> {
+    fn access_at(&self, path: &str) -> AxResult<Option<BaseDir<'_>>> {
         if path.starts_with('/') {
             Ok(None)
         } else {
-            Ok(Some(self.access_node(Cap::EXECUTE)?))
+            Ok(Some(BaseDir {
+                node: self.access_node(Cap::EXECUTE)?,
+                path: &self.path,
+            }))
         }
     }
 
@@ -318,7 +348,7 @@ impl Directory {
 
     /// Creates an empty directory at the path relative to this directory.
     pub fn create_dir(&self, path: &str) -> AxResult {
-        crate::root::create_dir(self.access_at(path)?, path)
+        crate::root::create_dir(self.access_at(path)?, path, FilePerm::default_dir())
     }
 
     /// Removes a file at the path relative to this directory.
@@ -344,6 +374,28 @@ impl Directory {
         Ok(n)
     }
 
+    /// Returns the position of the next entry to be read by
+    /// [`read_dir`](Directory::read_dir).
+    pub fn entry_index(&self) -> usize {
+        self.entry_idx
+    }
+
+    /// Sets the position of the next entry to be read by
+    /// [`read_dir`](Directory::read_dir).
+    pub fn set_entry_index(&mut self, idx: usize) {
+        self.entry_idx = idx;
+    }
+
+    /// Gets the directory attributes.
+    pub fn get_attr(&self) -> AxResult<FileAttr> {
+        crate::root::node_attr(&self.path, self.access_node(Cap::empty())?)
+    }
+
+    /// Sets the permissions of the directory.
+    pub fn set_perm(&self, perm: FilePerm) -> AxResult {
+        crate::root::set_node_perm(&self.path, self.access_node(Cap::empty())?, perm)
+    }
+
     /// Rename a file or directory to a new name.
     /// Delete the original file if `old` already exists.
     ///
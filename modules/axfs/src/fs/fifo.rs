@@ -0,0 +1,47 @@
+use core::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A named pipe (FIFO).
+///
+/// It holds no data: the pipe shared by its open ends is found by its
+/// [`id`](FifoNode::id), which does not change when it is renamed.
+pub struct FifoNode {
+    id: u64,
+    perm: AtomicU16,
+}
+
+impl FifoNode {
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            perm: AtomicU16::new(VfsNodePerm::default_file().bits()),
+        }
+    }
+
+    /// Returns the number identifying the named pipe.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn set_perm(&self, perm: VfsNodePerm) {
+        self.perm.store(perm.bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for FifoNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsNodeOps for FifoNode {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(self.perm.load(Ordering::Relaxed));
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::Fifo, 0, 0))
+    }
+}
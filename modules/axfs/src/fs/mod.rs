@@ -1,3 +1,5 @@
+use axfs_vfs::{VfsNodePerm, VfsNodeRef};
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "myfs")] {
         pub mod myfs;
@@ -9,5 +11,22 @@ cfg_if::cfg_if! {
 #[cfg(feature = "devfs")]
 pub use axfs_devfs as devfs;
 
-#[cfg(feature = "ramfs")]
-pub use axfs_ramfs as ramfs;
+pub mod fifo;
+
+#[cfg(any(feature = "ramfs", feature = "procfs", feature = "sysfs"))]
+pub mod ramfs;
+
+/// Sets the permissions of `node`, and returns whether its filesystem can
+/// store them.
+pub fn store_perm(node: &VfsNodeRef, perm: VfsNodePerm) -> bool {
+    let node = node.as_any();
+    if let Some(fifo) = node.downcast_ref::<fifo::FifoNode>() {
+        fifo.set_perm(perm);
+        return true;
+    }
+    #[cfg(any(feature = "ramfs", feature = "procfs", feature = "sysfs"))]
+    if ramfs::set_perm(node, perm) {
+        return true;
+    }
+    false
+}
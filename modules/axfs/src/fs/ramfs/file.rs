@@ -0,0 +1,65 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use spin::RwLock;
+
+/// The file node in the RAM filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct FileNode {
+    content: RwLock<Vec<u8>>,
+    perm: AtomicU16,
+}
+
+impl FileNode {
+    pub(super) fn new() -> Self {
+        Self {
+            content: RwLock::new(Vec::new()),
+            perm: AtomicU16::new(VfsNodePerm::default_file().bits()),
+        }
+    }
+
+    pub(super) fn set_perm(&self, perm: VfsNodePerm) {
+        self.perm.store(perm.bits(), Ordering::Relaxed);
+    }
+}
+
+impl VfsNodeOps for FileNode {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(self.perm.load(Ordering::Relaxed));
+        let size = self.content.read().len() as u64;
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::File, size, 0))
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        let mut content = self.content.write();
+        if size < content.len() as u64 {
+            content.truncate(size as _);
+        } else {
+            content.resize(size as _, 0);
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = self.content.read();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(offset as usize + buf.len());
+        let src = &content[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let offset = offset as usize;
+        let mut content = self.content.write();
+        if offset + buf.len() > content.len() {
+            content.resize(offset + buf.len(), 0);
+        }
+        content[offset..offset + buf.len()].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+}
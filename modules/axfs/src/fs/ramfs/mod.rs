@@ -0,0 +1,59 @@
+//! RAM filesystem, used by `/tmp`, `/proc` and `/sys`.
+//!
+//! Besides regular files and directories, it stores named pipes as
+//! [`FifoNode`](super::fifo::FifoNode)s.
+
+mod dir;
+mod file;
+
+use alloc::sync::Arc;
+use core::any::Any;
+
+use axfs_vfs::{VfsNodePerm, VfsNodeRef, VfsOps, VfsResult};
+
+use self::dir::DirNode;
+use self::file::FileNode;
+
+/// A RAM filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct RamFileSystem {
+    root: Arc<DirNode>,
+}
+
+impl RamFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            root: DirNode::new(None),
+        }
+    }
+}
+
+impl VfsOps for RamFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.root.set_parent(mount_point.parent().as_ref());
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// Sets the permissions of `node` if it is a file or a directory of a RAM
+/// filesystem, and returns whether it is.
+pub fn set_perm(node: &dyn Any, perm: VfsNodePerm) -> bool {
+    if let Some(file) = node.downcast_ref::<FileNode>() {
+        file.set_perm(perm);
+    } else if let Some(dir) = node.downcast_ref::<DirNode>() {
+        dir.set_perm(perm);
+    } else {
+        return false;
+    }
+    true
+}
+
+impl Default for RamFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
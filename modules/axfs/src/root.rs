@@ -2,13 +2,15 @@
 //!
 //! TODO: it doesn't work very well if the mount points have containment relationships.
 
-use alloc::{string::String, sync::Arc, vec::Vec};
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+
 use axerrno::{AxError, AxResult, ax_err};
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
 use axns::{ResArc, def_resource};
 use axsync::Mutex;
 use lazyinit::LazyInit;
 
+use crate::fs::fifo::FifoNode;
 use crate::{api::FileType, fs, mounts};
 
 def_resource! {
@@ -28,6 +30,21 @@ struct RootDirectory {
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
+/// The permissions set with a chmod on the nodes whose filesystems cannot
+/// store them, by their absolute paths.
+///
+/// An entry follows its node when it is renamed, and is dropped when the node
+/// is removed.
+static NODE_PERMS: Mutex<BTreeMap<String, VfsNodePerm>> = Mutex::new(BTreeMap::new());
+
+/// An open directory which relative paths are resolved from.
+#[derive(Clone, Copy)]
+pub(crate) struct BaseDir<'a> {
+    pub node: &'a VfsNodeRef,
+    /// The absolute path it was opened at.
+    pub path: &'a str,
+}
+
 impl MountPoint {
     pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
         Self { path, fs }
@@ -136,12 +153,16 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
-        self.lookup_mounted_fs(src_path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot rename mount points
-            } else {
-                fs.root_dir().rename(rest_path, dst_path)
-            }
+        self.lookup_mounted_fs(src_path, |fs, src_rest| {
+            self.lookup_mounted_fs(dst_path, |dst_fs, dst_rest| {
+                if src_rest.is_empty() || dst_rest.is_empty() {
+                    ax_err!(PermissionDenied) // cannot rename mount points
+                } else if !core::ptr::addr_eq(Arc::as_ptr(&fs), Arc::as_ptr(&dst_fs)) {
+                    ax_err!(Unsupported) // cannot rename across filesystems
+                } else {
+                    fs.root_dir().rename(src_rest, dst_rest)
+                }
+            })
         })
     }
 }
@@ -187,11 +208,11 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     CURRENT_DIR_PATH.init_new(Mutex::new("/".into()));
 }
 
-fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
+fn parent_node_of(dir: Option<BaseDir>, path: &str) -> VfsNodeRef {
     if path.starts_with('/') {
         ROOT_DIR.clone()
     } else {
-        dir.cloned().unwrap_or_else(|| CURRENT_DIR.lock().clone())
+        dir.map_or_else(|| CURRENT_DIR.lock().clone(), |dir| dir.node.clone())
     }
 }
 
@@ -204,7 +225,7 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
-pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+pub(crate) fn lookup(dir: Option<BaseDir>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
@@ -216,7 +237,7 @@ pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRe
     }
 }
 
-pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+pub(crate) fn create_file(dir: Option<BaseDir>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     } else if path.ends_with('/') {
@@ -227,27 +248,125 @@ pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsN
     parent.lookup(path)
 }
 
-pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
+/// Returns the absolute path of the node at `path` relative to `dir`, which
+/// is its key in [`NODE_PERMS`].
+pub(crate) fn node_key(dir: Option<BaseDir>, path: &str) -> AxResult<String> {
+    let path = match dir {
+        Some(dir) if !path.starts_with('/') => {
+            axfs_vfs::path::canonicalize(&format!("{}/{}", dir.path, path))
+        }
+        _ => absolute_path(path)?,
+    };
+    match path.trim_end_matches('/') {
+        "" => Ok("/".into()),
+        trimmed => Ok(trimmed.into()),
+    }
+}
+
+/// Returns the attributes of `node`, whose key in [`NODE_PERMS`] is `key`.
+pub(crate) fn node_attr(key: &str, node: &VfsNodeRef) -> AxResult<VfsNodeAttr> {
+    let attr = node.get_attr()?;
+    match NODE_PERMS.lock().get(key) {
+        Some(&perm) => Ok(VfsNodeAttr::new(
+            perm,
+            attr.file_type(),
+            attr.size(),
+            attr.blocks(),
+        )),
+        None => Ok(attr),
+    }
+}
+
+fn get_attr(dir: Option<BaseDir>, path: &str, node: &VfsNodeRef) -> AxResult<VfsNodeAttr> {
+    node_attr(&node_key(dir, path)?, node)
+}
+
+/// Returns the number identifying the named pipe at `path` relative to `dir`,
+/// or `None` if it is not a named pipe.
+pub(crate) fn fifo_id(dir: Option<BaseDir>, path: &str) -> Option<u64> {
+    let node = lookup(dir, path).ok()?;
+    let fifo = node.as_any().downcast_ref::<FifoNode>()?;
+    Some(fifo.id())
+}
+
+/// Creates a named pipe with the permissions `perm` at `path` relative to
+/// `dir`, in a filesystem that can store them.
+pub(crate) fn create_fifo(dir: Option<BaseDir>, path: &str, perm: VfsNodePerm) -> AxResult {
+    match lookup(dir, path) {
+        Ok(_) => return ax_err!(AlreadyExists),
+        Err(AxError::NotFound) => {}
+        Err(e) => return Err(e),
+    }
+    if path.ends_with('/') {
+        return ax_err!(NotADirectory);
+    }
+    let parent = parent_node_of(dir, path);
+    parent.create(path, VfsNodeType::Fifo)?;
+    let node = parent.lookup(path)?;
+    let fifo = node
+        .as_any()
+        .downcast_ref::<FifoNode>()
+        .ok_or(AxError::Unsupported)?;
+    fifo.set_perm(perm);
+    Ok(())
+}
+
+/// Sets the permissions of the node at `path` relative to `dir`.
+pub(crate) fn set_perm(dir: Option<BaseDir>, path: &str, perm: VfsNodePerm) -> AxResult {
+    let node = lookup(dir, path)?;
+    set_node_perm(&node_key(dir, path)?, &node, perm)
+}
+
+/// Sets the permissions of `node`, whose key in [`NODE_PERMS`] is `key`.
+///
+/// They are stored in the node if its filesystem can, or else recorded in
+/// [`NODE_PERMS`].
+pub(crate) fn set_node_perm(key: &str, node: &VfsNodeRef, perm: VfsNodePerm) -> AxResult {
+    let mut perms = NODE_PERMS.lock();
+    if fs::store_perm(node, perm) || node.get_attr()?.perm() == perm {
+        perms.remove(key);
+    } else {
+        perms.insert(key.into(), perm);
+    }
+    Ok(())
+}
+
+/// Sets the permissions of the node at `path` relative to `dir`, which has
+/// just been created, if its filesystem can store them.
+fn set_created_perm(dir: Option<BaseDir>, path: &str, perm: VfsNodePerm) -> AxResult {
+    fs::store_perm(&lookup(dir, path)?, perm);
+    Ok(())
+}
+
+/// Drops the permissions recorded for the node at `path` relative to `dir`,
+/// which has been removed.
+fn forget_node(dir: Option<BaseDir>, path: &str) -> AxResult {
+    NODE_PERMS.lock().remove(&node_key(dir, path)?);
+    Ok(())
+}
+
+pub(crate) fn create_dir(dir: Option<BaseDir>, path: &str, perm: VfsNodePerm) -> AxResult {
     match lookup(dir, path) {
         Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
+        Err(AxError::NotFound) => {
+            parent_node_of(dir, path).create(path, VfsNodeType::Dir)?;
+            set_created_perm(dir, path, perm)
+        }
         Err(e) => Err(e),
     }
 }
 
-pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
+pub(crate) fn remove_file(dir: Option<BaseDir>, path: &str) -> AxResult {
     let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
-    if attr.is_dir() {
+    if node.get_attr()?.is_dir() {
         ax_err!(IsADirectory)
-    } else if !attr.perm().owner_writable() {
-        ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, path).remove(path)?;
+        forget_node(dir, path)
     }
 }
 
-pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
+pub(crate) fn remove_dir(dir: Option<BaseDir>, path: &str) -> AxResult {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
@@ -266,13 +385,11 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     }
 
     let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
-    if !attr.is_dir() {
+    if !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
-    } else if !attr.perm().owner_writable() {
-        ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, path).remove(path)?;
+        forget_node(dir, path)
     }
 }
 
@@ -292,7 +409,7 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
     }
 
     let node = lookup(None, &abs_path)?;
-    let attr = node.get_attr()?;
+    let attr = get_attr(None, &abs_path, &node)?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_executable() {
@@ -309,5 +426,22 @@ pub(crate) fn rename(old: &str, new: &str) -> AxResult {
         warn!("dst file already exist, now remove it");
         remove_file(None, new)?;
     }
-    parent_node_of(None, old).rename(old, new)
+    ROOT_DIR.rename(&absolute_path(old)?, &absolute_path(new)?)?;
+
+    // Move the permissions of the nodes at or under `old`.
+    let (old, new) = (node_key(None, old)?, node_key(None, new)?);
+    let mut perms = NODE_PERMS.lock();
+    let moved: Vec<String> = perms
+        .keys()
+        .filter(|path| {
+            path.strip_prefix(old.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .cloned()
+        .collect();
+    for path in moved {
+        let perm = perms.remove(&path).unwrap();
+        perms.insert(format!("{}{}", new, &path[old.len()..]), perm);
+    }
+    Ok(())
 }
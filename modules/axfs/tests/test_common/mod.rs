@@ -1,7 +1,7 @@
 use axfs::api as fs;
 use axio as io;
 
-use fs::{File, FileType, OpenOptions};
+use fs::{File, FileType, OpenOptions, Permissions};
 use io::{Error, Result, prelude::*};
 
 macro_rules! assert_err {
@@ -79,6 +79,40 @@ fn test_file_permission() -> Result<()> {
     // open with empty options
     assert_err!(OpenOptions::new().open(fname), InvalidInput);
 
+    // open a file whose permissions have been changed
+    let perm = fs::metadata(fname)?.permissions();
+    assert_eq!(fs::set_permissions(fname, Permissions::OWNER_READ), Ok(()));
+    assert_eq!(
+        fs::metadata("/short.txt")?.permissions(),
+        Permissions::OWNER_READ
+    );
+    assert_err!(File::create(fname), PermissionDenied);
+    assert!(File::open(fname).is_ok());
+    assert_eq!(fs::set_permissions(fname, perm), Ok(()));
+    assert!(File::create(fname)?.write(&buf[..n]).is_ok());
+
+    // remove a read-only file, whose permissions are not kept
+    let ro_fname = "./read-only.txt";
+    fs::write(ro_fname, "test")?;
+    let perm = fs::metadata(ro_fname)?.permissions();
+    assert_eq!(
+        fs::set_permissions(ro_fname, Permissions::OWNER_READ),
+        Ok(())
+    );
+    assert_eq!(fs::remove_file(ro_fname), Ok(()));
+    fs::write(ro_fname, "test")?;
+    assert_eq!(fs::metadata(ro_fname)?.permissions(), perm);
+    assert_eq!(fs::remove_file(ro_fname), Ok(()));
+
+    // the RAM filesystem stores the permissions of its nodes
+    let dname = "/tmp/perm-dir";
+    let dir_perm = Permissions::from_bits_truncate(0o700);
+    assert_eq!(fs::DirBuilder::new().mode(0o700).create(dname), Ok(()));
+    assert_eq!(fs::metadata(dname)?.permissions(), dir_perm);
+    assert_eq!(fs::rename(dname, "/tmp/perm-dir2"), Ok(()));
+    assert_eq!(fs::metadata("/tmp/perm-dir2")?.permissions(), dir_perm);
+    assert_eq!(fs::remove_dir("/tmp/perm-dir2"), Ok(()));
+
     // read as a directory
     assert_err!(fs::read_dir(fname), NotADirectory);
     assert_err!(fs::read("short.txt/"), NotADirectory);
@@ -164,6 +198,43 @@ fn test_remove_file_dir() -> Result<()> {
     Ok(())
 }
 
+fn test_fifo() -> Result<()> {
+    let fname = "/tmp/./fifo";
+    println!("test fifo {:?}:", fname);
+    let perm = Permissions::from_bits_truncate(0o640);
+    assert_eq!(fs::create_fifo(fname, perm), Ok(()));
+    assert_err!(fs::create_fifo(fname, perm), AlreadyExists);
+    let md = fs::metadata(fname)?;
+    assert_eq!(md.file_type(), FileType::Fifo);
+    assert_eq!(md.permissions(), perm);
+    let id = fs::fifo_id("tmp/fifo");
+    assert!(id.is_some());
+    assert_err!(fs::create_fifo("/dev/fifo", perm));
+
+    // the node keeps its type and its id when it is renamed
+    assert_eq!(fs::rename(fname, "/tmp/fifo2"), Ok(()));
+    assert_eq!(fs::fifo_id(fname), None);
+    assert_err!(fs::metadata(fname), NotFound);
+    assert_eq!(fs::fifo_id("/tmp/./fifo2"), id);
+    assert_eq!(fs::metadata("tmp/fifo2")?.file_type(), FileType::Fifo);
+
+    // a file recreated at the path is a regular file
+    assert_eq!(fs::remove_file("/tmp/fifo2"), Ok(()));
+    assert_eq!(fs::write("/tmp/fifo2", "test"), Ok(()));
+    assert_eq!(fs::fifo_id("/tmp/fifo2"), None);
+    assert_eq!(fs::metadata("/tmp/fifo2")?.file_type(), FileType::File);
+
+    // a named pipe renamed over is replaced
+    assert_eq!(fs::create_fifo("/tmp/fifo3", perm), Ok(()));
+    assert_eq!(fs::rename("/tmp/fifo2", "/tmp/fifo3"), Ok(()));
+    assert_eq!(fs::metadata("/tmp/fifo3")?.file_type(), FileType::File);
+    assert_eq!(fs::read_to_string("/tmp/fifo3")?, "test");
+    assert_eq!(fs::remove_file("/tmp/fifo3"), Ok(()));
+
+    println!("test_fifo() OK!");
+    Ok(())
+}
+
 fn test_devfs_ramfs() -> Result<()> {
     const N: usize = 32;
     let mut buf = [1; N];
@@ -258,5 +329,6 @@ pub fn test_all() {
     test_file_permission().expect("test_file_permission() failed");
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
+    test_fifo().expect("test_fifo() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
 }
@@ -1,4 +1,4 @@
-#![cfg(feature = "myfs")]
+#![cfg(all(feature = "myfs", feature = "ramfs"))]
 
 mod test_common;
 
@@ -6,9 +6,9 @@ use std::sync::Arc;
 
 use axdriver::AxDeviceContainer;
 use axdriver_block::ramdisk::RamDisk;
+use axfs::RamFileSystem;
 use axfs::api::{self as fs, File};
 use axfs::fops::{Disk, MyFileSystemIf};
-use axfs_ramfs::RamFileSystem;
 use axfs_vfs::VfsOps;
 use axio::{Result, Write};
 
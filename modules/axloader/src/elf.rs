@@ -0,0 +1,495 @@
+//! Parsing and loading ELF executables.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::mem::size_of;
+
+use axerrno::{AxError, AxResult, ax_err};
+use axhal::mem::{PAGE_SIZE_4K, VirtAddr};
+use axhal::paging::MappingFlags;
+use axmm::AddrSpace;
+use memory_addr::{MemoryAddr, VirtAddrRange, align_down_4k};
+
+use crate::LoadedImage;
+use crate::stack::*;
+
+const ELFMAG: [u8; 4] = *b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+
+#[cfg(target_arch = "x86_64")]
+const EM_CURRENT: u16 = 62; // EM_X86_64
+#[cfg(target_arch = "aarch64")]
+const EM_CURRENT: u16 = 183; // EM_AARCH64
+#[cfg(target_arch = "riscv64")]
+const EM_CURRENT: u16 = 243; // EM_RISCV
+#[cfg(target_arch = "loongarch64")]
+const EM_CURRENT: u16 = 258; // EM_LOONGARCH
+
+const PT_LOAD: u32 = 1;
+const PT_INTERP: u32 = 3;
+const PT_PHDR: u32 = 6;
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+/// Where position-independent executables are loaded.
+const PIE_BASE: usize = 0x40_0000;
+
+/// The frequency of `times`, as reported to the C library.
+const CLOCKS_PER_SEC: usize = 100;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+struct Elf64Ehdr {
+    e_ident: [u8; 16],
+    e_type: u16,
+    e_machine: u16,
+    e_version: u32,
+    e_entry: u64,
+    e_phoff: u64,
+    e_shoff: u64,
+    e_flags: u32,
+    e_ehsize: u16,
+    e_phentsize: u16,
+    e_phnum: u16,
+    e_shentsize: u16,
+    e_shnum: u16,
+    e_shstrndx: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+struct Elf64Phdr {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_paddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+    p_align: u64,
+}
+
+/// Reads a `T` at `offset` of `data`, or returns `InvalidData` if it is out of
+/// bounds.
+fn read_struct<T: Copy>(data: &[u8], offset: usize) -> AxResult<T> {
+    match offset.checked_add(size_of::<T>()) {
+        Some(end) if end <= data.len() => {
+            Ok(unsafe { core::ptr::read_unaligned(data[offset..].as_ptr() as *const T) })
+        }
+        _ => ax_err!(InvalidData, "truncated ELF file"),
+    }
+}
+
+/// A validated ELF executable.
+pub struct ElfFile<'a> {
+    data: &'a [u8],
+    header: Elf64Ehdr,
+    phdrs: Vec<Elf64Phdr>,
+}
+
+impl<'a> ElfFile<'a> {
+    /// Parses a statically linked ELF64 executable for the current
+    /// architecture, which may be position-independent.
+    ///
+    /// Returns `InvalidData` if `data` is not such an executable, and
+    /// `Unsupported` if it is dynamically linked.
+    pub fn parse(data: &'a [u8]) -> AxResult<Self> {
+        let header: Elf64Ehdr = read_struct(data, 0)?;
+        let ident = &header.e_ident;
+        if ident[..4] != ELFMAG || ident[4] != ELFCLASS64 || ident[5] != ELFDATA2LSB {
+            return ax_err!(InvalidData, "not an ELF64 file");
+        }
+        if !matches!(header.e_type, ET_EXEC | ET_DYN) || header.e_machine != EM_CURRENT {
+            return ax_err!(InvalidData, "not an executable for this architecture");
+        }
+        if header.e_phentsize as usize != size_of::<Elf64Phdr>() {
+            return ax_err!(InvalidData, "bad program header size");
+        }
+        let phdrs = (0..header.e_phnum as usize)
+            .map(|i| {
+                let offset = i
+                    .checked_mul(size_of::<Elf64Phdr>())
+                    .and_then(|off| off.checked_add(usize::try_from(header.e_phoff).ok()?))
+                    .ok_or(AxError::InvalidData)?;
+                read_struct(data, offset)
+            })
+            .collect::<AxResult<Vec<Elf64Phdr>>>()?;
+        if phdrs.iter().any(|ph| ph.p_type == PT_INTERP) {
+            return ax_err!(Unsupported, "dynamically linked executable");
+        }
+
+        let mut prev_end = 0;
+        for ph in phdrs.iter().filter(|ph| ph.p_type == PT_LOAD) {
+            let file_end = ph.p_offset.checked_add(ph.p_filesz);
+            let mem_end = ph.p_vaddr.checked_add(ph.p_memsz);
+            if file_end.is_none_or(|end| end > data.len() as u64) || ph.p_filesz > ph.p_memsz {
+                return ax_err!(InvalidData, "bad segment size");
+            }
+            // Segments must be sorted by address, and must not overlap.
+            if mem_end.is_none_or(|end| end > isize::MAX as u64) || ph.p_vaddr < prev_end {
+                return ax_err!(InvalidData, "bad segment address");
+            }
+            prev_end = ph.p_vaddr + ph.p_memsz;
+        }
+        if prev_end == 0 {
+            return ax_err!(InvalidData, "no loadable segment");
+        }
+        Ok(Self {
+            data,
+            header,
+            phdrs,
+        })
+    }
+
+    fn load_segments(&self) -> impl Iterator<Item = &Elf64Phdr> {
+        self.phdrs.iter().filter(|ph| ph.p_type == PT_LOAD)
+    }
+
+    /// The offset from the addresses in the file to the loaded ones.
+    fn load_bias(&self) -> usize {
+        if self.header.e_type == ET_DYN {
+            let first = self.load_segments().next().unwrap();
+            PIE_BASE - align_down_4k(first.p_vaddr as usize)
+        } else {
+            0
+        }
+    }
+
+    /// The address of the program headers in the file.
+    fn phdr_addr(&self) -> usize {
+        if let Some(ph) = self.phdrs.iter().find(|ph| ph.p_type == PT_PHDR) {
+            return ph.p_vaddr as usize;
+        }
+        let phoff = self.header.e_phoff;
+        self.load_segments()
+            .find(|ph| ph.p_offset <= phoff && phoff < ph.p_offset + ph.p_filesz)
+            .map_or(0, |ph| (ph.p_vaddr + phoff - ph.p_offset) as usize)
+    }
+
+    /// Returns the page-aligned areas to map, with the union of the flags of
+    /// the segments in each page.
+    fn map_areas(&self, bias: usize) -> Vec<(VirtAddr, VirtAddr, MappingFlags)> {
+        let mut areas: Vec<(VirtAddr, VirtAddr, MappingFlags)> = Vec::new();
+        for ph in self.load_segments() {
+            let vaddr = VirtAddr::from(ph.p_vaddr as usize + bias);
+            let start = vaddr.align_down_4k();
+            let end = (vaddr + ph.p_memsz as usize).align_up_4k();
+            let flags = segment_flags(ph.p_flags);
+            if start == end {
+                continue;
+            }
+            match areas.last_mut() {
+                // Segments are sorted, so only the last page of the previous
+                // one may be shared with this one.
+                Some(last) if last.1 > start => {
+                    let shared = (start, last.1, last.2 | flags);
+                    last.1 = start;
+                    if last.0 == last.1 {
+                        areas.pop();
+                    }
+                    areas.push(shared);
+                    if shared.1 < end {
+                        areas.push((shared.1, end, flags));
+                    }
+                }
+                _ => areas.push((start, end, flags)),
+            }
+        }
+        areas
+    }
+
+    /// Maps the segments and the stack `stack` into `aspace`, which must have
+    /// no user mappings, and pushes `args`, `envs` and the auxiliary vector
+    /// onto the stack.
+    ///
+    /// Position-independent executables are loaded at a fixed base address.
+    /// Returns `NoMemory` if the arguments do not fit in the stack.
+    pub fn load(
+        &self,
+        aspace: &mut AddrSpace,
+        args: &[String],
+        envs: &[String],
+        stack: VirtAddrRange,
+    ) -> AxResult<LoadedImage> {
+        let bias = self.load_bias();
+        let entry = self.header.e_entry as usize + bias;
+        let auxv = vec![
+            (AT_PHDR, self.phdr_addr() + bias),
+            (AT_PHENT, size_of::<Elf64Phdr>()),
+            (AT_PHNUM, self.phdrs.len()),
+            (AT_PAGESZ, PAGE_SIZE_4K),
+            // No interpreter, which a static PIE relocates itself without.
+            (AT_BASE, 0),
+            (AT_FLAGS, 0),
+            (AT_ENTRY, entry),
+            (AT_UID, 0),
+            (AT_EUID, 0),
+            (AT_GID, 0),
+            (AT_EGID, 0),
+            (AT_HWCAP, 0),
+            (AT_CLKTCK, CLOCKS_PER_SEC),
+            (AT_SECURE, 0),
+        ];
+        let (stack_data, sp) = init_stack(args, envs, auxv, stack)?;
+
+        let areas = self.map_areas(bias);
+        for &(start, end, flags) in areas.iter() {
+            aspace.map_alloc(start, end - start, flags, true)?;
+        }
+        for ph in self.load_segments() {
+            let vaddr = VirtAddr::from(ph.p_vaddr as usize + bias);
+            let offset = ph.p_offset as usize;
+            aspace.write(vaddr, &self.data[offset..offset + ph.p_filesz as usize])?;
+        }
+        aspace.map_alloc(
+            stack.start,
+            stack.size(),
+            MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
+            true,
+        )?;
+        aspace.write(VirtAddr::from(sp), &stack_data)?;
+
+        let brk = areas
+            .last()
+            .map(|area| area.1)
+            .ok_or(AxError::InvalidData)?;
+        debug!("loaded ELF: entry {entry:#x}, brk {brk:?}, sp {sp:#x}");
+        Ok(LoadedImage {
+            entry: VirtAddr::from(entry),
+            stack_top: VirtAddr::from(sp),
+            brk,
+        })
+    }
+}
+
+fn segment_flags(p_flags: u32) -> MappingFlags {
+    let mut flags = MappingFlags::USER;
+    if p_flags & PF_R != 0 {
+        flags |= MappingFlags::READ;
+    }
+    if p_flags & PF_W != 0 {
+        flags |= MappingFlags::WRITE;
+    }
+    if p_flags & PF_X != 0 {
+        flags |= MappingFlags::EXECUTE;
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EHDR_SIZE: usize = size_of::<Elf64Ehdr>();
+    const PHDR_SIZE: usize = size_of::<Elf64Phdr>();
+
+    fn header(e_type: u16, phnum: u16) -> Elf64Ehdr {
+        let mut e_ident = [0; 16];
+        e_ident[..4].copy_from_slice(&ELFMAG);
+        e_ident[4] = ELFCLASS64;
+        e_ident[5] = ELFDATA2LSB;
+        e_ident[6] = 1; // EV_CURRENT
+        Elf64Ehdr {
+            e_ident,
+            e_type,
+            e_machine: EM_CURRENT,
+            e_version: 1,
+            e_entry: 0x1100,
+            e_phoff: EHDR_SIZE as u64,
+            e_shoff: 0,
+            e_flags: 0,
+            e_ehsize: EHDR_SIZE as u16,
+            e_phentsize: PHDR_SIZE as u16,
+            e_phnum: phnum,
+            e_shentsize: 0,
+            e_shnum: 0,
+            e_shstrndx: 0,
+        }
+    }
+
+    fn phdr(
+        p_type: u32,
+        p_flags: u32,
+        offset: u64,
+        vaddr: u64,
+        filesz: u64,
+        memsz: u64,
+    ) -> Elf64Phdr {
+        Elf64Phdr {
+            p_type,
+            p_flags,
+            p_offset: offset,
+            p_vaddr: vaddr,
+            p_paddr: vaddr,
+            p_filesz: filesz,
+            p_memsz: memsz,
+            p_align: PAGE_SIZE_4K as u64,
+        }
+    }
+
+    fn as_bytes<T: Copy>(val: &T) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(val as *const T as *const u8, size_of::<T>()) }
+    }
+
+    /// Builds a file of `size` bytes with `header` and `phdrs` at its start.
+    fn build(header: &Elf64Ehdr, phdrs: &[Elf64Phdr], size: usize) -> Vec<u8> {
+        let mut data = vec![0; size];
+        data[..EHDR_SIZE].copy_from_slice(as_bytes(header));
+        for (i, ph) in phdrs.iter().enumerate() {
+            let offset = EHDR_SIZE + i * PHDR_SIZE;
+            data[offset..offset + PHDR_SIZE].copy_from_slice(as_bytes(ph));
+        }
+        data
+    }
+
+    /// The layout of a static PIE: headers and read-only data, code, then
+    /// data and bss sharing a page with the end of the code.
+    fn static_pie_phdrs() -> [Elf64Phdr; 4] {
+        let phdrs_size = 4 * PHDR_SIZE as u64;
+        [
+            phdr(
+                PT_PHDR,
+                PF_R,
+                EHDR_SIZE as u64,
+                EHDR_SIZE as u64,
+                phdrs_size,
+                phdrs_size,
+            ),
+            phdr(PT_LOAD, PF_R, 0, 0, 0x400, 0x400),
+            phdr(PT_LOAD, PF_R | PF_X, 0x1000, 0x1000, 0x800, 0x800),
+            phdr(PT_LOAD, PF_R | PF_W, 0x1800, 0x1800, 0x100, 0x2000),
+        ]
+    }
+
+    fn parse_err(data: &[u8]) -> AxError {
+        ElfFile::parse(data).err().expect("parse should fail")
+    }
+
+    #[test]
+    fn parse_static_pie() {
+        let data = build(&header(ET_DYN, 4), &static_pie_phdrs(), 0x2000);
+        let elf = ElfFile::parse(&data).unwrap();
+        let bias = elf.load_bias();
+        assert_eq!(bias, PIE_BASE);
+        assert_eq!(elf.phdr_addr(), EHDR_SIZE);
+
+        let ro = MappingFlags::USER | MappingFlags::READ;
+        let rx = ro | MappingFlags::EXECUTE;
+        let rw = ro | MappingFlags::WRITE;
+        let va = |addr: usize| VirtAddr::from(PIE_BASE + addr);
+        assert_eq!(
+            elf.map_areas(bias),
+            [
+                (va(0), va(0x1000), ro),
+                (va(0x1000), va(0x2000), rx | rw),
+                (va(0x2000), va(0x4000), rw),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_static_exec() {
+        let mut phdrs = static_pie_phdrs();
+        for ph in phdrs.iter_mut() {
+            ph.p_vaddr += 0x40_0000;
+        }
+        let data = build(&header(ET_EXEC, 4), &phdrs, 0x2000);
+        let elf = ElfFile::parse(&data).unwrap();
+        assert_eq!(elf.load_bias(), 0);
+        assert_eq!(elf.phdr_addr(), 0x40_0000 + EHDR_SIZE);
+    }
+
+    #[test]
+    fn parse_malformed_header() {
+        let phdrs = static_pie_phdrs();
+        let valid = header(ET_DYN, 4);
+
+        assert_eq!(parse_err(&[0x7f, b'E', b'L']), AxError::InvalidData);
+
+        let mut bad = valid;
+        bad.e_ident[0] = 0;
+        assert_eq!(
+            parse_err(&build(&bad, &phdrs, 0x2000)),
+            AxError::InvalidData
+        );
+
+        let mut bad = valid;
+        bad.e_ident[4] = 1; // ELFCLASS32
+        assert_eq!(
+            parse_err(&build(&bad, &phdrs, 0x2000)),
+            AxError::InvalidData
+        );
+
+        let mut bad = valid;
+        bad.e_type = 1; // ET_REL
+        assert_eq!(
+            parse_err(&build(&bad, &phdrs, 0x2000)),
+            AxError::InvalidData
+        );
+
+        let mut bad = valid;
+        bad.e_phentsize = 0;
+        assert_eq!(
+            parse_err(&build(&bad, &phdrs, 0x2000)),
+            AxError::InvalidData
+        );
+
+        // Program header offsets that overflow or point past the end.
+        for phoff in [u64::MAX, u64::MAX - PHDR_SIZE as u64 + 1, 0x2000] {
+            let mut bad = valid;
+            bad.e_phoff = phoff;
+            assert_eq!(
+                parse_err(&build(&bad, &phdrs, 0x2000)),
+                AxError::InvalidData
+            );
+        }
+
+        let mut bad = valid;
+        bad.e_phnum = u16::MAX;
+        assert_eq!(
+            parse_err(&build(&bad, &phdrs, 0x2000)),
+            AxError::InvalidData
+        );
+    }
+
+    #[test]
+    fn parse_malformed_segments() {
+        let valid = static_pie_phdrs();
+        let check = |phdrs: &[Elf64Phdr], err: AxError| {
+            let data = build(&header(ET_DYN, phdrs.len() as u16), phdrs, 0x2000);
+            assert_eq!(parse_err(&data), err);
+        };
+
+        let mut phdrs = valid;
+        phdrs[0] = phdr(PT_INTERP, PF_R, 0x400, 0x400, 0x10, 0x10);
+        check(&phdrs, AxError::Unsupported);
+
+        let mut phdrs = valid;
+        phdrs[3].p_filesz = 0x1000; // past the end of the file
+        check(&phdrs, AxError::InvalidData);
+
+        let mut phdrs = valid;
+        phdrs[3].p_offset = u64::MAX;
+        check(&phdrs, AxError::InvalidData);
+
+        let mut phdrs = valid;
+        phdrs[3].p_memsz = 0x10; // smaller than `p_filesz`
+        check(&phdrs, AxError::InvalidData);
+
+        let mut phdrs = valid;
+        phdrs[3].p_vaddr = 0x1400; // overlaps the code
+        check(&phdrs, AxError::InvalidData);
+
+        let mut phdrs = valid;
+        phdrs[3].p_vaddr = u64::MAX - 0x100;
+        check(&phdrs, AxError::InvalidData);
+
+        check(&valid[..1], AxError::InvalidData);
+    }
+}
@@ -0,0 +1,69 @@
+//! [ArceOS](https://github.com/arceos-org/arceos) ELF loader module.
+//!
+//! Loads statically linked ELF64 executables, including static PIEs, into user
+//! address spaces created by [`axmm::new_user_aspace`], and sets up the initial
+//! stack as Linux does, so that programs linked against a static libc (e.g.,
+//! musl) can run in user mode.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! let mut aspace = axmm::new_user_aspace(va!(0x1000), 0x3f_ffff_f000)?;
+//! let stack = VirtAddrRange::from_start_size(va!(0x3f_fffc_1000), 0x4_0000);
+//! let image = axloader::load_user_app(&mut aspace, &args[0], args, envs, stack)?;
+//! let uctx = image.uspace_context();
+//! // Switch to `aspace`, then enter user mode with `uctx.enter_uspace()`.
+//! ```
+
+#![cfg_attr(not(test), no_std)]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+mod elf;
+mod stack;
+
+pub use self::elf::ElfFile;
+
+use alloc::string::String;
+
+use axerrno::AxResult;
+use axhal::context::UspaceContext;
+use axmm::AddrSpace;
+use memory_addr::{VirtAddr, VirtAddrRange};
+
+/// A program loaded into an address space.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadedImage {
+    /// The entry point.
+    pub entry: VirtAddr,
+    /// The initial stack pointer, where `argc` is.
+    pub stack_top: VirtAddr,
+    /// The end of the loaded segments, where the heap may begin.
+    pub brk: VirtAddr,
+}
+
+impl LoadedImage {
+    /// Returns the context to enter the program in user mode.
+    pub fn uspace_context(&self) -> UspaceContext {
+        UspaceContext::new(self.entry.as_usize(), self.stack_top, 0)
+    }
+}
+
+/// Loads the executable at `path` into `aspace`, which must have no user
+/// mappings, with its stack in `stack`.
+///
+/// See [`ElfFile::load`] for details.
+pub fn load_user_app(
+    aspace: &mut AddrSpace,
+    path: &str,
+    args: &[String],
+    envs: &[String],
+    stack: VirtAddrRange,
+) -> AxResult<LoadedImage> {
+    let data = axfs::api::read(path)?;
+    let elf = ElfFile::parse(&data)?;
+    debug!("loading {path:?}");
+    elf.load(aspace, args, envs, stack)
+}
@@ -0,0 +1,100 @@
+//! The initial user stack.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::mem::size_of;
+
+use axerrno::{AxResult, ax_err};
+use memory_addr::VirtAddrRange;
+
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHENT: usize = 4;
+pub const AT_PHNUM: usize = 5;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_BASE: usize = 7;
+pub const AT_FLAGS: usize = 8;
+pub const AT_ENTRY: usize = 9;
+pub const AT_UID: usize = 11;
+pub const AT_EUID: usize = 12;
+pub const AT_GID: usize = 13;
+pub const AT_EGID: usize = 14;
+pub const AT_HWCAP: usize = 16;
+pub const AT_CLKTCK: usize = 17;
+pub const AT_SECURE: usize = 23;
+pub const AT_RANDOM: usize = 25;
+pub const AT_EXECFN: usize = 31;
+
+/// Returns 16 bytes for `AT_RANDOM`.
+///
+/// They differ between runs, but are not cryptographically secure.
+fn random_bytes() -> [u8; 16] {
+    // SplitMix64, seeded with the current time.
+    let mut seed = axhal::time::monotonic_time_nanos();
+    let mut bytes = [0; 16];
+    for chunk in bytes.chunks_mut(8) {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        chunk.copy_from_slice(&(z ^ (z >> 31)).to_ne_bytes());
+    }
+    bytes
+}
+
+/// Builds the initial stack at the top of `stack`: `argc`, the `argv` and
+/// `envp` arrays and the auxiliary vector `auxv`, followed by the strings and
+/// the random bytes they point to.
+///
+/// `AT_EXECFN`, `AT_RANDOM` and `AT_NULL` are appended to `auxv`.
+///
+/// Returns the stack contents and the stack pointer, where they start.
+pub fn init_stack(
+    args: &[String],
+    envs: &[String],
+    mut auxv: Vec<(usize, usize)>,
+    stack: VirtAddrRange,
+) -> AxResult<(Vec<u8>, usize)> {
+    let mut data = Vec::new();
+    let mut str_offsets = Vec::with_capacity(args.len() + envs.len());
+    for s in args.iter().chain(envs) {
+        str_offsets.push(data.len());
+        data.extend_from_slice(s.as_bytes());
+        data.push(0);
+    }
+    let random_offset = data.len();
+    data.extend_from_slice(&random_bytes());
+
+    let words = 1 + (args.len() + 1) + (envs.len() + 1) + (auxv.len() + 3) * 2;
+    let size = (data.len() + words * size_of::<usize>() + 0xf) & !0xf;
+    if size > stack.size() {
+        return ax_err!(NoMemory, "arguments too long");
+    }
+    let stack_top = stack.end.as_usize();
+    let data_start = stack_top - data.len();
+    let sp = (data_start - words * size_of::<usize>()) & !0xf;
+
+    let (arg_offsets, env_offsets) = str_offsets.split_at(args.len());
+    if let Some(offset) = arg_offsets.first() {
+        auxv.push((AT_EXECFN, data_start + offset));
+    }
+    auxv.push((AT_RANDOM, data_start + random_offset));
+    auxv.push((AT_NULL, 0));
+
+    let mut pointers = Vec::with_capacity(words);
+    pointers.push(args.len());
+    pointers.extend(arg_offsets.iter().map(|offset| data_start + offset));
+    pointers.push(0);
+    pointers.extend(env_offsets.iter().map(|offset| data_start + offset));
+    pointers.push(0);
+    for (key, value) in auxv {
+        pointers.extend([key, value]);
+    }
+
+    let mut contents = vec![0u8; stack_top - sp];
+    for (i, ptr) in pointers.iter().enumerate() {
+        let offset = i * size_of::<usize>();
+        contents[offset..offset + size_of::<usize>()].copy_from_slice(&ptr.to_ne_bytes());
+    }
+    contents[data_start - sp..].copy_from_slice(&data);
+    Ok((contents, sp))
+}
@@ -246,6 +246,53 @@ impl AddrSpace {
         Ok(())
     }
 
+    /// Creates a copy of the address space for a forked process.
+    ///
+    /// The kernel portion is copied as in [`new_user_aspace`]. Allocated
+    /// frames are shared rather than copied: both address spaces map them
+    /// read-only, and a write fault gives the writer its own copy.
+    ///
+    /// [`new_user_aspace`]: crate::new_user_aspace
+    pub fn clone_cow(&mut self) -> AxResult<Self> {
+        let mut new_aspace = crate::new_user_aspace(self.base(), self.size())?;
+        for area in self.areas.iter() {
+            let (start, size, flags) = (area.start(), area.size(), area.flags());
+            match *area.backend() {
+                Backend::Linear { pa_va_offset } => {
+                    let start_paddr = PhysAddr::from(start.as_usize() - pa_va_offset);
+                    new_aspace.map_linear(start, start_paddr, size, flags)?;
+                }
+                Backend::Alloc { .. } => {
+                    // Pages are all mapped below, and the missing ones are
+                    // allocated on demand.
+                    let new_area = MemoryArea::new(start, size, flags, Backend::new_alloc(false));
+                    new_aspace
+                        .areas
+                        .map(new_area, &mut new_aspace.pt, false)
+                        .map_err(mapping_err_to_ax_err)?;
+                    let cow_flags = flags - MappingFlags::WRITE;
+                    for vaddr in PageIter4K::new(start, area.end()).unwrap() {
+                        let frame = match self.pt.query(vaddr) {
+                            Ok((frame, page_flags, _)) if !page_flags.is_empty() => frame,
+                            _ => continue,
+                        };
+                        crate::backend::share_frame(frame);
+                        new_aspace
+                            .pt
+                            .cursor()
+                            .remap(vaddr, frame, cow_flags)
+                            .map_err(|_| AxError::BadState)?;
+                        self.pt
+                            .cursor()
+                            .remap(vaddr, frame, cow_flags)
+                            .map_err(|_| AxError::BadState)?;
+                    }
+                }
+            }
+        }
+        Ok(new_aspace)
+    }
+
     /// Removes all mappings in the address space.
     pub fn clear(&mut self) {
         self.areas.clear(&mut self.pt).unwrap();
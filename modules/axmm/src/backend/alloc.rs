@@ -1,10 +1,18 @@
+use alloc::collections::BTreeMap;
+
 use axalloc::global_allocator;
 use axhal::mem::{phys_to_virt, virt_to_phys};
 use axhal::paging::{MappingFlags, PageSize, PageTable};
-use memory_addr::{PAGE_SIZE_4K, PageIter4K, PhysAddr, VirtAddr};
+use kspin::SpinNoIrq;
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, PageIter4K, PhysAddr, VirtAddr};
 
 use super::Backend;
 
+/// Reference counts of the frames shared by copy-on-write mappings.
+///
+/// A frame that is not in the map has a single owner.
+static FRAME_REFS: SpinNoIrq<BTreeMap<PhysAddr, usize>> = SpinNoIrq::new(BTreeMap::new());
+
 fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
     let vaddr = VirtAddr::from(global_allocator().alloc_pages(1, PAGE_SIZE_4K).ok()?);
     if zeroed {
@@ -15,10 +23,25 @@ fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
 }
 
 fn dealloc_frame(frame: PhysAddr) {
+    {
+        let mut refs = FRAME_REFS.lock();
+        if let Some(count) = refs.get_mut(&frame) {
+            *count -= 1;
+            if *count == 1 {
+                refs.remove(&frame);
+            }
+            return;
+        }
+    }
     let vaddr = phys_to_virt(frame);
     global_allocator().dealloc_pages(vaddr.as_usize(), 1);
 }
 
+/// Adds an owner to the frame, which is then shared copy-on-write.
+pub(crate) fn share_frame(frame: PhysAddr) {
+    *FRAME_REFS.lock().entry(frame).or_insert(1) += 1;
+}
+
 impl Backend {
     /// Creates a new allocation mapping backend.
     pub const fn new_alloc(populate: bool) -> Self {
@@ -100,7 +123,12 @@ impl Backend {
         pt: &mut PageTable,
         populate: bool,
     ) -> bool {
-        if populate {
+        if let Ok((frame, flags, _)) = pt.query(vaddr)
+            && !flags.is_empty()
+        {
+            // The page is mapped, so this is a write to a copy-on-write page.
+            self.handle_cow_fault(vaddr, frame, orig_flags, pt)
+        } else if populate {
             false // Populated mappings should not trigger page faults.
         } else if let Some(frame) = alloc_frame(true) {
             // Allocate a physical frame lazily and map it to the fault address.
@@ -119,4 +147,41 @@ impl Backend {
             false
         }
     }
+
+    /// Gives the faulting page its own frame with the original permissions,
+    /// copying the shared frame unless this is its last owner.
+    fn handle_cow_fault(
+        &self,
+        vaddr: VirtAddr,
+        frame: PhysAddr,
+        orig_flags: MappingFlags,
+        pt: &mut PageTable,
+    ) -> bool {
+        let vaddr = vaddr.align_down_4k();
+        let frame = frame.align_down_4k();
+        let mut refs = FRAME_REFS.lock();
+        let Some(count) = refs.get_mut(&frame) else {
+            return pt.cursor().remap(vaddr, frame, orig_flags).is_ok();
+        };
+        let Some(new_frame) = alloc_frame(false) else {
+            return false;
+        };
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                phys_to_virt(frame).as_ptr(),
+                phys_to_virt(new_frame).as_mut_ptr(),
+                PAGE_SIZE_4K,
+            )
+        };
+        if pt.cursor().remap(vaddr, new_frame, orig_flags).is_err() {
+            drop(refs);
+            dealloc_frame(new_frame);
+            return false;
+        }
+        *count -= 1;
+        if *count == 1 {
+            refs.remove(&frame);
+        }
+        true
+    }
 }
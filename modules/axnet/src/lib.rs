@@ -8,16 +8,71 @@
 //!
 //! - [`TcpSocket`]: A TCP socket that provides POSIX-like APIs.
 //! - [`UdpSocket`]: A UDP socket that provides POSIX-like APIs.
+//! - [`IcmpSocket`]: An ICMP socket for echo (ping) messages.
+//! - [`RawSocket`]: A raw IP socket that provides POSIX-like APIs.
 //! - [`dns_query`]: Function for DNS query.
+//! - [`interfaces`], [`add_ip_addr`], [`add_route`], [`set_mtu`],
+//!   [`set_dns_servers`], etc.: Functions to configure the network interfaces
+//!   at runtime.
+//!
+//! # Interfaces
+//!
+//! Each NIC is an interface named `eth0`, `eth1`, etc., with its own sockets.
+//! The loopback interface `lo` is always present, even if there is no NIC,
+//! with the addresses `127.0.0.1/8` and `::1/128`.
+//!
+//! Both IPv4 and IPv6 are supported. Each NIC interface has an IPv6 link-local
+//! address, and `eth0` is configured with the static addresses from the
+//! `AX_IP`, `AX_GW`, `AX_IP6` and `AX_GW6` environment variables. A socket
+//! bound to `0.0.0.0` only accepts IPv4 traffic, while one bound to `::` also
+//! accepts IPv4 traffic unless `set_only_v6` is called (`IPV6_V6ONLY`). A
+//! socket that is not bound accepts both.
+//!
+//! A socket sends packets through the interface chosen by the routing table,
+//! i.e., the interface with the most specific network or route to the
+//! destination, unless it is bound to an interface by `bind_to_device`.
+//!
+//! # Data Path
+//!
+//! Received frames are parsed in place in the RX buffers of the NIC driver,
+//! which are recycled once processed, and outgoing frames are built directly
+//! in its TX buffers. smoltcp keeps the data of each socket in its own ring
+//! buffers, so the payload is normally copied once between them and the
+//! driver buffers.
+//!
+//! [`UdpSocket::recv_zc`] removes that copy on the receive side: once it is
+//! called, the datagrams that a NIC receives for the socket bypass smoltcp and
+//! stay in the RX buffers of the driver, which are queued in the socket, lent
+//! to the application in place, and recycled to the driver afterwards. At most
+//! half of the RX buffers of a driver are lent at a time. Fragments and
+//! datagrams that do not fit in the queue take the normal path.
+//! [`TcpSocket::recv_zc`] and the `send_zc` methods lend the socket buffers
+//! instead, which avoids the copy to and from the application. There is no
+//! scatter-gather transmission, as a driver transmits a frame from a single
+//! TX buffer.
 //!
 //! # Cargo Features
 //!
 //! - `smoltcp`: Use [smoltcp] as the underlying network stack. This is enabled
 //!   by default.
+//! - `multitask`: Poll the interfaces in a dedicated task, and block the
+//!   socket operations in per-socket wait queues instead of busy polling.
+//! - `irq`: Kick the poll task by NIC interrupts, and sleep until the next
+//!   socket timer expires. Otherwise, the interfaces are polled periodically.
+//! - `async`: Enable the `poll_*` methods of sockets, which register the waker
+//!   of an asynchronous task and return [`Poll::Pending`] instead of blocking.
+//! - `dhcp`: Configure the IPv4 address, gateway and DNS servers by DHCP,
+//!   which runs in the background whenever the interfaces are polled. The
+//!   static config from the `AX_IP` and `AX_GW` environment variables is used
+//!   until a lease is acquired.
+//! - `tcp-cubic`: Support the CUBIC congestion control algorithm and use it by
+//!   default instead of Reno. It uses floating-point arithmetic, so `fp-simd`
+//!   must be enabled on architectures with hardware floating-point ABI.
 //!
+//! [`Poll::Pending`]: core::task::Poll::Pending
 //! [smoltcp]: https://github.com/smoltcp-rs/smoltcp
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 #[macro_use]
 extern crate log;
@@ -32,16 +87,40 @@ cfg_if::cfg_if! {
 
 pub use self::net_impl::TcpSocket;
 pub use self::net_impl::UdpSocket;
+pub use self::net_impl::{
+    CongestionControl, TcpInfo, TcpState, default_congestion_control,
+    set_default_congestion_control,
+};
+pub use self::net_impl::{IcmpSocket, RawSocket};
+pub use self::net_impl::{
+    InterfaceInfo, add_ip_addr, add_route, dns_servers, interfaces, remove_ip_addr, remove_route,
+    set_default_gateway, set_dns_servers, set_mtu,
+};
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, poll_interfaces};
 
-use axdriver::{AxDeviceContainer, prelude::*};
+use alloc::vec::Vec;
+use axdriver::{AxDeviceContainer, DeviceIrq, prelude::*};
 
 /// Initializes the network subsystem by NIC devices.
-pub fn init_network(mut net_devs: AxDeviceContainer<AxNetDevice>) {
+///
+/// `net_irqs` are the interrupts of the NIC devices, in the same order as
+/// `net_devs`.
+pub fn init_network(
+    mut net_devs: AxDeviceContainer<AxNetDevice>,
+    mut net_irqs: AxDeviceContainer<Option<DeviceIrq>>,
+) {
     info!("Initialize network subsystem...");
 
-    let dev = net_devs.take_one().expect("No NIC device found!");
-    info!("  use NIC 0: {:?}", dev.device_name());
-    net_impl::init(dev);
+    let mut devs = Vec::new();
+    let mut irqs = Vec::new();
+    while let Some(dev) = net_devs.take_one() {
+        info!("  use NIC {}: {:?}", devs.len(), dev.device_name());
+        devs.push(dev);
+        irqs.push(net_irqs.take_one().flatten());
+    }
+    if devs.is_empty() {
+        warn!("No NIC device found, only the loopback interface is available");
+    }
+    net_impl::init(devs, irqs);
 }
@@ -2,3 +2,16 @@ use smoltcp::wire::{IpAddress, IpEndpoint};
 
 pub const UNSPECIFIED_IP: IpAddress = IpAddress::v4(0, 0, 0, 0);
 pub const UNSPECIFIED_ENDPOINT: IpEndpoint = IpEndpoint::new(UNSPECIFIED_IP, 0);
+
+/// Returns whether a socket bound to the address `local` accepts the traffic
+/// to `dst` by their address families.
+///
+/// A socket bound to an IPv4 address only accepts IPv4 traffic. One bound to
+/// an IPv6 address also accepts IPv4 traffic, unless `v6only` is set.
+pub fn family_accepts(local: IpAddress, dst: IpAddress, v6only: bool) -> bool {
+    match (local, dst) {
+        (IpAddress::Ipv4(_), IpAddress::Ipv4(_)) | (IpAddress::Ipv6(_), IpAddress::Ipv6(_)) => true,
+        (IpAddress::Ipv4(_), IpAddress::Ipv6(_)) => false,
+        (IpAddress::Ipv6(_), IpAddress::Ipv4(_)) => !v6only,
+    }
+}
@@ -0,0 +1,187 @@
+//! Runtime configuration of the network interfaces.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use axerrno::{AxResult, ax_err, ax_err_type};
+use smoltcp::iface::Route;
+use smoltcp::wire::{IpAddress, IpCidr};
+
+use super::{IFACES, InterfaceWrapper, dns, route};
+
+/// The minimum MTU of an IPv4 interface.
+const MIN_MTU: usize = 68;
+
+/// Information of a network interface.
+#[derive(Debug, Clone)]
+pub struct InterfaceInfo {
+    /// The name of the interface, e.g., `eth0` or `lo`.
+    pub name: String,
+    /// The hardware (MAC) address, all zeros for the loopback interface.
+    pub mac_addr: [u8; 6],
+    /// The IP addresses and their prefix lengths.
+    pub addrs: Vec<(IpAddr, u8)>,
+    /// The maximum transmission unit in bytes, excluding the link layer header.
+    pub mtu: usize,
+}
+
+fn find_iface(name: &str) -> AxResult<&'static InterfaceWrapper> {
+    match route::iface_by_name(name) {
+        Some(i) => Ok(&IFACES[i]),
+        None => ax_err!(NotFound, "no such interface"),
+    }
+}
+
+fn ip_cidr(addr: IpAddr, prefix_len: u8) -> AxResult<IpCidr> {
+    let max_prefix_len = match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    if prefix_len > max_prefix_len {
+        return ax_err!(InvalidInput, "invalid prefix length");
+    }
+    Ok(IpCidr::new(IpAddress::from(addr), prefix_len))
+}
+
+/// Returns the information of all network interfaces.
+pub fn interfaces() -> Vec<InterfaceInfo> {
+    IFACES
+        .iter()
+        .map(|iface| {
+            let addrs = iface
+                .iface
+                .lock()
+                .ip_addrs()
+                .iter()
+                .map(|cidr| (IpAddr::from(cidr.address()), cidr.prefix_len()))
+                .collect();
+            InterfaceInfo {
+                name: iface.name().to_string(),
+                mac_addr: iface.ethernet_address().0,
+                addrs,
+                mtu: iface.dev.lock().mtu,
+            }
+        })
+        .collect()
+}
+
+/// Adds an IP address to the interface.
+pub fn add_ip_addr(name: &str, addr: IpAddr, prefix_len: u8) -> AxResult {
+    let cidr = ip_cidr(addr, prefix_len)?;
+    let mut res = Ok(());
+    find_iface(name)?.iface.lock().update_ip_addrs(|addrs| {
+        res = if addrs.iter().any(|a| a.address() == cidr.address()) {
+            ax_err!(AlreadyExists, "address already exists")
+        } else {
+            addrs
+                .push(cidr)
+                .or_else(|_| ax_err!(NoMemory, "too many addresses"))
+        };
+    });
+    res
+}
+
+/// Removes an IP address from the interface.
+pub fn remove_ip_addr(name: &str, addr: IpAddr) -> AxResult {
+    let addr = IpAddress::from(addr);
+    let mut found = false;
+    find_iface(name)?.iface.lock().update_ip_addrs(|addrs| {
+        let len = addrs.len();
+        addrs.retain(|a| a.address() != addr);
+        found = addrs.len() != len;
+    });
+    if found {
+        Ok(())
+    } else {
+        ax_err!(NotFound, "no such address")
+    }
+}
+
+/// Adds a route to the network `dest/prefix_len` via `gateway`.
+///
+/// The route is added to the interface that `gateway` is directly reachable
+/// from. An existing route to the same network is replaced.
+pub fn add_route(dest: IpAddr, prefix_len: u8, gateway: IpAddr) -> AxResult {
+    let cidr = ip_cidr(dest, prefix_len)?;
+    if dest.is_ipv4() != gateway.is_ipv4() {
+        return ax_err!(InvalidInput, "address family mismatch");
+    }
+    let via_router = IpAddress::from(gateway);
+    let index = route::lookup_on_link(via_router)
+        .ok_or_else(|| ax_err_type!(InvalidInput, "gateway is unreachable"))?;
+    let route = Route {
+        cidr,
+        via_router,
+        preferred_until: None,
+        expires_at: None,
+    };
+    remove_routes(cidr);
+    let mut res = Ok(());
+    IFACES[index].iface.lock().routes_mut().update(|routes| {
+        res = routes
+            .push(route)
+            .or_else(|_| ax_err!(NoMemory, "too many routes"));
+    });
+    res
+}
+
+/// Removes the route to the network `dest/prefix_len`.
+pub fn remove_route(dest: IpAddr, prefix_len: u8) -> AxResult {
+    let cidr = ip_cidr(dest, prefix_len)?;
+    if remove_routes(cidr) {
+        Ok(())
+    } else {
+        ax_err!(NotFound, "no such route")
+    }
+}
+
+/// Removes the routes to `cidr` from all interfaces, returns whether any
+/// route is removed.
+fn remove_routes(cidr: IpCidr) -> bool {
+    let mut found = false;
+    for iface in IFACES.iter() {
+        iface.iface.lock().routes_mut().update(|routes| {
+            let len = routes.len();
+            routes.retain(|r| r.cidr != cidr);
+            found |= routes.len() != len;
+        });
+    }
+    found
+}
+
+/// Sets the default gateway of the address family of `gateway`.
+pub fn set_default_gateway(gateway: IpAddr) -> AxResult {
+    match gateway {
+        IpAddr::V4(_) => add_route(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0, gateway),
+        IpAddr::V6(_) => add_route(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0, gateway),
+    }
+}
+
+/// Sets the maximum transmission unit of the interface.
+///
+/// It can not exceed the standard Ethernet MTU of 1500 bytes, or 65535 bytes
+/// for the loopback interface.
+pub fn set_mtu(name: &str, mtu: usize) -> AxResult {
+    let iface = find_iface(name)?;
+    if !(MIN_MTU..=iface.max_mtu()).contains(&mtu) {
+        return ax_err!(InvalidInput, "invalid MTU");
+    }
+    iface.set_mtu(mtu);
+    Ok(())
+}
+
+/// Returns the DNS servers used by [`dns_query`](super::dns_query).
+pub fn dns_servers() -> Vec<IpAddr> {
+    dns::servers().into_iter().map(IpAddr::from).collect()
+}
+
+/// Replaces the DNS servers used by [`dns_query`](super::dns_query).
+pub fn set_dns_servers(servers: &[IpAddr]) -> AxResult {
+    if servers.is_empty() {
+        return ax_err!(InvalidInput, "no DNS servers");
+    }
+    let servers: Vec<_> = servers.iter().map(|&s| IpAddress::from(s)).collect();
+    dns::set_servers(&servers);
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! DHCPv4 clients to configure the interfaces.
+
+use alloc::vec::Vec;
+
+use lazyinit::LazyInit;
+use smoltcp::iface::{Interface, SocketSet};
+use smoltcp::socket::dhcpv4::{self, Event};
+use smoltcp::wire::{IpAddress, IpCidr, Ipv4Address, Ipv4Cidr};
+
+use super::{IFACES, SOCKET_SET, SocketHandle, dns};
+
+/// The DHCP clients of the NIC interfaces.
+static CLIENTS: LazyInit<Vec<SocketHandle>> = LazyInit::new();
+static STATIC_CONFIG: LazyInit<StaticConfig> = LazyInit::new();
+
+/// The static interface config from the build environment.
+pub(crate) struct StaticConfig {
+    pub ip: IpAddress,
+    pub prefix_len: u8,
+    pub gateway: IpAddress,
+    pub dns_server: IpAddress,
+}
+
+/// Starts a DHCP client on each NIC interface.
+///
+/// The clients run whenever the interfaces are polled, so the boot does not
+/// wait for the leases. `eth0` is configured with `static_config` until a
+/// lease is acquired, and again whenever the lease is lost.
+pub(crate) fn init(static_config: StaticConfig) {
+    STATIC_CONFIG.init_once(static_config);
+    CLIENTS.init_once(
+        (0..IFACES.len())
+            .filter(|&iface| !IFACES[iface].is_loopback())
+            .map(|iface| SOCKET_SET.add(iface, dhcpv4::Socket::new()))
+            .collect(),
+    );
+    for handle in CLIENTS.iter() {
+        info!("DHCP client started on {}", IFACES[handle.iface].name());
+    }
+}
+
+/// Processes the events of the DHCP client of the interface `index`, called
+/// after each poll of the interface.
+pub(crate) fn poll(index: usize, iface: &mut Interface, sockets: &mut SocketSet) {
+    if !CLIENTS.is_inited() {
+        return;
+    }
+    let Some(handle) = CLIENTS.iter().find(|h| h.iface == index) else {
+        // the loopback interface
+        return;
+    };
+    let name = IFACES[index].name();
+    match sockets.get_mut::<dhcpv4::Socket>(handle.handle).poll() {
+        None => {}
+        Some(Event::Configured(config)) => {
+            info!(
+                "DHCP lease acquired on {} from {}:",
+                name, config.server.address
+            );
+            info!("  ip:       {}", config.address);
+            set_ipv4_config(iface, Some(config.address), config.router);
+            if let Some(router) = config.router {
+                info!("  gateway:  {}", router);
+            }
+            if !config.dns_servers.is_empty() {
+                let servers: Vec<_> = config
+                    .dns_servers
+                    .iter()
+                    .map(|&s| IpAddress::Ipv4(s))
+                    .collect();
+                info!("  dns:      {:?}", servers);
+                dns::set_servers(&servers);
+            }
+        }
+        Some(Event::Deconfigured) if index != 0 => {
+            warn!("DHCP lease lost on {}", name);
+            set_ipv4_config(iface, None, None);
+        }
+        Some(Event::Deconfigured) => {
+            warn!("DHCP lease lost on {}, use the static config", name);
+            let config = &*STATIC_CONFIG;
+            let cidr = match config.ip {
+                IpAddress::Ipv4(ip) => Some(Ipv4Cidr::new(ip, config.prefix_len)),
+                _ => None,
+            };
+            let router = match config.gateway {
+                IpAddress::Ipv4(gateway) => Some(gateway),
+                _ => None,
+            };
+            set_ipv4_config(iface, cidr, router);
+            dns::set_servers(&[config.dns_server]);
+        }
+    }
+}
+
+/// Replaces the IPv4 address and the default IPv4 route of the interface.
+fn set_ipv4_config(iface: &mut Interface, cidr: Option<Ipv4Cidr>, router: Option<Ipv4Address>) {
+    iface.update_ip_addrs(|addrs| {
+        addrs.retain(|addr| !matches!(addr, IpCidr::Ipv4(_)));
+        if let Some(cidr) = cidr {
+            addrs.push(IpCidr::Ipv4(cidr)).unwrap();
+        }
+    });
+    iface.routes_mut().remove_default_ipv4_route();
+    if let Some(router) = router {
+        iface.routes_mut().add_default_ipv4_route(router).unwrap();
+    }
+}
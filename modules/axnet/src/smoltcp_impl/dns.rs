@@ -1,12 +1,24 @@
 use alloc::vec::Vec;
-use axerrno::{AxError, AxResult, ax_err_type};
+use axerrno::{AxResult, ax_err, ax_err_type};
 use core::net::IpAddr;
 
-use smoltcp::iface::SocketHandle;
+use axsync::Mutex;
 use smoltcp::socket::dns::{self, GetQueryResultError, StartQueryError};
-use smoltcp::wire::DnsQueryType;
+use smoltcp::wire::{DnsQueryType, IpAddress};
 
-use super::{ETH0, SOCKET_SET, SocketSetWrapper};
+use super::{IFACES, SOCKET_SET, SocketHandle, SocketSetWrapper, route};
+
+static DNS_SERVERS: Mutex<Vec<IpAddress>> = Mutex::new(Vec::new());
+
+/// Returns the DNS servers used by new queries.
+pub(crate) fn servers() -> Vec<IpAddress> {
+    DNS_SERVERS.lock().clone()
+}
+
+/// Replaces the DNS servers used by new queries.
+pub(crate) fn set_servers(servers: &[IpAddress]) {
+    *DNS_SERVERS.lock() = servers.to_vec();
+}
 
 /// A DNS socket.
 struct DnsSocket {
@@ -16,28 +28,35 @@ struct DnsSocket {
 impl DnsSocket {
     #[allow(clippy::new_without_default)]
     /// Creates a new DNS socket.
+    ///
+    /// It queries the current DNS servers, and is added to the interface that
+    /// the first DNS server is reachable through.
     pub fn new() -> Self {
         let socket = SocketSetWrapper::new_dns_socket();
-        let handle = Some(SOCKET_SET.add(socket));
+        let iface = servers()
+            .first()
+            .and_then(|&server| route::lookup(server))
+            .unwrap_or(0);
+        let handle = Some(SOCKET_SET.add(iface, socket));
         Self { handle }
     }
 
-    #[allow(dead_code)]
-    /// Update the list of DNS servers, will replace all existing servers.
-    pub fn update_servers(self, servers: &[smoltcp::wire::IpAddress]) {
-        SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(self.handle.unwrap(), |socket| {
-            socket.update_servers(servers)
-        });
-    }
-
-    /// Query a address with given DNS query type.
-    pub fn query(&self, name: &str, query_type: DnsQueryType) -> AxResult<Vec<IpAddr>> {
-        // let local_addr = self.local_addr.unwrap_or_else(f);
+    /// Queries the addresses of `name` with the given DNS query types at the
+    /// same time.
+    ///
+    /// The addresses are returned in the order of the query types. It fails
+    /// only if all the queries fail.
+    pub fn query(&self, name: &str, query_types: &[DnsQueryType]) -> AxResult<Vec<IpAddr>> {
         let handle = self.handle.ok_or_else(|| ax_err_type!(InvalidInput))?;
-        let iface = &ETH0.iface;
-        let query_handle = SOCKET_SET
+        // Lock the interface before the socket set, in the same order as
+        // polling the interface.
+        let mut iface = IFACES[handle.iface].iface.lock();
+        let query_handles = SOCKET_SET
             .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
-                socket.start_query(iface.lock().context(), name, query_type)
+                query_types
+                    .iter()
+                    .map(|&query_type| socket.start_query(iface.context(), name, query_type))
+                    .collect::<Result<Vec<_>, _>>()
             })
             .map_err(|e| match e {
                 StartQueryError::NoFreeSlot => {
@@ -50,27 +69,37 @@ impl DnsSocket {
                     ax_err_type!(InvalidInput, "socket query() failed: too long name")
                 }
             })?;
-        loop {
+        drop(iface);
+
+        // The result of each query, `None` if it is pending.
+        let mut results: Vec<Option<Result<Vec<IpAddr>, GetQueryResultError>>> =
+            query_handles.iter().map(|_| None).collect();
+        while results.iter().any(Option::is_none) {
             SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
-                socket.get_query_result(query_handle).map_err(|e| match e {
-                    GetQueryResultError::Pending => AxError::WouldBlock,
-                    GetQueryResultError::Failed => {
-                        ax_err_type!(ConnectionRefused, "socket query() failed")
+            SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+                for (result, &query_handle) in results.iter_mut().zip(&query_handles) {
+                    if result.is_some() {
+                        continue;
                     }
-                })
-            }) {
-                Ok(n) => {
-                    let mut res = Vec::with_capacity(n.capacity());
-                    for ip in n {
-                        res.push(IpAddr::from(ip))
+                    match socket.get_query_result(query_handle) {
+                        Ok(addrs) => {
+                            *result = Some(Ok(addrs.into_iter().map(IpAddr::from).collect()))
+                        }
+                        Err(GetQueryResultError::Pending) => {}
+                        Err(e) => *result = Some(Err(e)),
                     }
-                    return Ok(res);
                 }
-                Err(AxError::WouldBlock) => axtask::yield_now(),
-                Err(e) => return Err(e),
+            });
+            if results.iter().any(Option::is_none) {
+                axtask::yield_now();
             }
         }
+
+        let succeeded: Vec<Vec<IpAddr>> = results.into_iter().flatten().flatten().collect();
+        if succeeded.is_empty() {
+            return ax_err!(ConnectionRefused, "socket query() failed");
+        }
+        Ok(succeeded.concat())
     }
 }
 
@@ -83,7 +112,10 @@ impl Drop for DnsSocket {
 }
 
 /// Public function for DNS query.
+///
+/// It queries both the IPv4 (A) and IPv6 (AAAA) addresses of the name, the
+/// IPv4 addresses come first.
 pub fn dns_query(name: &str) -> AxResult<alloc::vec::Vec<IpAddr>> {
     let socket = DnsSocket::new();
-    socket.query(name, DnsQueryType::A)
+    socket.query(name, &[DnsQueryType::A, DnsQueryType::Aaaa])
 }
@@ -0,0 +1,356 @@
+use alloc::vec::Vec;
+use core::net::IpAddr;
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "multitask")]
+use core::task::Waker;
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult, ax_err, ax_err_type};
+use axio::PollState;
+use axsync::Mutex;
+use spin::RwLock;
+
+use smoltcp::socket::icmp::{self, BindError, Endpoint, SendError};
+use smoltcp::wire::IpAddress;
+
+use super::options::{Deadline, SocketOptions, check_timeout};
+#[cfg(feature = "multitask")]
+use super::poll;
+use super::{IFACES, SOCKET_SET, SocketHandle, SocketSetWrapper, route};
+
+/// The length of the ICMP header, including the identifier and the sequence
+/// number of echo messages.
+const ICMP_HEADER_LEN: usize = 8;
+const ICMPV4_ECHO_REQUEST: u8 = 8;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+
+/// An ICMP socket for echo (ping) messages, like the `SOCK_DGRAM` ICMP
+/// sockets of Linux.
+///
+/// The socket is bound to an ICMP identifier, and receives the echo requests
+/// and replies with the identifier. The identifier of the echo requests sent
+/// through it is replaced by the bound one, and the checksum is computed by
+/// the network stack.
+pub struct IcmpSocket {
+    /// The smoltcp sockets in each interface, indexed by the interface.
+    handles: Vec<SocketHandle>,
+    /// The interface that the socket is bound to by
+    /// [`bind_to_device`](Self::bind_to_device).
+    device: RwLock<Option<usize>>,
+    ident: RwLock<Option<u16>>,
+    opts: RwLock<SocketOptions>,
+    nonblock: AtomicBool,
+}
+
+impl IcmpSocket {
+    /// Creates a new ICMP socket.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let handles = (0..IFACES.len())
+            .map(|iface| SOCKET_SET.add(iface, SocketSetWrapper::new_icmp_socket()))
+            .collect();
+        Self {
+            handles,
+            device: RwLock::new(None),
+            ident: RwLock::new(None),
+            opts: RwLock::new(SocketOptions::new()),
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the ICMP identifier that the socket is bound to, or
+    /// [`Err(NotConnected)`](AxError::NotConnected) if not bound.
+    pub fn ident(&self) -> AxResult<u16> {
+        self.ident.read().ok_or(AxError::NotConnected)
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    #[inline]
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this ICMP socket into or out of nonblocking mode.
+    ///
+    /// This will result in `recv_from` and `send_to` operations becoming
+    /// nonblocking, i.e., immediately returning from their calls. If the IO
+    /// operation could not be completed and needs to be retried, an error
+    /// with kind [`Err(WouldBlock)`](AxError::WouldBlock) is returned.
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Returns the timeout of blocking receive operations (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.opts.read().recv_timeout
+    }
+
+    /// Sets the timeout of blocking receive operations (`SO_RCVTIMEO`), or
+    /// no timeout if `timeout` is `None`.
+    ///
+    /// An operation that times out returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). A zero timeout is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().recv_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of blocking send operations (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.opts.read().send_timeout
+    }
+
+    /// Sets the timeout of blocking send operations (`SO_SNDTIMEO`), or no
+    /// timeout if `timeout` is `None`.
+    ///
+    /// An operation that times out returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). A zero timeout is invalid.
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().send_timeout = timeout;
+        Ok(())
+    }
+
+    /// Binds the socket to the network interface with the given name.
+    ///
+    /// Messages are only sent and received through the interface. It must
+    /// be called before [`bind`](Self::bind).
+    pub fn bind_to_device(&self, ifname: &str) -> AxResult {
+        let device = route::iface_by_name(ifname)
+            .ok_or_else(|| ax_err_type!(NotFound, "socket bind_to_device() failed"))?;
+        if self.ident.read().is_some() {
+            return ax_err!(
+                InvalidInput,
+                "socket bind_to_device() failed: already bound"
+            );
+        }
+        *self.device.write() = Some(device);
+        Ok(())
+    }
+
+    /// Binds an unbound socket to the given ICMP identifier, or an automatically
+    /// chosen one if `ident` is 0.
+    ///
+    /// The socket is bound automatically by [`send_to`](Self::send_to), but
+    /// it must be bound before [`recv_from`](Self::recv_from).
+    pub fn bind(&self, mut ident: u16) -> AxResult {
+        let mut self_ident = self.ident.write();
+        if self_ident.is_some() {
+            return ax_err!(InvalidInput, "socket bind() failed: already bound");
+        }
+        if ident == 0 {
+            ident = get_ephemeral_ident();
+        }
+
+        for handle in self.active_handles(*self.device.read()) {
+            SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                socket.bind(Endpoint::Ident(ident)).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            })?;
+        }
+
+        *self_ident = Some(ident);
+        debug!("ICMP socket {}: bound on ident {}", self.handles[0], ident);
+        Ok(())
+    }
+
+    /// Sets the hop limit (TTL) of the outgoing messages, or the default of
+    /// the network stack if `hop_limit` is `None`.
+    pub fn set_hop_limit(&self, hop_limit: Option<u8>) -> AxResult {
+        if hop_limit == Some(0) {
+            return ax_err!(InvalidInput, "socket set_hop_limit() failed");
+        }
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                socket.set_hop_limit(hop_limit)
+            });
+        }
+        Ok(())
+    }
+
+    /// Sends an ICMP message, starting with the ICMP header, to the given
+    /// address. On success, returns the number of bytes written.
+    pub fn send_to(&self, buf: &[u8], remote_addr: IpAddr) -> AxResult<usize> {
+        if remote_addr.is_unspecified() {
+            return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
+        }
+        if buf.len() < ICMP_HEADER_LEN {
+            return ax_err!(InvalidInput, "socket send_to() failed: message too short");
+        }
+        if self.ident.read().is_none() {
+            self.bind(0)?;
+        }
+        self.block_on(self.send_timeout(), || {
+            self.try_send(buf, IpAddress::from(remote_addr))
+        })
+    }
+
+    /// Receives a single ICMP message, starting with the ICMP header, on the
+    /// socket. On success, returns the number of bytes read and the origin.
+    pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+        if self.ident.read().is_none() {
+            return ax_err!(NotConnected, "socket recv_from() failed");
+        }
+        self.block_on(self.recv_timeout(), || self.try_recv(buf))
+    }
+
+    /// Whether the socket is readable or writable.
+    pub fn poll(&self) -> AxResult<PollState> {
+        let mut state = PollState {
+            readable: false,
+            writable: false,
+        };
+        let bound = self.ident.read().is_some();
+        for handle in self.active_handles(*self.device.read()) {
+            SOCKET_SET.with_socket::<icmp::Socket, _, _>(handle, |socket| {
+                state.readable |= bound && socket.can_recv();
+                state.writable |= socket.can_send();
+            });
+        }
+        Ok(state)
+    }
+}
+
+/// Private methods
+impl IcmpSocket {
+    /// Returns the handles of the smoltcp sockets in use, which are all of
+    /// them unless the socket is bound to an interface.
+    fn active_handles(&self, device: Option<usize>) -> impl Iterator<Item = SocketHandle> + '_ {
+        self.handles
+            .iter()
+            .copied()
+            .filter(move |handle| device.is_none_or(|device| device == handle.iface))
+    }
+
+    /// Returns the waiter for events of all smoltcp sockets.
+    #[cfg(feature = "multitask")]
+    fn waiter(&self) -> alloc::sync::Arc<poll::SocketWaiter> {
+        poll::waiter(self.handles[0])
+    }
+
+    /// Sends the message without blocking, returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock) if the tx buffer is full.
+    fn try_send(&self, buf: &[u8], remote_addr: IpAddress) -> AxResult<usize> {
+        let ident = self.ident()?;
+        let iface = route::egress(*self.device.read(), None, remote_addr)?;
+        SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(self.handles[iface], |socket| {
+            if !socket.can_send() {
+                // tx buffer is full
+                return Err(AxError::WouldBlock);
+            }
+            let packet = socket.send(buf.len(), remote_addr).map_err(|e| match e {
+                SendError::BufferFull => AxError::WouldBlock,
+                SendError::Unaddressable => {
+                    ax_err_type!(ConnectionRefused, "socket send() failed")
+                }
+            })?;
+            packet.copy_from_slice(buf);
+            let echo_request = match remote_addr {
+                IpAddress::Ipv4(_) => ICMPV4_ECHO_REQUEST,
+                IpAddress::Ipv6(_) => ICMPV6_ECHO_REQUEST,
+            };
+            if packet[0] == echo_request {
+                packet[4..6].copy_from_slice(&ident.to_be_bytes());
+            }
+            Ok(buf.len())
+        })
+    }
+
+    /// Receives a message without blocking if there is one available,
+    /// otherwise returns [`Err(WouldBlock)`](AxError::WouldBlock).
+    fn try_recv(&self, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+        for handle in self.active_handles(*self.device.read()) {
+            let res = SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                if socket.can_recv() {
+                    // message available
+                    match socket.recv_slice(buf) {
+                        Ok((len, addr)) => Ok((len, IpAddr::from(addr))),
+                        Err(_) => ax_err!(BadState, "socket recv_from() failed"),
+                    }
+                } else {
+                    Err(AxError::WouldBlock)
+                }
+            });
+            match res {
+                Err(AxError::WouldBlock) => {}
+                res => return res,
+            }
+        }
+        // no more messages
+        Err(AxError::WouldBlock)
+    }
+
+    #[cfg(not(feature = "multitask"))]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        if self.is_nonblocking() {
+            f()
+        } else {
+            let deadline = Deadline::after(timeout);
+            loop {
+                SOCKET_SET.poll_interfaces();
+                match f() {
+                    Ok(t) => return Ok(t),
+                    Err(AxError::WouldBlock) => {
+                        deadline.remaining()?;
+                        axtask::yield_now()
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "multitask")]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        if self.is_nonblocking() {
+            let res = f();
+            poll::kick();
+            res
+        } else {
+            self.waiter().block_on(
+                f,
+                |waker| self.register_waker(waker),
+                Deadline::after(timeout),
+            )
+        }
+    }
+
+    /// Registers the waker of the socket waiter in the smoltcp sockets.
+    #[cfg(feature = "multitask")]
+    fn register_waker(&self, waker: &Waker) {
+        for handle in self.active_handles(*self.device.read()) {
+            SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            })
+        }
+    }
+}
+
+impl Drop for IcmpSocket {
+    fn drop(&mut self) {
+        for &handle in &self.handles {
+            SOCKET_SET.remove(handle);
+        }
+    }
+}
+
+fn get_ephemeral_ident() -> u16 {
+    const IDENT_START: u16 = 0x1000;
+    static CURR: Mutex<u16> = Mutex::new(IDENT_START);
+    let mut curr = CURR.lock();
+
+    let ident = *curr;
+    *curr = curr.checked_add(1).unwrap_or(IDENT_START);
+    ident
+}
@@ -0,0 +1,372 @@
+//! Lending the RX buffers of NIC drivers to UDP sockets.
+//!
+//! Once a UDP socket receives by `recv_zc`, it registers a [`LendQueue`], and
+//! the datagrams for it are taken out of the data path before smoltcp: the RX
+//! buffer of the driver is queued in the socket as is, its payload is lent to
+//! the application in place, and the buffer is recycled to the driver after
+//! the datagram is received. Datagrams that smoltcp must see, e.g., fragments,
+//! and those that do not fit in the queue, take the normal path through the
+//! socket buffers.
+
+use alloc::collections::VecDeque;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "multitask")]
+use core::task::Waker;
+
+use axdriver_net::NetBufPtr;
+use axerrno::{AxError, AxResult};
+use axsync::Mutex;
+use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, IpAddress, IpEndpoint, IpProtocol,
+    Ipv4Packet, Ipv6Packet, UdpPacket,
+};
+
+use super::addr::family_accepts;
+
+/// The maximum number of datagrams lent to a socket and not received yet.
+const MAX_QUEUED: usize = 64;
+
+/// The queues of the sockets that datagrams are lent to.
+static QUEUES: Mutex<Vec<Weak<LendQueue>>> = Mutex::new(Vec::new());
+
+/// A datagram in an RX buffer of a NIC driver.
+struct LentDatagram {
+    buf: NetBufPtr,
+    /// The interface that received it.
+    iface: usize,
+    /// The range of the payload in the packet.
+    payload: Range<usize>,
+    src: IpEndpoint,
+}
+
+// SAFETY: the buffer is owned by the datagram until it is recycled.
+unsafe impl Send for LentDatagram {}
+
+impl LentDatagram {
+    fn payload(&self) -> &[u8] {
+        &self.buf.packet()[self.payload.clone()]
+    }
+
+    /// Returns the buffer to the driver.
+    fn recycle(self) {
+        super::recycle_lent_rx_buffer(self.iface, self.buf);
+    }
+}
+
+struct QueueInner {
+    datagrams: VecDeque<LentDatagram>,
+    /// Set when the socket is shut down, after which nothing is lent.
+    closed: bool,
+    #[cfg(feature = "multitask")]
+    waker: Option<Waker>,
+}
+
+/// The datagrams lent to a UDP socket.
+pub(crate) struct LendQueue {
+    /// The interface that the socket is bound to, if any.
+    device: Option<usize>,
+    local: IpEndpoint,
+    v6only: bool,
+    inner: Mutex<QueueInner>,
+}
+
+impl LendQueue {
+    /// Creates the queue of a socket bound to `local`, and starts lending the
+    /// datagrams for it.
+    pub fn register(device: Option<usize>, local: IpEndpoint, v6only: bool) -> Arc<Self> {
+        let queue = Arc::new(Self {
+            device,
+            local,
+            v6only,
+            inner: Mutex::new(QueueInner {
+                datagrams: VecDeque::new(),
+                closed: false,
+                #[cfg(feature = "multitask")]
+                waker: None,
+            }),
+        });
+        let mut queues = QUEUES.lock();
+        queues.retain(|q| q.strong_count() > 0);
+        queues.push(Arc::downgrade(&queue));
+        queue
+    }
+
+    /// Whether a datagram to `dst` received by the interface `iface` is for
+    /// the socket.
+    fn accepts(&self, iface: usize, dst: IpEndpoint) -> bool {
+        self.device.is_none_or(|device| device == iface)
+            && self.local.port == dst.port
+            && (self.local.addr.is_unspecified() || self.local.addr == dst.addr)
+            && family_accepts(self.local.addr, dst.addr, self.v6only)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().datagrams.is_empty()
+    }
+
+    /// Calls `f` with the first datagram and its origin, or returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock) if there is none.
+    ///
+    /// The datagram is removed and its buffer recycled unless `peek` is set.
+    pub fn recv<F, T>(&self, peek: bool, f: F) -> AxResult<T>
+    where
+        F: FnOnce(&[u8], IpEndpoint) -> AxResult<T>,
+    {
+        let mut inner = self.inner.lock();
+        let datagram = inner.datagrams.front().ok_or(AxError::WouldBlock)?;
+        let res = f(datagram.payload(), datagram.src);
+        if !peek {
+            let datagram = inner.datagrams.pop_front().unwrap();
+            // Recycling locks the device, which is locked before the queue.
+            drop(inner);
+            datagram.recycle();
+        }
+        res
+    }
+
+    /// Saves `waker` to be woken up when a datagram is lent.
+    #[cfg(feature = "multitask")]
+    pub fn register_waker(&self, waker: &Waker) {
+        self.inner.lock().waker = Some(waker.clone());
+    }
+
+    /// Stops lending datagrams to the socket, and recycles the queued ones.
+    pub fn close(&self) {
+        let mut inner = self.inner.lock();
+        inner.closed = true;
+        let datagrams = core::mem::take(&mut inner.datagrams);
+        drop(inner);
+        for datagram in datagrams {
+            datagram.recycle();
+        }
+    }
+
+    /// Queues a datagram, or returns its buffer if the queue is full or
+    /// closed.
+    fn push(&self, datagram: LentDatagram) -> Result<(), NetBufPtr> {
+        let mut inner = self.inner.lock();
+        if inner.closed || inner.datagrams.len() >= MAX_QUEUED {
+            return Err(datagram.buf);
+        }
+        inner.datagrams.push_back(datagram);
+        #[cfg(feature = "multitask")]
+        if let Some(waker) = inner.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+/// Lends the frame `buf` received by the interface `iface`, whose Ethernet
+/// address is `ether_addr` and whose IP addresses are `local_addrs`, to a
+/// socket if it is a UDP datagram for one with a [`LendQueue`].
+///
+/// Returns the buffer if it is not lent, to be processed by smoltcp.
+pub(crate) fn lend(
+    iface: usize,
+    ether_addr: EthernetAddress,
+    local_addrs: &[IpAddress],
+    buf: NetBufPtr,
+) -> Result<(), NetBufPtr> {
+    if QUEUES.lock().is_empty() {
+        return Err(buf);
+    }
+    let Some((payload, src, dst)) = parse_datagram(buf.packet(), ether_addr, local_addrs) else {
+        return Err(buf);
+    };
+    let queue = QUEUES
+        .lock()
+        .iter()
+        .filter_map(Weak::upgrade)
+        .find(|q| q.accepts(iface, dst));
+    match queue {
+        Some(queue) => queue.push(LentDatagram {
+            buf,
+            iface,
+            payload,
+            src,
+        }),
+        None => Err(buf),
+    }
+}
+
+/// Returns the range of the payload of the UDP datagram in the Ethernet frame
+/// `frame`, with its source and destination endpoints, if it is sent to
+/// `ether_addr` and one of `local_addrs`.
+///
+/// Broadcasts, multicasts, fragments, IPv6 extension headers and datagrams
+/// with bad checksums are left to smoltcp.
+fn parse_datagram(
+    frame: &[u8],
+    ether_addr: EthernetAddress,
+    local_addrs: &[IpAddress],
+) -> Option<(Range<usize>, IpEndpoint, IpEndpoint)> {
+    let ether_frame = EthernetFrame::new_checked(frame).ok()?;
+    if ether_frame.dst_addr() != ether_addr {
+        return None;
+    }
+    let (src_addr, dst_addr, segment) = match ether_frame.ethertype() {
+        EthernetProtocol::Ipv4 => {
+            let packet = Ipv4Packet::new_checked(ether_frame.payload()).ok()?;
+            if !packet.verify_checksum()
+                || packet.more_frags()
+                || packet.frag_offset() != 0
+                || packet.next_header() != IpProtocol::Udp
+            {
+                return None;
+            }
+            let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+            (src, dst, packet.payload())
+        }
+        EthernetProtocol::Ipv6 => {
+            let packet = Ipv6Packet::new_checked(ether_frame.payload()).ok()?;
+            if packet.next_header() != IpProtocol::Udp {
+                return None;
+            }
+            let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+            (src, dst, packet.payload())
+        }
+        _ => return None,
+    };
+    if !local_addrs.contains(&dst_addr) {
+        return None;
+    }
+    let datagram = UdpPacket::new_checked(segment).ok()?;
+    if datagram.src_port() == 0
+        || datagram.dst_port() == 0
+        || !datagram.verify_checksum(&src_addr, &dst_addr)
+    {
+        return None;
+    }
+    let payload = datagram.payload();
+    let start = payload.as_ptr() as usize - frame.as_ptr() as usize;
+    Some((
+        start..start + payload.len(),
+        IpEndpoint::new(src_addr, datagram.src_port()),
+        IpEndpoint::new(dst_addr, datagram.dst_port()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use smoltcp::wire::{Ipv4Address, Ipv6Address};
+
+    const LOCAL_MAC: EthernetAddress = EthernetAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+    const PEER_MAC: EthernetAddress = EthernetAddress([0x52, 0x54, 0, 0x65, 0x43, 0x21]);
+    const LOCAL_V4: Ipv4Address = Ipv4Address::new(10, 0, 2, 15);
+    const PEER_V4: Ipv4Address = Ipv4Address::new(10, 0, 2, 2);
+    const LOCAL_V6: Ipv6Address = Ipv6Address::new(0xfe80, 0, 0, 0, 0x5054, 0xff, 0xfe12, 0x3456);
+    const PEER_V6: Ipv6Address = Ipv6Address::new(0xfe80, 0, 0, 0, 0x5054, 0xff, 0xfe65, 0x4321);
+    const ETHER_LEN: usize = 14;
+    const IPV4_LEN: usize = 20;
+    const IPV6_LEN: usize = 40;
+    const UDP_LEN: usize = 8;
+
+    fn local_addrs() -> [IpAddress; 2] {
+        [LOCAL_V4.into(), LOCAL_V6.into()]
+    }
+
+    fn fill_udp(buf: &mut [u8], src: IpAddress, dst: IpAddress, payload: &[u8]) {
+        let mut datagram = UdpPacket::new_unchecked(buf);
+        datagram.set_src_port(5555);
+        datagram.set_dst_port(8000);
+        datagram.set_len((UDP_LEN + payload.len()) as u16);
+        datagram.payload_mut().copy_from_slice(payload);
+        datagram.fill_checksum(&src, &dst);
+    }
+
+    fn ipv4_frame(dst: Ipv4Address, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; ETHER_LEN + IPV4_LEN + UDP_LEN + payload.len()];
+        let mut ether_frame = EthernetFrame::new_unchecked(&mut frame[..]);
+        ether_frame.set_dst_addr(LOCAL_MAC);
+        ether_frame.set_src_addr(PEER_MAC);
+        ether_frame.set_ethertype(EthernetProtocol::Ipv4);
+        let mut packet = Ipv4Packet::new_unchecked(ether_frame.payload_mut());
+        packet.set_version(4);
+        packet.set_header_len(IPV4_LEN as u8);
+        packet.set_total_len((IPV4_LEN + UDP_LEN + payload.len()) as u16);
+        packet.set_hop_limit(64);
+        packet.set_next_header(IpProtocol::Udp);
+        packet.set_src_addr(PEER_V4);
+        packet.set_dst_addr(dst);
+        fill_udp(packet.payload_mut(), PEER_V4.into(), dst.into(), payload);
+        packet.fill_checksum();
+        frame
+    }
+
+    fn ipv6_frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; ETHER_LEN + IPV6_LEN + UDP_LEN + payload.len()];
+        let mut ether_frame = EthernetFrame::new_unchecked(&mut frame[..]);
+        ether_frame.set_dst_addr(LOCAL_MAC);
+        ether_frame.set_src_addr(PEER_MAC);
+        ether_frame.set_ethertype(EthernetProtocol::Ipv6);
+        let mut packet = Ipv6Packet::new_unchecked(ether_frame.payload_mut());
+        packet.set_version(6);
+        packet.set_payload_len((UDP_LEN + payload.len()) as u16);
+        packet.set_next_header(IpProtocol::Udp);
+        packet.set_hop_limit(64);
+        packet.set_src_addr(PEER_V6);
+        packet.set_dst_addr(LOCAL_V6);
+        fill_udp(
+            packet.payload_mut(),
+            PEER_V6.into(),
+            LOCAL_V6.into(),
+            payload,
+        );
+        frame
+    }
+
+    #[test]
+    fn parse_ipv4_datagram() {
+        let frame = ipv4_frame(LOCAL_V4, b"hello");
+        let (payload, src, dst) = parse_datagram(&frame, LOCAL_MAC, &local_addrs()).unwrap();
+        assert_eq!(&frame[payload], b"hello");
+        assert_eq!(src, IpEndpoint::new(PEER_V4.into(), 5555));
+        assert_eq!(dst, IpEndpoint::new(LOCAL_V4.into(), 8000));
+    }
+
+    #[test]
+    fn parse_ipv6_datagram() {
+        let frame = ipv6_frame(b"hello");
+        let (payload, src, dst) = parse_datagram(&frame, LOCAL_MAC, &local_addrs()).unwrap();
+        assert_eq!(&frame[payload], b"hello");
+        assert_eq!(src, IpEndpoint::new(PEER_V6.into(), 5555));
+        assert_eq!(dst, IpEndpoint::new(LOCAL_V6.into(), 8000));
+    }
+
+    #[test]
+    fn leave_datagrams_to_smoltcp() {
+        let addrs = local_addrs();
+
+        // Not for this host.
+        let frame = ipv4_frame(LOCAL_V4, b"hello");
+        assert!(parse_datagram(&frame, PEER_MAC, &addrs).is_none());
+        let frame = ipv4_frame(Ipv4Address::new(10, 0, 2, 16), b"hello");
+        assert!(parse_datagram(&frame, LOCAL_MAC, &addrs).is_none());
+
+        // A bad UDP checksum.
+        let mut frame = ipv4_frame(LOCAL_V4, b"hello");
+        *frame.last_mut().unwrap() ^= 1;
+        assert!(parse_datagram(&frame, LOCAL_MAC, &addrs).is_none());
+
+        // A bad IPv4 header checksum.
+        let mut frame = ipv4_frame(LOCAL_V4, b"hello");
+        frame[ETHER_LEN + 8] -= 1; // TTL
+        assert!(parse_datagram(&frame, LOCAL_MAC, &addrs).is_none());
+
+        // The first fragment of a datagram.
+        let mut frame = ipv4_frame(LOCAL_V4, b"hello");
+        let mut packet = Ipv4Packet::new_unchecked(&mut frame[ETHER_LEN..]);
+        packet.set_more_frags(true);
+        packet.fill_checksum();
+        assert!(parse_datagram(&frame, LOCAL_MAC, &addrs).is_none());
+
+        // Truncated.
+        let frame = ipv6_frame(b"hello");
+        assert!(parse_datagram(&frame[..frame.len() - 1], LOCAL_MAC, &addrs).is_none());
+    }
+}
@@ -1,34 +1,80 @@
+#[cfg(feature = "multitask")]
+use alloc::sync::Arc;
 use alloc::{boxed::Box, collections::VecDeque};
 use core::ops::{Deref, DerefMut};
 
 use axerrno::{AxError, AxResult, ax_err};
 use axsync::Mutex;
-use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::iface::SocketSet;
 use smoltcp::socket::tcp::{self, State};
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::{LISTEN_QUEUE_SIZE, SOCKET_SET, SocketSetWrapper};
+use super::addr::family_accepts;
+use super::options::SocketOptions;
+#[cfg(feature = "multitask")]
+use super::poll::SocketWaiter;
+use super::{
+    MAX_HALF_OPEN, MAX_HALF_OPEN_BUF_LEN, MAX_LISTEN_BACKLOG, SOCKET_SET, SocketHandle,
+    SocketSetWrapper, SocketSetsGuard, TCP_RX_BUF_LEN, TCP_TX_BUF_LEN,
+};
 
 const PORT_NUM: usize = 65536;
 
 struct ListenTableEntry {
     listen_endpoint: IpListenEndpoint,
+    /// The address the listening socket is bound to, which may be
+    /// unspecified, or `None` if it is not bound.
+    bound_addr: Option<IpAddress>,
+    /// The interface the listening socket is bound to.
+    device: Option<usize>,
+    /// The options of the listening socket, used to create the sockets in the
+    /// SYN queue.
+    opts: SocketOptions,
+    /// The maximum number of established connections waiting to be accepted.
+    backlog: usize,
+    /// The maximum number of half-open connections, limited by the size of
+    /// their buffers.
+    max_half_open: usize,
+    /// The sockets of the half-open connections and the established ones that
+    /// are not accepted yet, in the order of their SYNs.
     syn_queue: VecDeque<SocketHandle>,
+    /// Woken up when a socket in the SYN queue changes its state.
+    #[cfg(feature = "multitask")]
+    waiter: Arc<SocketWaiter>,
 }
 
 impl ListenTableEntry {
-    pub fn new(listen_endpoint: IpListenEndpoint) -> Self {
+    pub fn new(
+        listen_endpoint: IpListenEndpoint,
+        bound_addr: Option<IpAddress>,
+        device: Option<usize>,
+        opts: SocketOptions,
+        backlog: usize,
+    ) -> Self {
         Self {
             listen_endpoint,
-            syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            bound_addr,
+            device,
+            opts,
+            backlog: backlog.clamp(1, MAX_LISTEN_BACKLOG),
+            max_half_open: max_half_open(&opts),
+            // Grows with the connections, not allocated for idle listeners.
+            syn_queue: VecDeque::new(),
+            #[cfg(feature = "multitask")]
+            waiter: SocketWaiter::new(),
         }
     }
 
     #[inline]
-    fn can_accept(&self, dst: IpAddress) -> bool {
+    fn can_accept(&self, iface: usize, dst: IpAddress) -> bool {
+        if self.device.is_some_and(|device| device != iface) {
+            return false;
+        }
         match self.listen_endpoint.addr {
             Some(addr) => addr == dst,
-            None => true,
+            None => self
+                .bound_addr
+                .is_none_or(|addr| family_accepts(addr, dst, self.opts.v6only)),
         }
     }
 }
@@ -61,12 +107,25 @@ impl ListenTable {
         self.tcp[port as usize].lock().is_none()
     }
 
-    pub fn listen(&self, listen_endpoint: IpListenEndpoint) -> AxResult {
+    pub fn listen(
+        &self,
+        listen_endpoint: IpListenEndpoint,
+        bound_addr: Option<IpAddress>,
+        device: Option<usize>,
+        opts: SocketOptions,
+        backlog: usize,
+    ) -> AxResult {
         let port = listen_endpoint.port;
         assert_ne!(port, 0);
         let mut entry = self.tcp[port as usize].lock();
         if entry.is_none() {
-            *entry = Some(Box::new(ListenTableEntry::new(listen_endpoint)));
+            *entry = Some(Box::new(ListenTableEntry::new(
+                listen_endpoint,
+                bound_addr,
+                device,
+                opts,
+                backlog,
+            )));
             Ok(())
         } else {
             ax_err!(AddrInUse, "socket listen() failed")
@@ -75,25 +134,33 @@ impl ListenTable {
 
     pub fn unlisten(&self, port: u16) {
         debug!("TCP socket unlisten on {}", port);
-        *self.tcp[port as usize].lock() = None;
+        // Drop the entry after unlocking it, as it removes the sockets in the
+        // SYN queue from the socket sets.
+        let entry = self.tcp[port as usize].lock().take();
+        drop(entry);
     }
 
     pub fn can_accept(&self, port: u16) -> AxResult<bool> {
+        let sockets = SOCKET_SET.lock_all();
         if let Some(entry) = self.tcp[port as usize].lock().deref() {
-            Ok(entry.syn_queue.iter().any(|&handle| is_connected(handle)))
+            Ok(entry
+                .syn_queue
+                .iter()
+                .any(|&handle| is_connected(&sockets, handle)))
         } else {
             ax_err!(InvalidInput, "socket accept() failed: not listen")
         }
     }
 
     pub fn accept(&self, port: u16) -> AxResult<(SocketHandle, (IpEndpoint, IpEndpoint))> {
+        let sockets = SOCKET_SET.lock_all();
         if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
             let syn_queue = &mut entry.syn_queue;
             let (idx, addr_tuple) = syn_queue
                 .iter()
                 .enumerate()
                 .find_map(|(idx, &handle)| {
-                    is_connected(handle).then(|| (idx, get_addr_tuple(handle)))
+                    is_connected(&sockets, handle).then(|| (idx, get_addr_tuple(&sockets, handle)))
                 })
                 .ok_or(AxError::WouldBlock)?; // wait for connection
             if idx > 0 {
@@ -110,25 +177,96 @@ impl ListenTable {
         }
     }
 
+    /// Returns the waiter to wait for new connections on the port.
+    #[cfg(feature = "multitask")]
+    pub fn waiter(&self, port: u16) -> AxResult<Arc<SocketWaiter>> {
+        if let Some(entry) = self.tcp[port as usize].lock().deref() {
+            Ok(entry.waiter.clone())
+        } else {
+            ax_err!(InvalidInput, "socket accept() failed: not listen")
+        }
+    }
+
+    /// Registers the waker in all sockets in the SYN queue of the port.
+    ///
+    /// A waker is consumed once the socket changes its state, so it needs to be
+    /// registered again to be woken up when the connection is established.
+    #[cfg(feature = "multitask")]
+    pub fn register_waker(&self, port: u16, waker: &core::task::Waker) {
+        let mut sockets = SOCKET_SET.lock_all();
+        if let Some(entry) = self.tcp[port as usize].lock().deref() {
+            for &handle in &entry.syn_queue {
+                sockets
+                    .get_mut::<tcp::Socket>(handle)
+                    .register_recv_waker(waker);
+            }
+        }
+    }
+
+    /// Creates a socket in the SYN queue for an incoming connection.
+    ///
+    /// `sockets` is the socket set of the interface `iface` that the packet
+    /// is received from, it is locked while polling the interface.
+    ///
+    /// The SYN is dropped if the backlog of established connections is full.
+    /// If there are too many half-open connections, the oldest one is dropped
+    /// to make room for the new one.
     pub fn incoming_tcp_packet(
         &self,
+        iface: usize,
         src: IpEndpoint,
         dst: IpEndpoint,
-        sockets: &mut SocketSet<'_>,
+        sockets: &mut SocketSet<'static>,
     ) {
         if let Some(entry) = self.tcp[dst.port as usize].lock().deref_mut() {
-            if !entry.can_accept(dst.addr) {
+            if !entry.can_accept(iface, dst.addr) {
                 // not listening on this address
                 return;
             }
-            if entry.syn_queue.len() >= LISTEN_QUEUE_SIZE {
-                // SYN queue is full, drop the packet
-                warn!("SYN queue overflow!");
+
+            // Only the sockets in this interface can be inspected, as the
+            // socket sets of other interfaces are not locked. The others are
+            // counted as established, which only makes the backlog stricter.
+            let mut half_open = 0;
+            let mut oldest_half_open = None;
+            for (idx, &handle) in entry.syn_queue.iter().enumerate() {
+                if handle.iface != iface {
+                    continue;
+                }
+                let socket = sockets.get::<tcp::Socket>(handle.handle);
+                if matches!(socket.state(), State::Listen | State::SynReceived) {
+                    if socket.remote_endpoint() == Some(src) {
+                        // retransmitted SYN of a half-open connection
+                        return;
+                    }
+                    half_open += 1;
+                    oldest_half_open.get_or_insert(idx);
+                }
+            }
+            if entry.syn_queue.len() - half_open >= entry.backlog {
+                // accept queue is full, drop the packet
+                warn!("TCP listen queue of port {} overflow!", dst.port);
                 return;
             }
-            let mut socket = SocketSetWrapper::new_tcp_socket();
+            if half_open >= entry.max_half_open {
+                // SYN queue is full, drop the oldest half-open connection
+                let idx = oldest_half_open.unwrap();
+                let handle = entry.syn_queue.remove(idx).unwrap();
+                sockets.remove(handle.handle);
+                warn!(
+                    "SYN queue of port {} overflow, drop socket {}",
+                    dst.port, handle
+                );
+            }
+            let mut socket = SocketSetWrapper::new_tcp_socket(&entry.opts);
+            // woken up when the connection is established
+            #[cfg(feature = "multitask")]
+            socket.register_recv_waker(&entry.waiter.waker());
             if socket.listen(entry.listen_endpoint).is_ok() {
-                let handle = sockets.add(socket);
+                let handle = SocketHandle {
+                    iface,
+                    handle: sockets.add(socket),
+                };
                 debug!(
                     "TCP socket {}: prepare for connection {} -> {}",
                     handle, src, entry.listen_endpoint
@@ -139,17 +277,23 @@ impl ListenTable {
     }
 }
 
-fn is_connected(handle: SocketHandle) -> bool {
-    SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
-        !matches!(socket.state(), State::Listen | State::SynReceived)
-    })
+/// Returns how many half-open connections of a listening socket with the
+/// options `opts` fit in [`MAX_HALF_OPEN_BUF_LEN`], at least one.
+fn max_half_open(opts: &SocketOptions) -> usize {
+    let rx_len = opts.recv_buf_len.unwrap_or(TCP_RX_BUF_LEN);
+    let tx_len = opts.send_buf_len.unwrap_or(TCP_TX_BUF_LEN);
+    (MAX_HALF_OPEN_BUF_LEN / (rx_len + tx_len)).clamp(1, MAX_HALF_OPEN)
+}
+
+fn is_connected(sockets: &SocketSetsGuard, handle: SocketHandle) -> bool {
+    let socket = sockets.get::<tcp::Socket>(handle);
+    !matches!(socket.state(), State::Listen | State::SynReceived)
 }
 
-fn get_addr_tuple(handle: SocketHandle) -> (IpEndpoint, IpEndpoint) {
-    SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
-        (
-            socket.local_endpoint().unwrap(),
-            socket.remote_endpoint().unwrap(),
-        )
-    })
+fn get_addr_tuple(sockets: &SocketSetsGuard, handle: SocketHandle) -> (IpEndpoint, IpEndpoint) {
+    let socket = sockets.get::<tcp::Socket>(handle);
+    (
+        socket.local_endpoint().unwrap(),
+        socket.remote_endpoint().unwrap(),
+    )
 }
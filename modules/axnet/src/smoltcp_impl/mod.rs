@@ -1,30 +1,61 @@
 mod addr;
 mod bench;
+mod config;
+#[cfg(feature = "dhcp")]
+mod dhcp;
 mod dns;
+mod icmp;
+mod lend;
 mod listen_table;
+mod options;
+mod raw;
+mod route;
 mod tcp;
+mod tcp_stats;
 mod udp;
 
+#[cfg(feature = "multitask")]
+mod poll;
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec;
+use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::fmt;
 use core::ops::DerefMut;
 
-use axdriver::prelude::*;
+use axdriver::{DeviceIrq, prelude::*};
 use axdriver_net::{DevError, NetBufPtr};
 use axhal::time::{NANOS_PER_MICROS, wall_time_nanos};
-use axsync::Mutex;
+use axsync::{Mutex, MutexGuard};
 use lazyinit::LazyInit;
-use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
-use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
+use smoltcp::iface::{Config, Interface, SocketSet};
+use smoltcp::phy::{ChecksumCapabilities, Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::socket::{self, AnySocket};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
+use smoltcp::wire::{
+    EthernetAddress, HardwareAddress, IpAddress, IpCidr, IpEndpoint, IpProtocol, IpVersion,
+    Ipv4Address, Ipv6Address, TcpPacket,
+};
 
 use self::listen_table::ListenTable;
+use self::options::SocketOptions;
 
+pub use self::config::{
+    InterfaceInfo, add_ip_addr, add_route, dns_servers, interfaces, remove_ip_addr, remove_route,
+    set_default_gateway, set_dns_servers, set_mtu,
+};
 pub use self::dns::dns_query;
-pub use self::tcp::TcpSocket;
+pub use self::icmp::IcmpSocket;
+pub use self::options::{
+    CongestionControl, default_congestion_control, set_default_congestion_control,
+};
+pub use self::raw::RawSocket;
+pub use self::tcp::{TcpInfo, TcpSocket};
 pub use self::udp::UdpSocket;
+pub use smoltcp::socket::tcp::State as TcpState;
 
 macro_rules! env_or_default {
     ($key:literal) => {
@@ -37,10 +68,15 @@ macro_rules! env_or_default {
 
 const IP: &str = env_or_default!("AX_IP");
 const GATEWAY: &str = env_or_default!("AX_GW");
+const IP6: &str = env_or_default!("AX_IP6");
+const GATEWAY6: &str = env_or_default!("AX_GW6");
 const DNS_SEVER: &str = "8.8.8.8";
 const IP_PREFIX: u8 = 24;
+const IP6_PREFIX: u8 = 64;
 
 const STANDARD_MTU: usize = 1500;
+const LOOPBACK_MTU: usize = 65535;
+const ETHERNET_HEADER_LEN: usize = 14;
 
 const RANDOM_SEED: u64 = 0xA2CE_05A2_CE05_A2CE;
 
@@ -48,114 +84,271 @@ const TCP_RX_BUF_LEN: usize = 64 * 1024;
 const TCP_TX_BUF_LEN: usize = 64 * 1024;
 const UDP_RX_BUF_LEN: usize = 64 * 1024;
 const UDP_TX_BUF_LEN: usize = 64 * 1024;
-const LISTEN_QUEUE_SIZE: usize = 512;
+const ICMP_RX_BUF_LEN: usize = 16 * 1024;
+const ICMP_TX_BUF_LEN: usize = 16 * 1024;
+const RAW_RX_BUF_LEN: usize = 64 * 1024;
+const RAW_TX_BUF_LEN: usize = 64 * 1024;
+/// The maximum backlog of a listening TCP socket, like `SOMAXCONN` of Linux.
+const MAX_LISTEN_BACKLOG: usize = 512;
+/// The maximum number of half-open connections of a listening TCP socket.
+///
+/// The oldest one is dropped for a new SYN when there are too many, e.g., in a
+/// SYN flood.
+const MAX_HALF_OPEN: usize = 64;
+/// The maximum size of the buffers of the half-open connections of a listening
+/// TCP socket, which lowers [`MAX_HALF_OPEN`] for large buffers.
+///
+/// smoltcp cannot replace the buffers of a socket, so they are allocated in
+/// full when the SYN is received, as the window is advertised in the SYN-ACK.
+const MAX_HALF_OPEN_BUF_LEN: usize = 2 * 1024 * 1024;
 
 static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
-static SOCKET_SET: LazyInit<SocketSetWrapper> = LazyInit::new();
-static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
+static SOCKET_SET: SocketSetWrapper = SocketSetWrapper;
+static IFACES: LazyInit<Vec<InterfaceWrapper>> = LazyInit::new();
+
+/// A handle to a socket in the socket set of an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SocketHandle {
+    /// The index of the interface in [`IFACES`].
+    iface: usize,
+    handle: smoltcp::iface::SocketHandle,
+}
 
-struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
+/// Accesses the sockets of all interfaces.
+///
+/// Each interface has its own socket set, so that a socket is only polled
+/// by the interface it sends packets through.
+struct SocketSetWrapper;
+
+/// The socket sets of all interfaces, locked in the order of the interfaces.
+struct SocketSetsGuard(Vec<MutexGuard<'static, SocketSet<'static>>>);
+
+/// The device under an interface.
+enum NetDevice {
+    Nic(RefCell<AxNetDevice>), // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    /// The packets transmitted through the loopback device, which are
+    /// received in FIFO order.
+    Loopback(VecDeque<Vec<u8>>),
+}
 
 struct DeviceWrapper {
-    inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    inner: NetDevice,
+    mtu: usize,
+    /// The index of the interface in [`IFACES`].
+    iface: usize,
+    ether_addr: EthernetAddress,
+    /// The IP addresses of the interface, updated on each poll.
+    ip_addrs: Vec<IpAddress>,
+    /// The number of RX buffers lent to sockets and not recycled yet.
+    lent_rx_bufs: usize,
 }
 
 struct InterfaceWrapper {
-    name: &'static str,
+    name: String,
     ether_addr: EthernetAddress,
     dev: Mutex<DeviceWrapper>,
     iface: Mutex<Interface>,
+    sockets: Mutex<SocketSet<'static>>,
 }
 
-impl<'a> SocketSetWrapper<'a> {
-    fn new() -> Self {
-        Self(Mutex::new(SocketSet::new(vec![])))
+impl fmt::Display for SocketHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", IFACES[self.iface].name(), self.handle)
     }
+}
 
-    pub fn new_tcp_socket() -> socket::tcp::Socket<'a> {
-        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_RX_BUF_LEN]);
-        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_TX_BUF_LEN]);
-        socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
+impl SocketSetWrapper {
+    /// Creates a TCP socket with the buffer sizes and the options in `opts`.
+    pub fn new_tcp_socket<'a>(opts: &SocketOptions) -> socket::tcp::Socket<'a> {
+        let rx_len = opts.recv_buf_len.unwrap_or(TCP_RX_BUF_LEN);
+        let tx_len = opts.send_buf_len.unwrap_or(TCP_TX_BUF_LEN);
+        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; rx_len]);
+        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; tx_len]);
+        let mut socket = socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
+        opts.apply_tcp(&mut socket);
+        socket
     }
 
-    pub fn new_udp_socket() -> socket::udp::Socket<'a> {
+    /// Creates a UDP socket with the buffer sizes in `opts`.
+    pub fn new_udp_socket<'a>(opts: &SocketOptions) -> socket::udp::Socket<'a> {
+        let rx_len = opts.recv_buf_len.unwrap_or(UDP_RX_BUF_LEN);
+        let tx_len = opts.send_buf_len.unwrap_or(UDP_TX_BUF_LEN);
         let udp_rx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_RX_BUF_LEN],
+            vec![0; rx_len],
         );
         let udp_tx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_TX_BUF_LEN],
+            vec![0; tx_len],
         );
         socket::udp::Socket::new(udp_rx_buffer, udp_tx_buffer)
     }
 
-    pub fn new_dns_socket() -> socket::dns::Socket<'a> {
-        let server_addr = DNS_SEVER.parse().expect("invalid DNS server address");
-        socket::dns::Socket::new(&[server_addr], vec![])
+    pub fn new_icmp_socket<'a>() -> socket::icmp::Socket<'a> {
+        let icmp_rx_buffer = socket::icmp::PacketBuffer::new(
+            vec![socket::icmp::PacketMetadata::EMPTY; 8],
+            vec![0; ICMP_RX_BUF_LEN],
+        );
+        let icmp_tx_buffer = socket::icmp::PacketBuffer::new(
+            vec![socket::icmp::PacketMetadata::EMPTY; 8],
+            vec![0; ICMP_TX_BUF_LEN],
+        );
+        socket::icmp::Socket::new(icmp_rx_buffer, icmp_tx_buffer)
+    }
+
+    pub fn new_raw_socket<'a>(
+        ip_version: IpVersion,
+        protocol: IpProtocol,
+    ) -> socket::raw::Socket<'a> {
+        let raw_rx_buffer = socket::raw::PacketBuffer::new(
+            vec![socket::raw::PacketMetadata::EMPTY; 8],
+            vec![0; RAW_RX_BUF_LEN],
+        );
+        let raw_tx_buffer = socket::raw::PacketBuffer::new(
+            vec![socket::raw::PacketMetadata::EMPTY; 8],
+            vec![0; RAW_TX_BUF_LEN],
+        );
+        socket::raw::Socket::new(ip_version, protocol, raw_rx_buffer, raw_tx_buffer)
+    }
+
+    pub fn new_dns_socket<'a>() -> socket::dns::Socket<'a> {
+        socket::dns::Socket::new(&dns::servers(), vec![])
     }
 
-    pub fn add<T: AnySocket<'a>>(&self, socket: T) -> SocketHandle {
-        let handle = self.0.lock().add(socket);
+    /// Adds a socket to the socket set of the interface `iface`.
+    pub fn add<T: AnySocket<'static>>(&self, iface: usize, socket: T) -> SocketHandle {
+        let handle = SocketHandle {
+            iface,
+            handle: IFACES[iface].sockets.lock().add(socket),
+        };
         debug!("socket {}: created", handle);
         handle
     }
 
-    pub fn with_socket<T: AnySocket<'a>, R, F>(&self, handle: SocketHandle, f: F) -> R
+    pub fn with_socket<T: AnySocket<'static>, R, F>(&self, handle: SocketHandle, f: F) -> R
     where
         F: FnOnce(&T) -> R,
     {
-        let set = self.0.lock();
-        let socket = set.get(handle);
+        let set = IFACES[handle.iface].sockets.lock();
+        let socket = set.get(handle.handle);
         f(socket)
     }
 
-    pub fn with_socket_mut<T: AnySocket<'a>, R, F>(&self, handle: SocketHandle, f: F) -> R
+    pub fn with_socket_mut<T: AnySocket<'static>, R, F>(&self, handle: SocketHandle, f: F) -> R
     where
         F: FnOnce(&mut T) -> R,
     {
-        let mut set = self.0.lock();
-        let socket = set.get_mut(handle);
+        let mut set = IFACES[handle.iface].sockets.lock();
+        let socket = set.get_mut(handle.handle);
         f(socket)
     }
 
+    /// Locks the socket sets of all interfaces.
+    ///
+    /// The interfaces are polled with their socket sets locked, so the socket
+    /// sets must be locked before any lock taken while polling, e.g., the
+    /// entries of the listen table.
+    pub fn lock_all(&self) -> SocketSetsGuard {
+        SocketSetsGuard(IFACES.iter().map(|iface| iface.sockets.lock()).collect())
+    }
+
     pub fn poll_interfaces(&self) {
-        ETH0.poll(&self.0);
+        for iface in IFACES.iter() {
+            iface.poll();
+        }
+        // Let the poll task update its timeout, as the sockets may have
+        // changed.
+        #[cfg(feature = "multitask")]
+        poll::kick();
+    }
+
+    /// Returns how long to wait before the interfaces need to be polled
+    /// again, or `None` if there are no pending socket timers.
+    #[cfg(feature = "multitask")]
+    pub fn poll_delay(&self) -> Option<core::time::Duration> {
+        IFACES.iter().filter_map(|iface| iface.poll_delay()).min()
     }
 
     pub fn remove(&self, handle: SocketHandle) {
-        self.0.lock().remove(handle);
+        IFACES[handle.iface].sockets.lock().remove(handle.handle);
+        #[cfg(feature = "multitask")]
+        poll::remove_waiter(handle);
         debug!("socket {}: destroyed", handle);
     }
 }
 
-impl InterfaceWrapper {
-    fn new(name: &'static str, dev: AxNetDevice, ether_addr: EthernetAddress) -> Self {
-        let mut config = Config::new(HardwareAddress::Ethernet(ether_addr));
-        config.random_seed = RANDOM_SEED;
+impl SocketSetsGuard {
+    pub fn get<T: AnySocket<'static>>(&self, handle: SocketHandle) -> &T {
+        self.0[handle.iface].get(handle.handle)
+    }
 
-        let mut dev = DeviceWrapper::new(dev);
-        let iface = Mutex::new(Interface::new(config, &mut dev, Self::current_time()));
+    pub fn get_mut<T: AnySocket<'static>>(&mut self, handle: SocketHandle) -> &mut T {
+        self.0[handle.iface].get_mut(handle.handle)
+    }
+}
+
+impl InterfaceWrapper {
+    fn new(index: usize, name: String, dev: NetDevice, ether_addr: EthernetAddress) -> Self {
+        let mut dev = DeviceWrapper::new(dev, index, ether_addr);
+        let iface = Mutex::new(Self::new_iface(ether_addr, &mut dev));
         Self {
             name,
             ether_addr,
             dev: Mutex::new(dev),
             iface,
+            sockets: Mutex::new(SocketSet::new(vec![])),
         }
     }
 
+    /// Creates the loopback interface `lo`, with the addresses `127.0.0.1/8`
+    /// and `::1/128`.
+    fn new_loopback(index: usize) -> Self {
+        let lo = Self::new(
+            index,
+            "lo".to_string(),
+            NetDevice::Loopback(VecDeque::new()),
+            EthernetAddress([0; 6]),
+        );
+        lo.setup_ip_addr(IpAddress::Ipv4(Ipv4Address::new(127, 0, 0, 1)), 8);
+        lo.setup_ip_addr(IpAddress::Ipv6(Ipv6Address::LOCALHOST), 128);
+        lo
+    }
+
+    fn new_iface(ether_addr: EthernetAddress, dev: &mut DeviceWrapper) -> Interface {
+        let hardware_addr = match dev.inner {
+            NetDevice::Nic(_) => HardwareAddress::Ethernet(ether_addr),
+            NetDevice::Loopback(_) => HardwareAddress::Ip,
+        };
+        let mut config = Config::new(hardware_addr);
+        config.random_seed = RANDOM_SEED;
+        Interface::new(config, dev, Self::current_time())
+    }
+
     fn current_time() -> Instant {
         Instant::from_micros_const((wall_time_nanos() / NANOS_PER_MICROS) as i64)
     }
 
     pub fn name(&self) -> &str {
-        self.name
+        &self.name
     }
 
     pub fn ethernet_address(&self) -> EthernetAddress {
         self.ether_addr
     }
 
+    pub fn is_loopback(&self) -> bool {
+        self.dev.lock().is_loopback()
+    }
+
+    /// Returns the largest MTU that the device supports.
+    pub fn max_mtu(&self) -> usize {
+        if self.is_loopback() {
+            LOOPBACK_MTU
+        } else {
+            STANDARD_MTU
+        }
+    }
+
     pub fn setup_ip_addr(&self, ip: IpAddress, prefix_len: u8) {
         let mut iface = self.iface.lock();
         iface.update_ip_addrs(|ip_addrs| {
@@ -171,19 +364,101 @@ impl InterfaceWrapper {
         };
     }
 
-    pub fn poll(&self, sockets: &Mutex<SocketSet>) {
+    /// Sets the MTU of the interface.
+    ///
+    /// The device capabilities are cached by the smoltcp interface, so it is
+    /// recreated with the addresses and routes preserved.
+    pub fn set_mtu(&self, mtu: usize) {
         let mut dev = self.dev.lock();
         let mut iface = self.iface.lock();
-        let mut sockets = sockets.lock();
+        dev.mtu = mtu;
+
+        let ip_addrs = iface.ip_addrs().to_vec();
+        let mut routes = Vec::new();
+        iface.routes_mut().update(|r| routes.extend_from_slice(r));
+
+        *iface = Self::new_iface(self.ether_addr, &mut dev);
+        iface.update_ip_addrs(|addrs| {
+            for addr in ip_addrs {
+                addrs.push(addr).unwrap();
+            }
+        });
+        iface.routes_mut().update(|r| {
+            for route in routes {
+                r.push(route).unwrap();
+            }
+        });
+    }
+
+    pub fn poll(&self) {
+        let mut dev = self.dev.lock();
+        let mut iface = self.iface.lock();
+        let mut sockets = self.sockets.lock();
         let timestamp = Self::current_time();
+        dev.set_ip_addrs(iface.ip_addrs());
         iface.poll(timestamp, dev.deref_mut(), &mut sockets);
+        #[cfg(feature = "dhcp")]
+        dhcp::poll(dev.iface, &mut iface, &mut sockets);
+    }
+
+    #[cfg(feature = "multitask")]
+    pub fn poll_delay(&self) -> Option<core::time::Duration> {
+        if self.dev.lock().has_pending_packets() {
+            // The loopback packets are received by the next poll.
+            return Some(core::time::Duration::ZERO);
+        }
+        let mut iface = self.iface.lock();
+        let sockets = self.sockets.lock();
+        let timestamp = Self::current_time();
+        iface
+            .poll_delay(timestamp, &sockets)
+            .map(|delay| core::time::Duration::from_micros(delay.total_micros()))
     }
 }
 
 impl DeviceWrapper {
-    fn new(inner: AxNetDevice) -> Self {
+    fn new(inner: NetDevice, iface: usize, ether_addr: EthernetAddress) -> Self {
+        let mtu = match inner {
+            NetDevice::Nic(_) => STANDARD_MTU,
+            NetDevice::Loopback(_) => LOOPBACK_MTU,
+        };
         Self {
-            inner: RefCell::new(inner),
+            inner,
+            mtu,
+            iface,
+            ether_addr,
+            ip_addrs: Vec::new(),
+            lent_rx_bufs: 0,
+        }
+    }
+
+    fn set_ip_addrs(&mut self, cidrs: &[IpCidr]) {
+        let addrs = cidrs.iter().map(IpCidr::address);
+        if !self.ip_addrs.iter().copied().eq(addrs.clone()) {
+            self.ip_addrs = addrs.collect();
+        }
+    }
+
+    /// Returns an RX buffer lent to a socket to the driver.
+    fn recycle_lent_rx_buffer(&mut self, buf: NetBufPtr) {
+        if let NetDevice::Nic(dev) = &self.inner {
+            self.lent_rx_bufs -= 1;
+            if let Err(e) = dev.borrow_mut().recycle_rx_buffer(buf) {
+                warn!("recycle_rx_buffer failed: {:?}", e);
+            }
+        }
+    }
+
+    fn is_loopback(&self) -> bool {
+        matches!(self.inner, NetDevice::Loopback(_))
+    }
+
+    /// Whether there are transmitted loopback packets not received yet.
+    #[cfg(feature = "multitask")]
+    fn has_pending_packets(&self) -> bool {
+        match &self.inner {
+            NetDevice::Nic(_) => false,
+            NetDevice::Loopback(queue) => !queue.is_empty(),
         }
     }
 }
@@ -199,35 +474,66 @@ impl Device for DeviceWrapper {
         Self: 'a;
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        let mut dev = self.inner.borrow_mut();
-        if let Err(e) = dev.recycle_tx_buffers() {
+        let dev = match &mut self.inner {
+            NetDevice::Nic(dev) => &*dev,
+            NetDevice::Loopback(queue) => {
+                let buf = queue.pop_front()?;
+                return Some((
+                    AxNetRxToken::Loopback(buf, self.iface),
+                    AxNetTxToken::Loopback(queue),
+                ));
+            }
+        };
+        let mut nic = dev.borrow_mut();
+        if let Err(e) = nic.recycle_tx_buffers() {
             warn!("recycle_tx_buffers failed: {:?}", e);
             return None;
         }
 
-        if !dev.can_transmit() {
+        if !nic.can_transmit() {
             return None;
         }
-        let rx_buf = match dev.receive() {
-            Ok(buf) => buf,
-            Err(err) => {
-                if !matches!(err, DevError::Again) {
-                    warn!("receive failed: {:?}", err);
+        // Keep half of the RX buffers for the driver, so that it can still
+        // receive while sockets hold the lent ones.
+        let max_lent = nic.rx_queue_size() / 2;
+        let rx_buf = loop {
+            let rx_buf = match nic.receive() {
+                Ok(buf) => buf,
+                Err(err) => {
+                    if !matches!(err, DevError::Again) {
+                        warn!("receive failed: {:?}", err);
+                    }
+                    return None;
                 }
-                return None;
+            };
+            if self.lent_rx_bufs >= max_lent {
+                break rx_buf;
+            }
+            match lend::lend(self.iface, self.ether_addr, &self.ip_addrs, rx_buf) {
+                Ok(()) => self.lent_rx_bufs += 1,
+                Err(rx_buf) => break rx_buf,
             }
         };
-        Some((AxNetRxToken(&self.inner, rx_buf), AxNetTxToken(&self.inner)))
+        drop(nic);
+        Some((
+            AxNetRxToken::Nic(dev, rx_buf, self.iface),
+            AxNetTxToken::Nic(dev),
+        ))
     }
 
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
-        let mut dev = self.inner.borrow_mut();
-        if let Err(e) = dev.recycle_tx_buffers() {
+        let dev = match &mut self.inner {
+            NetDevice::Nic(dev) => &*dev,
+            NetDevice::Loopback(queue) => return Some(AxNetTxToken::Loopback(queue)),
+        };
+        let mut nic = dev.borrow_mut();
+        if let Err(e) = nic.recycle_tx_buffers() {
             warn!("recycle_tx_buffers failed: {:?}", e);
             return None;
         }
-        if dev.can_transmit() {
-            Some(AxNetTxToken(&self.inner))
+        if nic.can_transmit() {
+            drop(nic);
+            Some(AxNetTxToken::Nic(dev))
         } else {
             None
         }
@@ -235,34 +541,66 @@ impl Device for DeviceWrapper {
 
     fn capabilities(&self) -> DeviceCapabilities {
         let mut caps = DeviceCapabilities::default();
-        caps.max_transmission_unit = 1514;
         caps.max_burst_size = None;
-        caps.medium = Medium::Ethernet;
+        match self.inner {
+            NetDevice::Nic(_) => {
+                caps.max_transmission_unit = self.mtu + ETHERNET_HEADER_LEN;
+                caps.medium = Medium::Ethernet;
+            }
+            NetDevice::Loopback(_) => {
+                // Packets never leave memory, no need to verify checksums.
+                caps.max_transmission_unit = self.mtu;
+                caps.medium = Medium::Ip;
+                caps.checksum = ChecksumCapabilities::ignored();
+            }
+        }
         caps
     }
 }
 
-struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr);
-struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
+/// A received packet lent to smoltcp. The RX buffer of a NIC is parsed in
+/// place and recycled to the driver after being consumed.
+enum AxNetRxToken<'a> {
+    Nic(&'a RefCell<AxNetDevice>, NetBufPtr, usize),
+    Loopback(Vec<u8>, usize),
+}
+
+/// A packet to be transmitted, which smoltcp builds directly in a TX buffer
+/// allocated from the driver of a NIC.
+enum AxNetTxToken<'a> {
+    Nic(&'a RefCell<AxNetDevice>),
+    Loopback(&'a mut VecDeque<Vec<u8>>),
+}
 
 impl RxToken for AxNetRxToken<'_> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
-        snoop_tcp_packet(self.1.packet(), sockets).ok();
+        match self {
+            Self::Nic(_, rx_buf, iface) => {
+                snoop_tcp_packet(rx_buf.packet(), Medium::Ethernet, *iface, sockets).ok();
+            }
+            Self::Loopback(buf, iface) => {
+                snoop_tcp_packet(buf, Medium::Ip, *iface, sockets).ok();
+            }
+        }
     }
 
     fn consume<R, F>(self, f: F) -> R
     where
         F: FnOnce(&[u8]) -> R,
     {
-        let rx_buf = self.1;
-        trace!(
-            "RECV {} bytes: {:02X?}",
-            rx_buf.packet_len(),
-            rx_buf.packet()
-        );
-        let result = f(rx_buf.packet());
-        self.0.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
-        result
+        match self {
+            Self::Nic(dev, rx_buf, _) => {
+                trace!(
+                    "RECV {} bytes: {:02X?}",
+                    rx_buf.packet_len(),
+                    rx_buf.packet()
+                );
+                let result = f(rx_buf.packet());
+                dev.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
+                result
+            }
+            Self::Loopback(buf, _) => f(&buf),
+        }
     }
 }
 
@@ -271,34 +609,131 @@ impl TxToken for AxNetTxToken<'_> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut dev = self.0.borrow_mut();
-        let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
-        let ret = f(tx_buf.packet_mut());
-        trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
-        dev.transmit(tx_buf).unwrap();
-        ret
+        match self {
+            Self::Nic(dev) => {
+                let mut dev = dev.borrow_mut();
+                let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
+                let ret = f(tx_buf.packet_mut());
+                trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
+                with_tcp_segment(tx_buf.packet(), Medium::Ethernet, |packet, src, dst| {
+                    tcp_stats::snoop_segment(packet, src, dst, true)
+                })
+                .ok();
+                dev.transmit(tx_buf).unwrap();
+                ret
+            }
+            Self::Loopback(queue) => {
+                let mut buf = vec![0; len];
+                let ret = f(&mut buf);
+                with_tcp_segment(&buf, Medium::Ip, |packet, src, dst| {
+                    tcp_stats::snoop_segment(packet, src, dst, true)
+                })
+                .ok();
+                queue.push_back(buf);
+                ret
+            }
+        }
     }
 }
 
-fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
-    use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
+/// Returns an RX buffer lent to a socket to the driver of the interface
+/// `iface`.
+fn recycle_lent_rx_buffer(iface: usize, buf: NetBufPtr) {
+    IFACES[iface].dev.lock().recycle_lent_rx_buffer(buf);
+}
 
-    let ether_frame = EthernetFrame::new_checked(buf)?;
-    let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
+/// Returns the IPv6 link-local address derived from the MAC address by the
+/// modified EUI-64 format (RFC 4291).
+fn link_local_addr(ether_addr: EthernetAddress) -> IpAddress {
+    let mac = ether_addr.0;
+    let mut octets = [0; 16];
+    octets[..2].copy_from_slice(&[0xfe, 0x80]);
+    octets[8..].copy_from_slice(&[
+        mac[0] ^ 0x02,
+        mac[1],
+        mac[2],
+        0xff,
+        0xfe,
+        mac[3],
+        mac[4],
+        mac[5],
+    ]);
+    IpAddress::Ipv6(Ipv6Address::from(octets))
+}
 
-    if ipv4_packet.next_header() == IpProtocol::Tcp {
-        let tcp_packet = TcpPacket::new_checked(ipv4_packet.payload())?;
-        let src_addr = (ipv4_packet.src_addr(), tcp_packet.src_port()).into();
-        let dst_addr = (ipv4_packet.dst_addr(), tcp_packet.dst_port()).into();
-        let is_first = tcp_packet.syn() && !tcp_packet.ack();
+/// Creates a socket in the SYN queue for an incoming TCP connection, and
+/// accounts the incoming TCP segments in the connection statistics.
+///
+/// `buf` is an Ethernet frame or an IP packet, depending on the `medium` of
+/// the interface.
+fn snoop_tcp_packet(
+    buf: &[u8],
+    medium: Medium,
+    iface: usize,
+    sockets: &mut SocketSet<'static>,
+) -> Result<(), smoltcp::wire::Error> {
+    with_tcp_segment(buf, medium, |packet, src_addr, dst_addr| {
+        tcp_stats::snoop_segment(packet, src_addr, dst_addr, false);
+        let is_first = packet.syn() && !packet.ack();
         if is_first {
             // create a socket for the first incoming TCP packet, as the later accept() returns.
-            LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, sockets);
+            LISTEN_TABLE.incoming_tcp_packet(iface, src_addr, dst_addr, sockets);
+        }
+    })
+}
+
+/// Calls `f` with the TCP segment in the packet `buf` of `medium` and its
+/// source and destination endpoints, if it is a TCP packet.
+fn with_tcp_segment<F>(buf: &[u8], medium: Medium, f: F) -> Result<(), smoltcp::wire::Error>
+where
+    F: FnOnce(&TcpPacket<&[u8]>, IpEndpoint, IpEndpoint),
+{
+    use smoltcp::wire::{EthernetFrame, Ipv4Packet, Ipv6Packet};
+
+    let ether_frame;
+    let ip_packet = match medium {
+        Medium::Ethernet => {
+            ether_frame = EthernetFrame::new_checked(buf)?;
+            ether_frame.payload()
+        }
+        _ => buf,
+    };
+
+    match IpVersion::of_packet(ip_packet)? {
+        IpVersion::Ipv4 => {
+            let packet = Ipv4Packet::new_checked(ip_packet)?;
+            if packet.next_header() == IpProtocol::Tcp {
+                let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+                with_segment_endpoints(packet.payload(), src, dst, f)?;
+            }
+        }
+        IpVersion::Ipv6 => {
+            let packet = Ipv6Packet::new_checked(ip_packet)?;
+            if packet.next_header() == IpProtocol::Tcp {
+                let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+                with_segment_endpoints(packet.payload(), src, dst, f)?;
+            }
         }
     }
     Ok(())
 }
 
+fn with_segment_endpoints<F>(
+    buf: &[u8],
+    src_ip: IpAddress,
+    dst_ip: IpAddress,
+    f: F,
+) -> Result<(), smoltcp::wire::Error>
+where
+    F: FnOnce(&TcpPacket<&[u8]>, IpEndpoint, IpEndpoint),
+{
+    let packet = TcpPacket::new_checked(buf)?;
+    let src_addr = IpEndpoint::new(src_ip, packet.src_port());
+    let dst_addr = IpEndpoint::new(dst_ip, packet.dst_port());
+    f(&packet, src_addr, dst_addr);
+    Ok(())
+}
+
 /// Poll the network stack.
 ///
 /// It may receive packets from the NIC and process them, and transmit queued
@@ -309,29 +744,83 @@ pub fn poll_interfaces() {
 
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
-    ETH0.dev.lock().bench_transmit_bandwidth();
+    IFACES[0].dev.lock().bench_transmit_bandwidth();
 }
 
 /// Benchmark raw socket receive bandwidth.
 pub fn bench_receive() {
-    ETH0.dev.lock().bench_receive_bandwidth();
+    IFACES[0].dev.lock().bench_receive_bandwidth();
 }
 
-pub(crate) fn init(net_dev: AxNetDevice) {
-    let ether_addr = EthernetAddress(net_dev.mac_address().0);
-    let eth0 = InterfaceWrapper::new("eth0", net_dev, ether_addr);
+/// Creates an interface for each NIC, named `eth0`, `eth1`, etc., followed by
+/// the loopback interface `lo`.
+///
+/// Each NIC interface has an IPv6 link-local address. Only `eth0` is
+/// configured with the static config from the build environment. `irqs` are
+/// the interrupts of the NICs, in the same order.
+pub(crate) fn init(net_devs: Vec<AxNetDevice>, irqs: Vec<Option<DeviceIrq>>) {
+    let mut ifaces: Vec<_> = net_devs
+        .into_iter()
+        .enumerate()
+        .map(|(i, dev)| {
+            let ether_addr = EthernetAddress(dev.mac_address().0);
+            let iface = InterfaceWrapper::new(
+                i,
+                format!("eth{i}"),
+                NetDevice::Nic(RefCell::new(dev)),
+                ether_addr,
+            );
+            iface.setup_ip_addr(link_local_addr(ether_addr), IP6_PREFIX);
+            iface
+        })
+        .collect();
+    ifaces.push(InterfaceWrapper::new_loopback(ifaces.len()));
 
     let ip = IP.parse().expect("invalid IP address");
     let gateway = GATEWAY.parse().expect("invalid gateway IP address");
-    eth0.setup_ip_addr(ip, IP_PREFIX);
-    eth0.setup_gateway(gateway);
+    let dns_server = DNS_SEVER.parse().expect("invalid DNS server address");
+    let eth0 = &ifaces[0];
+    if !eth0.is_loopback() {
+        eth0.setup_ip_addr(ip, IP_PREFIX);
+        eth0.setup_gateway(gateway);
+        if !IP6.is_empty() {
+            eth0.setup_ip_addr(IP6.parse().expect("invalid IPv6 address"), IP6_PREFIX);
+        }
+        if !GATEWAY6.is_empty() {
+            eth0.setup_gateway(GATEWAY6.parse().expect("invalid IPv6 gateway address"));
+        }
+    }
+    dns::set_servers(&[dns_server]);
 
-    ETH0.init_once(eth0);
-    SOCKET_SET.init_once(SocketSetWrapper::new());
+    IFACES.init_once(ifaces);
     LISTEN_TABLE.init_once(ListenTable::new());
 
-    info!("created net interface {:?}:", ETH0.name());
-    info!("  ether:    {}", ETH0.ethernet_address());
-    info!("  ip:       {}/{}", ip, IP_PREFIX);
-    info!("  gateway:  {}", gateway);
+    for iface in IFACES.iter() {
+        info!("created net interface {:?}:", iface.name());
+        if !iface.is_loopback() {
+            info!("  ether:    {}", iface.ethernet_address());
+        }
+        for cidr in iface.iface.lock().ip_addrs() {
+            info!("  ip:       {}", cidr);
+        }
+    }
+    if !IFACES[0].is_loopback() {
+        info!("default gateway: {}", gateway);
+        if !GATEWAY6.is_empty() {
+            info!("default IPv6 gateway: {}", GATEWAY6);
+        }
+    }
+
+    #[cfg(feature = "dhcp")]
+    dhcp::init(dhcp::StaticConfig {
+        ip,
+        prefix_len: IP_PREFIX,
+        gateway,
+        dns_server,
+    });
+
+    #[cfg(feature = "multitask")]
+    poll::init(&irqs);
+    #[cfg(not(feature = "multitask"))]
+    let _ = irqs;
 }
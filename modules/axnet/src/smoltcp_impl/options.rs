@@ -0,0 +1,203 @@
+//! Socket options, set by `setsockopt` or the setters of sockets.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult, ax_err};
+use axhal::time::{TimeValue, monotonic_time};
+use smoltcp::socket::tcp;
+
+/// The smallest size of the socket buffers that can be set.
+const MIN_BUF_LEN: usize = 2048;
+/// The largest size of the socket buffers that can be set.
+const MAX_BUF_LEN: usize = 4 * 1024 * 1024;
+/// The interval of TCP keep-alive probes, which is the default interval of
+/// Linux.
+const TCP_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(75);
+
+/// The congestion control algorithm of a TCP socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CongestionControl {
+    /// No congestion control, the sending rate is only limited by the window
+    /// of the peer.
+    None = 0,
+    /// TCP Reno (RFC 5681).
+    Reno = 1,
+    /// CUBIC (RFC 9438), which uses floating-point arithmetic.
+    #[cfg(feature = "tcp-cubic")]
+    Cubic = 2,
+}
+
+impl CongestionControl {
+    /// Returns the algorithm with the given name used by `TCP_CONGESTION`,
+    /// i.e., `"none"`, `"reno"` or `"cubic"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "reno" => Some(Self::Reno),
+            #[cfg(feature = "tcp-cubic")]
+            "cubic" => Some(Self::Cubic),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the algorithm used by `TCP_CONGESTION`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Reno => "reno",
+            #[cfg(feature = "tcp-cubic")]
+            Self::Cubic => "cubic",
+        }
+    }
+
+    const fn from_u8(val: u8) -> Self {
+        match val {
+            0 => Self::None,
+            #[cfg(feature = "tcp-cubic")]
+            2 => Self::Cubic,
+            _ => Self::Reno,
+        }
+    }
+}
+
+impl From<CongestionControl> for tcp::CongestionControl {
+    fn from(cc: CongestionControl) -> Self {
+        match cc {
+            CongestionControl::None => Self::None,
+            CongestionControl::Reno => Self::Reno,
+            #[cfg(feature = "tcp-cubic")]
+            CongestionControl::Cubic => Self::Cubic,
+        }
+    }
+}
+
+impl From<tcp::CongestionControl> for CongestionControl {
+    fn from(cc: tcp::CongestionControl) -> Self {
+        match cc {
+            tcp::CongestionControl::None => Self::None,
+            tcp::CongestionControl::Reno => Self::Reno,
+            #[cfg(feature = "tcp-cubic")]
+            tcp::CongestionControl::Cubic => Self::Cubic,
+        }
+    }
+}
+
+#[cfg(feature = "tcp-cubic")]
+const INITIAL_CONGESTION_CONTROL: CongestionControl = CongestionControl::Cubic;
+#[cfg(not(feature = "tcp-cubic"))]
+const INITIAL_CONGESTION_CONTROL: CongestionControl = CongestionControl::Reno;
+
+/// The congestion control algorithm of the TCP sockets that have not set one.
+static DEFAULT_CONGESTION_CONTROL: AtomicU8 = AtomicU8::new(INITIAL_CONGESTION_CONTROL as u8);
+
+/// Returns the congestion control algorithm used by default, which is CUBIC
+/// if the `tcp-cubic` feature is enabled, or Reno otherwise.
+pub fn default_congestion_control() -> CongestionControl {
+    CongestionControl::from_u8(DEFAULT_CONGESTION_CONTROL.load(Ordering::Relaxed))
+}
+
+/// Sets the congestion control algorithm of the TCP sockets created later
+/// that have not set one.
+pub fn set_default_congestion_control(cc: CongestionControl) {
+    DEFAULT_CONGESTION_CONTROL.store(cc as u8, Ordering::Relaxed);
+}
+
+/// The options of a socket.
+///
+/// Not all options apply to all types of sockets.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SocketOptions {
+    /// Whether the local address can be reused (`SO_REUSEADDR`).
+    pub reuse_addr: bool,
+    /// Whether a socket bound to the unspecified IPv6 address only accepts
+    /// IPv6 traffic (`IPV6_V6ONLY`).
+    pub v6only: bool,
+    /// The size of the receive buffer (`SO_RCVBUF`), or `None` for the
+    /// default size.
+    pub recv_buf_len: Option<usize>,
+    /// The size of the send buffer (`SO_SNDBUF`), or `None` for the default
+    /// size.
+    pub send_buf_len: Option<usize>,
+    /// The timeout of blocking receive operations (`SO_RCVTIMEO`).
+    pub recv_timeout: Option<Duration>,
+    /// The timeout of blocking send operations (`SO_SNDTIMEO`).
+    pub send_timeout: Option<Duration>,
+    /// Whether TCP keep-alive probes are sent (`SO_KEEPALIVE`).
+    pub keep_alive: bool,
+    /// Whether Nagle's algorithm is disabled (`TCP_NODELAY`).
+    pub nodelay: bool,
+    /// How long closing a TCP socket waits for the unsent data to be sent
+    /// (`SO_LINGER`), or `None` to close in the background.
+    pub linger: Option<Duration>,
+    /// The congestion control algorithm of a TCP socket (`TCP_CONGESTION`),
+    /// or `None` for the default one.
+    pub congestion_control: Option<CongestionControl>,
+}
+
+impl SocketOptions {
+    pub const fn new() -> Self {
+        Self {
+            reuse_addr: false,
+            v6only: false,
+            recv_buf_len: None,
+            send_buf_len: None,
+            recv_timeout: None,
+            send_timeout: None,
+            keep_alive: false,
+            nodelay: false,
+            linger: None,
+            congestion_control: None,
+        }
+    }
+
+    /// Applies the options that are kept by the smoltcp TCP socket.
+    pub fn apply_tcp(&self, socket: &mut tcp::Socket) {
+        socket.set_nagle_enabled(!self.nodelay);
+        socket.set_keep_alive(self.keep_alive.then_some(TCP_KEEP_ALIVE_INTERVAL));
+        let cc = self
+            .congestion_control
+            .unwrap_or_else(default_congestion_control);
+        socket.set_congestion_control(cc.into());
+    }
+}
+
+/// Clamps the size of a socket buffer to the range that can be set.
+pub(crate) fn clamp_buf_len(len: usize) -> usize {
+    len.clamp(MIN_BUF_LEN, MAX_BUF_LEN)
+}
+
+/// Checks that a timeout is valid, i.e., not zero.
+///
+/// A zero timeout of `setsockopt` means no timeout, which is `None` here.
+pub(crate) fn check_timeout(timeout: Option<Duration>) -> AxResult {
+    if timeout == Some(Duration::ZERO) {
+        return ax_err!(InvalidInput, "cannot set a 0 duration timeout");
+    }
+    Ok(())
+}
+
+/// The deadline of a blocking operation with a timeout.
+#[derive(Clone, Copy)]
+pub(crate) struct Deadline(Option<TimeValue>);
+
+impl Deadline {
+    pub fn after(timeout: Option<Duration>) -> Self {
+        Self(timeout.map(|t| monotonic_time() + t))
+    }
+
+    /// Returns the remaining time, or `None` if there is no deadline.
+    ///
+    /// Returns [`Err(WouldBlock)`](AxError::WouldBlock) if the deadline has
+    /// passed, like a timed out socket operation of Linux.
+    pub fn remaining(&self) -> AxResult<Option<Duration>> {
+        match self.0 {
+            Some(deadline) => match deadline.checked_sub(monotonic_time()) {
+                Some(d) if !d.is_zero() => Ok(Some(d)),
+                _ => Err(AxError::WouldBlock),
+            },
+            None => Ok(None),
+        }
+    }
+}
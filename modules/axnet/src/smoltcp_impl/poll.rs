@@ -0,0 +1,279 @@
+//! The network poll task, and blocking on socket events.
+//!
+//! The interfaces are polled by a dedicated task, which sleeps until it is
+//! kicked by a NIC interrupt or a socket operation, or until the next socket
+//! timer of any interface expires as reported by [`Interface::poll_delay`].
+//!
+//! Each socket has a [`SocketWaiter`], whose waker is registered in the
+//! smoltcp socket and woken when the socket becomes ready.
+//!
+//! [`Interface::poll_delay`]: smoltcp::iface::Interface::poll_delay
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::Waker;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use axdriver::DeviceIrq;
+use axerrno::{AxError, AxResult};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use kspin::SpinNoIrq;
+
+use super::options::Deadline;
+use super::{IFACES, SOCKET_SET, SocketHandle};
+
+/// The maximum interval between two polls of the interfaces, if we are not
+/// notified by interrupts when packets arrive.
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static POLL_WQ: WaitQueue = WaitQueue::new();
+static POLL_KICKED: AtomicBool = AtomicBool::new(false);
+/// Whether some NIC does not notify us by interrupts.
+static POLL_PERIODIC: AtomicBool = AtomicBool::new(false);
+/// The interrupts of the NICs with a registered interrupt handler.
+#[cfg(feature = "irq")]
+static NIC_IRQS: SpinNoIrq<Vec<DeviceIrq>> = SpinNoIrq::new(Vec::new());
+
+static WAITERS: Mutex<BTreeMap<SocketHandle, Arc<SocketWaiter>>> = Mutex::new(BTreeMap::new());
+
+/// Wakes up the poll task to poll the interfaces as soon as possible.
+pub(crate) fn kick() {
+    if !POLL_KICKED.swap(true, Ordering::AcqRel) {
+        POLL_WQ.notify_one(false);
+    }
+}
+
+fn is_kicked() -> bool {
+    POLL_KICKED.load(Ordering::Acquire)
+}
+
+fn poll_task() {
+    loop {
+        POLL_KICKED.store(false, Ordering::Release);
+        for iface in IFACES.iter() {
+            iface.poll();
+        }
+
+        // The NICs have been drained, unmask their interrupts.
+        #[cfg(feature = "irq")]
+        for dev_irq in NIC_IRQS.lock().iter() {
+            axhal::irq::set_enable(dev_irq.irq, true);
+        }
+
+        let mut delay = SOCKET_SET.poll_delay();
+        if POLL_PERIODIC.load(Ordering::Acquire) {
+            // Not notified when packets arrive, poll periodically.
+            delay = Some(delay.map_or(MAX_POLL_INTERVAL, |d| d.min(MAX_POLL_INTERVAL)));
+        }
+        match delay {
+            None => POLL_WQ.wait_until(is_kicked),
+            Some(d) if d.is_zero() => axtask::yield_now(),
+            #[cfg(feature = "irq")]
+            Some(d) => {
+                POLL_WQ.wait_timeout_until(d, is_kicked);
+            }
+            #[cfg(not(feature = "irq"))]
+            Some(_) => {
+                // No timer interrupts to wake us up, poll again after other
+                // tasks have run.
+                if !is_kicked() {
+                    axtask::yield_now();
+                }
+            }
+        }
+    }
+}
+
+/// The interrupt handler of all NICs.
+///
+/// The handler does not know which NIC raises the interrupt, and the poll task
+/// polls all interfaces anyway, so all NICs are acknowledged.
+#[cfg(feature = "irq")]
+fn nic_irq_handler() {
+    for dev_irq in NIC_IRQS.lock().iter() {
+        dev_irq.ack();
+        // Mask the interrupts until the poll task has drained the NICs.
+        axhal::irq::set_enable(dev_irq.irq, false);
+    }
+    kick();
+}
+
+/// Spawns the poll task, and registers the NIC interrupt handler for the NICs
+/// whose IRQ numbers are known.
+///
+/// `nic_irqs` are the interrupts of the NICs, in the order of the interfaces.
+pub(crate) fn init(nic_irqs: &[Option<DeviceIrq>]) {
+    #[cfg(feature = "irq")]
+    for (i, dev_irq) in nic_irqs.iter().enumerate() {
+        let Some(dev_irq) = *dev_irq else {
+            POLL_PERIODIC.store(true, Ordering::Release);
+            continue;
+        };
+        let irq = dev_irq.irq;
+        // Added before the handler is registered, so that it is acknowledged
+        // by the first interrupt.
+        let shared = {
+            let mut nic_irqs = NIC_IRQS.lock();
+            let shared = nic_irqs.iter().any(|other| other.irq == irq);
+            nic_irqs.push(dev_irq);
+            shared
+        };
+        if !shared && !axhal::irq::register(irq, nic_irq_handler) {
+            NIC_IRQS.lock().pop();
+            warn!("failed to register the NIC interrupt handler (irq {})", irq);
+            POLL_PERIODIC.store(true, Ordering::Release);
+            continue;
+        }
+        info!("{}: irq {}", IFACES[i].name(), irq);
+    }
+    #[cfg(not(feature = "irq"))]
+    {
+        let _ = nic_irqs;
+        POLL_PERIODIC.store(true, Ordering::Release);
+    }
+
+    let task = axtask::spawn(poll_task);
+    debug!("spawned network poll task {}", task.id_name());
+}
+
+/// Returns the waiter of the given socket, creates one if it does not exist.
+pub(crate) fn waiter(handle: SocketHandle) -> Arc<SocketWaiter> {
+    WAITERS
+        .lock()
+        .entry(handle)
+        .or_insert_with(SocketWaiter::new)
+        .clone()
+}
+
+/// Removes the waiter of the given socket when the socket is destroyed.
+pub(crate) fn remove_waiter(handle: SocketHandle) {
+    WAITERS.lock().remove(&handle);
+}
+
+/// Tasks waiting for events of a socket.
+///
+/// Blocked tasks sleep in its wait queue, and asynchronous tasks keep their
+/// wakers in it. All of them are woken up when the socket reports an event.
+pub(crate) struct SocketWaiter {
+    events: AtomicUsize,
+    wq: WaitQueue,
+    wakers: SpinNoIrq<Vec<Waker>>,
+}
+
+impl SocketWaiter {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            events: AtomicUsize::new(0),
+            wq: WaitQueue::new(),
+            wakers: SpinNoIrq::new(Vec::new()),
+        })
+    }
+
+    /// Returns the waker to be registered in the smoltcp socket.
+    pub fn waker(self: &Arc<Self>) -> Waker {
+        Waker::from(self.clone())
+    }
+
+    /// Saves `waker` to be woken up on the next socket event.
+    pub fn add_waker(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    /// Blocks the current task until `f` completes or fails.
+    ///
+    /// `f` is a nonblocking operation. While it returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock), `register` is called to
+    /// register the socket waker in the smoltcp socket, and the task sleeps
+    /// until the socket reports an event or the deadline passes.
+    pub fn block_on<F, R, T>(
+        self: &Arc<Self>,
+        mut f: F,
+        register: R,
+        deadline: Deadline,
+    ) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+        R: Fn(&Waker),
+    {
+        let waker = self.waker();
+        loop {
+            // Register before trying, so that an event after the try is not
+            // missed.
+            let events = self.events.load(Ordering::Acquire);
+            register(&waker);
+            let res = f();
+            // The operation may have queued packets to transmit.
+            kick();
+            match res {
+                Err(AxError::WouldBlock) => {}
+                res => return res,
+            }
+            let has_event = || self.events.load(Ordering::Acquire) != events;
+            match deadline.remaining()? {
+                None => self.wq.wait_until(has_event),
+                #[cfg(feature = "irq")]
+                Some(d) => {
+                    self.wq.wait_timeout_until(d, has_event);
+                }
+                #[cfg(not(feature = "irq"))]
+                Some(_) => {
+                    // No timer interrupts to wake us up, check the deadline
+                    // again after other tasks have run.
+                    if !has_event() {
+                        axtask::yield_now();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Polls `f` from an asynchronous context.
+    ///
+    /// If `f` returns [`Err(WouldBlock)`](AxError::WouldBlock), the waker of
+    /// `cx` is saved to be woken up on the next socket event, and
+    /// [`Poll::Pending`] is returned.
+    #[cfg(feature = "async")]
+    pub fn poll_io<F, R, T>(
+        self: &Arc<Self>,
+        cx: &mut Context<'_>,
+        f: F,
+        register: R,
+    ) -> Poll<AxResult<T>>
+    where
+        F: FnOnce() -> AxResult<T>,
+        R: FnOnce(&Waker),
+    {
+        self.add_waker(cx.waker());
+        register(&self.waker());
+        let res = f();
+        kick();
+        match res {
+            Err(AxError::WouldBlock) => Poll::Pending,
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+impl Wake for SocketWaiter {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.events.fetch_add(1, Ordering::AcqRel);
+        self.wq.notify_all(false);
+        let wakers = core::mem::take(&mut *self.wakers.lock());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
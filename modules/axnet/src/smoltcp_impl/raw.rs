@@ -0,0 +1,334 @@
+use alloc::vec::Vec;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "multitask")]
+use core::task::Waker;
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult, ax_err, ax_err_type};
+use axio::PollState;
+use spin::RwLock;
+
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::raw;
+use smoltcp::wire::{IPV6_HEADER_LEN, Icmpv6Packet, IpAddress, IpProtocol, IpRepr, IpVersion};
+
+use super::options::{Deadline, SocketOptions, check_timeout};
+#[cfg(feature = "multitask")]
+use super::poll;
+use super::{IFACES, SOCKET_SET, SocketHandle, SocketSetWrapper, route};
+
+/// The default hop limit of the outgoing packets.
+const DEFAULT_HOP_LIMIT: u8 = 64;
+/// The length of the ICMPv6 header before the message body, which has the
+/// checksum in it.
+const ICMPV6_HEADER_LEN: usize = 4;
+
+/// A raw IP socket that provides POSIX-like APIs.
+///
+/// Like the `SOCK_RAW` sockets of Linux, the socket receives copies of all
+/// IP packets of its protocol. A received IPv4 packet includes the IP header,
+/// while a received IPv6 packet does not. The IP header of the outgoing
+/// packets is always built by the network stack, and the ICMPv6 checksum is
+/// computed for the ICMPv6 sockets.
+pub struct RawSocket {
+    /// The smoltcp sockets in each interface, indexed by the interface.
+    handles: Vec<SocketHandle>,
+    /// The interface that the socket is bound to by
+    /// [`bind_to_device`](Self::bind_to_device).
+    device: RwLock<Option<usize>>,
+    ip_version: IpVersion,
+    protocol: IpProtocol,
+    hop_limit: RwLock<Option<u8>>,
+    opts: RwLock<SocketOptions>,
+    nonblock: AtomicBool,
+}
+
+impl RawSocket {
+    /// Creates a new raw socket of the IP protocol number `protocol`, for
+    /// IPv6 if `ipv6` is true, otherwise for IPv4.
+    pub fn new(ipv6: bool, protocol: u8) -> Self {
+        let ip_version = if ipv6 {
+            IpVersion::Ipv6
+        } else {
+            IpVersion::Ipv4
+        };
+        let protocol = IpProtocol::from(protocol);
+        let handles = (0..IFACES.len())
+            .map(|iface| {
+                SOCKET_SET.add(
+                    iface,
+                    SocketSetWrapper::new_raw_socket(ip_version, protocol),
+                )
+            })
+            .collect();
+        Self {
+            handles,
+            device: RwLock::new(None),
+            ip_version,
+            protocol,
+            hop_limit: RwLock::new(None),
+            opts: RwLock::new(SocketOptions::new()),
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    #[inline]
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this raw socket into or out of nonblocking mode.
+    ///
+    /// This will result in `recv_from` and `send_to` operations becoming
+    /// nonblocking, i.e., immediately returning from their calls. If the IO
+    /// operation could not be completed and needs to be retried, an error
+    /// with kind [`Err(WouldBlock)`](AxError::WouldBlock) is returned.
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Returns the timeout of blocking receive operations (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.opts.read().recv_timeout
+    }
+
+    /// Sets the timeout of blocking receive operations (`SO_RCVTIMEO`), or
+    /// no timeout if `timeout` is `None`.
+    ///
+    /// An operation that times out returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). A zero timeout is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().recv_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of blocking send operations (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.opts.read().send_timeout
+    }
+
+    /// Sets the timeout of blocking send operations (`SO_SNDTIMEO`), or no
+    /// timeout if `timeout` is `None`.
+    ///
+    /// An operation that times out returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). A zero timeout is invalid.
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().send_timeout = timeout;
+        Ok(())
+    }
+
+    /// Binds the socket to the network interface with the given name.
+    ///
+    /// Packets are only sent and received through the interface.
+    pub fn bind_to_device(&self, ifname: &str) -> AxResult {
+        let device = route::iface_by_name(ifname)
+            .ok_or_else(|| ax_err_type!(NotFound, "socket bind_to_device() failed"))?;
+        *self.device.write() = Some(device);
+        Ok(())
+    }
+
+    /// Sets the hop limit (TTL) of the outgoing packets, or the default of
+    /// the network stack if `hop_limit` is `None`.
+    pub fn set_hop_limit(&self, hop_limit: Option<u8>) -> AxResult {
+        if hop_limit == Some(0) {
+            return ax_err!(InvalidInput, "socket set_hop_limit() failed");
+        }
+        *self.hop_limit.write() = hop_limit;
+        Ok(())
+    }
+
+    /// Sends the payload of an IP packet to the given address. On success,
+    /// returns the number of bytes written.
+    pub fn send_to(&self, buf: &[u8], remote_addr: IpAddr) -> AxResult<usize> {
+        if remote_addr.is_unspecified() || self.ip_version != version_of(remote_addr) {
+            return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
+        }
+        if self.protocol == IpProtocol::Icmpv6 && buf.len() < ICMPV6_HEADER_LEN {
+            return ax_err!(InvalidInput, "socket send_to() failed: message too short");
+        }
+        self.block_on(self.send_timeout(), || {
+            self.try_send(buf, IpAddress::from(remote_addr))
+        })
+    }
+
+    /// Receives a single IP packet on the socket. On success, returns the
+    /// number of bytes read and the origin.
+    pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+        self.block_on(self.recv_timeout(), || self.try_recv(buf))
+    }
+
+    /// Whether the socket is readable or writable.
+    pub fn poll(&self) -> AxResult<PollState> {
+        let mut state = PollState {
+            readable: false,
+            writable: false,
+        };
+        for handle in self.active_handles(*self.device.read()) {
+            SOCKET_SET.with_socket::<raw::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
+            });
+        }
+        Ok(state)
+    }
+}
+
+/// Private methods
+impl RawSocket {
+    /// Returns the handles of the smoltcp sockets in use, which are all of
+    /// them unless the socket is bound to an interface.
+    fn active_handles(&self, device: Option<usize>) -> impl Iterator<Item = SocketHandle> + '_ {
+        self.handles
+            .iter()
+            .copied()
+            .filter(move |handle| device.is_none_or(|device| device == handle.iface))
+    }
+
+    /// Returns the waiter for events of all smoltcp sockets.
+    #[cfg(feature = "multitask")]
+    fn waiter(&self) -> alloc::sync::Arc<poll::SocketWaiter> {
+        poll::waiter(self.handles[0])
+    }
+
+    /// Builds the IP packet and sends it without blocking, returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock) if the tx buffer is full.
+    fn try_send(&self, buf: &[u8], remote_addr: IpAddress) -> AxResult<usize> {
+        let iface = route::egress(*self.device.read(), None, remote_addr)?;
+        let local_addr = IFACES[iface]
+            .iface
+            .lock()
+            .get_source_address(&remote_addr)
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send() failed"))?;
+        let hop_limit = self.hop_limit.read().unwrap_or(DEFAULT_HOP_LIMIT);
+        let ip_repr = IpRepr::new(local_addr, remote_addr, self.protocol, buf.len(), hop_limit);
+        let header_len = ip_repr.header_len();
+
+        SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(self.handles[iface], |socket| {
+            if !socket.can_send() {
+                // tx buffer is full
+                return Err(AxError::WouldBlock);
+            }
+            let packet = socket
+                .send(header_len + buf.len())
+                .map_err(|_| AxError::WouldBlock)?;
+            ip_repr.emit(&mut packet[..header_len], &ChecksumCapabilities::default());
+            packet[header_len..].copy_from_slice(buf);
+            if let (IpRepr::Ipv6(repr), IpProtocol::Icmpv6) = (&ip_repr, self.protocol) {
+                Icmpv6Packet::new_unchecked(&mut packet[header_len..])
+                    .fill_checksum(&repr.src_addr, &repr.dst_addr);
+            }
+            Ok(buf.len())
+        })
+    }
+
+    /// Receives a packet without blocking if there is one available,
+    /// otherwise returns [`Err(WouldBlock)`](AxError::WouldBlock).
+    fn try_recv(&self, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+        let ip_version = self.ip_version;
+        for handle in self.active_handles(*self.device.read()) {
+            let res = SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(handle, |socket| {
+                if !socket.can_recv() {
+                    return Err(AxError::WouldBlock);
+                }
+                // packet available
+                let packet = socket
+                    .recv()
+                    .map_err(|_| ax_err_type!(BadState, "socket recv_from() failed"))?;
+                let (src_addr, data) = match ip_version {
+                    IpVersion::Ipv4 => {
+                        let src: [u8; 4] = packet[12..16].try_into().unwrap();
+                        (IpAddr::from(Ipv4Addr::from(src)), packet)
+                    }
+                    IpVersion::Ipv6 => {
+                        let src: [u8; 16] = packet[8..24].try_into().unwrap();
+                        (
+                            IpAddr::from(Ipv6Addr::from(src)),
+                            &packet[IPV6_HEADER_LEN..],
+                        )
+                    }
+                };
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                Ok((len, src_addr))
+            });
+            match res {
+                Err(AxError::WouldBlock) => {}
+                res => return res,
+            }
+        }
+        // no more packets
+        Err(AxError::WouldBlock)
+    }
+
+    #[cfg(not(feature = "multitask"))]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        if self.is_nonblocking() {
+            f()
+        } else {
+            let deadline = Deadline::after(timeout);
+            loop {
+                SOCKET_SET.poll_interfaces();
+                match f() {
+                    Ok(t) => return Ok(t),
+                    Err(AxError::WouldBlock) => {
+                        deadline.remaining()?;
+                        axtask::yield_now()
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "multitask")]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        if self.is_nonblocking() {
+            let res = f();
+            poll::kick();
+            res
+        } else {
+            self.waiter().block_on(
+                f,
+                |waker| self.register_waker(waker),
+                Deadline::after(timeout),
+            )
+        }
+    }
+
+    /// Registers the waker of the socket waiter in the smoltcp sockets.
+    #[cfg(feature = "multitask")]
+    fn register_waker(&self, waker: &Waker) {
+        for handle in self.active_handles(*self.device.read()) {
+            SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            })
+        }
+    }
+}
+
+impl Drop for RawSocket {
+    fn drop(&mut self) {
+        for &handle in &self.handles {
+            SOCKET_SET.remove(handle);
+        }
+    }
+}
+
+fn version_of(addr: IpAddr) -> IpVersion {
+    match addr {
+        IpAddr::V4(_) => IpVersion::Ipv4,
+        IpAddr::V6(_) => IpVersion::Ipv6,
+    }
+}
@@ -0,0 +1,95 @@
+//! Choosing the interface to send packets through.
+//!
+//! The routing table is made up of the networks of the interface addresses
+//! and the routes of each interface. The most specific network that contains
+//! the destination decides the interface.
+
+use axerrno::{AxResult, ax_err_type};
+use smoltcp::iface::Interface;
+use smoltcp::wire::IpAddress;
+
+use super::{IFACES, InterfaceWrapper};
+
+impl InterfaceWrapper {
+    /// Returns the prefix length of the most specific network of this
+    /// interface that contains `dst`.
+    fn match_prefix_len(&self, dst: &IpAddress) -> Option<u8> {
+        let mut iface = self.iface.lock();
+        let mut best = on_link_prefix_len(&iface, dst);
+        let now = Self::current_time();
+        iface.routes_mut().update(|routes| {
+            for route in routes.iter() {
+                if route.cidr.contains_addr(dst) && route.expires_at.is_none_or(|t| now < t) {
+                    best = best.max(Some(route.cidr.prefix_len()));
+                }
+            }
+        });
+        best
+    }
+}
+
+/// Returns the prefix length of the most specific network of the interface
+/// addresses that contains `dst`.
+fn on_link_prefix_len(iface: &Interface, dst: &IpAddress) -> Option<u8> {
+    iface
+        .ip_addrs()
+        .iter()
+        .filter(|cidr| cidr.contains_addr(dst))
+        .map(|cidr| cidr.prefix_len())
+        .max()
+}
+
+/// Returns the index of the interface to send packets to `dst` through.
+///
+/// Ties go to the interface that was created first.
+pub(crate) fn lookup(dst: IpAddress) -> Option<usize> {
+    let mut best: Option<(usize, u8)> = None;
+    for (i, iface) in IFACES.iter().enumerate() {
+        let Some(len) = iface.match_prefix_len(&dst) else {
+            continue;
+        };
+        if best.is_none_or(|(_, best_len)| len > best_len) {
+            best = Some((i, len));
+        }
+    }
+    best.map(|(i, _)| i)
+}
+
+/// Returns the index of the interface that `addr` is directly reachable
+/// from, i.e., one of its addresses is in the same network.
+pub(crate) fn lookup_on_link(addr: IpAddress) -> Option<usize> {
+    IFACES
+        .iter()
+        .position(|iface| on_link_prefix_len(&iface.iface.lock(), &addr).is_some())
+}
+
+/// Returns the index of the interface that has the local address `addr`.
+pub(crate) fn iface_of_addr(addr: IpAddress) -> Option<usize> {
+    IFACES
+        .iter()
+        .position(|iface| iface.iface.lock().has_ip_addr(addr))
+}
+
+/// Returns the index of the interface with the given name.
+pub(crate) fn iface_by_name(name: &str) -> Option<usize> {
+    IFACES.iter().position(|iface| iface.name() == name)
+}
+
+/// Chooses the interface for a socket to send packets to `dst` through.
+///
+/// A socket bound to an interface (`device`) always uses it. Otherwise, a
+/// socket bound to a local address uses the interface with the address,
+/// and an unbound socket looks up the routing table.
+pub(crate) fn egress(
+    device: Option<usize>,
+    local_addr: Option<IpAddress>,
+    dst: IpAddress,
+) -> AxResult<usize> {
+    if let Some(iface) = device {
+        return Ok(iface);
+    }
+    if let Some(iface) = local_addr.and_then(iface_of_addr) {
+        return Ok(iface);
+    }
+    lookup(dst).ok_or_else(|| ax_err_type!(ConnectionRefused, "no route to host"))
+}
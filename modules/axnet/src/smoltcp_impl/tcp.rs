@@ -1,17 +1,34 @@
+#[cfg(feature = "multitask")]
+use alloc::sync::Arc;
 use core::cell::UnsafeCell;
 use core::net::SocketAddr;
-use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering};
+#[cfg(feature = "multitask")]
+use core::task::Waker;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+use core::time::Duration;
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
 use axsync::Mutex;
+use spin::RwLock;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::tcp::{self, ConnectError, State};
-use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
+use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
 use super::addr::UNSPECIFIED_ENDPOINT;
-use super::{ETH0, LISTEN_TABLE, SOCKET_SET, SocketSetWrapper};
+use super::options::{
+    CongestionControl, Deadline, SocketOptions, check_timeout, clamp_buf_len,
+    default_congestion_control,
+};
+#[cfg(feature = "multitask")]
+use super::poll::{self, SocketWaiter};
+use super::tcp_stats;
+use super::{
+    IFACES, LISTEN_TABLE, SOCKET_SET, SocketHandle, SocketSetWrapper, TCP_RX_BUF_LEN,
+    TCP_TX_BUF_LEN, route,
+};
 
 // State transitions:
 // CLOSED -(connect)-> BUSY -> CONNECTING -> CONNECTED -(shutdown)-> BUSY -> CLOSED
@@ -40,7 +57,55 @@ pub struct TcpSocket {
     handle: UnsafeCell<Option<SocketHandle>>,
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
+    /// The interface the socket is bound to by [`bind_to_device`](Self::bind_to_device).
+    device: UnsafeCell<Option<usize>>,
+    opts: RwLock<SocketOptions>,
+    /// Whether a connection started by [`connect`](Self::connect) failed and
+    /// the error has not been reported yet.
+    connect_failed: AtomicBool,
     nonblock: AtomicBool,
+    /// The number of bytes passed to the send buffer.
+    bytes_sent: AtomicU64,
+    /// The number of bytes taken from the receive buffer.
+    bytes_received: AtomicU64,
+}
+
+/// Statistics of a TCP connection, like `struct tcp_info` of Linux.
+///
+/// The round-trip time and the retransmissions are measured from the segments
+/// of the connection. The congestion window is internal to the congestion
+/// controller of smoltcp, so it is not reported.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpInfo {
+    /// The state of the connection.
+    pub state: State,
+    /// The congestion control algorithm in use.
+    pub congestion_control: CongestionControl,
+    /// The smoothed round-trip time, or `None` before it is sampled.
+    pub rtt: Option<Duration>,
+    /// The mean deviation of the round-trip time.
+    pub rtt_var: Duration,
+    /// The minimum round-trip time sampled.
+    pub min_rtt: Option<Duration>,
+    /// The number of retransmissions since the data was last acknowledged.
+    pub retransmits: u32,
+    /// The total number of retransmitted segments.
+    pub total_retrans: u32,
+    /// The number of segments sent, including retransmissions.
+    pub segs_out: u32,
+    /// The number of segments received.
+    pub segs_in: u32,
+    /// The number of bytes sent but not acknowledged yet.
+    pub bytes_in_flight: usize,
+    /// The number of bytes sent and acknowledged by the peer.
+    pub bytes_acked: u64,
+    /// The number of bytes received, including those not read yet.
+    pub bytes_received: u64,
+    /// The number of bytes in flight or not sent yet, i.e., sent by the
+    /// application but not acknowledged by the peer.
+    pub unacked_bytes: usize,
+    /// The number of bytes received but not read by the application yet.
+    pub unread_bytes: usize,
 }
 
 unsafe impl Sync for TcpSocket {}
@@ -53,22 +118,34 @@ impl TcpSocket {
             handle: UnsafeCell::new(None),
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
+            device: UnsafeCell::new(None),
+            opts: RwLock::new(SocketOptions::new()),
+            connect_failed: AtomicBool::new(false),
             nonblock: AtomicBool::new(false),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
         }
     }
 
-    /// Creates a new TCP socket that is already connected.
+    /// Creates a new TCP socket that is already connected, with the options
+    /// inherited from the listening socket.
     const fn new_connected(
         handle: SocketHandle,
         local_addr: IpEndpoint,
         peer_addr: IpEndpoint,
+        opts: SocketOptions,
     ) -> Self {
         Self {
             state: AtomicU8::new(STATE_CONNECTED),
             handle: UnsafeCell::new(Some(handle)),
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
+            device: UnsafeCell::new(Some(handle.iface)),
+            opts: RwLock::new(opts),
+            connect_failed: AtomicBool::new(false),
             nonblock: AtomicBool::new(false),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
         }
     }
 
@@ -118,17 +195,26 @@ impl TcpSocket {
     /// The local port is generated automatically.
     pub fn connect(&self, remote_addr: SocketAddr) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_CONNECTING, || {
+            let bound_endpoint = self.bound_endpoint()?;
             // SAFETY: no other threads can read or write these fields.
-            let handle = unsafe { self.handle.get().read() }
-                .unwrap_or_else(|| SOCKET_SET.add(SocketSetWrapper::new_tcp_socket()));
+            let handle = match unsafe { self.handle.get().read() } {
+                Some(handle) => handle,
+                None => {
+                    let device = unsafe { self.device.get().read() };
+                    let dst = IpAddress::from(remote_addr.ip());
+                    let iface = route::egress(device, bound_endpoint.addr, dst)?;
+                    let opts = self.opts.read();
+                    SOCKET_SET.add(iface, SocketSetWrapper::new_tcp_socket(&opts))
+                }
+            };
 
-            // TODO: check remote addr unreachable
-            let bound_endpoint = self.bound_endpoint()?;
-            let iface = &ETH0.iface;
+            // Lock the interface before the socket set, in the same order as
+            // polling the interface.
+            let mut iface = IFACES[handle.iface].iface.lock();
             let (local_endpoint, remote_endpoint) = SOCKET_SET
                 .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                     socket
-                        .connect(iface.lock().context(), remote_addr, bound_endpoint)
+                        .connect(iface.context(), remote_addr, bound_endpoint)
                         .or_else(|e| match e {
                             ConnectError::InvalidState => {
                                 ax_err!(BadState, "socket connect() failed")
@@ -142,6 +228,7 @@ impl TcpSocket {
                         socket.remote_endpoint().unwrap(),
                     ))
                 })?;
+            tcp_stats::register(handle, local_endpoint, remote_endpoint);
             unsafe {
                 // SAFETY: no other threads can read or write these fields as we
                 // have changed the state to `BUSY`.
@@ -155,18 +242,11 @@ impl TcpSocket {
 
         // Here our state must be `CONNECTING`, and only one thread can run here.
         if self.is_nonblocking() {
+            #[cfg(feature = "multitask")]
+            poll::kick(); // send the SYN packet
             Err(AxError::WouldBlock)
         } else {
-            self.block_on(|| {
-                let PollState { writable, .. } = self.poll_connect()?;
-                if !writable {
-                    Err(AxError::WouldBlock)
-                } else if self.get_state() == STATE_CONNECTED {
-                    Ok(())
-                } else {
-                    ax_err!(ConnectionRefused, "socket connect() failed")
-                }
-            })
+            self.block_on(self.send_timeout(), || self.connect_result())
         }
     }
 
@@ -196,17 +276,48 @@ impl TcpSocket {
         .unwrap_or_else(|_| ax_err!(InvalidInput, "socket bind() failed: already bound"))
     }
 
+    /// Binds the socket to the network interface with the given name.
+    ///
+    /// Packets of the socket are only sent and received through the
+    /// interface. It must be called before [`connect`](Self::connect) and
+    /// [`listen`](Self::listen).
+    pub fn bind_to_device(&self, ifname: &str) -> AxResult {
+        let device = route::iface_by_name(ifname)
+            .ok_or_else(|| ax_err_type!(NotFound, "socket bind_to_device() failed"))?;
+        self.update_state(STATE_CLOSED, STATE_CLOSED, || {
+            // SAFETY: no other threads can read or write `self.device` as we
+            // have changed the state to `BUSY`.
+            unsafe { self.device.get().write(Some(device)) };
+            Ok(())
+        })
+        .unwrap_or_else(|_| {
+            ax_err!(
+                InvalidInput,
+                "socket bind_to_device() failed: already connected"
+            )
+        })
+    }
+
     /// Starts listening on the bound address and port.
     ///
+    /// `backlog` is the maximum number of established connections waiting to
+    /// be accepted, new connections are refused when it is reached. It is
+    /// clamped to a supported range.
+    ///
     /// It's must be called after [`bind`](Self::bind) and before
     /// [`accept`](Self::accept).
-    pub fn listen(&self) -> AxResult {
+    pub fn listen(&self, backlog: usize) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_LISTENING, || {
             let bound_endpoint = self.bound_endpoint()?;
+            // A socket that is not bound accepts both address families.
+            let local_addr = unsafe { self.local_addr.get().read() };
+            let bound_addr = (local_addr != UNSPECIFIED_ENDPOINT).then_some(local_addr.addr);
             unsafe {
                 (*self.local_addr.get()).port = bound_endpoint.port;
             }
-            LISTEN_TABLE.listen(bound_endpoint)?;
+            let device = unsafe { self.device.get().read() };
+            let opts = *self.opts.read();
+            LISTEN_TABLE.listen(bound_endpoint, bound_addr, device, opts, backlog)?;
             debug!("TCP socket listening on {}", bound_endpoint);
             Ok(())
         })
@@ -226,26 +337,36 @@ impl TcpSocket {
 
         // SAFETY: `self.local_addr` should be initialized after `bind()`.
         let local_port = unsafe { self.local_addr.get().read().port };
-        self.block_on(|| {
-            let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
-            debug!("TCP socket accepted a new connection {}", peer_addr);
-            Ok(TcpSocket::new_connected(handle, local_addr, peer_addr))
-        })
+        self.block_on(self.recv_timeout(), || self.accept_impl(local_port))
     }
 
     /// Close the connection.
+    ///
+    /// If [`linger`](Self::set_linger) is set, a blocking socket waits for
+    /// the unsent data to be sent until the linger timeout, and a linger
+    /// timeout of zero resets the connection.
     pub fn shutdown(&self) -> AxResult {
         // stream
         self.update_state(STATE_CONNECTED, STATE_CLOSED, || {
             // SAFETY: `self.handle` should be initialized in a connected socket, and
             // no other threads can read or write it.
             let handle = unsafe { self.handle.get().read().unwrap() };
+            let linger = self.linger();
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 debug!("TCP socket {}: shutting down", handle);
-                socket.close();
+                if linger == Some(Duration::ZERO) {
+                    socket.abort();
+                } else {
+                    socket.close();
+                }
             });
             unsafe { self.local_addr.get().write(UNSPECIFIED_ENDPOINT) }; // clear bound address
             SOCKET_SET.poll_interfaces();
+            if let Some(timeout) = linger.filter(|t| !t.is_zero()) {
+                // The connection is closed anyway if the timeout expires.
+                self.block_on(Some(timeout), || Self::linger_impl(handle))
+                    .ok();
+            }
             Ok(())
         })
         .unwrap_or(Ok(()))?;
@@ -276,27 +397,8 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() {
-                    // not open
-                    ax_err!(ConnectionRefused, "socket recv() failed")
-                } else if !socket.may_recv() {
-                    // connection closed
-                    Ok(0)
-                } else if socket.recv_queue() > 0 {
-                    // data available
-                    // TODO: use socket.recv(|buf| {...})
-                    let len = socket
-                        .recv_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-                    Ok(len)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
-                }
-            })
-        })
+        self.block_on(self.recv_timeout(), || Self::recv_impl(handle, buf))
+            .inspect(|&len| self.count_received(len))
     }
 
     /// Transmits data in the given buffer.
@@ -309,24 +411,73 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() || !socket.may_send() {
-                    // closed by remote
-                    ax_err!(ConnectionReset, "socket send() failed")
-                } else if socket.can_send() {
-                    // connected, and the tx buffer is not full
-                    // TODO: use socket.send(|buf| {...})
-                    let len = socket
-                        .send_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket send() failed"))?;
-                    Ok(len)
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
-            })
-        })
+        self.block_on(self.send_timeout(), || Self::send_impl(handle, buf))
+            .inspect(|&len| self.count_sent(len))
+    }
+
+    /// Receives data by lending the receive buffer of the socket, without
+    /// copying it to a buffer of the caller.
+    ///
+    /// `f` is called once with the received data, which may be only a part of
+    /// it if the data wraps around the end of the ring buffer. It returns the
+    /// number of bytes consumed, which are removed from the buffer, and a
+    /// value passed to the caller. An empty slice is passed if the connection
+    /// has been closed by the peer. If `f` consumes more bytes than it is
+    /// lent, nothing is removed and
+    /// [`Err(InvalidInput)`](AxError::InvalidInput) is returned.
+    ///
+    /// `f` is called with the socket set locked, so it must not access any
+    /// socket.
+    pub fn recv_zc<F, R>(&self, f: F) -> AxResult<R>
+    where
+        F: FnOnce(&[u8]) -> (usize, R),
+    {
+        if self.is_connecting() {
+            return Err(AxError::WouldBlock);
+        } else if !self.is_connected() {
+            return ax_err!(NotConnected, "socket recv() failed");
+        }
+
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        let mut f = Some(|buf: &[u8]| {
+            let (len, res) = check_lent_len(buf.len(), f(buf));
+            self.count_received(len);
+            (len, res)
+        });
+        self.block_on(self.recv_timeout(), || Self::recv_zc_impl(handle, &mut f))?
+    }
+
+    /// Transmits data by lending the free space of the send buffer of the
+    /// socket, without copying it from a buffer of the caller.
+    ///
+    /// `f` is called once with the free space, which may be only a part of it
+    /// if it wraps around the end of the ring buffer. It fills the data to be
+    /// sent from the beginning, and returns its length and a value passed to
+    /// the caller. If the length is larger than the lent space, nothing is
+    /// sent and [`Err(InvalidInput)`](AxError::InvalidInput) is returned.
+    ///
+    /// `f` is called with the socket set locked, so it must not access any
+    /// socket.
+    pub fn send_zc<F, R>(&self, f: F) -> AxResult<R>
+    where
+        F: FnOnce(&mut [u8]) -> (usize, R),
+    {
+        if self.is_connecting() {
+            return Err(AxError::WouldBlock);
+        } else if !self.is_connected() {
+            return ax_err!(NotConnected, "socket send() failed");
+        }
+
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        let mut f = Some(|buf: &mut [u8]| {
+            let buf_len = buf.len();
+            let (len, res) = check_lent_len(buf_len, f(buf));
+            self.count_sent(len);
+            (len, res)
+        });
+        self.block_on(self.send_timeout(), || Self::send_zc_impl(handle, &mut f))?
     }
 
     /// Whether the socket is readable or writable.
@@ -342,25 +493,251 @@ impl TcpSocket {
         }
     }
 
-    /// Checks if Nagle's algorithm is enabled for this TCP socket.
-    pub fn nodelay(&self) -> AxResult<bool> {
-        if let Some(h) = unsafe { self.handle.get().read() } {
-            Ok(SOCKET_SET.with_socket::<tcp::Socket, _, _>(h, |socket| socket.nagle_enabled()))
-        } else {
-            ax_err!(NotConnected, "socket is not connected")
-        }
+    /// Registers `waker` to be woken up on the next event of the socket, which
+    /// may change the readiness returned by [`poll`](Self::poll).
+    ///
+    /// The waker is consumed by the event, so it is registered again before
+    /// each poll. Returns `false` if the socket is neither connected nor
+    /// listening, so it has no events to wait for.
+    #[cfg(feature = "multitask")]
+    pub fn register_poll_waker(&self, waker: &Waker) -> bool {
+        let Ok(waiter) = self.waiter() else {
+            return false;
+        };
+        waiter.add_waker(waker);
+        self.register_waker(&waiter.waker());
+        true
     }
 
-    /// Enables or disables Nagle's algorithm for this TCP socket.
-    pub fn set_nodelay(&self, enabled: bool) -> AxResult<()> {
-        if let Some(h) = unsafe { self.handle.get().read() } {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(h, |socket| {
-                socket.set_nagle_enabled(enabled);
+    /// Checks if Nagle's algorithm is disabled for this TCP socket
+    /// (`TCP_NODELAY`).
+    pub fn nodelay(&self) -> bool {
+        self.opts.read().nodelay
+    }
+
+    /// Disables or enables Nagle's algorithm for this TCP socket
+    /// (`TCP_NODELAY`).
+    ///
+    /// If `nodelay` is true, segments are sent as soon as possible, even if
+    /// there is only a small amount of data.
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.opts.write().nodelay = nodelay;
+        self.apply_opts();
+    }
+
+    /// Checks if TCP keep-alive probes are sent on this socket
+    /// (`SO_KEEPALIVE`).
+    pub fn keep_alive(&self) -> bool {
+        self.opts.read().keep_alive
+    }
+
+    /// Enables or disables sending TCP keep-alive probes on an idle
+    /// connection (`SO_KEEPALIVE`).
+    pub fn set_keep_alive(&self, keep_alive: bool) {
+        self.opts.write().keep_alive = keep_alive;
+        self.apply_opts();
+    }
+
+    /// Returns the congestion control algorithm of this socket
+    /// (`TCP_CONGESTION`).
+    pub fn congestion_control(&self) -> CongestionControl {
+        self.opts
+            .read()
+            .congestion_control
+            .unwrap_or_else(default_congestion_control)
+    }
+
+    /// Sets the congestion control algorithm of this socket
+    /// (`TCP_CONGESTION`), which takes effect immediately even if it is
+    /// connected.
+    pub fn set_congestion_control(&self, cc: CongestionControl) {
+        self.opts.write().congestion_control = Some(cc);
+        self.apply_opts();
+    }
+
+    /// Returns the statistics of the connection (`TCP_INFO`).
+    pub fn tcp_info(&self) -> TcpInfo {
+        let mut info = TcpInfo {
+            state: if self.is_listening() {
+                State::Listen
+            } else {
+                State::Closed
+            },
+            congestion_control: self.congestion_control(),
+            rtt: None,
+            rtt_var: Duration::ZERO,
+            min_rtt: None,
+            retransmits: 0,
+            total_retrans: 0,
+            segs_out: 0,
+            segs_in: 0,
+            bytes_in_flight: 0,
+            bytes_acked: 0,
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            unacked_bytes: 0,
+            unread_bytes: 0,
+        };
+        if let Some(handle) = unsafe { self.handle.get().read() } {
+            SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
+                info.state = socket.state();
+                info.unacked_bytes = socket.send_queue();
+                info.unread_bytes = socket.recv_queue();
             });
-            Ok(())
-        } else {
-            ax_err!(NotConnected, "socket is not connected")
+            if let Some(stats) = tcp_stats::get(handle) {
+                info.rtt = stats.srtt;
+                info.rtt_var = stats.rttvar;
+                info.min_rtt = stats.min_rtt;
+                info.retransmits = stats.retransmits;
+                info.total_retrans = stats.total_retrans;
+                info.segs_out = stats.segs_out;
+                info.segs_in = stats.segs_in;
+                info.bytes_in_flight = stats.bytes_in_flight();
+            }
+        }
+        // smoltcp removes the data from the send buffer exactly when it is
+        // acknowledged, so this is the exact number of bytes acknowledged.
+        info.bytes_acked = self
+            .bytes_sent
+            .load(Ordering::Relaxed)
+            .saturating_sub(info.unacked_bytes as u64);
+        info.bytes_received += info.unread_bytes as u64;
+        info
+    }
+
+    /// Returns whether the local address can be reused (`SO_REUSEADDR`).
+    pub fn reuse_address(&self) -> bool {
+        self.opts.read().reuse_addr
+    }
+
+    /// Sets whether the local address can be reused (`SO_REUSEADDR`).
+    ///
+    /// The option is only recorded, as the ports of closed connections are
+    /// not held in the `TIME-WAIT` state, and can always be reused.
+    pub fn set_reuse_address(&self, reuse_addr: bool) {
+        self.opts.write().reuse_addr = reuse_addr;
+    }
+
+    /// Returns whether the socket only accepts IPv6 connections when bound to
+    /// the unspecified IPv6 address (`IPV6_V6ONLY`).
+    pub fn only_v6(&self) -> bool {
+        self.opts.read().v6only
+    }
+
+    /// Sets whether the socket only accepts IPv6 connections when bound to
+    /// the unspecified IPv6 address (`IPV6_V6ONLY`).
+    ///
+    /// It must be called before [`listen`](Self::listen).
+    pub fn set_only_v6(&self, only_v6: bool) -> AxResult {
+        if self.get_state() != STATE_CLOSED {
+            return ax_err!(InvalidInput, "socket set_only_v6() failed");
         }
+        self.opts.write().v6only = only_v6;
+        Ok(())
+    }
+
+    /// Returns the linger timeout of [`shutdown`](Self::shutdown)
+    /// (`SO_LINGER`).
+    pub fn linger(&self) -> Option<Duration> {
+        self.opts.read().linger
+    }
+
+    /// Sets the linger timeout of [`shutdown`](Self::shutdown)
+    /// (`SO_LINGER`), or closes the connection in the background if
+    /// `linger` is `None`.
+    pub fn set_linger(&self, linger: Option<Duration>) {
+        self.opts.write().linger = linger;
+    }
+
+    /// Returns the timeout of blocking receive operations (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.opts.read().recv_timeout
+    }
+
+    /// Sets the timeout of blocking receive operations (`SO_RCVTIMEO`), or
+    /// no timeout if `timeout` is `None`.
+    ///
+    /// It also applies to [`accept`](Self::accept). An operation that times
+    /// out returns [`Err(WouldBlock)`](AxError::WouldBlock). A zero timeout
+    /// is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().recv_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of blocking send operations (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.opts.read().send_timeout
+    }
+
+    /// Sets the timeout of blocking send operations (`SO_SNDTIMEO`), or no
+    /// timeout if `timeout` is `None`.
+    ///
+    /// It also applies to [`connect`](Self::connect). An operation that
+    /// times out returns [`Err(WouldBlock)`](AxError::WouldBlock). A zero
+    /// timeout is invalid.
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().send_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the size of the receive buffer in bytes (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> usize {
+        self.recv_capacity()
+            .unwrap_or_else(|_| self.opts.read().recv_buf_len.unwrap_or(TCP_RX_BUF_LEN))
+    }
+
+    /// Sets the size of the receive buffer in bytes (`SO_RCVBUF`).
+    ///
+    /// The size is clamped to a supported range. As the buffers of a
+    /// connection cannot be resized, it must be called before
+    /// [`connect`](Self::connect) or [`listen`](Self::listen).
+    pub fn set_recv_buffer_size(&self, size: usize) -> AxResult {
+        self.update_state(STATE_CLOSED, STATE_CLOSED, || {
+            self.check_unconnected()?;
+            self.opts.write().recv_buf_len = Some(clamp_buf_len(size));
+            Ok(())
+        })
+        .unwrap_or_else(|_| {
+            ax_err!(
+                InvalidInput,
+                "socket setsockopt() failed: already connected"
+            )
+        })
+    }
+
+    /// Returns the size of the send buffer in bytes (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> usize {
+        self.send_capacity()
+            .unwrap_or_else(|_| self.opts.read().send_buf_len.unwrap_or(TCP_TX_BUF_LEN))
+    }
+
+    /// Sets the size of the send buffer in bytes (`SO_SNDBUF`).
+    ///
+    /// The size is clamped to a supported range. As the buffers of a
+    /// connection cannot be resized, it must be called before
+    /// [`connect`](Self::connect) or [`listen`](Self::listen).
+    pub fn set_send_buffer_size(&self, size: usize) -> AxResult {
+        self.update_state(STATE_CLOSED, STATE_CLOSED, || {
+            self.check_unconnected()?;
+            self.opts.write().send_buf_len = Some(clamp_buf_len(size));
+            Ok(())
+        })
+        .unwrap_or_else(|_| {
+            ax_err!(
+                InvalidInput,
+                "socket setsockopt() failed: already connected"
+            )
+        })
+    }
+
+    /// Returns the error of a failed nonblocking [`connect`](Self::connect)
+    /// and clears it (`SO_ERROR`).
+    pub fn take_error(&self) -> Option<AxError> {
+        self.connect_failed
+            .swap(false, Ordering::AcqRel)
+            .then_some(AxError::ConnectionRefused)
     }
 
     /// Returns the maximum capacity of the receive buffer in bytes.
@@ -438,6 +815,168 @@ impl TcpSocket {
         self.get_state() == STATE_LISTENING
     }
 
+    /// Checks that the socket has never been connected, so that its buffers
+    /// are not created yet.
+    fn check_unconnected(&self) -> AxResult {
+        // SAFETY: no other threads can read or write `self.handle` as we have
+        // changed the state to `BUSY`.
+        if unsafe { self.handle.get().read() }.is_some() {
+            return ax_err!(
+                InvalidInput,
+                "socket setsockopt() failed: already connected"
+            );
+        }
+        Ok(())
+    }
+
+    fn count_sent(&self, len: usize) {
+        self.bytes_sent.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    fn count_received(&self, len: usize) {
+        self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// Applies the options kept by the smoltcp socket, if it has been created.
+    fn apply_opts(&self) {
+        if let Some(handle) = unsafe { self.handle.get().read() } {
+            let opts = *self.opts.read();
+            SOCKET_SET
+                .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| opts.apply_tcp(socket));
+        }
+    }
+
+    /// Checks whether the connection started by [`connect`](Self::connect)
+    /// is established, returns [`Err(WouldBlock)`](AxError::WouldBlock) if it
+    /// is still in progress.
+    fn connect_result(&self) -> AxResult {
+        if self.is_connected() {
+            return Ok(());
+        } else if !self.is_connecting() {
+            self.take_error();
+            return ax_err!(ConnectionRefused, "socket connect() failed");
+        }
+        let PollState { writable, .. } = self.poll_connect()?;
+        if !writable {
+            Err(AxError::WouldBlock)
+        } else if self.get_state() == STATE_CONNECTED {
+            Ok(())
+        } else {
+            // The error is reported here, not by `take_error`.
+            self.take_error();
+            ax_err!(ConnectionRefused, "socket connect() failed")
+        }
+    }
+
+    fn accept_impl(&self, local_port: u16) -> AxResult<TcpSocket> {
+        let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
+        debug!("TCP socket accepted a new connection {}", peer_addr);
+        tcp_stats::register(handle, local_addr, peer_addr);
+        let socket = TcpSocket::new_connected(handle, local_addr, peer_addr, *self.opts.read());
+        socket.apply_opts();
+        Ok(socket)
+    }
+
+    /// Checks whether all data sent before closing has been acknowledged,
+    /// returns [`Err(WouldBlock)`](AxError::WouldBlock) if not.
+    fn linger_impl(handle: SocketHandle) -> AxResult {
+        SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| match socket.state() {
+            State::FinWait2 | State::TimeWait | State::Closed => Ok(()),
+            _ => Err(AxError::WouldBlock),
+        })
+    }
+
+    fn recv_impl(handle: SocketHandle, buf: &mut [u8]) -> AxResult<usize> {
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            if !socket.is_active() {
+                // not open
+                ax_err!(ConnectionRefused, "socket recv() failed")
+            } else if !socket.may_recv() {
+                // connection closed
+                Ok(0)
+            } else if socket.recv_queue() > 0 {
+                // data available
+                // TODO: use socket.recv(|buf| {...})
+                let len = socket
+                    .recv_slice(buf)
+                    .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
+                Ok(len)
+            } else {
+                // no more data
+                Err(AxError::WouldBlock)
+            }
+        })
+    }
+
+    fn send_impl(handle: SocketHandle, buf: &[u8]) -> AxResult<usize> {
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            if !socket.is_active() || !socket.may_send() {
+                // closed by remote
+                ax_err!(ConnectionReset, "socket send() failed")
+            } else if socket.can_send() {
+                // connected, and the tx buffer is not full
+                // TODO: use socket.send(|buf| {...})
+                let len = socket
+                    .send_slice(buf)
+                    .map_err(|_| ax_err_type!(BadState, "socket send() failed"))?;
+                Ok(len)
+            } else {
+                // tx buffer is full
+                Err(AxError::WouldBlock)
+            }
+        })
+    }
+
+    /// Like [`recv_impl`](Self::recv_impl), but lends the receive buffer to
+    /// `f`, which is taken once there is data or the connection is closed.
+    fn recv_zc_impl<F, R>(handle: SocketHandle, f: &mut Option<F>) -> AxResult<R>
+    where
+        F: FnOnce(&[u8]) -> (usize, R),
+    {
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            if !socket.is_active() {
+                // not open
+                ax_err!(ConnectionRefused, "socket recv() failed")
+            } else if !socket.may_recv() {
+                // connection closed
+                let f = f.take().unwrap();
+                Ok(f(&[]).1)
+            } else if socket.recv_queue() > 0 {
+                // data available
+                let f = f.take().unwrap();
+                socket
+                    .recv(|buf| f(buf))
+                    .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))
+            } else {
+                // no more data
+                Err(AxError::WouldBlock)
+            }
+        })
+    }
+
+    /// Like [`send_impl`](Self::send_impl), but lends the send buffer to `f`,
+    /// which is taken once there is free space.
+    fn send_zc_impl<F, R>(handle: SocketHandle, f: &mut Option<F>) -> AxResult<R>
+    where
+        F: FnOnce(&mut [u8]) -> (usize, R),
+    {
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            if !socket.is_active() || !socket.may_send() {
+                // closed by remote
+                ax_err!(ConnectionReset, "socket send() failed")
+            } else if socket.can_send() {
+                // connected, and the tx buffer is not full
+                let f = f.take().unwrap();
+                socket
+                    .send(f)
+                    .map_err(|_| ax_err_type!(BadState, "socket send() failed"))
+            } else {
+                // tx buffer is full
+                Err(AxError::WouldBlock)
+            }
+        })
+    }
+
     fn bound_endpoint(&self) -> AxResult<IpListenEndpoint> {
         // SAFETY: no other threads can read or write `self.local_addr`.
         let local_addr = unsafe { self.local_addr.get().read() };
@@ -475,6 +1014,7 @@ impl TcpSocket {
                         self.local_addr.get().write(UNSPECIFIED_ENDPOINT);
                         self.peer_addr.get().write(UNSPECIFIED_ENDPOINT);
                     }
+                    self.connect_failed.store(true, Ordering::Release);
                     self.set_state(STATE_CLOSED); // connection failed
                     true
                 }
@@ -509,24 +1049,167 @@ impl TcpSocket {
     ///
     /// If the socket is non-blocking, it calls the function once and returns
     /// immediately. Otherwise, it may call the function multiple times if it
-    /// returns [`Err(WouldBlock)`](AxError::WouldBlock).
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    /// returns [`Err(WouldBlock)`](AxError::WouldBlock), until the timeout
+    /// expires.
+    #[cfg(not(feature = "multitask"))]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
         if self.is_nonblocking() {
             f()
         } else {
+            let deadline = Deadline::after(timeout);
             loop {
                 SOCKET_SET.poll_interfaces();
                 match f() {
                     Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
+                    Err(AxError::WouldBlock) => {
+                        deadline.remaining()?;
+                        axtask::yield_now()
+                    }
                     Err(e) => return Err(e),
                 }
             }
         }
     }
+
+    /// Block the current thread until the given function completes or fails.
+    ///
+    /// If the socket is non-blocking, it calls the function once and returns
+    /// immediately. Otherwise, the thread sleeps in the wait queue of the
+    /// socket while the function returns [`Err(WouldBlock)`](AxError::WouldBlock),
+    /// until the timeout expires.
+    #[cfg(feature = "multitask")]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        if self.is_nonblocking() {
+            let res = f();
+            poll::kick();
+            res
+        } else {
+            self.waiter()?.block_on(
+                f,
+                |waker| self.register_waker(waker),
+                Deadline::after(timeout),
+            )
+        }
+    }
+
+    /// Returns the waiter for events of this socket.
+    ///
+    /// A listening socket has no smoltcp socket, it waits for events of the
+    /// sockets in the SYN queue.
+    #[cfg(feature = "multitask")]
+    fn waiter(&self) -> AxResult<Arc<SocketWaiter>> {
+        if self.is_listening() {
+            // SAFETY: `self.local_addr` should be initialized in a listening socket.
+            let local_port = unsafe { self.local_addr.get().read().port };
+            LISTEN_TABLE.waiter(local_port)
+        } else if let Some(handle) = unsafe { self.handle.get().read() } {
+            Ok(poll::waiter(handle))
+        } else {
+            ax_err!(NotConnected, "socket is not connected")
+        }
+    }
+
+    /// Registers the waker of the socket waiter in the smoltcp socket.
+    ///
+    /// For a listening socket, it is registered in the sockets in the SYN
+    /// queue.
+    #[cfg(feature = "multitask")]
+    fn register_waker(&self, waker: &Waker) {
+        if self.is_listening() {
+            // SAFETY: `self.local_addr` should be initialized in a listening socket.
+            let local_port = unsafe { self.local_addr.get().read().port };
+            LISTEN_TABLE.register_waker(local_port, waker);
+        } else if let Some(handle) = unsafe { self.handle.get().read() } {
+            register_waker(handle, waker);
+        }
+    }
+}
+
+/// Asynchronous methods
+#[cfg(feature = "async")]
+impl TcpSocket {
+    /// Polls whether the connection started by [`connect`](Self::connect) in
+    /// nonblocking mode is established.
+    ///
+    /// If the connection is still in progress, the waker of `cx` is
+    /// registered and [`Poll::Pending`] is returned.
+    pub fn poll_connected(&self, cx: &mut Context<'_>) -> Poll<AxResult> {
+        let Some(handle) = (unsafe { self.handle.get().read() }) else {
+            return Poll::Ready(ax_err!(NotConnected, "socket is not connected"));
+        };
+        poll::waiter(handle).poll_io(
+            cx,
+            || self.connect_result(),
+            |waker| register_waker(handle, waker),
+        )
+    }
+
+    /// Polls for a new connection on a listening socket.
+    ///
+    /// If no connection is established, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<AxResult<TcpSocket>> {
+        if !self.is_listening() {
+            return Poll::Ready(ax_err!(InvalidInput, "socket accept() failed: not listen"));
+        }
+
+        // SAFETY: `self.local_addr` should be initialized after `bind()`.
+        let local_port = unsafe { self.local_addr.get().read().port };
+        match LISTEN_TABLE.waiter(local_port) {
+            Ok(waiter) => waiter.poll_io(
+                cx,
+                || self.accept_impl(local_port),
+                |waker| LISTEN_TABLE.register_waker(local_port, waker),
+            ),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Polls to receive data from the socket into the given buffer.
+    ///
+    /// If no data is available, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<AxResult<usize>> {
+        if !self.is_connected() {
+            return Poll::Ready(ax_err!(NotConnected, "socket recv() failed"));
+        }
+
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        poll::waiter(handle)
+            .poll_io(
+                cx,
+                || Self::recv_impl(handle, buf),
+                |waker| register_waker(handle, waker),
+            )
+            .map(|res| res.inspect(|&len| self.count_received(len)))
+    }
+
+    /// Polls to transmit data in the given buffer.
+    ///
+    /// If the transmit buffer is full, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>> {
+        if !self.is_connected() {
+            return Poll::Ready(ax_err!(NotConnected, "socket send() failed"));
+        }
+
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        poll::waiter(handle)
+            .poll_io(
+                cx,
+                || Self::send_impl(handle, buf),
+                |waker| register_waker(handle, waker),
+            )
+            .map(|res| res.inspect(|&len| self.count_sent(len)))
+    }
 }
 
 impl Drop for TcpSocket {
@@ -534,6 +1217,7 @@ impl Drop for TcpSocket {
         self.shutdown().ok();
         // Safe because we have mut reference to `self`.
         if let Some(handle) = unsafe { self.handle.get().read() } {
+            tcp_stats::unregister(handle);
             SOCKET_SET.remove(handle);
         }
     }
@@ -561,3 +1245,88 @@ fn get_ephemeral_port() -> AxResult<u16> {
     }
     ax_err!(AddrInUse, "no avaliable ports!")
 }
+
+/// Checks the number of bytes that a zero-copy operation consumed from, or
+/// filled in, a lent buffer of `buf_len` bytes, as smoltcp panics if it is
+/// larger. Returns the number of bytes to remove or send, and the result.
+fn check_lent_len<R>(buf_len: usize, (len, res): (usize, R)) -> (usize, AxResult<R>) {
+    if len > buf_len {
+        (
+            0,
+            ax_err!(InvalidInput, "socket zero-copy length exceeds the buffer"),
+        )
+    } else {
+        (len, Ok(res))
+    }
+}
+
+#[cfg(feature = "multitask")]
+fn register_waker(handle: SocketHandle, waker: &Waker) {
+    SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+        socket.register_recv_waker(waker);
+        socket.register_send_waker(waker);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use smoltcp::iface::{Config, Interface, SocketSet};
+    use smoltcp::phy::{Loopback, Medium};
+    use smoltcp::time::Instant;
+    use smoltcp::wire::{HardwareAddress, IpCidr};
+
+    use super::*;
+
+    fn new_socket() -> tcp::Socket<'static> {
+        tcp::Socket::new(
+            tcp::SocketBuffer::new(vec![0; 64]),
+            tcp::SocketBuffer::new(vec![0; 64]),
+        )
+    }
+
+    #[test]
+    fn check_zero_copy_len() {
+        let localhost = IpAddress::v4(127, 0, 0, 1);
+        let mut device = Loopback::new(Medium::Ip);
+        let config = Config::new(HardwareAddress::Ip);
+        let mut iface = Interface::new(config, &mut device, Instant::ZERO);
+        iface.update_ip_addrs(|addrs| addrs.push(IpCidr::new(localhost, 8)).unwrap());
+        let mut sockets = SocketSet::new(vec![]);
+        let server = sockets.add(new_socket());
+        let client = sockets.add(new_socket());
+        sockets.get_mut::<tcp::Socket>(server).listen(80).unwrap();
+        sockets
+            .get_mut::<tcp::Socket>(client)
+            .connect(iface.context(), (localhost, 80), 49152)
+            .unwrap();
+        let mut poll = |sockets: &mut SocketSet<'static>| {
+            for _ in 0..4 {
+                iface.poll(Instant::ZERO, &mut device, sockets);
+            }
+        };
+        poll(&mut sockets);
+        assert!(sockets.get::<tcp::Socket>(client).may_send());
+
+        // Filling more than the lent space sends nothing.
+        let socket = sockets.get_mut::<tcp::Socket>(client);
+        let res = socket.send(|buf| check_lent_len(buf.len(), (buf.len() + 1, ())));
+        assert_eq!(res.unwrap(), Err(AxError::InvalidInput));
+        assert_eq!(socket.send_queue(), 0);
+        let res = socket.send(|buf| {
+            buf[..5].copy_from_slice(b"hello");
+            check_lent_len(buf.len(), (5, ()))
+        });
+        assert_eq!(res.unwrap(), Ok(()));
+        poll(&mut sockets);
+
+        // Consuming more than the lent data removes nothing.
+        let socket = sockets.get_mut::<tcp::Socket>(server);
+        let res = socket.recv(|buf| check_lent_len(buf.len(), (buf.len() + 1, ())));
+        assert_eq!(res.unwrap(), Err(AxError::InvalidInput));
+        assert_eq!(socket.recv_queue(), 5);
+        let res = socket.recv(|buf| check_lent_len(buf.len(), (buf.len(), buf.to_vec())));
+        assert_eq!(res.unwrap().unwrap(), b"hello");
+        assert_eq!(socket.recv_queue(), 0);
+    }
+}
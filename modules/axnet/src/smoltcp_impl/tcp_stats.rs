@@ -0,0 +1,158 @@
+//! Statistics of TCP connections measured from the segments they exchange.
+//!
+//! The round-trip time estimator and the retransmission state of a smoltcp
+//! socket are private, so they are measured again here, by inspecting the TCP
+//! segments of the registered connections as the interfaces send and receive
+//! them.
+
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+
+use axhal::time::monotonic_time;
+use kspin::SpinNoIrq;
+use smoltcp::wire::{IpEndpoint, TcpPacket, TcpSeqNumber};
+
+use super::SocketHandle;
+
+/// The registered connections by their local and remote endpoints, with the
+/// handles of their sockets and their statistics.
+static CONNECTIONS: SpinNoIrq<BTreeMap<(IpEndpoint, IpEndpoint), (SocketHandle, ConnStats)>> =
+    SpinNoIrq::new(BTreeMap::new());
+
+/// The statistics of a TCP connection.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConnStats {
+    /// The smoothed round-trip time, or `None` before the first sample.
+    pub srtt: Option<Duration>,
+    /// The mean deviation of the round-trip time.
+    pub rttvar: Duration,
+    /// The minimum round-trip time sampled.
+    pub min_rtt: Option<Duration>,
+    /// The number of retransmissions since the data was last acknowledged.
+    pub retransmits: u32,
+    /// The total number of retransmitted segments.
+    pub total_retrans: u32,
+    /// The number of segments sent, including retransmissions.
+    pub segs_out: u32,
+    /// The number of segments received.
+    pub segs_in: u32,
+    /// The oldest sequence number not acknowledged yet.
+    snd_una: Option<TcpSeqNumber>,
+    /// The sequence number after the last one sent.
+    snd_max: Option<TcpSeqNumber>,
+    /// The end of the segment being timed and when it was sent.
+    rtt_sample: Option<(TcpSeqNumber, Duration)>,
+}
+
+impl ConnStats {
+    /// The number of bytes sent but not acknowledged yet.
+    pub fn bytes_in_flight(&self) -> usize {
+        match (self.snd_una, self.snd_max) {
+            (Some(una), Some(max)) if una < max => max - una,
+            _ => 0,
+        }
+    }
+
+    fn on_send(&mut self, seq: TcpSeqNumber, len: usize, now: Duration) {
+        self.segs_out = self.segs_out.wrapping_add(1);
+        if len == 0 {
+            return;
+        }
+        let end = seq + len;
+        match self.snd_max {
+            Some(max) if seq < max => {
+                self.retransmits += 1;
+                self.total_retrans += 1;
+                // Karn's algorithm: the ACK of a retransmitted segment is
+                // ambiguous, so it is not timed.
+                if self
+                    .rtt_sample
+                    .is_some_and(|(sample_end, _)| seq < sample_end)
+                {
+                    self.rtt_sample = None;
+                }
+                if end > max {
+                    self.snd_max = Some(end);
+                }
+            }
+            _ => {
+                self.snd_max = Some(end);
+                self.snd_una.get_or_insert(seq);
+                self.rtt_sample.get_or_insert((end, now));
+            }
+        }
+    }
+
+    fn on_ack(&mut self, ack: TcpSeqNumber, now: Duration) {
+        if self.snd_una.is_none_or(|una| ack > una) && self.snd_max.is_some_and(|max| ack <= max) {
+            self.snd_una = Some(ack);
+            self.retransmits = 0;
+        }
+        if let Some((end, sent_at)) = self.rtt_sample
+            && ack >= end
+        {
+            self.rtt_sample = None;
+            self.update_rtt(now.saturating_sub(sent_at));
+        }
+    }
+
+    /// Updates the estimates with a round-trip time sample (RFC 6298).
+    fn update_rtt(&mut self, rtt: Duration) {
+        self.min_rtt = Some(self.min_rtt.map_or(rtt, |min| min.min(rtt)));
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            }
+            Some(srtt) => {
+                let delta = srtt.abs_diff(rtt);
+                self.rttvar = (self.rttvar * 3 + delta) / 4;
+                self.srtt = Some((srtt * 7 + rtt) / 8);
+            }
+        }
+    }
+}
+
+/// Starts measuring the connection of the socket `handle` between `local` and
+/// `remote`, replacing its previous connection if any.
+pub fn register(handle: SocketHandle, local: IpEndpoint, remote: IpEndpoint) {
+    let mut connections = CONNECTIONS.lock();
+    connections.retain(|_, (h, _)| *h != handle);
+    connections.insert((local, remote), (handle, ConnStats::default()));
+}
+
+/// Stops measuring the connection of the socket `handle`.
+pub fn unregister(handle: SocketHandle) {
+    CONNECTIONS.lock().retain(|_, (h, _)| *h != handle);
+}
+
+/// Returns the statistics of the connection of the socket `handle`.
+pub fn get(handle: SocketHandle) -> Option<ConnStats> {
+    CONNECTIONS
+        .lock()
+        .values()
+        .find(|(h, _)| *h == handle)
+        .map(|&(_, stats)| stats)
+}
+
+/// Accounts a TCP segment from `src` to `dst`, which is sent by this host if
+/// `outgoing` is true, or received otherwise.
+pub fn snoop_segment(packet: &TcpPacket<&[u8]>, src: IpEndpoint, dst: IpEndpoint, outgoing: bool) {
+    let mut connections = CONNECTIONS.lock();
+    if connections.is_empty() {
+        return;
+    }
+    let key = if outgoing { (src, dst) } else { (dst, src) };
+    let Some((_, stats)) = connections.get_mut(&key) else {
+        return;
+    };
+    let now = monotonic_time();
+    if outgoing {
+        stats.on_send(packet.seq_number(), packet.segment_len(), now);
+    } else {
+        stats.segs_in = stats.segs_in.wrapping_add(1);
+        if packet.ack() {
+            stats.on_ack(packet.ack_number(), now);
+        }
+    }
+}
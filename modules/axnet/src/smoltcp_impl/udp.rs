@@ -1,37 +1,71 @@
-use core::net::SocketAddr;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "multitask")]
+use core::task::Waker;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+use core::time::Duration;
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
 use axsync::Mutex;
-use spin::RwLock;
+use lazyinit::LazyInit;
+use spin::{Once, RwLock};
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::udp::{self, BindError, SendError};
-use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
+use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::addr::UNSPECIFIED_ENDPOINT;
-use super::{SOCKET_SET, SocketSetWrapper};
+use super::addr::family_accepts;
+use super::lend::LendQueue;
+use super::options::{Deadline, SocketOptions, check_timeout, clamp_buf_len};
+#[cfg(feature = "multitask")]
+use super::poll;
+use super::{
+    IFACES, SOCKET_SET, SocketHandle, SocketSetWrapper, UDP_RX_BUF_LEN, UDP_TX_BUF_LEN, route,
+};
 
 /// A UDP socket that provides POSIX-like APIs.
 pub struct UdpSocket {
-    handle: SocketHandle,
+    /// The smoltcp sockets, created when the socket is bound: one in the
+    /// interface it is bound to, or one in each interface.
+    handles: LazyInit<Vec<SocketHandle>>,
+    /// The interface that the socket is bound to, either by
+    /// [`bind_to_device`](Self::bind_to_device) or by binding to an address
+    /// of the interface.
+    device: RwLock<Option<usize>>,
     local_addr: RwLock<Option<IpEndpoint>>,
     peer_addr: RwLock<Option<IpEndpoint>>,
+    opts: RwLock<SocketOptions>,
     nonblock: AtomicBool,
+    /// The datagrams lent from the RX buffers of NIC drivers, once
+    /// [`recv_zc`](Self::recv_zc) is called.
+    lend: Once<Arc<LendQueue>>,
+}
+
+/// The next datagram to receive.
+enum Datagram<'a> {
+    /// A datagram lent from an RX buffer of a NIC driver, with its origin.
+    Lent(&'a [u8], IpEndpoint),
+    /// The receive queue of a smoltcp socket, which is not empty.
+    Queued(&'a mut udp::Socket<'static>),
 }
 
 impl UdpSocket {
     /// Creates a new UDP socket.
+    ///
+    /// The buffers are not allocated until the socket is bound.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let socket = SocketSetWrapper::new_udp_socket();
-        let handle = SOCKET_SET.add(socket);
         Self {
-            handle,
+            handles: LazyInit::new(),
+            device: RwLock::new(None),
             local_addr: RwLock::new(None),
             peer_addr: RwLock::new(None),
+            opts: RwLock::new(SocketOptions::new()),
             nonblock: AtomicBool::new(false),
+            lend: Once::new(),
         }
     }
 
@@ -69,12 +103,121 @@ impl UdpSocket {
         self.nonblock.store(nonblocking, Ordering::Release);
     }
 
+    /// Returns whether the local address can be reused (`SO_REUSEADDR`).
+    pub fn reuse_address(&self) -> bool {
+        self.opts.read().reuse_addr
+    }
+
+    /// Sets whether the local address can be reused (`SO_REUSEADDR`).
+    ///
+    /// The option is only recorded, as binding to an address in use is
+    /// always refused for UDP sockets.
+    pub fn set_reuse_address(&self, reuse_addr: bool) {
+        self.opts.write().reuse_addr = reuse_addr;
+    }
+
+    /// Returns whether the socket only receives IPv6 datagrams when bound to
+    /// the unspecified IPv6 address (`IPV6_V6ONLY`).
+    pub fn only_v6(&self) -> bool {
+        self.opts.read().v6only
+    }
+
+    /// Sets whether the socket only receives IPv6 datagrams when bound to the
+    /// unspecified IPv6 address (`IPV6_V6ONLY`).
+    ///
+    /// It must be called before [`bind`](Self::bind).
+    pub fn set_only_v6(&self, only_v6: bool) -> AxResult {
+        if self.local_addr.read().is_some() {
+            return ax_err!(InvalidInput, "socket set_only_v6() failed: already bound");
+        }
+        self.opts.write().v6only = only_v6;
+        Ok(())
+    }
+
+    /// Returns the size of the receive buffer in bytes (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> usize {
+        self.opts.read().recv_buf_len.unwrap_or(UDP_RX_BUF_LEN)
+    }
+
+    /// Sets the size of the receive buffer in bytes (`SO_RCVBUF`).
+    ///
+    /// The size is clamped to a supported range. The datagrams queued in the
+    /// buffers are dropped.
+    pub fn set_recv_buffer_size(&self, size: usize) -> AxResult {
+        self.opts.write().recv_buf_len = Some(clamp_buf_len(size));
+        self.rebuild_sockets()
+    }
+
+    /// Returns the size of the send buffer in bytes (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> usize {
+        self.opts.read().send_buf_len.unwrap_or(UDP_TX_BUF_LEN)
+    }
+
+    /// Sets the size of the send buffer in bytes (`SO_SNDBUF`).
+    ///
+    /// The size is clamped to a supported range. The datagrams queued in the
+    /// buffers are dropped.
+    pub fn set_send_buffer_size(&self, size: usize) -> AxResult {
+        self.opts.write().send_buf_len = Some(clamp_buf_len(size));
+        self.rebuild_sockets()
+    }
+
+    /// Returns the timeout of blocking receive operations (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.opts.read().recv_timeout
+    }
+
+    /// Sets the timeout of blocking receive operations (`SO_RCVTIMEO`), or
+    /// no timeout if `timeout` is `None`.
+    ///
+    /// An operation that times out returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). A zero timeout is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().recv_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of blocking send operations (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.opts.read().send_timeout
+    }
+
+    /// Sets the timeout of blocking send operations (`SO_SNDTIMEO`), or no
+    /// timeout if `timeout` is `None`.
+    ///
+    /// An operation that times out returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). A zero timeout is invalid.
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        check_timeout(timeout)?;
+        self.opts.write().send_timeout = timeout;
+        Ok(())
+    }
+
+    /// Binds the socket to the network interface with the given name.
+    ///
+    /// Datagrams are only sent and received through the interface. It must
+    /// be called before [`bind`](Self::bind).
+    pub fn bind_to_device(&self, ifname: &str) -> AxResult {
+        let device = route::iface_by_name(ifname)
+            .ok_or_else(|| ax_err_type!(NotFound, "socket bind_to_device() failed"))?;
+        if self.local_addr.read().is_some() {
+            return ax_err!(
+                InvalidInput,
+                "socket bind_to_device() failed: already bound"
+            );
+        }
+        *self.device.write() = Some(device);
+        Ok(())
+    }
+
     /// Binds an unbound socket to the given address and port.
     ///
     /// It's must be called before [`send_to`](Self::send_to) and
     /// [`recv_from`](Self::recv_from).
     pub fn bind(&self, mut local_addr: SocketAddr) -> AxResult {
         let mut self_local_addr = self.local_addr.write();
+        let mut device = self.device.write();
 
         if local_addr.port() == 0 {
             local_addr.set_port(get_ephemeral_port()?);
@@ -88,15 +231,36 @@ impl UdpSocket {
             addr: (!local_endpoint.addr.is_unspecified()).then_some(local_endpoint.addr),
             port: local_endpoint.port,
         };
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            socket.bind(endpoint).or_else(|e| match e {
-                BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
-                BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
-            })
-        })?;
+        // Only the interface with the address can receive datagrams for it.
+        let bound_device = device.or_else(|| endpoint.addr.and_then(route::iface_of_addr));
+        let ifaces = match bound_device {
+            Some(device) => device..device + 1,
+            None => 0..IFACES.len(),
+        };
+        let opts = *self.opts.read();
+        let mut handles = Vec::with_capacity(ifaces.len());
+        for iface in ifaces {
+            let handle = SOCKET_SET.add(iface, SocketSetWrapper::new_udp_socket(&opts));
+            handles.push(handle);
+            let res = SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.bind(endpoint).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            });
+            if let Err(e) = res {
+                // Leave the socket unbound, as if `bind` had not been called.
+                for handle in handles {
+                    SOCKET_SET.remove(handle);
+                }
+                return Err(e);
+            }
+        }
 
+        debug!("UDP socket {}: bound on {}", handles[0], endpoint);
+        self.handles.init_once(handles);
+        *device = bound_device;
         *self_local_addr = Some(local_endpoint);
-        debug!("UDP socket {}: bound on {}", self.handle, endpoint);
         Ok(())
     }
 
@@ -112,18 +276,67 @@ impl UdpSocket {
     /// Receives a single datagram message on the socket. On success, returns
     /// the number of bytes read and the origin.
     pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
-        self.recv_impl(|socket| match socket.recv_slice(buf) {
-            Ok((len, meta)) => Ok((len, SocketAddr::from(meta.endpoint))),
-            Err(_) => ax_err!(BadState, "socket recv_from() failed"),
-        })
+        self.recv_impl(false, |datagram| recv_from_datagram(datagram, buf))
     }
 
     /// Receives a single datagram message on the socket, without removing it from
     /// the queue. On success, returns the number of bytes read and the origin.
     pub fn peek_from(&self, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
-        self.recv_impl(|socket| match socket.peek_slice(buf) {
-            Ok((len, meta)) => Ok((len, SocketAddr::from(meta.endpoint))),
-            Err(_) => ax_err!(BadState, "socket recv_from() failed"),
+        self.recv_impl(true, |datagram| match datagram {
+            Datagram::Lent(data, src) => Ok((copy_datagram(data, buf), SocketAddr::from(src))),
+            Datagram::Queued(socket) => match socket.peek_slice(buf) {
+                Ok((len, meta)) => Ok((len, SocketAddr::from(meta.endpoint))),
+                Err(_) => ax_err!(BadState, "socket recv_from() failed"),
+            },
+        })
+    }
+
+    /// Sends a datagram of `len` bytes to the given address, by lending a slot
+    /// in the send buffer of the socket instead of copying the data.
+    ///
+    /// `f` is called once to fill the whole slot, with the socket set locked,
+    /// so it must not access any socket.
+    pub fn send_zc_to<F, R>(&self, len: usize, remote_addr: SocketAddr, f: F) -> AxResult<R>
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        if remote_addr.port() == 0 || remote_addr.ip().is_unspecified() {
+            return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
+        }
+        if self.local_addr.read().is_none() {
+            return ax_err!(NotConnected, "socket send() failed");
+        }
+        let remote_endpoint = IpEndpoint::from(remote_addr);
+        let mut f = Some(f);
+        self.block_on(self.send_timeout(), || {
+            self.try_send(len, remote_endpoint, |buf| f.take().unwrap()(buf))
+        })
+    }
+
+    /// Receives a single datagram by lending it to `f` with its origin,
+    /// instead of copying it.
+    ///
+    /// The first call makes the datagrams received by NICs for the socket stay
+    /// in the RX buffers of the drivers, which are lent to `f` in place and
+    /// recycled after `f` returns. The others are lent in the receive buffer
+    /// of the socket. `f` is called with the socket locked, so it must not
+    /// access any socket.
+    pub fn recv_zc<F, R>(&self, f: F) -> AxResult<R>
+    where
+        F: FnOnce(&[u8], SocketAddr) -> R,
+    {
+        let Some(local) = *self.local_addr.read() else {
+            return ax_err!(NotConnected, "socket recv() failed");
+        };
+        self.lend
+            .call_once(|| LendQueue::register(*self.device.read(), local, self.only_v6()));
+        let mut f = Some(f);
+        self.recv_impl(false, |datagram| match datagram {
+            Datagram::Lent(data, src) => Ok(f.take().unwrap()(data, SocketAddr::from(src))),
+            Datagram::Queued(socket) => match socket.recv() {
+                Ok((buf, meta)) => Ok(f.take().unwrap()(buf, SocketAddr::from(meta.endpoint))),
+                Err(_) => ax_err!(BadState, "socket recv_from() failed"),
+            },
         })
     }
 
@@ -131,18 +344,22 @@ impl UdpSocket {
     /// `recv` to be used to send data and also applies filters to only receive
     /// data from the specified address.
     ///
-    /// The local port will be generated automatically if the socket is not bound.
-    /// It's must be called before [`send`](Self::send) and
-    /// [`recv`](Self::recv).
+    /// The local port will be generated automatically if the socket is not
+    /// bound, on the unspecified address of the family of `addr`. It's must be
+    /// called before [`send`](Self::send) and [`recv`](Self::recv).
     pub fn connect(&self, addr: SocketAddr) -> AxResult {
         let mut self_peer_addr = self.peer_addr.write();
 
         if self.local_addr.read().is_none() {
-            self.bind(SocketAddr::from(UNSPECIFIED_ENDPOINT))?;
+            let unspecified = match addr.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            };
+            self.bind(SocketAddr::new(unspecified, 0))?;
         }
 
         *self_peer_addr = Some(IpEndpoint::from(addr));
-        debug!("UDP socket {}: connected to {}", self.handle, addr);
+        debug!("UDP socket {}: connected to {}", self.handles()[0], addr);
         Ok(())
     }
 
@@ -156,50 +373,104 @@ impl UdpSocket {
     /// to which it is connected. On success, returns the number of bytes read.
     pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
         let remote_endpoint = self.remote_endpoint()?;
-        self.recv_impl(|socket| {
-            let (len, meta) = socket
-                .recv_slice(buf)
-                .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-            if !remote_endpoint.addr.is_unspecified() && remote_endpoint.addr != meta.endpoint.addr
-            {
-                return Err(AxError::WouldBlock);
-            }
-            if remote_endpoint.port != 0 && remote_endpoint.port != meta.endpoint.port {
-                return Err(AxError::WouldBlock);
-            }
-            Ok(len)
+        self.recv_impl(false, |datagram| {
+            recv_connected(datagram, buf, remote_endpoint)
         })
     }
 
     /// Close the socket.
     pub fn shutdown(&self) -> AxResult {
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            debug!("UDP socket {}: shutting down", self.handle);
-            socket.close();
-        });
+        let Some(&first) = self.handles().first() else {
+            return Ok(());
+        };
+        debug!("UDP socket {}: shutting down", first);
+        if let Some(lend) = self.lend.get() {
+            lend.close();
+        }
+        for &handle in self.handles() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| socket.close());
+        }
         SOCKET_SET.poll_interfaces();
         Ok(())
     }
 
     /// Whether the socket is readable or writable.
     pub fn poll(&self) -> AxResult<PollState> {
-        if self.local_addr.read().is_none() {
+        let Some(local_endpoint) = *self.local_addr.read() else {
             return Ok(PollState {
                 readable: false,
                 writable: false,
             });
+        };
+        let v6only = self.only_v6();
+        let mut state = PollState {
+            readable: self.lend.get().is_some_and(|lend| !lend.is_empty()),
+            writable: false,
+        };
+        for &handle in self.handles() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                discard_foreign(socket, local_endpoint.addr, v6only);
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
+            });
         }
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            Ok(PollState {
-                readable: socket.can_recv(),
-                writable: socket.can_send(),
-            })
-        })
+        Ok(state)
+    }
+
+    /// Registers `waker` to be woken up on the next event of the socket, which
+    /// may change the readiness returned by [`poll`](Self::poll).
+    ///
+    /// The waker is consumed by the event, so it is registered again before
+    /// each poll. Returns `false` if the socket is not bound yet, so it has no
+    /// events to wait for.
+    #[cfg(feature = "multitask")]
+    pub fn register_poll_waker(&self, waker: &Waker) -> bool {
+        if self.handles().is_empty() {
+            return false;
+        }
+        let waiter = self.waiter();
+        waiter.add_waker(waker);
+        self.register_waker(&waiter.waker());
+        true
     }
 }
 
 /// Private methods
 impl UdpSocket {
+    /// Returns the handles of the smoltcp sockets, which is empty if the socket
+    /// is not bound.
+    fn handles(&self) -> &[SocketHandle] {
+        self.handles.get().map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the waiter for events of all smoltcp sockets, which must have
+    /// been created.
+    #[cfg(feature = "multitask")]
+    fn waiter(&self) -> alloc::sync::Arc<poll::SocketWaiter> {
+        poll::waiter(self.handles()[0])
+    }
+
+    /// Replaces the smoltcp sockets, if any, with new ones of the buffer sizes
+    /// in the options, keeping their bound endpoint and hop limit.
+    fn rebuild_sockets(&self) -> AxResult {
+        let opts = *self.opts.read();
+        for &handle in self.handles() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                let endpoint = socket.endpoint();
+                let hop_limit = socket.hop_limit();
+                *socket = SocketSetWrapper::new_udp_socket(&opts);
+                socket.set_hop_limit(hop_limit);
+                if endpoint.port != 0 {
+                    socket
+                        .bind(endpoint)
+                        .map_err(|_| ax_err_type!(BadState, "socket setsockopt() failed"))?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
     fn remote_endpoint(&self) -> AxResult<IpEndpoint> {
         match self.peer_addr.try_read() {
             Some(addr) => addr.ok_or(AxError::NotConnected),
@@ -212,71 +483,302 @@ impl UdpSocket {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if socket.can_send() {
-                    socket
-                        .send_slice(buf, remote_endpoint)
-                        .map_err(|e| match e {
-                            SendError::BufferFull => AxError::WouldBlock,
-                            SendError::Unaddressable => {
-                                ax_err_type!(ConnectionRefused, "socket send() failed")
-                            }
-                        })?;
-                    Ok(buf.len())
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
-            })
+        self.block_on(self.send_timeout(), || {
+            self.try_send_slice(buf, remote_endpoint)
         })
     }
 
-    fn recv_impl<F, T>(&self, mut op: F) -> AxResult<T>
+    fn recv_impl<F, T>(&self, peek: bool, mut op: F) -> AxResult<T>
     where
-        F: FnMut(&mut udp::Socket) -> AxResult<T>,
+        F: FnMut(Datagram<'_>) -> AxResult<T>,
     {
         if self.local_addr.read().is_none() {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+        self.block_on(self.recv_timeout(), || self.try_recv(peek, &mut op))
+    }
+
+    /// Enqueues a datagram of `len` bytes filled by `fill` without blocking,
+    /// returns [`Err(WouldBlock)`](AxError::WouldBlock) if the tx buffer is
+    /// full.
+    fn try_send<F, T>(&self, len: usize, remote_endpoint: IpEndpoint, fill: F) -> AxResult<T>
+    where
+        F: FnOnce(&mut [u8]) -> T,
+    {
+        let device = *self.device.read();
+        let iface = route::egress(device, None, remote_endpoint.addr)?;
+        let handle = self
+            .handles()
+            .iter()
+            .copied()
+            .find(|handle| handle.iface == iface)
+            .ok_or_else(|| ax_err_type!(NotConnected, "socket send() failed"))?;
+        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+            if len > socket.payload_send_capacity() {
+                ax_err!(InvalidInput, "socket send() failed: message too long")
+            } else if socket.can_send() {
+                let buf = socket.send(len, remote_endpoint).map_err(|e| match e {
+                    SendError::BufferFull => AxError::WouldBlock,
+                    SendError::Unaddressable => {
+                        ax_err_type!(ConnectionRefused, "socket send() failed")
+                    }
+                })?;
+                Ok(fill(buf))
+            } else {
+                // tx buffer is full
+                Err(AxError::WouldBlock)
+            }
+        })
+    }
+
+    /// Sends the data without blocking, returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock) if the tx buffer is full.
+    fn try_send_slice(&self, buf: &[u8], remote_endpoint: IpEndpoint) -> AxResult<usize> {
+        self.try_send(buf.len(), remote_endpoint, |dst| dst.copy_from_slice(buf))?;
+        Ok(buf.len())
+    }
+
+    /// Calls `op` on the next datagram without blocking if there is one,
+    /// otherwise returns [`Err(WouldBlock)`](AxError::WouldBlock).
+    ///
+    /// A lent datagram is removed after `op` returns, unless `peek` is set.
+    fn try_recv<F, T>(&self, peek: bool, mut op: F) -> AxResult<T>
+    where
+        F: FnMut(Datagram<'_>) -> AxResult<T>,
+    {
+        let Some(local_endpoint) = *self.local_addr.read() else {
+            return ax_err!(NotConnected, "socket recv() failed");
+        };
+        if let Some(lend) = self.lend.get() {
+            match lend.recv(peek, |data, src| op(Datagram::Lent(data, src))) {
+                Err(AxError::WouldBlock) => {}
+                res => return res,
+            }
+        }
+        let v6only = self.only_v6();
+        for &handle in self.handles() {
+            let res = SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                discard_foreign(socket, local_endpoint.addr, v6only);
                 if socket.can_recv() {
                     // data available
-                    op(socket)
+                    op(Datagram::Queued(socket))
                 } else {
-                    // no more data
                     Err(AxError::WouldBlock)
                 }
-            })
-        })
+            });
+            match res {
+                Err(AxError::WouldBlock) => {}
+                res => return res,
+            }
+        }
+        // no more data
+        Err(AxError::WouldBlock)
     }
 
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    #[cfg(not(feature = "multitask"))]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
         if self.is_nonblocking() {
             f()
         } else {
+            let deadline = Deadline::after(timeout);
             loop {
                 SOCKET_SET.poll_interfaces();
                 match f() {
                     Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
+                    Err(AxError::WouldBlock) => {
+                        deadline.remaining()?;
+                        axtask::yield_now()
+                    }
                     Err(e) => return Err(e),
                 }
             }
         }
     }
+
+    #[cfg(feature = "multitask")]
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        if self.is_nonblocking() {
+            let res = f();
+            poll::kick();
+            res
+        } else {
+            self.waiter().block_on(
+                f,
+                |waker| self.register_waker(waker),
+                Deadline::after(timeout),
+            )
+        }
+    }
+
+    /// Registers the waker of the socket waiter in the smoltcp sockets.
+    #[cfg(feature = "multitask")]
+    fn register_waker(&self, waker: &Waker) {
+        if let Some(lend) = self.lend.get() {
+            lend.register_waker(waker);
+        }
+        for &handle in self.handles() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            })
+        }
+    }
+}
+
+/// Asynchronous methods
+#[cfg(feature = "async")]
+impl UdpSocket {
+    /// Polls to send data on the socket to the given address.
+    ///
+    /// If the transmit buffer is full, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        remote_addr: SocketAddr,
+    ) -> Poll<AxResult<usize>> {
+        if remote_addr.port() == 0 || remote_addr.ip().is_unspecified() {
+            return Poll::Ready(ax_err!(
+                InvalidInput,
+                "socket send_to() failed: invalid address"
+            ));
+        }
+        self.poll_send_impl(cx, buf, IpEndpoint::from(remote_addr))
+    }
+
+    /// Polls to receive a single datagram message on the socket.
+    ///
+    /// If no datagram is available, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<AxResult<(usize, SocketAddr)>> {
+        self.poll_recv_impl(cx, |datagram| recv_from_datagram(datagram, buf))
+    }
+
+    /// Polls to send data on the socket to the remote address to which it is
+    /// connected.
+    pub fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<AxResult<usize>> {
+        match self.remote_endpoint() {
+            Ok(remote_endpoint) => self.poll_send_impl(cx, buf, remote_endpoint),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Polls to receive a single datagram message on the socket from the
+    /// remote address to which it is connected.
+    pub fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<AxResult<usize>> {
+        match self.remote_endpoint() {
+            Ok(remote_endpoint) => self.poll_recv_impl(cx, |datagram| {
+                recv_connected(datagram, buf, remote_endpoint)
+            }),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn poll_send_impl(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        remote_endpoint: IpEndpoint,
+    ) -> Poll<AxResult<usize>> {
+        if self.local_addr.read().is_none() {
+            return Poll::Ready(ax_err!(NotConnected, "socket send() failed"));
+        }
+        self.waiter().poll_io(
+            cx,
+            || self.try_send_slice(buf, remote_endpoint),
+            |waker| self.register_waker(waker),
+        )
+    }
+
+    fn poll_recv_impl<F, T>(&self, cx: &mut Context<'_>, mut op: F) -> Poll<AxResult<T>>
+    where
+        F: FnMut(Datagram<'_>) -> AxResult<T>,
+    {
+        if self.local_addr.read().is_none() {
+            return Poll::Ready(ax_err!(NotConnected, "socket recv() failed"));
+        }
+        self.waiter().poll_io(
+            cx,
+            || self.try_recv(false, &mut op),
+            |waker| self.register_waker(waker),
+        )
+    }
 }
 
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
-        SOCKET_SET.remove(self.handle);
+        for &handle in self.handles() {
+            SOCKET_SET.remove(handle);
+        }
+    }
+}
+
+/// Drops the datagrams at the head of the receive queue of `socket` that are
+/// of an address family not accepted by a socket bound to `local_addr`.
+///
+/// smoltcp delivers the datagrams of both families to a socket bound to an
+/// unspecified address.
+fn discard_foreign(socket: &mut udp::Socket, local_addr: IpAddress, v6only: bool) {
+    while let Ok((_, meta)) = socket.peek() {
+        if family_accepts(local_addr, meta.endpoint.addr, v6only) {
+            break;
+        }
+        socket.recv().ok();
+    }
+}
+
+/// Copies the lent datagram `data` to `buf`, truncating it if `buf` is too
+/// small, and returns the number of bytes copied.
+fn copy_datagram(data: &[u8], buf: &mut [u8]) -> usize {
+    let len = data.len().min(buf.len());
+    buf[..len].copy_from_slice(&data[..len]);
+    len
+}
+
+fn recv_from_datagram(datagram: Datagram, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
+    match datagram {
+        Datagram::Lent(data, src) => Ok((copy_datagram(data, buf), SocketAddr::from(src))),
+        Datagram::Queued(socket) => match socket.recv_slice(buf) {
+            Ok((len, meta)) => Ok((len, SocketAddr::from(meta.endpoint))),
+            Err(_) => ax_err!(BadState, "socket recv_from() failed"),
+        },
+    }
+}
+
+fn recv_connected(
+    datagram: Datagram,
+    buf: &mut [u8],
+    remote_endpoint: IpEndpoint,
+) -> AxResult<usize> {
+    let (len, src) = match datagram {
+        Datagram::Lent(data, src) => (copy_datagram(data, buf), src),
+        Datagram::Queued(socket) => {
+            let (len, meta) = socket
+                .recv_slice(buf)
+                .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
+            (len, meta.endpoint)
+        }
+    };
+    if !remote_endpoint.addr.is_unspecified() && remote_endpoint.addr != src.addr {
+        return Err(AxError::WouldBlock);
+    }
+    if remote_endpoint.port != 0 && remote_endpoint.port != src.port {
+        return Err(AxError::WouldBlock);
     }
+    Ok(len)
 }
 
 fn get_ephemeral_port() -> AxResult<u16> {
@@ -136,6 +136,17 @@ impl<T> ResArc<T> {
         self.0.init_once(data);
     }
 
+    /// Replaces the resource with the shared data, without dropping the old
+    /// one.
+    ///
+    /// A thread-local namespace starts as a bitwise copy of the global one, so
+    /// its initialized resources are aliases that do not own a reference to
+    /// the data. This gives such a resource its own data.
+    pub fn reinit_shared(&mut self, data: Arc<T>) {
+        core::mem::forget(core::mem::replace(self, Self::new()));
+        self.init_shared(data);
+    }
+
     /// Checks whether the value is initialized.
     pub fn is_inited(&self) -> bool {
         self.0.is_inited()
@@ -228,12 +239,15 @@ macro_rules! def_resource {
             $vis struct $name { __value: () }
 
             impl $name {
-                unsafe fn deref_from_base(&self, ns_base: *mut u8) -> &$ty {
+                unsafe fn ptr_from_base(&self, ns_base: *mut u8) -> *mut $ty {
                     $crate::def_static_resource!(RES, $ty, $default);
 
                     let offset = &RES as *const _ as *const u8 as usize - $crate::link::section_start() as usize;
-                    let ptr = unsafe{ ns_base.add(offset) } as *const _;
-                    unsafe{ &*ptr }
+                    unsafe{ ns_base.add(offset) } as *mut _
+                }
+
+                unsafe fn deref_from_base(&self, ns_base: *mut u8) -> &$ty {
+                    unsafe{ &*self.ptr_from_base(ns_base) }
                 }
 
                 /// Dereference the resource from the given namespace.
@@ -241,6 +255,11 @@ macro_rules! def_resource {
                     unsafe { self.deref_from_base(ns.base()) }
                 }
 
+                /// Mutably dereference the resource from the given namespace.
+                pub fn deref_from_mut(&self, ns: &mut $crate::AxNamespace) -> &mut $ty {
+                    unsafe { &mut *self.ptr_from_base(ns.base()) }
+                }
+
                 /// Dereference the resource from the global namespace.
                 pub fn deref_global(&self) -> &$ty {
                     self.deref_from(&$crate::AxNamespace::global())
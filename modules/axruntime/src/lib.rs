@@ -167,7 +167,7 @@ pub fn rust_main(cpu_id: usize, arg: usize) -> ! {
         axfs::init_filesystems(all_devices.block);
 
         #[cfg(feature = "net")]
-        axnet::init_network(all_devices.net);
+        axnet::init_network(all_devices.net, all_devices.net_irqs);
 
         #[cfg(feature = "display")]
         axdisplay::init_display(all_devices.display);
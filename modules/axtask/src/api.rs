@@ -6,6 +6,8 @@ use kernel_guard::NoPreemptIrqSave;
 
 pub(crate) use crate::run_queue::{current_run_queue, select_run_queue};
 
+#[doc(cfg(feature = "multitask"))]
+pub use crate::futex::{FutexError, futex_requeue, futex_wait, futex_wake};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task::{CurrentTask, TaskId, TaskInner};
 #[doc(cfg(feature = "multitask"))]
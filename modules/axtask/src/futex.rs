@@ -0,0 +1,197 @@
+//! Futex-style wait/wake primitives keyed on memory addresses.
+//!
+//! Tasks can park on an arbitrary [`AtomicU32`] without allocating a
+//! [`WaitQueue`] for it. Waiters are kept in a global table of hashed
+//! buckets, each holding the wait queues of the addresses currently being
+//! waited on. A wait queue is created when the first task waits on an address,
+//! and is removed once it has no waiters.
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use kspin::SpinNoIrq;
+
+use crate::{AxTaskRef, WaitQueue};
+
+/// Number of buckets in the futex table, must be a power of two.
+const FUTEX_BUCKETS: usize = 256;
+
+static FUTEX_TABLE: [FutexBucket; FUTEX_BUCKETS] = [const { FutexBucket::new() }; FUTEX_BUCKETS];
+
+/// The keys of the futexes that waiting tasks were moved to by
+/// [`futex_requeue`], indexed by task ID.
+static REQUEUED: SpinNoIrq<BTreeMap<u64, usize>> = SpinNoIrq::new(BTreeMap::new());
+
+/// Reasons for [`futex_wait`] to return without being woken up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FutexError {
+    /// The value at the futex address is not the expected value (`EAGAIN`).
+    WouldBlock,
+    /// The timeout elapsed before the task was woken up (`ETIMEDOUT`).
+    TimedOut,
+}
+
+struct FutexBucket {
+    queues: SpinNoIrq<Vec<(usize, Arc<WaitQueue>)>>,
+}
+
+impl FutexBucket {
+    const fn new() -> Self {
+        Self {
+            queues: SpinNoIrq::new(Vec::new()),
+        }
+    }
+
+    /// Returns the wait queue of `key` if it exists.
+    fn get(&self, key: usize) -> Option<Arc<WaitQueue>> {
+        self.queues
+            .lock()
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, wq)| wq.clone())
+    }
+
+    /// Returns the wait queue of `key`, creates one if it does not exist.
+    fn get_or_insert(&self, key: usize) -> Arc<WaitQueue> {
+        let mut queues = self.queues.lock();
+        if let Some((_, wq)) = queues.iter().find(|(k, _)| *k == key) {
+            return wq.clone();
+        }
+        let wq = Arc::new(WaitQueue::new());
+        queues.push((key, wq.clone()));
+        wq
+    }
+
+    /// Drops a reference to the wait queue of `key` obtained from
+    /// [`get`](Self::get) or [`get_or_insert`](Self::get_or_insert).
+    ///
+    /// The wait queue is removed from the bucket if it is empty and no one
+    /// else holds a reference to it.
+    fn put(&self, key: usize, wq: Arc<WaitQueue>) {
+        let mut queues = self.queues.lock();
+        // One reference is held by the bucket, the other is `wq`.
+        if Arc::strong_count(&wq) == 2 && wq.is_empty() {
+            queues.retain(|(k, _)| *k != key);
+        }
+    }
+}
+
+fn futex_key(futex: &AtomicU32) -> usize {
+    futex.as_ptr() as usize
+}
+
+fn futex_bucket(key: usize) -> &'static FutexBucket {
+    let hash = (key >> 2) ^ (key >> 12) ^ (key >> 22);
+    &FUTEX_TABLE[hash & (FUTEX_BUCKETS - 1)]
+}
+
+/// Blocks the current task on the given futex if its value equals `expected`,
+/// until it is woken up by [`futex_wake`] or [`futex_requeue`], or the given
+/// duration has elapsed (if specified).
+///
+/// The value is compared atomically with respect to the wake operations, so a
+/// wake issued after the value is changed will not be missed.
+///
+/// Returns [`FutexError::WouldBlock`] if the value does not match, or
+/// [`FutexError::TimedOut`] if the timeout elapsed. If the feature `irq` is
+/// not enabled, the `timeout` is ignored.
+pub fn futex_wait(
+    futex: &AtomicU32,
+    expected: u32,
+    timeout: Option<Duration>,
+) -> Result<(), FutexError> {
+    let key = futex_key(futex);
+    let bucket = futex_bucket(key);
+    let wq = bucket.get_or_insert(key);
+    let res = wq.wait_if(|| futex.load(Ordering::Acquire) == expected, timeout);
+    bucket.put(key, wq);
+
+    match res {
+        None => Err(FutexError::WouldBlock),
+        Some(false) => {
+            REQUEUED.lock().remove(&crate::current().id().as_u64());
+            Ok(())
+        }
+        Some(true) => {
+            // The task has been removed from the wait queue of `futex`, but it
+            // may have been moved to another futex by `futex_requeue`.
+            remove_requeued(crate::current().as_task_ref());
+            Err(FutexError::TimedOut)
+        }
+    }
+}
+
+/// Removes a task that timed out from the wait queue of the futex it was
+/// moved to by [`futex_requeue`], if any.
+fn remove_requeued(task: &AxTaskRef) {
+    let id = task.id().as_u64();
+    loop {
+        let Some(key) = REQUEUED.lock().get(&id).copied() else {
+            return;
+        };
+        let bucket = futex_bucket(key);
+        if let Some(wq) = bucket.get(key) {
+            wq.remove_task(task);
+            bucket.put(key, wq);
+        }
+        // Unless it has been moved again in the meantime, the task is now in
+        // no wait queue.
+        let mut requeued = REQUEUED.lock();
+        if requeued.get(&id) == Some(&key) {
+            requeued.remove(&id);
+            return;
+        }
+    }
+}
+
+/// Wakes up at most `count` tasks waiting on the given futex.
+///
+/// Returns the number of tasks woken up.
+pub fn futex_wake(futex: &AtomicU32, count: usize) -> usize {
+    let key = futex_key(futex);
+    let bucket = futex_bucket(key);
+    let Some(wq) = bucket.get(key) else {
+        return 0;
+    };
+    let mut woken = 0;
+    while woken < count && wq.notify_one(false) {
+        woken += 1;
+    }
+    bucket.put(key, wq);
+    woken
+}
+
+/// Wakes up at most `wake_count` tasks waiting on `futex`, and moves at most
+/// `requeue_count` of the remaining waiters to wait on `target`.
+///
+/// Returns the number of tasks woken up plus the number of tasks requeued.
+pub fn futex_requeue(
+    futex: &AtomicU32,
+    wake_count: usize,
+    target: &AtomicU32,
+    requeue_count: usize,
+) -> usize {
+    let key = futex_key(futex);
+    let bucket = futex_bucket(key);
+    let Some(wq) = bucket.get(key) else {
+        return 0;
+    };
+    let mut woken = 0;
+    while woken < wake_count && wq.notify_one(false) {
+        woken += 1;
+    }
+
+    let mut requeued = 0;
+    let target_key = futex_key(target);
+    if requeue_count > 0 && target_key != key && !wq.is_empty() {
+        let target_bucket = futex_bucket(target_key);
+        let target_wq = target_bucket.get_or_insert(target_key);
+        requeued = wq.requeue_with(requeue_count, &target_wq, |task| {
+            REQUEUED.lock().insert(task.id().as_u64(), target_key);
+        });
+        target_bucket.put(target_key, target_wq);
+    }
+    bucket.put(key, wq);
+    woken + requeued
+}
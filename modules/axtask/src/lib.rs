@@ -43,6 +43,7 @@ cfg_if::cfg_if! {
         mod task;
         mod task_ext;
         mod api;
+        mod futex;
         mod wait_queue;
 
         #[cfg(feature = "irq")]
@@ -38,7 +38,7 @@ pub(crate) enum TaskState {
 /// The inner task structure.
 pub struct TaskInner {
     id: TaskId,
-    name: String,
+    name: SpinNoIrq<String>,
     is_idle: bool,
     is_init: bool,
 
@@ -125,7 +125,7 @@ impl TaskInner {
         t.entry = Some(Box::into_raw(Box::new(entry)));
         t.ctx_mut().init(task_entry as usize, kstack.top(), tls);
         t.kstack = Some(kstack);
-        if t.name == "idle" {
+        if *t.name.lock() == "idle" {
             t.is_idle = true;
         }
         t
@@ -137,13 +137,18 @@ impl TaskInner {
     }
 
     /// Gets the name of the task.
-    pub fn name(&self) -> &str {
-        self.name.as_str()
+    pub fn name(&self) -> String {
+        self.name.lock().clone()
+    }
+
+    /// Sets the name of the task.
+    pub fn set_name(&self, name: &str) {
+        *self.name.lock() = name.into();
     }
 
     /// Get a combined string of the task ID and name.
     pub fn id_name(&self) -> alloc::string::String {
-        alloc::format!("Task({}, {:?})", self.id.as_u64(), self.name)
+        alloc::format!("Task({}, {:?})", self.id.as_u64(), self.name.lock())
     }
 
     /// Wait for the task to exit, and return the exit code.
@@ -228,7 +233,7 @@ impl TaskInner {
 
         Self {
             id,
-            name,
+            name: SpinNoIrq::new(name),
             is_idle: false,
             is_init: false,
             entry: None,
@@ -268,7 +273,7 @@ impl TaskInner {
         t.is_init = true;
         #[cfg(feature = "smp")]
         t.set_on_cpu(true);
-        if t.name == "idle" {
+        if *t.name.lock() == "idle" {
             t.is_idle = true;
         }
         t
@@ -442,7 +447,7 @@ impl fmt::Debug for TaskInner {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("TaskInner")
             .field("id", &self.id)
-            .field("name", &self.name)
+            .field("name", &self.name.lock())
             .field("state", &self.state())
             .finish()
     }
@@ -1,4 +1,4 @@
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::{Mutex, Once};
 
 use crate::{WaitQueue, api as axtask, current};
@@ -128,3 +128,134 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_task_name() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let task = axtask::spawn_raw(|| axtask::exit(0), "T0".into(), 0x1000);
+    assert_eq!(task.name(), "T0");
+    task.set_name("renamed");
+    assert_eq!(task.name(), "renamed");
+    assert!(task.id_name().contains("\"renamed\""));
+    assert_eq!(task.join(), Some(0));
+}
+
+#[test]
+fn test_futex() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_TASKS: usize = 10;
+
+    static FUTEX: AtomicU32 = AtomicU32::new(0);
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+    static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+    assert_eq!(
+        axtask::futex_wait(&FUTEX, 1, None),
+        Err(axtask::FutexError::WouldBlock)
+    );
+
+    for _ in 0..NUM_TASKS {
+        axtask::spawn(move || {
+            STARTED.fetch_add(1, Ordering::Release);
+            while FUTEX.load(Ordering::Acquire) == 0 {
+                axtask::futex_wait(&FUTEX, 0, None).ok();
+            }
+            assert!(!current().in_wait_queue());
+            FINISHED.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    while STARTED.load(Ordering::Acquire) < NUM_TASKS {
+        axtask::yield_now();
+    }
+    assert_eq!(FINISHED.load(Ordering::Acquire), 0);
+
+    FUTEX.store(1, Ordering::Release);
+    assert_eq!(axtask::futex_wake(&FUTEX, 1), 1);
+    assert_eq!(axtask::futex_wake(&FUTEX, usize::MAX), NUM_TASKS - 1);
+    while FINISHED.load(Ordering::Acquire) < NUM_TASKS {
+        axtask::yield_now();
+    }
+    assert_eq!(axtask::futex_wake(&FUTEX, usize::MAX), 0);
+}
+
+#[test]
+fn test_futex_requeue() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_TASKS: usize = 6;
+
+    static FUTEX: AtomicU32 = AtomicU32::new(0);
+    static TARGET: AtomicU32 = AtomicU32::new(0);
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+    static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+    for _ in 0..NUM_TASKS {
+        axtask::spawn(move || {
+            STARTED.fetch_add(1, Ordering::Release);
+            // Woken up on `FUTEX`, or on `TARGET` once requeued.
+            while FUTEX.load(Ordering::Acquire) == 0 {
+                axtask::futex_wait(&FUTEX, 0, None).ok();
+            }
+            assert!(!current().in_wait_queue());
+            FINISHED.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    while STARTED.load(Ordering::Acquire) < NUM_TASKS {
+        axtask::yield_now();
+    }
+
+    FUTEX.store(1, Ordering::Release);
+    assert_eq!(axtask::futex_requeue(&FUTEX, 1, &TARGET, 2), 3);
+    assert_eq!(axtask::futex_wake(&FUTEX, usize::MAX), NUM_TASKS - 3);
+    assert_eq!(axtask::futex_wake(&TARGET, usize::MAX), 2);
+    while FINISHED.load(Ordering::Acquire) < NUM_TASKS {
+        axtask::yield_now();
+    }
+    assert_eq!(axtask::futex_wake(&FUTEX, usize::MAX), 0);
+    assert_eq!(axtask::futex_wake(&TARGET, usize::MAX), 0);
+}
+
+#[cfg(feature = "irq")]
+#[test]
+fn test_futex_timeout() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static FUTEX: AtomicU32 = AtomicU32::new(0);
+    static TARGET: AtomicU32 = AtomicU32::new(0);
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+    static TIMED_OUT: AtomicUsize = AtomicUsize::new(0);
+
+    // One task times out on `FUTEX`, the other after being moved to `TARGET`.
+    for _ in 0..2 {
+        axtask::spawn(move || {
+            STARTED.fetch_add(1, Ordering::Release);
+            let timeout = Some(core::time::Duration::from_millis(10));
+            assert_eq!(
+                axtask::futex_wait(&FUTEX, 0, timeout),
+                Err(axtask::FutexError::TimedOut)
+            );
+            assert!(!current().in_wait_queue());
+            TIMED_OUT.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    while STARTED.load(Ordering::Acquire) < 2 {
+        axtask::yield_now();
+    }
+    assert_eq!(axtask::futex_requeue(&FUTEX, 0, &TARGET, 1), 1);
+    while TIMED_OUT.load(Ordering::Acquire) < 2 {
+        axtask::on_timer_tick();
+        axtask::yield_now();
+    }
+    // Neither task is left in a wait queue.
+    assert_eq!(axtask::futex_wake(&FUTEX, usize::MAX), 0);
+    assert_eq!(axtask::futex_wake(&TARGET, usize::MAX), 0);
+}
@@ -165,6 +165,55 @@ impl WaitQueue {
         timeout
     }
 
+    /// Blocks the current task and put it into the wait queue if the given
+    /// `condition` is true, until other tasks notify it, or the given
+    /// duration has elapsed (if specified).
+    ///
+    /// The condition is evaluated only once, with the wait queue locked, so a
+    /// notification issued after the condition is changed cannot be missed.
+    ///
+    /// Returns [`None`] if the condition is false and the task is not blocked,
+    /// otherwise returns whether the wait timed out. If the feature `irq` is
+    /// not enabled, the `timeout` is ignored.
+    pub(crate) fn wait_if<F>(
+        &self,
+        condition: F,
+        timeout: Option<core::time::Duration>,
+    ) -> Option<bool>
+    where
+        F: FnOnce() -> bool,
+    {
+        let mut rq = current_run_queue::<NoPreemptIrqSave>();
+        let curr = crate::current();
+        let wq = self.queue.lock();
+        if !condition() {
+            return None;
+        }
+
+        #[cfg(feature = "irq")]
+        if let Some(dur) = timeout {
+            let deadline = axhal::time::wall_time() + dur;
+            debug!("task wait_if: {}, deadline={:?}", curr.id_name(), deadline);
+            crate::timers::set_alarm_wakeup(deadline, curr.clone());
+        }
+
+        rq.blocked_resched(wq);
+
+        let timeout_expired = curr.in_wait_queue(); // still in the wait queue, must have timed out
+        self.cancel_events(curr, timeout.is_some());
+        Some(timeout_expired)
+    }
+
+    /// Removes the given task from the wait queue without waking it up.
+    ///
+    /// Returns `true` if the task was in the wait queue.
+    pub(crate) fn remove_task(&self, task: &AxTaskRef) -> bool {
+        let mut wq = self.queue.lock();
+        let len = wq.len();
+        wq.retain(|t| !Arc::ptr_eq(t, task));
+        wq.len() != len
+    }
+
     /// Wakes up one task in the wait queue, usually the first one.
     ///
     /// If `resched` is true, the current task will be preempted when the
@@ -226,6 +275,32 @@ impl WaitQueue {
         count
     }
 
+    /// Transfers up to `count` tasks from this wait queue to `target` like
+    /// [`requeue`](Self::requeue), but with both queues locked, so a moved
+    /// task is always in one of them. `f` is called on each moved task.
+    pub(crate) fn requeue_with<F>(&self, count: usize, target: &WaitQueue, mut f: F) -> usize
+    where
+        F: FnMut(&AxTaskRef),
+    {
+        if core::ptr::eq(self, target) {
+            return 0;
+        }
+        // Lock the queues in address order to avoid deadlocks.
+        let (mut src, mut dst) = if (self as *const Self) < (target as *const Self) {
+            let src = self.queue.lock();
+            (src, target.queue.lock())
+        } else {
+            let dst = target.queue.lock();
+            (self.queue.lock(), dst)
+        };
+        let count = count.min(src.len());
+        for task in src.drain(..count) {
+            f(&task);
+            dst.push_back(task);
+        }
+        count
+    }
+
     /// Returns the number of tasks in the wait queue.
     pub fn len(&self) -> usize {
         self.queue.lock().len()
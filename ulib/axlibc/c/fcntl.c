@@ -39,6 +39,23 @@ int open(const char *filename, int flags, ...)
     return ax_open(filename, flags, mode);
 }
 
+// TODO: remove this function in future work
+int ax_openat(int dirfd, const char *filename, int flags, mode_t mode);
+
+int openat(int dirfd, const char *filename, int flags, ...)
+{
+    mode_t mode = 0;
+
+    if ((flags & O_CREAT) || (flags & O_TMPFILE) == O_TMPFILE) {
+        va_list ap;
+        va_start(ap, flags);
+        mode = va_arg(ap, mode_t);
+        va_end(ap);
+    }
+
+    return ax_openat(dirfd, filename, flags, mode);
+}
+
 // TODO
 int posix_fadvise(int __fd, unsigned long __offset, unsigned long __len, int __advise)
 {
@@ -13,6 +13,9 @@
 
 int h_errno;
 
+const struct in6_addr in6addr_any = IN6ADDR_ANY_INIT;
+const struct in6_addr in6addr_loopback = IN6ADDR_LOOPBACK_INIT;
+
 static const char gai_msgs[] = "Invalid flags\0"
                                "Name does not resolve\0"
                                "Try again\0"
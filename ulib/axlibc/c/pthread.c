@@ -39,13 +39,6 @@ int pthread_mutex_trylock(pthread_mutex_t *m)
     return 0;
 }
 
-// TODO
-int pthread_setname_np(pthread_t thread, const char *name)
-{
-    unimplemented();
-    return 0;
-}
-
 int pthread_cond_init(pthread_cond_t *restrict c, const pthread_condattr_t *restrict a)
 {
     *c = (pthread_cond_t){0};
@@ -57,27 +50,6 @@ int pthread_cond_init(pthread_cond_t *restrict c, const pthread_condattr_t *rest
     return 0;
 }
 
-// TODO
-int pthread_cond_signal(pthread_cond_t *__cond)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO
-int pthread_cond_wait(pthread_cond_t *__restrict__ __cond, pthread_mutex_t *__restrict__ __mutex)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO
-int pthread_cond_broadcast(pthread_cond_t *c)
-{
-    unimplemented();
-    return 0;
-}
-
 #define DEFAULT_STACK_SIZE 131072
 #define DEFAULT_GUARD_SIZE 8192
 
@@ -107,4 +79,23 @@ int pthread_attr_setstacksize(pthread_attr_t *a, size_t size)
     return 0;
 }
 
+int pthread_attr_destroy(pthread_attr_t *a)
+{
+    return 0;
+}
+
+int pthread_attr_getdetachstate(const pthread_attr_t *a, int *state)
+{
+    *state = a->_a_detach;
+    return 0;
+}
+
+int pthread_attr_setdetachstate(pthread_attr_t *a, int state)
+{
+    if (state > 1U)
+        return EINVAL;
+    a->_a_detach = state;
+    return 0;
+}
+
 #endif // AX_CONFIG_MULTITASK
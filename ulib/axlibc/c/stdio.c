@@ -1,5 +1,6 @@
 #include "printf.h"
 #include <assert.h>
+#include <ctype.h>
 #include <errno.h>
 #include <fcntl.h>
 #include <limits.h>
@@ -8,10 +9,11 @@
 #include <stdint.h>
 #include <stdio.h>
 #include <string.h>
+#include <sys/stat.h>
 #include <unistd.h>
 #include <stdlib.h>
 
-// LOCK used by `puts()`
+// LOCK used by `puts()` and the open file list
 #ifdef AX_CONFIG_MULTITASK
 #include <pthread.h>
 static pthread_mutex_t lock = PTHREAD_MUTEX_INITIALIZER;
@@ -20,74 +22,245 @@ static pthread_mutex_t lock = PTHREAD_MUTEX_INITIALIZER;
 #define MAX(a, b) ((a) > (b) ? (a) : (b))
 #define MIN(a, b) ((a) < (b) ? (a) : (b))
 
-FILE __stdin_FILE = {.fd = 0, .buffer_len = 0};
+// Every buffer keeps `UNGET` bytes in front of `buf` for `ungetc()`
+static unsigned char stdin_buf[UNGET + BUFSIZ];
+static unsigned char stdout_buf[UNGET + BUFSIZ];
+static unsigned char stderr_buf[UNGET + BUFSIZ];
 
-FILE __stdout_FILE = {.fd = 1, .buffer_len = 0};
+FILE __stdin_FILE = {
+    .flags = F_PERM | F_NOWR,
+    .fd = 0,
+    .bmode = _IOLBF,
+    .buf = stdin_buf + UNGET,
+    .buf_size = BUFSIZ,
+};
 
-FILE __stderr_FILE = {.fd = 2, .buffer_len = 0};
+FILE __stdout_FILE = {
+    .flags = F_PERM | F_NORD,
+    .fd = 1,
+    .bmode = _IOLBF,
+    .buf = stdout_buf + UNGET,
+    .buf_size = BUFSIZ,
+};
+
+FILE __stderr_FILE = {
+    .flags = F_PERM | F_NORD,
+    .fd = 2,
+    .bmode = _IOLBF,
+    .buf = stderr_buf + UNGET,
+    .buf_size = BUFSIZ,
+};
 
 FILE *const stdin = &__stdin_FILE;
 FILE *const stdout = &__stdout_FILE;
 FILE *const stderr = &__stderr_FILE;
 
-// Returns: number of chars written, negative for failure
-// Warn: buffer_len[f] will not be changed
-static int __write_buffer(FILE *f)
+// Files opened by `fopen()` and `fdopen()`, flushed by `fflush(NULL)`
+static FILE *ofl_head;
+
+static void ofl_lock(void)
 {
-    int r = 0;
-    if (f->buffer_len == 0)
+#ifdef AX_CONFIG_MULTITASK
+    pthread_mutex_lock(&lock);
+#endif
+}
+
+static void ofl_unlock(void)
+{
+#ifdef AX_CONFIG_MULTITASK
+    pthread_mutex_unlock(&lock);
+#endif
+}
+
+// Writes out the pending write buffer.
+// Returns: 0 on success, EOF on failure (the buffer is discarded)
+static int __fflush_unlocked(FILE *f)
+{
+    unsigned char *p = f->wbase;
+    while (p < f->wpos) {
+        ssize_t r = write(f->fd, p, f->wpos - p);
+        if (r <= 0) {
+            f->flags |= F_ERR;
+            f->wpos = f->wbase = f->wend = NULL;
+            return EOF;
+        }
+        p += r;
+    }
+    f->wpos = f->wbase;
+    return 0;
+}
+
+// Switches `f` to reading, flushing pending output first.
+static int __toread(FILE *f)
+{
+    if (f->flags & F_NORD) {
+        f->flags |= F_ERR;
+        errno = EBADF;
+        return EOF;
+    }
+    if (f->wpos != f->wbase && __fflush_unlocked(f))
+        return EOF;
+    f->wpos = f->wbase = f->wend = NULL;
+    if (!f->rpos)
+        f->rpos = f->rend = f->buf;
+    return 0;
+}
+
+// Switches `f` to writing, dropping buffered input.
+static int __towrite(FILE *f)
+{
+    if (f->flags & F_NOWR) {
+        f->flags |= F_ERR;
+        errno = EBADF;
+        return EOF;
+    }
+#ifdef AX_CONFIG_FS
+    // Move the file offset back to where the reader actually is
+    if (f->rpos != f->rend)
+        lseek(f->fd, f->rpos - f->rend, SEEK_CUR);
+#endif
+    f->rpos = f->rend = NULL;
+    f->wbase = f->wpos = f->buf;
+    f->wend = f->buf + f->buf_size;
+    return 0;
+}
+
+// Reads from the underlying fd, updating the EOF/error flags.
+static ssize_t __read(FILE *f, void *buf, size_t len)
+{
+    ssize_t r;
+    if (f->flags & F_EOF)
         return 0;
-    r = write(f->fd, f->buf, f->buffer_len);
+    if (f->fd < 0) {
+        f->flags |= F_EOF;
+        return 0;
+    }
+    // Make sure a prompt is visible before blocking on the console
+    if (f == stdin && stdout->wpos != stdout->wbase)
+        __fflush_unlocked(stdout);
+    r = read(f->fd, buf, len);
+    if (r <= 0)
+        f->flags |= r ? F_ERR : F_EOF;
     return r;
 }
 
-// Clear buffer_len[f]
-static void __clear_buffer(FILE *f)
+// Refills the read buffer and returns its first byte.
+int __uflow(FILE *f)
 {
-    f->buffer_len = 0;
+    ssize_t r;
+    if (__toread(f))
+        return EOF;
+    if (f->rpos < f->rend)
+        return *f->rpos++;
+    r = __read(f, f->buf, f->buf_size);
+    if (r <= 0)
+        return EOF;
+    f->rpos = f->buf;
+    f->rend = f->buf + r;
+    return *f->rpos++;
 }
 
-static int __fflush(FILE *f)
+// Returns: number of bytes accepted
+static size_t __fwritex(const unsigned char *s, size_t l, FILE *f)
 {
-    int r = __write_buffer(f);
-    __clear_buffer(f);
-    return r >= 0 ? 0 : r;
+    if (!f->wend && __towrite(f))
+        return 0;
+
+    if (l > (size_t)(f->wend - f->wpos)) {
+        if (__fflush_unlocked(f))
+            return 0;
+        if (l >= f->buf_size) {
+            // Too large for the buffer, write it out directly
+            size_t i = 0;
+            while (i < l) {
+                ssize_t r = write(f->fd, s + i, l - i);
+                if (r <= 0) {
+                    f->flags |= F_ERR;
+                    break;
+                }
+                i += r;
+            }
+            return i;
+        }
+    }
+
+    memcpy(f->wpos, s, l);
+    f->wpos += l;
+    if (f->bmode == _IONBF || (f->bmode == _IOLBF && memchr(s, '\n', l))) {
+        if (__fflush_unlocked(f))
+            return 0;
+    }
+    return l;
 }
 
 static int out(FILE *f, const char *s, size_t l)
 {
-    int ret = 0;
-    for (size_t i = 0; i < l; i++) {
-        char c = s[i];
-        f->buf[f->buffer_len++] = c;
-        if (f->buffer_len == FILE_BUF_SIZE || c == '\n') {
-            int r = __write_buffer(f);
-            __clear_buffer(f);
-            if (r < 0)
-                return r;
-            if (r < f->buffer_len)
-                return ret + r;
-            ret += r;
+    return __fwritex((const unsigned char *)s, l, f) == l ? (int)l : EOF;
+}
+
+int fflush(FILE *f)
+{
+    int r = 0;
+
+    if (!f) {
+        r |= fflush(stdout);
+        r |= fflush(stderr);
+        ofl_lock();
+        for (f = ofl_head; f; f = f->next) {
+            if (f->wpos != f->wbase)
+                r |= __fflush_unlocked(f);
         }
+        ofl_unlock();
+        return r ? EOF : 0;
     }
-    return ret;
+
+    if (f->wpos != f->wbase && __fflush_unlocked(f))
+        return EOF;
+#ifdef AX_CONFIG_FS
+    // Give unread input back to the file, if it is seekable
+    if (f->rpos != f->rend && lseek(f->fd, f->rpos - f->rend, SEEK_CUR) >= 0)
+        f->rpos = f->rend = NULL;
+#endif
+    return 0;
+}
+
+int fgetc(FILE *f)
+{
+    return f->rpos < f->rend ? *f->rpos++ : __uflow(f);
+}
+
+int getc(FILE *f)
+{
+    return fgetc(f);
+}
+
+int getc_unlocked(FILE *f)
+{
+    return fgetc(f);
 }
 
 int getchar(void)
 {
-    unimplemented();
-    return 0;
+    return fgetc(stdin);
 }
 
-int fflush(FILE *f)
+int ungetc(int c, FILE *f)
 {
-    return __fflush(f);
+    if (c == EOF)
+        return EOF;
+    if (!f->rpos && __toread(f))
+        return EOF;
+    if (!f->rpos || f->rpos <= f->buf - UNGET)
+        return EOF;
+    *--f->rpos = c;
+    f->flags &= ~F_EOF;
+    return (unsigned char)c;
 }
 
 static inline int do_putc(int c, FILE *f)
 {
-    char byte = c;
-    return out(f, &byte, 1);
+    unsigned char byte = c;
+    return __fwritex(&byte, 1, f) ? byte : EOF;
 }
 
 int fputc(int c, FILE *f)
@@ -105,15 +278,19 @@ int putchar(int c)
     return do_putc(c, stdout);
 }
 
+int fputs(const char *restrict s, FILE *restrict f)
+{
+    size_t l = strlen(s);
+    return __fwritex((const unsigned char *)s, l, f) == l ? 0 : EOF;
+}
+
 int puts(const char *s)
 {
 #ifdef AX_CONFIG_MULTITASK
     pthread_mutex_lock(&lock);
 #endif
 
-    int r = write(1, (const void *)s, strlen(s));
-    char brk[1] = {'\n'};
-    write(1, (const void *)brk, 1);
+    int r = -(fputs(s, stdout) < 0 || do_putc('\n', stdout) < 0);
 
 #ifdef AX_CONFIG_MULTITASK
     pthread_mutex_unlock(&lock);
@@ -122,6 +299,112 @@ int puts(const char *s)
     return r;
 }
 
+char *fgets(char *restrict s, int n, FILE *restrict f)
+{
+    int cnt = 0;
+
+    if (n <= 0)
+        return NULL;
+    while (cnt < n - 1) {
+        int c = fgetc(f);
+        if (c == EOF)
+            break;
+        s[cnt++] = c;
+        if (c == '\n')
+            break;
+    }
+    if (!cnt && n > 1)
+        return NULL;
+    s[cnt] = '\0';
+    return s;
+}
+
+size_t fread(void *restrict destv, size_t size, size_t nmemb, FILE *restrict f)
+{
+    unsigned char *dest = destv;
+    size_t len = size * nmemb, l = len;
+
+    if (!len)
+        return 0;
+    if (__toread(f))
+        return 0;
+
+    while (l) {
+        if (f->rpos < f->rend) {
+            size_t k = MIN((size_t)(f->rend - f->rpos), l);
+            memcpy(dest, f->rpos, k);
+            f->rpos += k;
+            dest += k;
+            l -= k;
+        } else if (l < f->buf_size) {
+            if (__uflow(f) == EOF)
+                break;
+            f->rpos--;
+        } else {
+            // Large reads bypass the buffer
+            ssize_t r = __read(f, dest, l);
+            if (r <= 0)
+                break;
+            dest += r;
+            l -= r;
+        }
+    }
+    return (len - l) / size;
+}
+
+size_t fwrite(const void *restrict src, size_t size, size_t nmemb, FILE *restrict f)
+{
+    size_t total = size * nmemb;
+    size_t k;
+
+    if (!total)
+        return 0;
+    k = __fwritex(src, total, f);
+    return k == total ? nmemb : k / size;
+}
+
+int feof(FILE *f)
+{
+    return !!(f->flags & F_EOF);
+}
+
+int ferror(FILE *f)
+{
+    return !!(f->flags & F_ERR);
+}
+
+void clearerr(FILE *f)
+{
+    f->flags &= ~(F_EOF | F_ERR);
+}
+
+int fileno(FILE *f)
+{
+    return f->fd;
+}
+
+int setvbuf(FILE *restrict f, char *restrict buf, int type, size_t size)
+{
+    if (type != _IOFBF && type != _IOLBF && type != _IONBF) {
+        errno = EINVAL;
+        return -1;
+    }
+    if (buf && size > UNGET && type != _IONBF) {
+        f->buf = (unsigned char *)buf + UNGET;
+        f->buf_size = size - UNGET;
+        f->rpos = f->rend = NULL;
+        f->wbase = f->wpos = f->wend = NULL;
+    }
+    f->bmode = type;
+    f->flags |= F_SVB;
+    return 0;
+}
+
+void setbuf(FILE *restrict f, char *restrict buf)
+{
+    setvbuf(f, buf, buf ? _IOFBF : _IONBF, BUFSIZ);
+}
+
 void perror(const char *msg)
 {
     FILE *f = stderr;
@@ -135,9 +418,21 @@ void perror(const char *msg)
     out(f, "\n", 1);
 }
 
+// Collects formatted output so that it reaches the stream in chunks
+struct out_buf {
+    FILE *f;
+    size_t len;
+    unsigned char data[128];
+};
+
 static void __out_wrapper(char c, void *arg)
 {
-    out(arg, &c, 1);
+    struct out_buf *o = arg;
+    o->data[o->len++] = c;
+    if (o->len == sizeof(o->data)) {
+        __fwritex(o->data, o->len, o->f);
+        o->len = 0;
+    }
 }
 
 int printf(const char *restrict fmt, ...)
@@ -162,15 +457,474 @@ int fprintf(FILE *restrict f, const char *restrict fmt, ...)
 
 int vfprintf(FILE *restrict f, const char *restrict fmt, va_list ap)
 {
-    return vfctprintf(__out_wrapper, f, fmt, ap);
+    struct out_buf o = {.f = f, .len = 0};
+    unsigned olderr = f->flags & F_ERR;
+    int ret;
+
+    f->flags &= ~F_ERR;
+    ret = vfctprintf(__out_wrapper, &o, fmt, ap);
+    if (o.len)
+        __fwritex(o.data, o.len, f);
+    if (f->flags & F_ERR)
+        ret = -1;
+    f->flags |= olderr;
+    return ret;
 }
 
-// TODO
-int sscanf(const char *restrict __s, const char *restrict __format, ...)
+// scanf family
+
+#define SIZE_hh -2
+#define SIZE_h  -1
+#define SIZE_def 0
+#define SIZE_l  1
+#define SIZE_L  2
+#define SIZE_ll 3
+
+static int sc_getc(FILE *f, size_t *pos)
+{
+    int c = fgetc(f);
+    if (c != EOF)
+        (*pos)++;
+    return c;
+}
+
+// Only the byte just read is ever pushed back, so it is still in the buffer
+static void sc_unget(FILE *f, int c, size_t *pos)
+{
+    if (c != EOF) {
+        f->rpos--;
+        (*pos)--;
+    }
+}
+
+static int sc_digit(int c)
+{
+    if (isdigit(c))
+        return c - '0';
+    if (isalpha(c))
+        return (c | 32) - 'a' + 10;
+    return INT_MAX;
+}
+
+static void sc_store_int(void *dest, int size, unsigned long long v)
+{
+    switch (size) {
+    case SIZE_hh:
+        *(char *)dest = v;
+        break;
+    case SIZE_h:
+        *(short *)dest = v;
+        break;
+    case SIZE_def:
+        *(int *)dest = v;
+        break;
+    case SIZE_l:
+        *(long *)dest = v;
+        break;
+    case SIZE_ll:
+        *(long long *)dest = v;
+        break;
+    }
+}
+
+// Reads at most `width` bytes of an integer in `base` (0 means auto-detect).
+// Returns: 0 on success, -1 if no digits were matched
+static int sc_int(FILE *f, size_t *pos, size_t width, int base, unsigned long long *out)
+{
+    unsigned long long v = 0;
+    int neg = 0, digits = 0, c, d;
+    size_t n = 0;
+
+#define NEXT() (++n < width ? sc_getc(f, pos) : EOF)
+    c = sc_getc(f, pos);
+    if (c == '+' || c == '-') {
+        neg = c == '-';
+        c = NEXT();
+    }
+    if ((base == 0 || base == 16) && c == '0') {
+        digits = 1;
+        c = NEXT();
+        if ((c | 32) == 'x') {
+            base = 16;
+            c = NEXT();
+        } else if (base == 0) {
+            base = 8;
+        }
+    }
+    if (base == 0)
+        base = 10;
+    while ((d = sc_digit(c)) < base) {
+        v = v * base + d;
+        digits = 1;
+        c = NEXT();
+    }
+    sc_unget(f, c, pos);
+
+    *out = neg ? -v : v;
+    return digits ? 0 : -1;
+}
+
+#ifdef AX_CONFIG_FP_SIMD
+// Reads at most `width` bytes of a decimal floating-point number.
+// Returns: 0 on success, -1 if no digits were matched
+static int sc_float(FILE *f, size_t *pos, size_t width, double *out)
 {
-    unimplemented();
+    char tmp[64];
+    size_t n = 0, k = 0;
+    int digits = 0, c;
+
+#define PUT(ch)                      \
+    do {                             \
+        if (k < sizeof(tmp) - 1)     \
+            tmp[k++] = (ch);         \
+    } while (0)
+
+    c = sc_getc(f, pos);
+    if (c == '+' || c == '-') {
+        PUT(c);
+        c = NEXT();
+    }
+    for (; isdigit(c); c = NEXT(), digits = 1)
+        PUT(c);
+    if (c == '.') {
+        PUT(c);
+        for (c = NEXT(); isdigit(c); c = NEXT(), digits = 1)
+            PUT(c);
+    }
+    if (digits && (c | 32) == 'e') {
+        PUT(c);
+        c = NEXT();
+        if (c == '+' || c == '-') {
+            PUT(c);
+            c = NEXT();
+        }
+        for (; isdigit(c); c = NEXT())
+            PUT(c);
+    }
+    sc_unget(f, c, pos);
+#undef PUT
+
+    if (!digits)
+        return -1;
+    tmp[k] = '\0';
+    *out = strtod(tmp, NULL);
     return 0;
 }
+#endif // AX_CONFIG_FP_SIMD
+#undef NEXT
+
+int vfscanf(FILE *restrict f, const char *restrict fmt, va_list ap)
+{
+    const unsigned char *p;
+    unsigned char scanset[256];
+    size_t pos = 0;
+    int matches = 0;
+    int c;
+
+    for (p = (const unsigned char *)fmt; *p; p++) {
+        int suppress = 0, size = SIZE_def, t;
+        size_t width = 0;
+        void *dest = NULL;
+
+        if (isspace(*p)) {
+            while (isspace(p[1]))
+                p++;
+            do
+                c = sc_getc(f, &pos);
+            while (isspace(c));
+            sc_unget(f, c, &pos);
+            continue;
+        }
+        if (*p != '%' || p[1] == '%') {
+            if (*p == '%') {
+                p++;
+                do
+                    c = sc_getc(f, &pos);
+                while (isspace(c));
+            } else {
+                c = sc_getc(f, &pos);
+            }
+            if (c != *p) {
+                sc_unget(f, c, &pos);
+                if (c == EOF)
+                    goto input_fail;
+                goto match_fail;
+            }
+            continue;
+        }
+
+        p++;
+        if (*p == '*') {
+            suppress = 1;
+            p++;
+        }
+        for (; isdigit(*p); p++)
+            width = 10 * width + *p - '0';
+
+        switch (*p++) {
+        case 'h':
+            size = SIZE_h;
+            if (*p == 'h') {
+                p++;
+                size = SIZE_hh;
+            }
+            break;
+        case 'l':
+            size = SIZE_l;
+            if (*p == 'l') {
+                p++;
+                size = SIZE_ll;
+            }
+            break;
+        case 'j':
+        case 'q':
+            size = SIZE_ll;
+            break;
+        case 'z':
+        case 't':
+            size = SIZE_l;
+            break;
+        case 'L':
+            size = SIZE_L;
+            break;
+        default:
+            p--;
+        }
+
+        t = *p;
+        if (!t)
+            goto match_fail;
+        if (!suppress)
+            dest = va_arg(ap, void *);
+
+        if (t == 'n') {
+            if (dest)
+                sc_store_int(dest, size, pos);
+            continue;
+        }
+
+        do
+            c = sc_getc(f, &pos);
+        while (isspace(c) && t != 'c' && t != '[');
+        sc_unget(f, c, &pos);
+        if (c == EOF)
+            goto input_fail;
+
+        switch (t) {
+        case 'c': {
+            size_t i;
+            if (!width)
+                width = 1;
+            for (i = 0; i < width; i++) {
+                if ((c = sc_getc(f, &pos)) == EOF)
+                    break;
+                if (dest)
+                    ((char *)dest)[i] = c;
+            }
+            if (i < width)
+                goto input_fail;
+            break;
+        }
+        case 's':
+        case '[': {
+            size_t i = 0;
+            if (t == 's') {
+                memset(scanset, 1, sizeof(scanset));
+                for (c = 0; c < 256; c++) {
+                    if (isspace(c))
+                        scanset[c] = 0;
+                }
+            } else {
+                const unsigned char *start;
+                int invert = 0;
+                p++;
+                if (*p == '^') {
+                    invert = 1;
+                    p++;
+                }
+                memset(scanset, invert, sizeof(scanset));
+                start = p;
+                if (*p == ']')
+                    scanset[*p++] = !invert;
+                for (; *p && *p != ']'; p++) {
+                    if (*p == '-' && p > start && p[1] && p[1] != ']') {
+                        for (c = p[-1]; c <= p[1]; c++)
+                            scanset[c] = !invert;
+                        p++;
+                    } else {
+                        scanset[*p] = !invert;
+                    }
+                }
+                if (!*p)
+                    goto match_fail;
+            }
+            if (!width)
+                width = SIZE_MAX;
+            for (; i < width; i++) {
+                c = sc_getc(f, &pos);
+                if (c == EOF || !scanset[c]) {
+                    sc_unget(f, c, &pos);
+                    break;
+                }
+                if (dest)
+                    ((char *)dest)[i] = c;
+            }
+            if (!i)
+                goto match_fail;
+            if (dest)
+                ((char *)dest)[i] = '\0';
+            break;
+        }
+        case 'd':
+        case 'i':
+        case 'o':
+        case 'u':
+        case 'x':
+        case 'X':
+        case 'p': {
+            unsigned long long v;
+            int base = t == 'i' ? 0 : t == 'o' ? 8 : (t | 32) == 'x' || t == 'p' ? 16 : 10;
+            if (sc_int(f, &pos, width ? width : SIZE_MAX, base, &v))
+                goto match_fail;
+            if (dest && t == 'p')
+                *(void **)dest = (void *)(uintptr_t)v;
+            else if (dest)
+                sc_store_int(dest, size, v);
+            break;
+        }
+#ifdef AX_CONFIG_FP_SIMD
+        case 'a':
+        case 'e':
+        case 'f':
+        case 'g':
+        case 'A':
+        case 'E':
+        case 'F':
+        case 'G': {
+            double v;
+            if (sc_float(f, &pos, width ? width : SIZE_MAX, &v))
+                goto match_fail;
+            if (dest && size == SIZE_L)
+                *(long double *)dest = v;
+            else if (dest && size == SIZE_l)
+                *(double *)dest = v;
+            else if (dest)
+                *(float *)dest = v;
+            break;
+        }
+#endif
+        default:
+            goto match_fail;
+        }
+
+        if (dest)
+            matches++;
+    }
+    return matches;
+
+input_fail:
+    if (!matches)
+        return EOF;
+match_fail:
+    return matches;
+}
+
+int vscanf(const char *restrict fmt, va_list ap)
+{
+    return vfscanf(stdin, fmt, ap);
+}
+
+int vsscanf(const char *restrict s, const char *restrict fmt, va_list ap)
+{
+    FILE f = {
+        .flags = F_PERM | F_NOWR,
+        .fd = -1,
+        .buf = (unsigned char *)s,
+        .rpos = (unsigned char *)s,
+        .rend = (unsigned char *)s + strlen(s),
+    };
+    return vfscanf(&f, fmt, ap);
+}
+
+int scanf(const char *restrict fmt, ...)
+{
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = vfscanf(stdin, fmt, ap);
+    va_end(ap);
+    return ret;
+}
+
+int fscanf(FILE *restrict f, const char *restrict fmt, ...)
+{
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = vfscanf(f, fmt, ap);
+    va_end(ap);
+    return ret;
+}
+
+int sscanf(const char *restrict s, const char *restrict fmt, ...)
+{
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = vsscanf(s, fmt, ap);
+    va_end(ap);
+    return ret;
+}
+
+#ifdef AX_CONFIG_ALLOC
+
+ssize_t getdelim(char **restrict s, size_t *restrict n, int delim, FILE *restrict f)
+{
+    size_t i = 0;
+    int c;
+
+    if (!n || !s) {
+        f->flags |= F_ERR;
+        errno = EINVAL;
+        return -1;
+    }
+    if (!*s)
+        *n = 0;
+
+    for (;;) {
+        c = fgetc(f);
+        if (c == EOF)
+            break;
+        if (i + 2 > *n) {
+            size_t m = MAX(2 * *n, i + 2);
+            char *tmp = realloc(*s, MAX(m, 128));
+            if (!tmp) {
+                f->flags |= F_ERR;
+                errno = ENOMEM;
+                return -1;
+            }
+            *s = tmp;
+            *n = MAX(m, 128);
+        }
+        (*s)[i++] = c;
+        if (c == delim)
+            break;
+    }
+    if (!i) {
+        if (*s && *n)
+            (*s)[0] = '\0';
+        return -1;
+    }
+    (*s)[i] = '\0';
+    return i;
+}
+
+ssize_t getline(char **restrict s, size_t *restrict n, FILE *restrict f)
+{
+    return getdelim(s, n, '\n', f);
+}
+
+#endif // AX_CONFIG_ALLOC
 
 #ifdef AX_CONFIG_FS
 
@@ -196,216 +950,209 @@ int __fmodeflags(const char *mode)
     return flags;
 }
 
-FILE *fopen(const char *filename, const char *mode)
+static unsigned __fmode_rw(const char *mode)
+{
+    if (strchr(mode, '+'))
+        return 0;
+    return *mode == 'r' ? F_NOWR : F_NORD;
+}
+
+FILE *fdopen(int fd, const char *mode)
 {
     FILE *f;
-    int flags;
 
     if (!strchr("rwa", *mode)) {
         errno = EINVAL;
-        return 0;
+        return NULL;
     }
 
-    f = (FILE *)malloc(sizeof(FILE));
-
-    flags = __fmodeflags(mode);
-    // TODO: currently mode is unused in ax_open
-    int fd = open(filename, flags, 0666);
-    if (fd < 0)
+    // The FILE and its buffer share one allocation
+    f = (FILE *)malloc(sizeof(FILE) + UNGET + BUFSIZ);
+    if (!f)
         return NULL;
+    memset(f, 0, sizeof(FILE));
+    f->flags = __fmode_rw(mode);
     f->fd = fd;
+    f->bmode = _IOFBF;
+    f->buf = (unsigned char *)(f + 1) + UNGET;
+    f->buf_size = BUFSIZ;
+
+    ofl_lock();
+    f->next = ofl_head;
+    if (ofl_head)
+        ofl_head->prev = f;
+    ofl_head = f;
+    ofl_unlock();
 
     return f;
 }
 
-char *fgets(char *restrict s, int n, FILE *restrict f)
+FILE *fopen(const char *filename, const char *mode)
 {
-    if (n == 0)
+    FILE *f;
+    int fd;
+
+    if (!strchr("rwa", *mode)) {
+        errno = EINVAL;
         return NULL;
-    if (n == 1) {
-        *s = '\0';
-        return s;
     }
 
-    int cnt = 0;
-    while (cnt < n - 1) {
-        char c;
-        if (read(f->fd, (void *)&c, 1) > 0) {
-            if (c != '\n')
-                s[cnt++] = c;
-            else
-                break;
-        } else
-            break;
-    }
-    s[cnt] = '\0';
-    return s;
+    fd = open(filename, __fmodeflags(mode), 0666);
+    if (fd < 0)
+        return NULL;
+    f = fdopen(fd, mode);
+    if (!f)
+        close(fd);
+    return f;
 }
 
-size_t fread(void *restrict destv, size_t size, size_t nmemb, FILE *restrict f)
+FILE *freopen(const char *restrict filename, const char *restrict mode, FILE *restrict f)
 {
-    size_t total = size * nmemb;
-    size_t read_len = 0;
-    size_t len = 0;
-    do {
-        len = read(f->fd, destv + read_len, total - read_len);
-        if (len < 0)
-            break;
-        read_len += len;
-    } while (len > 0);
-    return read_len == size * nmemb ? nmemb : read_len / size;
-}
+    fflush(f);
 
-size_t fwrite(const void *restrict src, size_t size, size_t nmemb, FILE *restrict f)
-{
-    size_t total = size * nmemb;
-    size_t write_len = 0;
-    size_t len = 0;
-    do {
-        len = write(f->fd, src + write_len, total - write_len);
-        if (len < 0)
-            break;
-        write_len += len;
-    } while (len > 0);
-    return write_len == size * nmemb ? nmemb : write_len / size;
-}
+    if (filename) {
+        int fd = open(filename, __fmodeflags(mode), 0666);
+        if (fd < 0)
+            goto fail;
+        // Keep the descriptor number, so that `freopen(.., stdout)` redirects fd 1 as well
+        if (fd != f->fd) {
+            int r = dup2(fd, f->fd);
+            close(fd);
+            if (r < 0)
+                goto fail;
+        }
+    }
 
-int fputs(const char *restrict s, FILE *restrict f)
-{
-    size_t l = strlen(s);
-    return (fwrite(s, 1, l, f) == l) - 1;
-}
+    f->flags = (f->flags & F_PERM) | __fmode_rw(mode);
+    f->rpos = f->rend = NULL;
+    f->wbase = f->wpos = f->wend = NULL;
+    return f;
 
-int fclose(FILE *f)
-{
-    return close(f->fd);
+fail:
+    fclose(f);
+    return NULL;
 }
 
-int fileno(FILE *f)
+int fclose(FILE *f)
 {
-    return f->fd;
-}
+    int r = fflush(f);
+    r |= close(f->fd);
 
-int feof(FILE *f)
-{
-    unimplemented();
-    return 0;
-}
+    if (f->flags & F_PERM)
+        return r ? EOF : 0;
 
-// TODO
-int fseek(FILE *__stream, long __off, int __whence)
-{
-    unimplemented();
-    return 0;
-}
+    ofl_lock();
+    if (f->prev)
+        f->prev->next = f->next;
+    if (f->next)
+        f->next->prev = f->prev;
+    if (ofl_head == f)
+        ofl_head = f->next;
+    ofl_unlock();
 
-// TODO
-off_t ftello(FILE *__stream)
-{
-    unimplemented();
-    return 0;
+    free(f);
+    return r ? EOF : 0;
 }
 
-// TODO
-char *tmpnam(char *buf)
+int fseeko(FILE *f, off_t off, int whence)
 {
-    unimplemented();
-    return 0;
-}
+    if (whence != SEEK_SET && whence != SEEK_CUR && whence != SEEK_END) {
+        errno = EINVAL;
+        return -1;
+    }
 
-// TODO
-void clearerr(FILE *f)
-{
-    unimplemented();
-}
+    // Account for input that has been buffered but not consumed
+    if (whence == SEEK_CUR && f->rend)
+        off -= f->rend - f->rpos;
+    if (f->wpos != f->wbase && __fflush_unlocked(f))
+        return -1;
+    f->wpos = f->wbase = f->wend = NULL;
 
-// TODO
-int ferror(FILE *f)
-{
-    unimplemented();
+    if (lseek(f->fd, off, whence) < 0)
+        return -1;
+    f->rpos = f->rend = NULL;
+    f->flags &= ~F_EOF;
     return 0;
 }
 
-// TODO
-FILE *freopen(const char *restrict filename, const char *restrict mode, FILE *restrict f)
+int fseek(FILE *f, long off, int whence)
 {
-    unimplemented();
-    return 0;
+    return fseeko(f, off, whence);
 }
 
-// TODO
-int fscanf(FILE *restrict f, const char *restrict fmt, ...)
+off_t ftello(FILE *f)
 {
-    unimplemented();
-    return 0;
+    off_t pos = lseek(f->fd, 0, SEEK_CUR);
+    if (pos < 0)
+        return pos;
+    if (f->rend)
+        pos -= f->rend - f->rpos;
+    else if (f->wbase)
+        pos += f->wpos - f->wbase;
+    return pos;
 }
 
-// TODO
 long ftell(FILE *f)
 {
-    unimplemented();
-    return 0;
+    off_t pos = ftello(f);
+    if (pos > LONG_MAX) {
+        errno = EOVERFLOW;
+        return -1;
+    }
+    return pos;
 }
 
-int getc(FILE *f)
+void rewind(FILE *f)
 {
-    unimplemented();
-    return 0;
+    fseeko(f, 0, SEEK_SET);
+    f->flags &= ~F_ERR;
 }
 
-// TODO
 int remove(const char *path)
 {
-    unimplemented();
-    return 0;
+    int r = unlink(path);
+    if (r && errno == EISDIR)
+        r = rmdir(path);
+    return r;
 }
 
-// TODO
-int setvbuf(FILE *restrict f, char *restrict buf, int type, size_t size)
+char *tmpnam(char *buf)
 {
-    unimplemented();
-    return 0;
-}
+    static char internal[L_tmpnam];
+    static unsigned int counter;
+    char *s = buf ? buf : internal;
+    struct stat st;
 
-// TODO
-FILE *tmpfile(void)
-{
-    unimplemented();
+    for (int try = 0; try < 100; try++) {
+        snprintf(s, L_tmpnam, "/tmp/tmp_%u", counter++);
+        if (stat(s, &st) < 0 && errno == ENOENT)
+            return s;
+    }
     return NULL;
 }
 
-int ungetc(int c, FILE *f)
-{
-    unimplemented();
-    return 0;
-}
-
-ssize_t getdelim(char **restrict s, size_t *restrict n, int delim, FILE *restrict f)
-{
-    unimplemented();
-    return 0;
-}
-
-ssize_t getline(char **restrict s, size_t *restrict n, FILE *restrict f)
-{
-    return getdelim(s, n, '\n', f);
-}
-
-int __uflow(FILE *f)
+FILE *tmpfile(void)
 {
-    unimplemented();
-    return 0;
-}
+    char name[L_tmpnam];
 
-int getc_unlocked(FILE *f)
-{
-    unimplemented();
-    return 0;
-}
+    for (int try = 0; try < 100; try++) {
+        FILE *f;
+        int fd;
 
-FILE *fdopen(int fd, const char *mode)
-{
-    unimplemented();
+        if (!tmpnam(name))
+            return NULL;
+        fd = open(name, O_RDWR | O_CREAT | O_EXCL, 0600);
+        if (fd < 0) {
+            if (errno == EEXIST)
+                continue;
+            return NULL;
+        }
+        unlink(name);
+        f = fdopen(fd, "w+");
+        if (!f)
+            close(fd);
+        return f;
+    }
     return NULL;
 }
 
@@ -54,13 +54,6 @@ int usleep(unsigned useconds)
 
 #ifdef AX_CONFIG_FS
 
-// TODO:
-int access(const char *pathname, int mode)
-{
-    unimplemented();
-    return 0;
-}
-
 // TODO:
 ssize_t readlink(const char *path, char *buf, size_t bufsiz)
 {
@@ -68,34 +61,6 @@ ssize_t readlink(const char *path, char *buf, size_t bufsiz)
     return 0;
 }
 
-// TODO:
-int unlink(const char *pathname)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-int rmdir(const char *pathname)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-int fsync(int fd)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO
-int fdatasync(int __fildes)
-{
-    unimplemented();
-    return 0;
-}
-
 // TODO:
 int fchown(int fd, uid_t owner, gid_t group)
 {
@@ -103,27 +68,6 @@ int fchown(int fd, uid_t owner, gid_t group)
     return 0;
 }
 
-// TODO:
-int ftruncate(int fd, off_t length)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO
-int chdir(const char *__path)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO
-int truncate(const char *path, off_t length)
-{
-    unimplemented();
-    return 0;
-}
-
 #endif // AX_CONFIG_FS
 
 #ifdef AX_CONFIG_PIPE
@@ -159,6 +103,8 @@ _Noreturn void _exit(int status)
     exit(status);
 }
 
+#ifndef AX_CONFIG_USPACE
+
 // TODO
 int execve(const char *__path, char *const *__argv, char *const *__envp)
 {
@@ -172,3 +118,5 @@ pid_t fork(void)
     unimplemented();
     return -1;
 }
+
+#endif // AX_CONFIG_USPACE
@@ -1,2 +1,3 @@
 #include <setjmp.h>
+#include <signal.h>
 #include <time.h>
@@ -28,8 +28,12 @@ DIR *opendir(const char *);
 struct dirent *readdir(DIR *);
 int readdir_r(DIR *__restrict, struct dirent *__restrict, struct dirent **__restrict);
 void rewinddir(DIR *);
+void seekdir(DIR *, long);
+long telldir(DIR *);
 int dirfd(DIR *);
 
+ssize_t getdents64(int, void *, size_t);
+
 #define DT_UNKNOWN 0
 #define DT_FIFO    1
 #define DT_CHR     2
@@ -56,6 +56,8 @@
 
 #define FD_CLOEXEC      1
 #define F_DUPFD_CLOEXEC 1030
+#define F_SETPIPE_SZ    1031
+#define F_GETPIPE_SZ    1032
 
 #define F_RDLCK 0
 #define F_WRLCK 1
@@ -97,8 +99,11 @@
 #define POSIX_FADV_NOREUSE  5
 #endif
 
-#define AT_FDCWD      (-100)
-#define AT_EMPTY_PATH 0x1000
+#define AT_FDCWD            (-100)
+#define AT_SYMLINK_NOFOLLOW 0x100
+#define AT_REMOVEDIR        0x200
+#define AT_EACCESS          0x200
+#define AT_EMPTY_PATH       0x1000
 
 #define SYNC_FILE_RANGE_WAIT_BEFORE 1
 #define SYNC_FILE_RANGE_WRITE       2
@@ -119,5 +124,6 @@ int posix_fadvise(int __fd, unsigned long __offset, unsigned long __len, int __a
 int sync_file_range(int, off_t, off_t, unsigned);
 
 int open(const char *filename, int flags, ...);
+int openat(int dirfd, const char *filename, int flags, ...);
 
 #endif
@@ -23,8 +23,11 @@
 #define LLONG_MAX  0x7fffffffffffffffLL
 #define ULLONG_MAX (2ULL * LLONG_MAX + 1)
 #define IOV_MAX    1024
+#define PIPE_BUF   4096
 
-#define PTHREAD_STACK_MIN 2048
+#define PTHREAD_STACK_MIN             2048
+#define PTHREAD_KEYS_MAX              128
+#define PTHREAD_DESTRUCTOR_ITERATIONS 4
 
 #define LOGIN_NAME_MAX 256
 #ifndef NAME_MAX
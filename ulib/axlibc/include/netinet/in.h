@@ -126,4 +126,6 @@ struct sockaddr_in6 {
     uint32_t sin6_scope_id;
 };
 
+extern const struct in6_addr in6addr_any, in6addr_loopback;
+
 #endif // _NETINET_IN_H
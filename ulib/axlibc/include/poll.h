@@ -1,6 +1,9 @@
 #ifndef _POLL_H
 #define _POLL_H
 
+#include <signal.h>
+#include <time.h>
+
 struct pollfd {
     int fd;
     short events;
@@ -17,5 +20,6 @@ struct pollfd {
 typedef unsigned long nfds_t;
 
 int poll(struct pollfd *__fds, nfds_t __nfds, int __timeout);
+int ppoll(struct pollfd *, nfds_t, const struct timespec *, const sigset_t *);
 
 #endif // _POLL_H
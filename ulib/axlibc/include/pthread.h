@@ -11,6 +11,11 @@
 #define PTHREAD_CANCEL_DEFERRED     0
 #define PTHREAD_CANCEL_ASYNCHRONOUS 1
 
+#define PTHREAD_CREATE_JOINABLE 0
+#define PTHREAD_CREATE_DETACHED 1
+
+#define PTHREAD_ONCE_INIT 0
+
 typedef struct {
     unsigned __attr;
 } pthread_condattr_t;
@@ -31,6 +36,7 @@ typedef struct {
 #define _a_stacksize __u.__s[0]
 #define _a_guardsize __u.__s[1]
 #define _a_stackaddr __u.__s[2]
+#define _a_detach    __u.__i[3 * sizeof(long) / sizeof(int)]
 
 typedef struct {
     union {
@@ -43,6 +49,8 @@ typedef struct {
 #define _c_shared __u.__p[0]
 
 typedef void *pthread_t;
+typedef unsigned pthread_key_t;
+typedef int pthread_once_t;
 
 #define PTHREAD_CANCELED ((void *)-1)
 #define SIGCANCEL        33
@@ -55,6 +63,7 @@ pthread_t pthread_self(void);
 int pthread_create(pthread_t *__restrict, const pthread_attr_t *__restrict, void *(*)(void *),
                    void *__restrict);
 int pthread_join(pthread_t t, void **res);
+int pthread_detach(pthread_t);
 
 int pthread_setcancelstate(int, int *);
 int pthread_setcanceltype(int, int *);
@@ -67,6 +76,14 @@ int pthread_mutex_unlock(pthread_mutex_t *);
 int pthread_mutex_trylock(pthread_mutex_t *);
 
 int pthread_setname_np(pthread_t, const char *);
+int pthread_getname_np(pthread_t, char *, size_t);
+
+int pthread_key_create(pthread_key_t *, void (*)(void *));
+int pthread_key_delete(pthread_key_t);
+void *pthread_getspecific(pthread_key_t);
+int pthread_setspecific(pthread_key_t, const void *);
+
+int pthread_once(pthread_once_t *, void (*)(void));
 
 int pthread_cond_init(pthread_cond_t *__restrict__ __cond,
                       const pthread_condattr_t *__restrict__ __cond_attr);
@@ -78,6 +95,9 @@ int pthread_attr_init(pthread_attr_t *__attr);
 int pthread_attr_getstacksize(const pthread_attr_t *__restrict__ __attr,
                               size_t *__restrict__ __stacksize);
 int pthread_attr_setstacksize(pthread_attr_t *__attr, size_t __stacksize);
+int pthread_attr_destroy(pthread_attr_t *__attr);
+int pthread_attr_getdetachstate(const pthread_attr_t *__attr, int *__detachstate);
+int pthread_attr_setdetachstate(pthread_attr_t *__attr, int __detachstate);
 
 #endif // AX_CONFIG_MULTITASK
 
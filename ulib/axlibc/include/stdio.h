@@ -3,17 +3,21 @@
 
 #include <stdarg.h>
 #include <stddef.h>
+#include <sys/types.h>
 
 #define _IOFBF 0
 #define _IOLBF 1
 #define _IONBF 2
 
-#define FILE_BUF_SIZE 1024
-// TODO: complete this struct
 struct IO_FILE {
+    unsigned flags;
     int fd;
-    uint16_t buffer_len;
-    char buf[FILE_BUF_SIZE];
+    int bmode;
+    unsigned char *buf;
+    size_t buf_size;
+    unsigned char *rpos, *rend;
+    unsigned char *wbase, *wpos, *wend;
+    struct IO_FILE *prev, *next;
 };
 
 typedef struct IO_FILE FILE;
@@ -45,10 +49,12 @@ extern FILE *const stderr;
 #define SEEK_CUR 1
 #define SEEK_END 2
 
+#define F_PERM 1
+#define F_NORD 4
+#define F_NOWR 8
 #define F_EOF  16
 #define F_ERR  32
 #define F_SVB  64
-#define F_NORD 4
 #define UNGET  8
 
 #define FILENAME_MAX 4096
@@ -61,6 +67,7 @@ int fclose(FILE *);
 
 int remove(const char *);
 int rename(const char *, const char *);
+int renameat(int, const char *, int, const char *);
 
 int feof(FILE *__stream);
 int ferror(FILE *);
@@ -69,10 +76,14 @@ void clearerr(FILE *);
 
 int fseek(FILE *__stream, long __off, int __whence);
 long ftell(FILE *);
+int fseeko(FILE *, off_t, int);
+off_t ftello(FILE *);
+void rewind(FILE *);
 
 size_t fread(void *__restrict, size_t, size_t, FILE *__restrict);
 size_t fwrite(const void *__restrict, size_t, size_t, FILE *__restrict);
 
+int fgetc(FILE *);
 int getc(FILE *);
 int getchar(void);
 int ungetc(int, FILE *);
@@ -95,11 +106,16 @@ int vfprintf(FILE *__restrict, const char *__restrict, va_list);
 int vsprintf(char *__restrict, const char *__restrict, va_list);
 int vsnprintf(char *__restrict, size_t, const char *__restrict, va_list);
 
+int scanf(const char *__restrict, ...);
 int fscanf(FILE *__restrict, const char *__restrict, ...);
 int sscanf(const char *__restrict, const char *__restrict, ...);
+int vscanf(const char *__restrict, va_list);
+int vfscanf(FILE *__restrict, const char *__restrict, va_list);
+int vsscanf(const char *__restrict, const char *__restrict, va_list);
 
 void perror(const char *);
 
+void setbuf(FILE *__restrict, char *__restrict);
 int setvbuf(FILE *__restrict, char *__restrict, int, size_t);
 
 char *tmpnam(char *);
@@ -107,7 +123,6 @@ FILE *tmpfile(void);
 
 FILE *fdopen(int, const char *);
 int fileno(FILE *);
-off_t ftello(FILE *);
 
 int getc_unlocked(FILE *);
 ssize_t getdelim(char **__restrict, size_t *__restrict, int, FILE *__restrict);
@@ -60,6 +60,18 @@
 #define TIOCGISO7816 0x80285442
 #define TIOCSISO7816 0xc0285443
 
+#define SIOCADDRT      0x890b
+#define SIOCDELRT      0x890c
+#define SIOCGIFCONF    0x8912
+#define SIOCGIFADDR    0x8915
+#define SIOCSIFADDR    0x8916
+#define SIOCGIFNETMASK 0x891b
+#define SIOCSIFNETMASK 0x891c
+#define SIOCGIFMTU     0x8921
+#define SIOCSIFMTU     0x8922
+#define SIOCGIFHWADDR  0x8927
+#define SIOCDIFADDR    0x8936
+
 int ioctl(int, int, ...);
 
 #endif // __SYS_IOCTL_H__
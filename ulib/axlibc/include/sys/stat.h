@@ -72,6 +72,8 @@ int lstat(const char *path, struct stat *buf);
 int fchmod(int fd, mode_t mode);
 int chmod(const char *file, mode_t mode);
 int mkdir(const char *pathname, mode_t mode);
+int mkdirat(int dirfd, const char *pathname, mode_t mode);
+int mkfifo(const char *pathname, mode_t mode);
 mode_t umask(mode_t mask);
 int fstatat(int, const char *__restrict, struct stat *__restrict, int);
 
@@ -32,6 +32,11 @@ struct itimerval {
     struct timeval it_value;
 };
 
+struct itimerspec {
+    struct timespec it_interval;
+    struct timespec it_value;
+};
+
 int gettimeofday(struct timeval *tv, struct timezone *tz);
 
 int getitimer(int, struct itimerval *);
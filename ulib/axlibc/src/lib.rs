@@ -18,6 +18,7 @@
 //! - Lib C functions
 //!     - `fd`: Enable file descriptor table.
 //!     - `pipe`: Enable pipe support.
+//!     - `signal`: Enable signal support.
 //!     - `select`: Enable synchronous I/O multiplexing ([select]) support.
 //!     - `epoll`: Enable event polling ([epoll]) support.
 //!
@@ -47,11 +48,13 @@ mod ctypes {
 #[macro_use]
 mod utils;
 
+#[cfg(feature = "eventfd")]
+mod eventfd;
 #[cfg(feature = "fd")]
 mod fd_ops;
 #[cfg(feature = "fs")]
 mod fs;
-#[cfg(any(feature = "select", feature = "epoll"))]
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 mod io_mpx;
 #[cfg(feature = "alloc")]
 mod malloc;
@@ -61,10 +64,16 @@ mod net;
 mod pipe;
 #[cfg(feature = "multitask")]
 mod pthread;
+#[cfg(feature = "signal")]
+mod signal;
+#[cfg(feature = "signalfd")]
+mod signalfd;
 #[cfg(feature = "alloc")]
 mod strftime;
 #[cfg(feature = "fp-simd")]
 mod strtod;
+#[cfg(feature = "timerfd")]
+mod timerfd;
 
 mod errno;
 mod io;
@@ -88,6 +97,8 @@ pub use self::setjmp::{longjmp, setjmp};
 pub use self::sys::sysconf;
 pub use self::time::{clock_gettime, nanosleep};
 pub use self::unistd::{abort, exit, getpid};
+#[cfg(feature = "uspace")]
+pub use self::unistd::{execve, fork, waitpid};
 
 #[cfg(feature = "alloc")]
 pub use self::malloc::{free, malloc};
@@ -95,10 +106,14 @@ pub use self::malloc::{free, malloc};
 pub use self::strftime::strftime;
 
 #[cfg(feature = "fd")]
-pub use self::fd_ops::{ax_fcntl, close, dup, dup2, dup3};
+pub use self::fd_ops::{ax_fcntl, ax_ioctl, close, dup, dup2, dup3};
 
 #[cfg(feature = "fs")]
-pub use self::fs::{ax_open, fstat, getcwd, lseek, lstat, rename, stat};
+pub use self::fs::{
+    access, ax_open, ax_openat, chdir, chmod, faccessat, fchdir, fchmod, fdatasync, fstat, fstatat,
+    fsync, ftruncate, getcwd, getdents64, lseek, lstat, mkdir, mkdirat, rename, renameat, rmdir,
+    stat, truncate, umask, unlink, unlinkat,
+};
 
 #[cfg(feature = "net")]
 pub use self::net::{
@@ -107,17 +122,45 @@ pub use self::net::{
 };
 
 #[cfg(feature = "multitask")]
-pub use self::pthread::{pthread_create, pthread_exit, pthread_join, pthread_self};
+pub use self::pthread::{pthread_cond_broadcast, pthread_cond_signal, pthread_cond_wait};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_create, pthread_detach, pthread_exit, pthread_getname_np, pthread_join, pthread_self,
+    pthread_setname_np,
+};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_getspecific, pthread_key_create, pthread_key_delete, pthread_once, pthread_setspecific,
+};
 #[cfg(feature = "multitask")]
 pub use self::pthread::{pthread_mutex_init, pthread_mutex_lock, pthread_mutex_unlock};
 
+#[cfg(all(feature = "fs", feature = "pipe"))]
+pub use self::pipe::mkfifo;
 #[cfg(feature = "pipe")]
 pub use self::pipe::pipe;
 
+#[cfg(all(feature = "signal", feature = "multitask"))]
+pub use self::signal::pthread_kill;
+#[cfg(feature = "signal")]
+pub use self::signal::{
+    alarm, getitimer, kill, pthread_sigmask, setitimer, sigaction, sigpending, sigprocmask,
+    sigsuspend,
+};
+
+#[cfg(feature = "eventfd")]
+pub use self::eventfd::eventfd;
+#[cfg(feature = "signalfd")]
+pub use self::signalfd::signalfd;
+#[cfg(feature = "timerfd")]
+pub use self::timerfd::{timerfd_create, timerfd_gettime, timerfd_settime};
+
 #[cfg(feature = "select")]
 pub use self::io_mpx::select;
 #[cfg(feature = "epoll")]
 pub use self::io_mpx::{epoll_create, epoll_ctl, epoll_wait};
+#[cfg(feature = "poll")]
+pub use self::io_mpx::{poll, ppoll};
 
 #[cfg(feature = "fp-simd")]
 pub use self::strtod::{strtod, strtof};
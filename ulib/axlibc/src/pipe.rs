@@ -1,7 +1,11 @@
+#[cfg(feature = "fs")]
+use core::ffi::c_char;
 use core::ffi::c_int;
 
 use arceos_posix_api::sys_pipe;
 
+#[cfg(feature = "fs")]
+use crate::ctypes;
 use crate::utils::e;
 
 /// Create a pipe
@@ -12,3 +16,12 @@ pub unsafe extern "C" fn pipe(fd: *mut c_int) -> c_int {
     let fds = unsafe { core::slice::from_raw_parts_mut(fd, 2) };
     e(sys_pipe(fds))
 }
+
+/// Create a named pipe (FIFO) at `pathname`
+///
+/// Return 0 if succeed
+#[cfg(feature = "fs")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mkfifo(pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
+    e(arceos_posix_api::sys_mkfifo(pathname, mode))
+}
@@ -1,6 +1,6 @@
 use crate::{ctypes, utils::e};
 use arceos_posix_api as api;
-use core::ffi::{c_int, c_void};
+use core::ffi::{c_char, c_int, c_void};
 
 /// Returns the `pthread` struct of current thread.
 #[unsafe(no_mangle)]
@@ -57,3 +57,89 @@ pub unsafe extern "C" fn pthread_mutex_lock(mutex: *mut ctypes::pthread_mutex_t)
 pub unsafe extern "C" fn pthread_mutex_unlock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
     e(api::sys_pthread_mutex_unlock(mutex))
 }
+
+/// Wait on a condition variable, the mutex is released while waiting.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    e(api::sys_pthread_cond_wait(cond, mutex))
+}
+
+/// Unblock at least one of the threads that are blocked on the condition
+/// variable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_signal(cond))
+}
+
+/// Unblock all threads that are blocked on the condition variable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_broadcast(cond))
+}
+
+/// Detach the given thread, so that its resources are released when it exits.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_detach(thread: ctypes::pthread_t) -> c_int {
+    -api::sys_pthread_detach(thread)
+}
+
+/// Set the name of the given thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_setname_np(
+    thread: ctypes::pthread_t,
+    name: *const c_char,
+) -> c_int {
+    -api::sys_pthread_setname_np(thread, name)
+}
+
+/// Get the name of the given thread into `buf` of `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_getname_np(
+    thread: ctypes::pthread_t,
+    buf: *mut c_char,
+    len: usize,
+) -> c_int {
+    -api::sys_pthread_getname_np(thread, buf, len)
+}
+
+/// Create a thread-specific data key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_key_create(
+    key: *mut ctypes::pthread_key_t,
+    destructor: Option<unsafe extern "C" fn(*mut c_void)>,
+) -> c_int {
+    -api::sys_pthread_key_create(key, destructor)
+}
+
+/// Delete a thread-specific data key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_key_delete(key: ctypes::pthread_key_t) -> c_int {
+    -api::sys_pthread_key_delete(key)
+}
+
+/// Get the value of a thread-specific data key for the current thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_getspecific(key: ctypes::pthread_key_t) -> *mut c_void {
+    api::sys_pthread_getspecific(key)
+}
+
+/// Set the value of a thread-specific data key for the current thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_setspecific(
+    key: ctypes::pthread_key_t,
+    value: *const c_void,
+) -> c_int {
+    -api::sys_pthread_setspecific(key, value)
+}
+
+/// Call `init_routine` exactly once for the given `once_control`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_once(
+    once_control: *mut ctypes::pthread_once_t,
+    init_routine: extern "C" fn(),
+) -> c_int {
+    -api::sys_pthread_once(once_control, init_routine)
+}
@@ -0,0 +1,89 @@
+use core::ffi::{c_int, c_uint};
+
+use arceos_posix_api::{
+    sys_alarm, sys_getitimer, sys_kill, sys_setitimer, sys_sigaction, sys_sigpending,
+    sys_sigprocmask, sys_sigsuspend,
+};
+
+use crate::{ctypes, utils::e};
+
+/// Examine and change the action taken on a signal
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sigaction(
+    signum: c_int,
+    act: *const ctypes::sigaction,
+    oldact: *mut ctypes::sigaction,
+) -> c_int {
+    e(sys_sigaction(signum, act, oldact))
+}
+
+/// Examine and change the signal mask of the calling thread
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sigprocmask(
+    how: c_int,
+    set: *const ctypes::sigset_t,
+    oldset: *mut ctypes::sigset_t,
+) -> c_int {
+    e(sys_sigprocmask(how, set, oldset))
+}
+
+/// Examine and change the signal mask of the calling thread
+///
+/// Return 0 if succeed, or the error number.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_sigmask(
+    how: c_int,
+    set: *const ctypes::sigset_t,
+    oldset: *mut ctypes::sigset_t,
+) -> c_int {
+    -sys_sigprocmask(how, set, oldset)
+}
+
+/// Examine the pending signals
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sigpending(set: *mut ctypes::sigset_t) -> c_int {
+    e(sys_sigpending(set))
+}
+
+/// Wait for a signal with the signal mask temporarily replaced by `mask`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sigsuspend(mask: *const ctypes::sigset_t) -> c_int {
+    e(sys_sigsuspend(mask))
+}
+
+/// Send a signal to a process
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kill(pid: c_int, sig: c_int) -> c_int {
+    e(sys_kill(pid, sig))
+}
+
+/// Send a signal to a thread
+///
+/// Return 0 if succeed, or the error number.
+#[cfg(feature = "multitask")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_kill(thread: ctypes::pthread_t, sig: c_int) -> c_int {
+    -arceos_posix_api::sys_pthread_kill(thread, sig)
+}
+
+/// Set an alarm clock for delivery of `SIGALRM`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn alarm(seconds: c_uint) -> c_uint {
+    sys_alarm(seconds)
+}
+
+/// Set the value of an interval timer
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn setitimer(
+    which: c_int,
+    new_value: *const ctypes::itimerval,
+    old_value: *mut ctypes::itimerval,
+) -> c_int {
+    e(sys_setitimer(which, new_value, old_value))
+}
+
+/// Get the value of an interval timer
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getitimer(which: c_int, curr_value: *mut ctypes::itimerval) -> c_int {
+    e(sys_getitimer(which, curr_value))
+}
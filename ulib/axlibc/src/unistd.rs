@@ -1,6 +1,11 @@
 use arceos_posix_api::{sys_exit, sys_getpid};
+#[cfg(feature = "uspace")]
+use core::ffi::c_char;
 use core::ffi::c_int;
 
+#[cfg(feature = "uspace")]
+use crate::utils::e;
+
 /// Get current thread ID.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn getpid() -> c_int {
@@ -18,3 +23,32 @@ pub unsafe extern "C" fn abort() -> ! {
 pub unsafe extern "C" fn exit(exit_code: c_int) -> ! {
     sys_exit(exit_code)
 }
+
+/// Create a child process.
+///
+/// Only programs running in user processes can fork, so this always fails with
+/// `ENOSYS`.
+#[cfg(feature = "uspace")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fork() -> c_int {
+    e(arceos_posix_api::sys_fork())
+}
+
+/// Run the program at `path` in a user process in place of the current
+/// thread, which exits with its exit status.
+#[cfg(feature = "uspace")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn execve(
+    path: *const c_char,
+    argv: *const *const c_char,
+    envp: *const *const c_char,
+) -> c_int {
+    e(unsafe { arceos_posix_api::sys_execve(path, argv, envp) })
+}
+
+/// Wait for a child process to exit.
+#[cfg(feature = "uspace")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn waitpid(pid: c_int, status: *mut c_int, options: c_int) -> c_int {
+    e(unsafe { arceos_posix_api::sys_waitpid(pid, status, options) })
+}
@@ -27,11 +27,18 @@
 //!     - `sched-fifo`: Use the FIFO cooperative scheduler.
 //!     - `sched-rr`: Use the Round-robin preemptive scheduler.
 //!     - `sched-cfs`: Use the Completely Fair Scheduler (CFS) preemptive scheduler.
+//!     - `async`: Enable the asynchronous runtime and async networking types.
 //! - Upperlayer stacks
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
 //!     - `net`: Enable networking support.
 //!     - `dns`: Enable DNS lookup support.
+//!     - `dhcp`: Configure the network interface by DHCP.
+//!     - `tcp-cubic`: Use the CUBIC TCP congestion control by default (needs
+//!       `fp-simd` on architectures with hardware floating-point ABI).
+//!     - `net-tls`: Enable TLS streams over TCP (not to be confused with `tls`).
+//!     - `net-tls-getrandom`: Register the TLS random number generator as the
+//!       custom `getrandom` backend, unless the application registers its own.
 //!     - `display`: Enable graphics support.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
@@ -74,3 +81,5 @@ pub mod time;
 pub mod fs;
 #[cfg(feature = "net")]
 pub mod net;
+#[cfg(feature = "async")]
+pub mod task;
@@ -0,0 +1,149 @@
+use core::future::poll_fn;
+use core::task::{Context, Poll};
+
+use super::{SocketAddr, ToSocketAddrs};
+use crate::io;
+
+use arceos_api::AxError;
+use arceos_api::net::{self as api, AxTcpSocketHandle};
+
+/// An asynchronous TCP stream between a local and a remote socket.
+///
+/// Unlike [`TcpStream`](super::TcpStream), the operations on this stream do
+/// not block the current thread. When they cannot complete immediately, the
+/// current task is woken up by the network stack once the socket is ready.
+pub struct AsyncTcpStream(AxTcpSocketHandle);
+
+/// An asynchronous TCP socket server, listening for connections.
+pub struct AsyncTcpListener(AxTcpSocketHandle);
+
+impl AsyncTcpStream {
+    /// Opens a TCP connection to a remote host.
+    ///
+    /// If `addr` yields multiple addresses, `connect` will be attempted with
+    /// each of the addresses until a connection is successful. If none of
+    /// the addresses result in a successful connection, the error returned from
+    /// the last connection attempt (the last address) is returned.
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncTcpStream> {
+        let mut last_err = None;
+        for addr in addr.to_socket_addrs()? {
+            match Self::connect_addr(addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            axerrno::ax_err_type!(InvalidInput, "could not resolve to any addresses")
+        }))
+    }
+
+    async fn connect_addr(addr: SocketAddr) -> io::Result<AsyncTcpStream> {
+        let socket = api::ax_tcp_socket();
+        api::ax_tcp_set_nonblocking(&socket, true)?;
+        match api::ax_tcp_connect(&socket, addr) {
+            Ok(()) | Err(AxError::WouldBlock) => {}
+            Err(e) => return Err(e),
+        }
+        poll_fn(|cx| api::ax_tcp_poll_connected(&socket, cx)).await?;
+        Ok(AsyncTcpStream(socket))
+    }
+
+    /// Returns the socket address of the local half of this TCP connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_tcp_socket_addr(&self.0)
+    }
+
+    /// Returns the socket address of the remote peer of this TCP connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_tcp_peer_addr(&self.0)
+    }
+
+    /// Shuts down the connection.
+    pub fn shutdown(&self) -> io::Result<()> {
+        api::ax_tcp_shutdown(&self.0)
+    }
+
+    /// Attempts to read data from the stream into `buf`.
+    ///
+    /// If no data is available, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_read(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        api::ax_tcp_poll_recv(&self.0, cx, buf)
+    }
+
+    /// Attempts to write data in `buf` to the stream.
+    ///
+    /// If the send buffer is full, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_write(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        api::ax_tcp_poll_send(&self.0, cx, buf)
+    }
+
+    /// Reads data from the stream into `buf`, returns the number of bytes
+    /// read. Zero is returned if the connection is closed by the peer.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_read(cx, buf)).await
+    }
+
+    /// Writes data in `buf` to the stream, returns the number of bytes
+    /// written.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_write(cx, buf)).await
+    }
+
+    /// Writes all data in `buf` to the stream.
+    pub async fn write_all(&self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf).await? {
+                0 => return axerrno::ax_err!(WriteZero, "failed to write whole buffer"),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AsyncTcpListener {
+    /// Creates a new `AsyncTcpListener` which will be bound to the specified
+    /// address.
+    ///
+    /// If `addr` yields multiple addresses, `bind` will be attempted with
+    /// each of the addresses until one succeeds and returns the listener. If
+    /// none of the addresses succeed in creating a listener, the error returned
+    /// from the last attempt (the last address) is returned.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncTcpListener> {
+        super::each_addr(addr, |addr: io::Result<&SocketAddr>| {
+            let addr = addr?;
+            let backlog = 128;
+            let socket = api::ax_tcp_socket();
+            api::ax_tcp_set_nonblocking(&socket, true)?;
+            api::ax_tcp_bind(&socket, *addr)?;
+            api::ax_tcp_listen(&socket, backlog)?;
+            Ok(AsyncTcpListener(socket))
+        })
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_tcp_socket_addr(&self.0)
+    }
+
+    /// Attempts to accept a new incoming connection from this listener.
+    ///
+    /// If no connection is established, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_accept(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(AsyncTcpStream, SocketAddr)>> {
+        api::ax_tcp_poll_accept(&self.0, cx).map_ok(|(socket, addr)| (AsyncTcpStream(socket), addr))
+    }
+
+    /// Accepts a new incoming connection from this listener.
+    ///
+    /// When established, the corresponding [`AsyncTcpStream`] and the remote
+    /// peer's address will be returned.
+    pub async fn accept(&self) -> io::Result<(AsyncTcpStream, SocketAddr)> {
+        poll_fn(|cx| self.poll_accept(cx)).await
+    }
+}
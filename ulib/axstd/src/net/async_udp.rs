@@ -0,0 +1,106 @@
+use core::future::poll_fn;
+use core::task::{Context, Poll};
+
+use super::{SocketAddr, ToSocketAddrs};
+use crate::io;
+
+use arceos_api::net::{self as api, AxUdpSocketHandle};
+
+/// An asynchronous UDP socket.
+///
+/// Unlike [`UdpSocket`](super::UdpSocket), the operations on this socket do
+/// not block the current thread. When they cannot complete immediately, the
+/// current task is woken up by the network stack once the socket is ready.
+pub struct AsyncUdpSocket(AxUdpSocketHandle);
+
+impl AsyncUdpSocket {
+    /// Creates a UDP socket from the given address.
+    ///
+    /// If `addr` yields multiple addresses, `bind` will be attempted with
+    /// each of the addresses until one succeeds and returns the socket. If none
+    /// of the addresses succeed in creating a socket, the error returned from
+    /// the last attempt (the last address) is returned.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncUdpSocket> {
+        super::each_addr(addr, |addr: io::Result<&SocketAddr>| {
+            let addr = addr?;
+            let socket = api::ax_udp_socket();
+            api::ax_udp_set_nonblocking(&socket, true)?;
+            api::ax_udp_bind(&socket, *addr)?;
+            Ok(AsyncUdpSocket(socket))
+        })
+    }
+
+    /// Returns the socket address that this socket was created from.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_udp_socket_addr(&self.0)
+    }
+
+    /// Returns the socket address of the remote peer this socket was connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_udp_peer_addr(&self.0)
+    }
+
+    /// Connects this UDP socket to a remote address, allowing the `send` and
+    /// `recv` to be used to send data and also applies filters to only
+    /// receive data from the specified address.
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        super::each_addr(addr, |addr: io::Result<&SocketAddr>| {
+            let addr = addr?;
+            api::ax_udp_connect(&self.0, *addr)
+        })
+    }
+
+    /// Attempts to receive a single datagram message on the socket.
+    ///
+    /// If no datagram is available, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        api::ax_udp_poll_recv_from(&self.0, cx, buf)
+    }
+
+    /// Attempts to send data on the socket to the given address.
+    ///
+    /// If the send buffer is full, the waker of `cx` is registered and
+    /// [`Poll::Pending`] is returned.
+    pub fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        api::ax_udp_poll_send_to(&self.0, cx, buf, addr)
+    }
+
+    /// Receives a single datagram message on the socket. On success, returns
+    /// the number of bytes read and the origin.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        poll_fn(|cx| self.poll_recv_from(cx, buf)).await
+    }
+
+    /// Sends data on the socket to the given address. On success, returns the
+    /// number of bytes written.
+    ///
+    /// It is possible for `addr` to yield multiple addresses, but `send_to`
+    /// will only send data to the first address yielded by `addr`.
+    pub async fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        match addr.to_socket_addrs()?.next() {
+            Some(addr) => poll_fn(|cx| self.poll_send_to(cx, buf, addr)).await,
+            None => axerrno::ax_err!(InvalidInput, "no addresses to send data to"),
+        }
+    }
+
+    /// Sends data on the socket to the remote address to which it is connected.
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| api::ax_udp_poll_send(&self.0, cx, buf)).await
+    }
+
+    /// Receives a single datagram message on the socket from the remote address to
+    /// which it is connected. On success, returns the number of bytes read.
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| api::ax_udp_poll_recv(&self.0, cx, buf)).await
+    }
+}
@@ -0,0 +1,75 @@
+use super::IpAddr;
+use crate::io;
+use core::time::Duration;
+
+use arceos_api::net::{self as api, AxIcmpSocketHandle};
+
+/// An ICMP socket for sending and receiving echo (ping) messages.
+///
+/// The messages sent and received start with the ICMP header. The socket
+/// is bound to an ICMP identifier, which replaces the identifier of the echo
+/// requests sent on it, and only the echo requests and replies with the
+/// identifier are received. The checksum is computed by the network stack.
+pub struct IcmpSocket(AxIcmpSocketHandle);
+
+impl IcmpSocket {
+    /// Creates an ICMP socket bound to the given ICMP identifier, or an
+    /// automatically chosen one if `ident` is 0.
+    pub fn bind(ident: u16) -> io::Result<IcmpSocket> {
+        let socket = api::ax_icmp_socket();
+        api::ax_icmp_bind(&socket, ident)?;
+        Ok(IcmpSocket(socket))
+    }
+
+    /// Returns the ICMP identifier that this socket is bound to.
+    pub fn ident(&self) -> io::Result<u16> {
+        api::ax_icmp_ident(&self.0)
+    }
+
+    /// Receives a single ICMP message on the socket. On success, returns the
+    /// number of bytes read and the origin.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, IpAddr)> {
+        api::ax_icmp_recv_from(&self.0, buf)
+    }
+
+    /// Sends an ICMP message on the socket to the given address. On success,
+    /// returns the number of bytes written.
+    pub fn send_to(&self, buf: &[u8], addr: IpAddr) -> io::Result<usize> {
+        api::ax_icmp_send_to(&self.0, buf, addr)
+    }
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet
+    /// sent from this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        let ttl = u8::try_from(ttl)
+            .map_err(|_| axerrno::ax_err_type!(InvalidInput, "invalid TTL value"))?;
+        api::ax_icmp_set_hop_limit(&self.0, Some(ttl))
+    }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`recv_from`] calls will
+    /// block indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method.
+    ///
+    /// A [`recv_from`] call that times out returns
+    /// [`Err(WouldBlock)`](io::Error::WouldBlock).
+    ///
+    /// [`recv_from`]: IcmpSocket::recv_from
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_icmp_set_read_timeout(&self.0, dur)
+    }
+
+    /// Moves this ICMP socket into or out of nonblocking mode.
+    ///
+    /// This will result in `recv_from` and `send_to` operations becoming
+    /// nonblocking, i.e., immediately returning from their calls. If the IO
+    /// operation is successful, `Ok` is returned and no further action is
+    /// required. If the IO operation could not be completed and needs to be
+    /// retried, [`Err(WouldBlock)`](io::Error::WouldBlock) is returned.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        api::ax_icmp_set_nonblocking(&self.0, nonblocking)
+    }
+}
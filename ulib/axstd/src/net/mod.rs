@@ -1,4 +1,4 @@
-//! Networking primitives for TCP/UDP communication.
+//! Networking primitives for TCP/UDP/ICMP communication.
 //!
 //! This module provides networking functionality for the Transmission Control and User
 //! Datagram Protocols, as well as types for IP and socket addresses.
@@ -7,6 +7,12 @@
 //!
 //! * [`TcpListener`] and [`TcpStream`] provide functionality for communication over TCP
 //! * [`UdpSocket`] provides functionality for communication over UDP
+//! * [`IcmpSocket`] provides functionality for sending and receiving ICMP echo
+//!   (ping) messages
+//! * [`tls::TlsStream`] wraps a [`TcpStream`] with TLS, created by
+//!   [`tls::TlsConnector`] or [`tls::TlsAcceptor`]
+//! * [`AsyncTcpListener`], [`AsyncTcpStream`] and [`AsyncUdpSocket`] are the
+//!   asynchronous counterparts of the above, to be run by the `task` runtime
 //! * [`IpAddr`] represents IP addresses of either IPv4 or IPv6; [`Ipv4Addr`] and
 //!   [`Ipv6Addr`] are respectively IPv4 and IPv6 addresses
 //! * [`SocketAddr`] represents socket addresses of either IPv4 or IPv6; [`SocketAddrV4`]
@@ -14,15 +20,30 @@
 //! * [`ToSocketAddrs`] is a trait that is used for generic address resolution when interacting
 //!   with networking objects like [`TcpListener`], [`TcpStream`] or [`UdpSocket`]
 
+mod icmp;
 mod socket_addr;
 mod tcp;
 mod udp;
 
+#[cfg(feature = "net-tls")]
+pub mod tls;
+
+#[cfg(feature = "async")]
+mod async_tcp;
+#[cfg(feature = "async")]
+mod async_udp;
+
+pub use self::icmp::IcmpSocket;
 pub use self::socket_addr::{IpAddr, Ipv4Addr, Ipv6Addr};
 pub use self::socket_addr::{SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
-pub use self::tcp::{TcpListener, TcpStream};
+pub use self::tcp::{TcpInfo, TcpListener, TcpStream};
 pub use self::udp::UdpSocket;
 
+#[cfg(feature = "async")]
+pub use self::async_tcp::{AsyncTcpListener, AsyncTcpStream};
+#[cfg(feature = "async")]
+pub use self::async_udp::AsyncUdpSocket;
+
 use crate::io;
 
 fn each_addr<A: ToSocketAddrs, F, T>(addr: A, mut f: F) -> io::Result<T>
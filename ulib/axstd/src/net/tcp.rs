@@ -1,8 +1,12 @@
+use core::time::Duration;
+
 use super::{SocketAddr, ToSocketAddrs};
 use crate::io::{self, prelude::*};
 
 use arceos_api::net::{self as api, AxTcpSocketHandle};
 
+pub use arceos_api::net::AxTcpInfo as TcpInfo;
+
 /// A TCP stream between a local and a remote socket.
 pub struct TcpStream(AxTcpSocketHandle);
 
@@ -43,6 +47,151 @@ impl TcpStream {
     pub fn shutdown(&self) -> io::Result<()> {
         api::ax_tcp_shutdown(&self.0)
     }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`read`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method.
+    ///
+    /// A [`read`] call that times out returns
+    /// [`Err(WouldBlock)`](io::Error::WouldBlock).
+    ///
+    /// [`read`]: Read::read
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_read_timeout(&self.0, dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    ///
+    /// If the timeout is [`None`], then [`read`] calls will block indefinitely.
+    ///
+    /// [`read`]: Read::read
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_read_timeout(&self.0)
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`write`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method.
+    ///
+    /// A [`write`] call that times out returns
+    /// [`Err(WouldBlock)`](io::Error::WouldBlock).
+    ///
+    /// [`write`]: Write::write
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_write_timeout(&self.0, dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    ///
+    /// If the timeout is [`None`], then [`write`] calls will block indefinitely.
+    ///
+    /// [`write`]: Write::write
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_write_timeout(&self.0)
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// If set, this option disables the Nagle algorithm. This means that
+    /// segments are always sent as soon as possible, even if there is only a
+    /// small amount of data.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        api::ax_tcp_set_nodelay(&self.0, nodelay)
+    }
+
+    /// Gets the value of the `TCP_NODELAY` option on this socket.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        api::ax_tcp_nodelay(&self.0)
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// If `linger` is set, [`shutdown`](Self::shutdown) and dropping the
+    /// stream block until the unsent data is sent or the timeout expires, and
+    /// a zero timeout resets the connection.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_linger(&self.0, linger)
+    }
+
+    /// Gets the value of the `SO_LINGER` option on this socket.
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_linger(&self.0)
+    }
+
+    /// Sets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// If set, keep-alive probes are sent when the connection is idle.
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        api::ax_tcp_set_keepalive(&self.0, keepalive)
+    }
+
+    /// Gets the value of the `SO_KEEPALIVE` option on this socket.
+    pub fn keepalive(&self) -> io::Result<bool> {
+        api::ax_tcp_keepalive(&self.0)
+    }
+
+    /// Sets the congestion control algorithm of this socket
+    /// (`TCP_CONGESTION`).
+    ///
+    /// `name` is one of `"none"`, `"reno"` and `"cubic"`, the last of which
+    /// requires the `tcp-cubic` feature.
+    pub fn set_congestion_control(&self, name: &str) -> io::Result<()> {
+        api::ax_tcp_set_congestion_control(&self.0, name)
+    }
+
+    /// Gets the name of the congestion control algorithm of this socket.
+    pub fn congestion_control(&self) -> io::Result<&'static str> {
+        api::ax_tcp_congestion_control(&self.0)
+    }
+
+    /// Gets the statistics of this connection (`TCP_INFO`).
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        api::ax_tcp_info(&self.0)
+    }
+
+    /// Gets the size of the receive buffer of this socket (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        api::ax_tcp_recv_buffer_size(&self.0)
+    }
+
+    /// Gets the size of the send buffer of this socket (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        api::ax_tcp_send_buffer_size(&self.0)
+    }
+
+    /// Reads data without copying it out of the socket, `f` is called with the
+    /// received data in the receive buffer and returns the number of bytes
+    /// consumed.
+    ///
+    /// The data may be only a part of the received one if it wraps around the
+    /// end of the buffer. An empty slice means the peer has closed the
+    /// connection. Consuming more bytes than `f` is given is an
+    /// `InvalidInput` error. `f` runs with the network stack locked, so it
+    /// must not use any socket.
+    pub fn recv_zc<F: FnOnce(&[u8]) -> usize>(&self, f: F) -> io::Result<usize> {
+        api::ax_tcp_recv_zc(&self.0, f)
+    }
+
+    /// Writes data without copying it into the socket, `f` is called with the
+    /// free space in the send buffer, fills the data from the beginning and
+    /// returns its length, which must not exceed the free space.
+    ///
+    /// `f` runs with the network stack locked, so it must not use any socket.
+    pub fn send_zc<F: FnOnce(&mut [u8]) -> usize>(&self, f: F) -> io::Result<usize> {
+        api::ax_tcp_send_zc(&self.0, f)
+    }
+
+    /// Gets the value of the `SO_ERROR` option on this socket.
+    ///
+    /// This will retrieve the stored error in the underlying socket, clearing
+    /// the field in the process.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        api::ax_tcp_take_error(&self.0)
+    }
 }
 
 impl Read for TcpStream {
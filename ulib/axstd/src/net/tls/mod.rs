@@ -0,0 +1,472 @@
+//! TLS streams on top of [`TcpStream`], powered by [rustls].
+//!
+//! A client wraps a connected [`TcpStream`] with [`TlsConnector::connect`],
+//! a server wraps an accepted one with [`TlsAcceptor::accept`]. Both perform
+//! the handshake before returning a [`TlsStream`], which then reads and writes
+//! the application data like a plain [`TcpStream`].
+//!
+//! The cryptography is implemented in pure Rust by [rustls-rustcrypto]. Its
+//! random numbers come from a ChaCha20 generator that must be seeded with
+//! [`seed_rng`] or [`seed_rng_from_file`] before the first connection. The
+//! generator also backs `getrandom` through [`getrandom`], which needs to be
+//! registered by the application or by the `net-tls-getrandom` feature.
+//!
+//! [rustls]: https://docs.rs/rustls
+//! [rustls-rustcrypto]: https://docs.rs/rustls-rustcrypto
+
+mod rng;
+
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use rustls::client::UnbufferedClientConnection;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::server::UnbufferedServerConnection;
+use rustls::unbuffered::{ConnectionState, EncodeError, EncryptError, UnbufferedStatus};
+use rustls::{ConfigBuilder, WantsVersions};
+
+use super::TcpStream;
+use crate::io::{self, prelude::*};
+
+pub use self::rng::{getrandom, seed_rng};
+pub use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+pub use rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+/// The maximum size of a TLS record, used as the size of socket reads.
+const MAX_RECORD_SIZE: usize = 16 * 1024 + 256;
+
+/// The maximum amount of application data encrypted by one `write` call.
+const MAX_WRITE_SIZE: usize = 16 * 1024;
+
+fn tls_error(e: impl Display) -> io::Error {
+    axerrno::ax_err_type!(InvalidData, e)
+}
+
+/// Returns the crypto provider used by TLS connections, which takes its
+/// random numbers from the generator seeded by [`seed_rng`].
+pub fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(CryptoProvider {
+        secure_random: &rng::AxRandom,
+        ..rustls_rustcrypto::provider()
+    })
+}
+
+/// Starts building a [`ClientConfig`] that uses the crypto provider of
+/// [`crypto_provider`] and the wall clock of ArceOS.
+pub fn client_config_builder() -> ConfigBuilder<ClientConfig, WantsVersions> {
+    ClientConfig::builder_with_details(crypto_provider(), Arc::new(rng::AxWallTime))
+}
+
+/// Starts building a [`ServerConfig`] that uses the crypto provider of
+/// [`crypto_provider`] and the wall clock of ArceOS.
+pub fn server_config_builder() -> ConfigBuilder<ServerConfig, WantsVersions> {
+    ServerConfig::builder_with_details(crypto_provider(), Arc::new(rng::AxWallTime))
+}
+
+/// Parses all certificates in a PEM file.
+pub fn parse_certs(pem: &[u8]) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_slice_iter(pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(tls_error)?;
+    if certs.is_empty() {
+        return Err(axerrno::ax_err_type!(InvalidData, "no certificate found"));
+    }
+    Ok(certs)
+}
+
+/// Parses the first private key in a PEM file, which can be in the PKCS#1,
+/// PKCS#8 or SEC1 format.
+pub fn parse_private_key(pem: &[u8]) -> io::Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_slice(pem).map_err(tls_error)
+}
+
+/// Loads all certificates from a PEM file.
+#[cfg(feature = "fs")]
+pub fn load_certs(path: &str) -> io::Result<Vec<CertificateDer<'static>>> {
+    parse_certs(&crate::fs::read(path)?)
+}
+
+/// Loads the private key from a PEM file.
+#[cfg(feature = "fs")]
+pub fn load_private_key(path: &str) -> io::Result<PrivateKeyDer<'static>> {
+    parse_private_key(&crate::fs::read(path)?)
+}
+
+/// Seeds the random number generator with the 32-byte seed file at `path`,
+/// e.g., created on the host by `head -c 32 /dev/urandom`.
+///
+/// The file is then overwritten with a new seed taken from the generator, so
+/// that the next boot does not reuse the same random numbers.
+#[cfg(feature = "fs")]
+pub fn seed_rng_from_file(path: &str) -> io::Result<()> {
+    let seed = crate::fs::read(path)?
+        .try_into()
+        .map_err(|_| axerrno::ax_err_type!(InvalidData, "the seed file must be 32 bytes"))?;
+    seed_rng(seed);
+    let mut next_seed = [0; 32];
+    rng::fill_random(&mut next_seed)
+        .map_err(|_| axerrno::ax_err_type!(BadState, "failed to generate the next seed"))?;
+    crate::fs::write(path, next_seed)
+}
+
+/// Creates client side TLS streams.
+#[derive(Clone)]
+pub struct TlsConnector {
+    config: Arc<ClientConfig>,
+}
+
+impl TlsConnector {
+    /// Creates a connector that verifies the servers with the given root
+    /// certificates, with the default protocol versions and cipher suites.
+    pub fn new(roots: RootCertStore) -> io::Result<Self> {
+        let config = client_config_builder()
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Self::with_config(Arc::new(config)))
+    }
+
+    /// Creates a connector with a custom configuration, which is usually built
+    /// by [`client_config_builder`].
+    pub fn with_config(config: Arc<ClientConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Performs the TLS handshake on a connected TCP stream.
+    ///
+    /// `server_name` is the DNS name or the IP address that the certificate
+    /// of the server must be valid for.
+    pub fn connect(&self, server_name: &str, stream: TcpStream) -> io::Result<TlsStream> {
+        let name = ServerName::try_from(server_name.to_string())
+            .map_err(|e| axerrno::ax_err_type!(InvalidInput, e))?;
+        let conn = UnbufferedClientConnection::new(self.config.clone(), name).map_err(tls_error)?;
+        TlsStream::handshake(stream, Connection::Client(conn))
+    }
+}
+
+/// Creates server side TLS streams.
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    config: Arc<ServerConfig>,
+}
+
+impl TlsAcceptor {
+    /// Creates an acceptor that presents the given certificate chain, whose
+    /// first certificate must match the private key `key`.
+    pub fn new(
+        certs: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> io::Result<Self> {
+        let config = server_config_builder()
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(tls_error)?;
+        Ok(Self::with_config(Arc::new(config)))
+    }
+
+    /// Creates an acceptor with a custom configuration, which is usually built
+    /// by [`server_config_builder`].
+    pub fn with_config(config: Arc<ServerConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Performs the TLS handshake on an accepted TCP stream.
+    pub fn accept(&self, stream: TcpStream) -> io::Result<TlsStream> {
+        let conn = UnbufferedServerConnection::new(self.config.clone()).map_err(tls_error)?;
+        TlsStream::handshake(stream, Connection::Server(conn))
+    }
+}
+
+enum Connection {
+    Client(UnbufferedClientConnection),
+    Server(UnbufferedServerConnection),
+}
+
+/// What to do once the handshake allows sending application data.
+#[derive(Clone, Copy)]
+enum Action<'a> {
+    None,
+    Write(&'a [u8]),
+    Close,
+}
+
+/// The result of processing the buffered TLS records once.
+enum Event {
+    /// Some progress has been made, process again.
+    Continue,
+    /// More TLS records must be read from the socket.
+    NeedData,
+    /// The handshake is done, application data can be sent.
+    Ready,
+    /// The application data or the close_notify of the action has been sent.
+    Sent(usize),
+    /// The peer has sent a close_notify.
+    PeerClosed,
+    /// Both sides have sent a close_notify.
+    Closed,
+}
+
+/// A TLS stream between a local and a remote socket.
+///
+/// The handshake is completed when it is created by [`TlsConnector`] or
+/// [`TlsAcceptor`]. Data written to the stream is encrypted and sent
+/// immediately, so [`flush`](Write::flush) does nothing.
+pub struct TlsStream {
+    stream: TcpStream,
+    conn: Connection,
+    /// The TLS records received but not processed.
+    incoming: Vec<u8>,
+    /// The TLS records to be sent.
+    outgoing: Vec<u8>,
+    /// The decrypted application data not read yet.
+    plaintext: VecDeque<u8>,
+    peer_closed: bool,
+}
+
+impl TlsStream {
+    fn handshake(stream: TcpStream, conn: Connection) -> io::Result<Self> {
+        let mut tls = Self {
+            stream,
+            conn,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+            plaintext: VecDeque::new(),
+            peer_closed: false,
+        };
+        loop {
+            match tls.process(Action::None)? {
+                Event::Ready => return Ok(tls),
+                Event::NeedData => tls.read_tls()?,
+                Event::PeerClosed => tls.peer_closed = true,
+                Event::Closed => {
+                    return Err(axerrno::ax_err_type!(
+                        ConnectionReset,
+                        "TLS connection closed during handshake"
+                    ));
+                }
+                Event::Continue | Event::Sent(_) => {}
+            }
+        }
+    }
+
+    /// Returns a reference to the underlying TCP stream.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Sends a close_notify to the peer and shuts down the TCP connection.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        loop {
+            match self.process(Action::Close)? {
+                Event::Sent(_) | Event::Closed => break,
+                Event::NeedData => self.read_tls()?,
+                Event::PeerClosed => self.peer_closed = true,
+                Event::Continue | Event::Ready => {}
+            }
+        }
+        self.stream.shutdown()
+    }
+
+    /// Reads more TLS records from the socket.
+    fn read_tls(&mut self) -> io::Result<()> {
+        let len = self.incoming.len();
+        self.incoming.resize(len + MAX_RECORD_SIZE, 0);
+        let res = self.stream.read(&mut self.incoming[len..]);
+        self.incoming.truncate(len + *res.as_ref().unwrap_or(&0));
+        match res? {
+            0 => Err(axerrno::ax_err_type!(
+                UnexpectedEof,
+                "TLS connection closed without close_notify"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Processes the received TLS records until the next event.
+    fn process(&mut self, action: Action) -> io::Result<Event> {
+        let Self {
+            stream,
+            conn,
+            incoming,
+            outgoing,
+            plaintext,
+            ..
+        } = self;
+        let mut io = RecordIo {
+            stream,
+            outgoing,
+            plaintext,
+        };
+        let (discard, event) = match conn {
+            Connection::Client(conn) => io.handle(conn.process_tls_records(incoming), action)?,
+            Connection::Server(conn) => io.handle(conn.process_tls_records(incoming), action)?,
+        };
+        incoming.drain(..discard);
+        Ok(event)
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if !self.plaintext.is_empty() {
+                let len = buf.len().min(self.plaintext.len());
+                for (dst, src) in buf.iter_mut().zip(self.plaintext.drain(..len)) {
+                    *dst = src;
+                }
+                return Ok(len);
+            }
+            if self.peer_closed {
+                return Ok(0);
+            }
+            match self.process(Action::None)? {
+                Event::NeedData | Event::Ready => self.read_tls()?,
+                Event::PeerClosed | Event::Closed => self.peer_closed = true,
+                Event::Continue | Event::Sent(_) => {}
+            }
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let buf = &buf[..buf.len().min(MAX_WRITE_SIZE)];
+        loop {
+            match self.process(Action::Write(buf))? {
+                Event::Sent(len) => return Ok(len),
+                Event::NeedData => self.read_tls()?,
+                Event::PeerClosed => self.peer_closed = true,
+                Event::Closed => {
+                    return Err(axerrno::ax_err_type!(
+                        NotConnected,
+                        "TLS connection already closed"
+                    ));
+                }
+                Event::Continue | Event::Ready => {}
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The buffers touched when handling a [`ConnectionState`], borrowed apart
+/// from the connection and the incoming records.
+struct RecordIo<'a> {
+    stream: &'a mut TcpStream,
+    outgoing: &'a mut Vec<u8>,
+    plaintext: &'a mut VecDeque<u8>,
+}
+
+impl RecordIo<'_> {
+    /// Handles the state returned by `process_tls_records`, returns the number
+    /// of bytes to discard from the incoming records and the event.
+    fn handle<Data>(
+        &mut self,
+        status: UnbufferedStatus<'_, '_, Data>,
+        action: Action,
+    ) -> io::Result<(usize, Event)> {
+        let UnbufferedStatus { mut discard, state } = status;
+        let event = match state.map_err(tls_error)? {
+            ConnectionState::ReadTraffic(mut state) => {
+                while let Some(record) = state.next_record() {
+                    let record = record.map_err(tls_error)?;
+                    discard += record.discard;
+                    self.plaintext.extend(record.payload);
+                }
+                Event::Continue
+            }
+            ConnectionState::EncodeTlsData(mut state) => {
+                append_records(self.outgoing, |buf| state.encode(buf))?;
+                Event::Continue
+            }
+            ConnectionState::TransmitTlsData(state) => {
+                self.transmit()?;
+                state.done();
+                Event::Continue
+            }
+            ConnectionState::BlockedHandshake => Event::NeedData,
+            ConnectionState::WriteTraffic(mut state) => match action {
+                Action::None => Event::Ready,
+                Action::Write(data) => {
+                    append_records(self.outgoing, |buf| state.encrypt(data, buf))?;
+                    self.transmit()?;
+                    Event::Sent(data.len())
+                }
+                Action::Close => {
+                    append_records(self.outgoing, |buf| state.queue_close_notify(buf))?;
+                    self.transmit()?;
+                    Event::Sent(0)
+                }
+            },
+            ConnectionState::PeerClosed => Event::PeerClosed,
+            ConnectionState::Closed => Event::Closed,
+            // early data is not enabled
+            _ => return Err(axerrno::ax_err_type!(Unsupported, "unexpected TLS state")),
+        };
+        Ok((discard, event))
+    }
+
+    fn transmit(&mut self) -> io::Result<()> {
+        self.stream.write_all(self.outgoing.as_slice())?;
+        self.outgoing.clear();
+        Ok(())
+    }
+}
+
+/// Errors of rustls that report the buffer size needed for the records.
+trait InsufficientSize: Display {
+    fn required_size(&self) -> Option<usize>;
+}
+
+impl InsufficientSize for EncodeError {
+    fn required_size(&self) -> Option<usize> {
+        match self {
+            Self::InsufficientSize(e) => Some(e.required_size),
+            _ => None,
+        }
+    }
+}
+
+impl InsufficientSize for EncryptError {
+    fn required_size(&self) -> Option<usize> {
+        match self {
+            Self::InsufficientSize(e) => Some(e.required_size),
+            _ => None,
+        }
+    }
+}
+
+/// Appends the records written by `f` to `out`, growing it as needed.
+fn append_records<E: InsufficientSize>(
+    out: &mut Vec<u8>,
+    mut f: impl FnMut(&mut [u8]) -> Result<usize, E>,
+) -> io::Result<()> {
+    let len = out.len();
+    let mut size = 0;
+    loop {
+        out.resize(len + size, 0);
+        match f(&mut out[len..]) {
+            Ok(n) => {
+                out.truncate(len + n);
+                return Ok(());
+            }
+            Err(e) => {
+                out.truncate(len);
+                size = e.required_size().ok_or_else(|| tls_error(e))?;
+            }
+        }
+    }
+}
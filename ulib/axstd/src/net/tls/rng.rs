@@ -0,0 +1,74 @@
+//! The random number generator and the clock used by TLS connections.
+
+use core::num::NonZeroU32;
+
+use arceos_api::time::ax_wall_time;
+use kspin::SpinNoIrq;
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rustls::crypto::{GetRandomFailed, SecureRandom};
+use rustls::pki_types::UnixTime;
+use rustls::time_provider::TimeProvider;
+
+/// The CSPRNG shared by all TLS connections, which must be seeded by
+/// [`seed_rng`].
+static RNG: SpinNoIrq<Option<ChaCha20Rng>> = SpinNoIrq::new(None);
+
+/// The error of [`getrandom`] when [`seed_rng`] has not been called.
+const NOT_SEEDED: NonZeroU32 = NonZeroU32::new(getrandom::Error::CUSTOM_START).unwrap();
+
+/// Seeds (or reseeds) the random number generator used by TLS.
+///
+/// ArceOS has no hardware entropy source, so applications must call this with
+/// 32 bytes of real entropy before the first connection, otherwise the
+/// handshakes fail. A fixed seed makes the handshakes reproducible, which is
+/// useful for testing but must never be used in production.
+pub fn seed_rng(seed: [u8; 32]) {
+    *RNG.lock() = Some(ChaCha20Rng::from_seed(seed));
+}
+
+/// Fills `buf` with random bytes, or fails if the generator is not seeded.
+pub(super) fn fill_random(buf: &mut [u8]) -> Result<(), GetRandomFailed> {
+    match RNG.lock().as_mut() {
+        Some(rng) => {
+            rng.fill_bytes(buf);
+            Ok(())
+        }
+        None => Err(GetRandomFailed),
+    }
+}
+
+/// A custom [`getrandom`](https://docs.rs/getrandom) backend that reads the
+/// random number generator used by TLS.
+///
+/// The crypto provider generates the ephemeral keys with `getrandom`, so the
+/// application must register it with `getrandom::register_custom_getrandom!`,
+/// or enable the `net-tls-getrandom` feature to have it registered by axstd.
+/// It fails if [`seed_rng`] has not been called.
+pub fn getrandom(buf: &mut [u8]) -> Result<(), getrandom::Error> {
+    fill_random(buf).map_err(|_| getrandom::Error::from(NOT_SEEDED))
+}
+
+#[cfg(feature = "net-tls-getrandom")]
+getrandom::register_custom_getrandom!(getrandom);
+
+/// The [`SecureRandom`] implementation backed by [`RNG`].
+#[derive(Debug)]
+pub(super) struct AxRandom;
+
+impl SecureRandom for AxRandom {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), GetRandomFailed> {
+        fill_random(buf)
+    }
+}
+
+/// The [`TimeProvider`] that reads the wall clock, used to check the validity
+/// of certificates.
+#[derive(Debug)]
+pub(super) struct AxWallTime;
+
+impl TimeProvider for AxWallTime {
+    fn current_time(&self) -> Option<UnixTime> {
+        Some(UnixTime::since_unix_epoch(ax_wall_time()))
+    }
+}
@@ -62,6 +62,34 @@ impl UdpSocket {
         }
     }
 
+    /// Receives a single datagram message on the socket without copying it,
+    /// `f` is called with the datagram and its origin.
+    ///
+    /// After the first call, the datagrams received by a NIC for the socket
+    /// are lent in the RX buffers of the driver, which are recycled when `f`
+    /// returns.
+    ///
+    /// `f` runs with the network stack locked, so it must not use any socket.
+    pub fn recv_zc<F: FnOnce(&[u8], SocketAddr)>(&self, f: F) -> io::Result<()> {
+        api::ax_udp_recv_zc(&self.0, f)
+    }
+
+    /// Sends a datagram of `len` bytes to the given address without copying
+    /// it into the socket, `f` is called to fill the datagram in the send
+    /// buffer.
+    ///
+    /// `f` runs with the network stack locked, so it must not use any socket.
+    pub fn send_zc_to<A, F>(&self, len: usize, addr: A, f: F) -> io::Result<()>
+    where
+        A: ToSocketAddrs,
+        F: FnOnce(&mut [u8]),
+    {
+        match addr.to_socket_addrs()?.next() {
+            Some(addr) => api::ax_udp_send_zc_to(&self.0, len, addr, f),
+            None => axerrno::ax_err!(InvalidInput, "no addresses to send data to"),
+        }
+    }
+
     /// Connects this UDP socket to a remote address, allowing the `send` and
     /// `recv` syscalls to be used to send data and also applies filters to only
     /// receive data from the specified address.
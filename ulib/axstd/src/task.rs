@@ -0,0 +1,12 @@
+//! Asynchronous tasks and the runtime to run them.
+//!
+//! Futures are run to completion by [`block_on`] on the current thread, or by
+//! [`spawn`] on a new thread. When a future cannot make progress, the thread
+//! is blocked until the future is woken up, e.g., by the network stack when a
+//! socket becomes ready, or by a timer created by [`sleep`].
+
+#[doc(no_inline)]
+pub use core::task::{Context, Poll, Waker};
+
+pub use arceos_api::modules::axasync::{JoinHandle, Sleep, TimedOut, Timeout, YieldNow};
+pub use arceos_api::modules::axasync::{block_on, sleep, sleep_until, spawn, timeout, yield_now};